@@ -0,0 +1,6 @@
+fn main() {
+    if let Err(e) = tjimi_lib::cli::run() {
+        eprintln!("tjimi-cli: {:#}", e);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,6 @@
+fn main() {
+    if let Err(e) = tjimi_lib::headless::run() {
+        eprintln!("tjimi-server: {:#}", e);
+        std::process::exit(1);
+    }
+}
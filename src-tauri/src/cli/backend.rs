@@ -0,0 +1,329 @@
+use anyhow::{Context, Result, anyhow, bail};
+use reqwest::{Method, StatusCode};
+use serde::Serialize;
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::path::Path;
+use uuid::Uuid;
+use crate::database::init_database;
+use crate::models::{AuditChannel, CreateApiKeyRequest, SettingValue, SETTING_NAMES};
+use crate::server::middleware::ADMIN_TOKEN_HEADER;
+use crate::services::{
+    snapshot, ApiKeyService, AuditService, ClientTokenService, CustomAuthService, RequestLogService, SettingsService, TlsService,
+};
+
+// 远程模式下分页拉取列表时每页的条数，与管理 API 的上限一致
+const REMOTE_KEYS_PER_PAGE: u32 = 100;
+pub const MAX_LOGS_PER_PAGE: u32 = 200;
+
+fn to_json<T: Serialize>(value: T) -> Result<Value> {
+    Ok(serde_json::to_value(value)?)
+}
+
+fn parse_id(id: &str) -> Result<Uuid> {
+    Uuid::parse_str(id).map_err(|_| anyhow!("Invalid id '{}'", id))
+}
+
+fn check_setting_name(name: &str) -> Result<()> {
+    if !SETTING_NAMES.contains(&name) {
+        bail!("Unknown setting '{}', expected one of: {}", name, SETTING_NAMES.join(", "));
+    }
+    Ok(())
+}
+
+/// 管理 API 返回的错误响应
+#[derive(Debug, thiserror::Error)]
+#[error("Admin API returned {}: {message}", status.as_u16())]
+pub struct AdminApiStatusError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+/// 添加的密钥已经保存过
+#[derive(Debug, thiserror::Error)]
+#[error("API key already exists")]
+pub struct DuplicateApiKey;
+
+/// 本地操作记入审计日志的调用方，例如 `cli@deploy`
+fn local_actor() -> String {
+    let user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "unknown".to_string());
+    format!("cli@{}", user)
+}
+
+/// 管理 API 的客户端
+pub struct AdminClient {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+impl AdminClient {
+    /// 调用管理 API，错误响应转换为 `{"error": {...}}` 中的消息
+    async fn request(&self, method: Method, path: &str, body: Option<Value>) -> Result<Value> {
+        let mut request = self.client
+            .request(method, format!("{}{}", self.base_url, path))
+            .header(ADMIN_TOKEN_HEADER, &self.token);
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        let response = request.send().await
+            .with_context(|| format!("Failed to reach admin API at {}", self.base_url))?;
+
+        let status = response.status();
+        if status == StatusCode::NO_CONTENT {
+            return Ok(Value::Null);
+        }
+        let body: Value = response.json().await.unwrap_or(Value::Null);
+        if !status.is_success() {
+            let message = body
+                .pointer("/error/message")
+                .and_then(Value::as_str)
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or("request failed"))
+                .to_string();
+            return Err(AdminApiStatusError { status, message }.into());
+        }
+        Ok(body)
+    }
+}
+
+/// 命令行工具的操作对象：直接打开 SQLite 数据库，或者通过管理 API 操作运行中的实例
+pub enum Backend {
+    Local(SqlitePool),
+    Remote(AdminClient),
+}
+
+impl Backend {
+    pub async fn local(database: Option<&Path>) -> Result<Self> {
+        let pool = init_database(database).await.context("Failed to open database")?;
+        Ok(Self::Local(pool))
+    }
+
+    pub fn remote(base_url: &str, token: Option<String>) -> Result<Self> {
+        let token = token.ok_or_else(|| anyhow!("--admin-token (or TJIMI_ADMIN_TOKEN) is required with --url"))?;
+        Ok(Self::Remote(AdminClient {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token,
+        }))
+    }
+
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Local(_))
+    }
+
+    pub async fn close(self) {
+        if let Self::Local(pool) = self {
+            pool.close().await;
+        }
+    }
+
+    async fn record_audit(&self, action: &str, target: Option<&str>, before: Option<Value>, after: Option<Value>) {
+        let Self::Local(pool) = self else {
+            // 远程模式由管理 API 记录
+            return;
+        };
+        let result = AuditService::new(pool.clone())
+            .record(AuditChannel::Cli, None, Some(&local_actor()), action, target, before.as_ref(), after.as_ref())
+            .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to record audit event for {}: {}", action, e);
+        }
+    }
+
+    // ---- API 密钥 ----
+    // 与管理 API 一样只输出脱敏后的密钥
+
+    pub async fn list_api_keys(&self) -> Result<Vec<Value>> {
+        match self {
+            Self::Local(pool) => ApiKeyService::new(pool.clone())
+                .get_all_api_keys()
+                .await?
+                .into_iter()
+                .map(|api_key| to_json(api_key.masked()))
+                .collect(),
+            Self::Remote(api) => {
+                let mut keys = Vec::new();
+                let mut page = 1;
+                loop {
+                    let body = api
+                        .request(Method::GET, &format!("/admin/api-keys?page={}&perPage={}", page, REMOTE_KEYS_PER_PAGE), None)
+                        .await?;
+                    if let Some(items) = body["items"].as_array() {
+                        keys.extend(items.iter().cloned());
+                    }
+                    if page >= body["totalPages"].as_u64().unwrap_or(0) {
+                        break;
+                    }
+                    page += 1;
+                }
+                Ok(keys)
+            }
+        }
+    }
+
+    /// `request` 的格式与管理 API 的 `POST /admin/api-keys` 相同，密钥已经保存过时返回 [`DuplicateApiKey`]
+    pub async fn create_api_key(&self, request: Value) -> Result<Value> {
+        match self {
+            Self::Local(pool) => {
+                let api_key_service = ApiKeyService::new(pool.clone());
+                let request: CreateApiKeyRequest = serde_json::from_value(request)?;
+                if api_key_service.key_value_exists(request.key_value.trim()).await? {
+                    return Err(DuplicateApiKey.into());
+                }
+                let api_key = api_key_service.create_api_key(request).await?;
+                self.record_audit("create_api_key", Some(&api_key.id.to_string()), None, snapshot(&api_key)).await;
+                to_json(api_key.masked())
+            }
+            Self::Remote(api) => api
+                .request(Method::POST, "/admin/api-keys", Some(request))
+                .await
+                .map_err(|e| match e.downcast_ref::<AdminApiStatusError>() {
+                    Some(error) if error.status == StatusCode::CONFLICT => DuplicateApiKey.into(),
+                    _ => e,
+                }),
+        }
+    }
+
+    pub async fn set_api_key_active(&self, key_id: &str, is_active: bool) -> Result<Value> {
+        let key_uuid = parse_id(key_id)?;
+        let request = json!({ "isActive": is_active });
+        match self {
+            Self::Local(pool) => {
+                let api_key_service = ApiKeyService::new(pool.clone());
+                let before = api_key_service.get_api_key_by_id(key_uuid).await?;
+                let api_key = api_key_service
+                    .update_api_key(key_uuid, serde_json::from_value(request)?)
+                    .await?
+                    .ok_or_else(|| anyhow!("API key not found"))?;
+                self.record_audit("update_api_key", Some(key_id), before.as_ref().and_then(snapshot), snapshot(&api_key)).await;
+                to_json(api_key.masked())
+            }
+            Self::Remote(api) => api.request(Method::PATCH, &format!("/admin/api-keys/{}", key_uuid), Some(request)).await,
+        }
+    }
+
+    // ---- 请求日志 ----
+
+    /// 按时间倒序的一页日志和总页数
+    pub async fn request_logs(&self, page: u32, per_page: u32) -> Result<(Vec<Value>, u32)> {
+        match self {
+            Self::Local(pool) => {
+                let (logs, total_count) = RequestLogService::new(pool.clone()).paginated(page, per_page).await?;
+                let logs = logs.into_iter().map(to_json).collect::<Result<Vec<_>>>()?;
+                Ok((logs, total_count.div_ceil(per_page)))
+            }
+            Self::Remote(api) => {
+                let body = api
+                    .request(Method::GET, &format!("/admin/logs?page={}&perPage={}", page, per_page), None)
+                    .await?;
+                let logs = body["items"].as_array().cloned().unwrap_or_default();
+                Ok((logs, body["totalPages"].as_u64().unwrap_or(0) as u32))
+            }
+        }
+    }
+
+    // ---- 设置 ----
+
+    pub async fn get_setting(&self, name: &str) -> Result<Value> {
+        check_setting_name(name)?;
+        match self {
+            Self::Local(pool) => to_json(SettingsService::new(pool.clone()).get_named(name).await?),
+            Self::Remote(api) => api.request(Method::GET, &format!("/admin/settings/{}", name), None).await,
+        }
+    }
+
+    pub async fn get_all_settings(&self) -> Result<Value> {
+        match self {
+            Self::Local(_) => {
+                let mut settings = serde_json::Map::new();
+                for name in SETTING_NAMES {
+                    settings.insert(name.to_string(), self.get_setting(name).await?);
+                }
+                Ok(Value::Object(settings))
+            }
+            Self::Remote(api) => api.request(Method::GET, "/admin/settings", None).await,
+        }
+    }
+
+    /// 保存设置并返回保存后的值；本地模式下监听地址和 HTTPS 的修改在运行中的实例重启后生效
+    pub async fn set_setting(&self, name: &str, value: Value) -> Result<Value> {
+        check_setting_name(name)?;
+        match self {
+            Self::Local(pool) => {
+                let value = SettingValue::from_json(name, value)?;
+                if let SettingValue::Tls(settings) = &value
+                    && settings.enabled
+                {
+                    TlsService::new(pool.clone()).load_certificate(settings).await?;
+                }
+
+                let action = value.audit_action();
+                let before = self.get_setting(name).await.ok();
+                SettingsService::new(pool.clone()).set_named(value).await?;
+                let after = self.get_setting(name).await?;
+                self.record_audit(action, None, before, Some(after.clone())).await;
+                Ok(after)
+            }
+            Self::Remote(api) => api.request(Method::PUT, &format!("/admin/settings/{}", name), Some(value)).await,
+        }
+    }
+
+    // ---- 客户端令牌 ----
+
+    /// `request` 的格式与管理 API 的 `POST /admin/client-tokens` 相同，返回的明文只出现这一次
+    pub async fn create_client_token(&self, request: Value) -> Result<Value> {
+        match self {
+            Self::Local(pool) => {
+                let issued = ClientTokenService::new(pool.clone())
+                    .create_token(serde_json::from_value(request)?)
+                    .await?;
+                self.record_audit("create_client_token", Some(&issued.token.id.to_string()), None, snapshot(&issued.token)).await;
+                to_json(issued)
+            }
+            Self::Remote(api) => api.request(Method::POST, "/admin/client-tokens", Some(request)).await,
+        }
+    }
+
+    // ---- 全局自定义验证密钥 ----
+
+    pub async fn has_custom_auth_key(&self) -> Result<bool> {
+        match self {
+            Self::Local(pool) => CustomAuthService::new(pool.clone()).has_custom_key().await,
+            Self::Remote(api) => {
+                let body = api.request(Method::GET, "/admin/custom-auth-key", None).await?;
+                Ok(body["hasCustomKey"].as_bool().unwrap_or(false))
+            }
+        }
+    }
+
+    /// 设置全局验证密钥，key 为空时停用
+    pub async fn set_custom_auth_key(&self, key: Option<&str>) -> Result<()> {
+        match self {
+            Self::Local(pool) => {
+                let service = CustomAuthService::new(pool.clone());
+                let before = service.audit_snapshot().await;
+                let action = match key {
+                    Some(key) => {
+                        service.set_custom_key(key).await?;
+                        "set_custom_auth_key"
+                    }
+                    None => {
+                        service.disable_custom_key().await?;
+                        "reset_custom_auth_key"
+                    }
+                };
+                self.record_audit(action, None, before, service.audit_snapshot().await).await;
+                Ok(())
+            }
+            Self::Remote(api) => {
+                match key {
+                    Some(key) => api.request(Method::PUT, "/admin/custom-auth-key", Some(json!({ "key": key }))).await?,
+                    None => api.request(Method::DELETE, "/admin/custom-auth-key", None).await?,
+                };
+                Ok(())
+            }
+        }
+    }
+}
@@ -0,0 +1,536 @@
+pub mod backend;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+use crate::utils::csv_field;
+use backend::{Backend, DuplicateApiKey, MAX_LOGS_PER_PAGE};
+
+/// 命令行参数；指定 `--url` 时通过管理 API 操作运行中的实例，否则直接打开数据库
+#[derive(Debug, Parser)]
+#[command(name = "tjimi-cli", version, about = "Manage the Gemini proxy from scripts")]
+pub struct CliArgs {
+    /// SQLite 数据库文件，未指定时与桌面应用使用同一个数据库
+    #[arg(long, env = "TJIMI_DATABASE", global = true)]
+    database: Option<PathBuf>,
+    /// 运行中实例的地址，例如 `http://127.0.0.1:5675`，优先于 `--database`
+    #[arg(long, env = "TJIMI_URL", global = true)]
+    url: Option<String>,
+    /// 管理 API 令牌，使用 `--url` 时需要
+    #[arg(long, env = "TJIMI_ADMIN_TOKEN", hide_env_values = true, global = true)]
+    admin_token: Option<String>,
+    /// 以 JSON 输出结果，`logs tail` 每行输出一条 JSON
+    #[arg(long, global = true)]
+    json: bool,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// 上游 API 密钥
+    #[command(subcommand)]
+    Keys(KeysCommand),
+    /// 请求日志
+    #[command(subcommand)]
+    Logs(LogsCommand),
+    /// 按名称读写设置
+    #[command(subcommand)]
+    Settings(SettingsCommand),
+    /// 客户端令牌
+    #[command(subcommand)]
+    Tokens(TokensCommand),
+    /// 全局自定义验证密钥
+    #[command(subcommand)]
+    AuthKey(AuthKeyCommand),
+}
+
+#[derive(Debug, Subcommand)]
+enum KeysCommand {
+    List,
+    /// 添加密钥，类型根据内容自动识别
+    Add {
+        name: String,
+        /// 密钥内容，省略时从标准输入读取，避免出现在进程列表中
+        key: Option<String>,
+    },
+    Disable { id: String },
+    Enable { id: String },
+    /// 批量导入，每行一个密钥或 `名称,密钥`，跳过已存在的密钥
+    Import {
+        /// 导入文件，`-` 表示标准输入
+        file: PathBuf,
+        /// 没有名称的密钥命名为 `前缀-序号`
+        #[arg(long, default_value = "imported")]
+        name_prefix: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+enum LogExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Debug, Subcommand)]
+enum LogsCommand {
+    /// 显示最近的日志
+    Tail {
+        #[arg(short = 'n', long, default_value_t = 20)]
+        lines: u32,
+        /// 持续输出新的日志，直到按下 Ctrl-C
+        #[arg(short, long)]
+        follow: bool,
+        /// 持续输出时的查询间隔秒数
+        #[arg(long, default_value_t = 2)]
+        interval: u64,
+    },
+    /// 按时间倒序导出日志
+    Export {
+        #[arg(long, value_enum, default_value_t)]
+        format: LogExportFormat,
+        /// 输出文件，省略时写到标准输出
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// 最多导出的条数
+        #[arg(long)]
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum SettingsCommand {
+    /// 读取一项设置，省略名称时输出全部设置
+    Get { name: Option<String> },
+    /// 保存一项设置
+    Set {
+        name: String,
+        /// JSON 格式的值，与 `settings get` 的输出相同；不是合法 JSON 时按字符串处理
+        value: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum TokensCommand {
+    /// 创建客户端令牌，明文只输出这一次
+    Create {
+        name: String,
+        /// 过期时间（RFC 3339），省略时不过期
+        #[arg(long)]
+        expires_at: Option<DateTime<Utc>>,
+        /// 权限范围（JSON），格式与管理界面相同
+        #[arg(long)]
+        scopes: Option<String>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum AuthKeyCommand {
+    Status,
+    /// 设置密钥，省略时从标准输入读取
+    Set { key: Option<String> },
+    /// 停用全局密钥，之后只接受客户端令牌
+    Reset,
+}
+
+fn print_json(value: &Value) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// 参数中没有给出密钥时从标准输入读取一行
+fn secret_or_stdin(value: Option<String>, what: &str) -> Result<String> {
+    let value = match value {
+        Some(value) => value,
+        None => {
+            let mut line = String::new();
+            std::io::stdin().lock().read_line(&mut line)?;
+            line
+        }
+    };
+    let value = value.trim().to_string();
+    if value.is_empty() {
+        bail!("No {} given", what);
+    }
+    Ok(value)
+}
+
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn print_api_key_table(keys: &[Value]) {
+    println!("{:<36}  {:<24}  {:<8}  {:>8}  KEY", "ID", "NAME", "ACTIVE", "USAGE");
+    for key in keys {
+        println!(
+            "{:<36}  {:<24}  {:<8}  {:>8}  {}",
+            text(&key["id"]),
+            text(&key["name"]),
+            text(&key["isActive"]),
+            text(&key["usageCount"]),
+            text(&key["keyValue"]),
+        );
+    }
+}
+
+fn format_log_line(log: &Value) -> String {
+    let mut flags = String::new();
+    if log["cacheHit"].as_bool().unwrap_or(false) {
+        flags.push_str(" [cache]");
+    }
+    if log["coalesced"].as_bool().unwrap_or(false) {
+        flags.push_str(" [coalesced]");
+    }
+    format!(
+        "{} {} {} {} {}ms key={} client={}{}",
+        text(&log["createdAt"]),
+        text(&log["statusCode"]),
+        text(&log["method"]),
+        text(&log["path"]),
+        text(&log["responseTimeMs"]),
+        text(&log["apiKeyName"]),
+        text(&log["clientTokenName"]),
+        flags,
+    )
+}
+
+fn print_log(log: &Value, as_json: bool) -> Result<()> {
+    if as_json {
+        println!("{}", serde_json::to_string(log)?);
+    } else {
+        println!("{}", format_log_line(log));
+    }
+    Ok(())
+}
+
+const LOG_CSV_COLUMNS: &[(&str, &str)] = &[
+    ("id", "id"),
+    ("created_at", "createdAt"),
+    ("api_key_name", "apiKeyName"),
+    ("client_token_name", "clientTokenName"),
+    ("method", "method"),
+    ("path", "path"),
+    ("status_code", "statusCode"),
+    ("response_time_ms", "responseTimeMs"),
+    ("total_tokens", "totalTokens"),
+    ("cache_hit", "cacheHit"),
+    ("coalesced", "coalesced"),
+    ("fallback_from", "fallbackFrom"),
+];
+
+fn logs_to_csv(logs: &[Value]) -> String {
+    let header: Vec<&str> = LOG_CSV_COLUMNS.iter().map(|(column, _)| *column).collect();
+    let mut csv = header.join(",");
+    csv.push('\n');
+    for log in logs {
+        let row: Vec<String> = LOG_CSV_COLUMNS
+            .iter()
+            .map(|(_, field)| match &log[*field] {
+                Value::Null => String::new(),
+                value => csv_field(&text(value)),
+            })
+            .collect();
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+async fn run_keys(backend: &Backend, command: KeysCommand, as_json: bool) -> Result<()> {
+    match command {
+        KeysCommand::List => {
+            let keys = backend.list_api_keys().await?;
+            if as_json {
+                print_json(&Value::Array(keys))?;
+            } else {
+                print_api_key_table(&keys);
+            }
+        }
+        KeysCommand::Add { name, key } => {
+            let key_value = secret_or_stdin(key, "API key")?;
+            let api_key = backend.create_api_key(json!({ "name": name, "keyValue": key_value })).await?;
+            if as_json {
+                print_json(&api_key)?;
+            } else {
+                println!("Added API key {} ({})", text(&api_key["id"]), text(&api_key["name"]));
+            }
+        }
+        KeysCommand::Disable { id } => {
+            let api_key = backend.set_api_key_active(&id, false).await?;
+            if as_json {
+                print_json(&api_key)?;
+            } else {
+                println!("Disabled API key {} ({})", text(&api_key["id"]), text(&api_key["name"]));
+            }
+        }
+        KeysCommand::Enable { id } => {
+            let api_key = backend.set_api_key_active(&id, true).await?;
+            if as_json {
+                print_json(&api_key)?;
+            } else {
+                println!("Enabled API key {} ({})", text(&api_key["id"]), text(&api_key["name"]));
+            }
+        }
+        KeysCommand::Import { file, name_prefix } => {
+            let content = if file.as_os_str() == "-" {
+                let mut content = String::new();
+                std::io::stdin().read_to_string(&mut content)?;
+                content
+            } else {
+                std::fs::read_to_string(&file).with_context(|| format!("Failed to read {}", file.display()))?
+            };
+
+            // 已经保存过的密钥由添加时的重复检查跳过，这里只去掉文件内的重复
+            let mut known = HashSet::new();
+            let mut added = Vec::new();
+            let mut skipped = 0;
+            let mut failed = Vec::new();
+
+            for (index, line) in content.lines().enumerate() {
+                let line = line.trim();
+                if line.is_empty() || line.starts_with('#') {
+                    continue;
+                }
+                let (name, key_value) = match line.split_once(',') {
+                    Some((name, key_value)) => (name.trim().to_string(), key_value.trim().to_string()),
+                    None => (format!("{}-{}", name_prefix, added.len() + failed.len() + skipped + 1), line.to_string()),
+                };
+                if !known.insert(key_value.clone()) {
+                    skipped += 1;
+                    continue;
+                }
+
+                match backend.create_api_key(json!({ "name": name, "keyValue": key_value })).await {
+                    Ok(api_key) => added.push(api_key["id"].clone()),
+                    Err(e) if e.is::<DuplicateApiKey>() => skipped += 1,
+                    Err(e) => failed.push(json!({ "line": index + 1, "error": e.to_string() })),
+                }
+            }
+
+            if as_json {
+                print_json(&json!({ "added": added, "skipped": skipped, "failed": failed }))?;
+            } else {
+                for failure in &failed {
+                    eprintln!("line {}: {}", failure["line"], text(&failure["error"]));
+                }
+                println!("Imported {} keys, skipped {} duplicates, {} failed", added.len(), skipped, failed.len());
+            }
+            if !failed.is_empty() {
+                bail!("{} keys could not be imported", failed.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_logs(backend: &Backend, command: LogsCommand, as_json: bool) -> Result<()> {
+    match command {
+        LogsCommand::Tail { lines, follow, interval } => {
+            let (logs, _) = backend.request_logs(1, lines.clamp(1, MAX_LOGS_PER_PAGE)).await?;
+            // 查询结果按时间倒序，输出时旧的在前
+            for log in logs.iter().rev() {
+                print_log(log, as_json)?;
+            }
+            if !follow {
+                return Ok(());
+            }
+
+            let mut seen: HashSet<String> = logs.iter().map(|log| text(&log["id"])).collect();
+            loop {
+                tokio::time::sleep(Duration::from_secs(interval.max(1))).await;
+                let (logs, _) = backend.request_logs(1, MAX_LOGS_PER_PAGE).await?;
+                for log in logs.iter().rev().filter(|log| !seen.contains(&text(&log["id"]))) {
+                    print_log(log, as_json)?;
+                }
+                std::io::stdout().flush()?;
+                seen = logs.iter().map(|log| text(&log["id"])).collect();
+            }
+        }
+        LogsCommand::Export { format, output, limit } => {
+            let limit = limit.unwrap_or(u32::MAX) as usize;
+            let mut logs = Vec::new();
+            let mut page = 1;
+            while logs.len() < limit {
+                let (items, total_pages) = backend.request_logs(page, MAX_LOGS_PER_PAGE).await?;
+                logs.extend(items);
+                if page >= total_pages {
+                    break;
+                }
+                page += 1;
+            }
+            logs.truncate(limit);
+
+            let content = match format {
+                LogExportFormat::Json => serde_json::to_string_pretty(&logs)? + "\n",
+                LogExportFormat::Csv => logs_to_csv(&logs),
+            };
+            match output {
+                Some(path) => {
+                    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+                    eprintln!("Exported {} logs to {}", logs.len(), path.display());
+                }
+                None => std::io::stdout().write_all(content.as_bytes())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_settings(backend: &Backend, command: SettingsCommand) -> Result<()> {
+    match command {
+        SettingsCommand::Get { name: Some(name) } => print_json(&backend.get_setting(&name).await?),
+        SettingsCommand::Get { name: None } => print_json(&backend.get_all_settings().await?),
+        SettingsCommand::Set { name, value } => {
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            let saved = backend.set_setting(&name, value).await?;
+            if backend.is_local() && (name == "network" || name == "tls") {
+                eprintln!("Saved; a running proxy applies listen address and HTTPS changes after it restarts");
+            }
+            print_json(&saved)
+        }
+    }
+}
+
+async fn run_tokens(backend: &Backend, command: TokensCommand, as_json: bool) -> Result<()> {
+    match command {
+        TokensCommand::Create { name, expires_at, scopes } => {
+            let scopes: Option<Value> = scopes
+                .map(|scopes| serde_json::from_str(&scopes).map_err(|e| anyhow!("Invalid --scopes: {}", e)))
+                .transpose()?;
+            let issued = backend
+                .create_client_token(json!({ "name": name, "expiresAt": expires_at, "scopes": scopes }))
+                .await?;
+            if as_json {
+                print_json(&issued)?;
+            } else {
+                eprintln!("Created client token {} ({}); the secret below is shown only once", text(&issued["token"]["id"]), text(&issued["token"]["name"]));
+                println!("{}", text(&issued["secret"]));
+            }
+        }
+    }
+    Ok(())
+}
+
+async fn run_auth_key(backend: &Backend, command: AuthKeyCommand, as_json: bool) -> Result<()> {
+    let message = match command {
+        AuthKeyCommand::Status => {
+            let has_custom_key = backend.has_custom_auth_key().await?;
+            if as_json {
+                return print_json(&json!({ "hasCustomKey": has_custom_key }));
+            }
+            if has_custom_key { "Auth key is configured" } else { "No auth key configured" }
+        }
+        AuthKeyCommand::Set { key } => {
+            let key = secret_or_stdin(key, "auth key")?;
+            backend.set_custom_auth_key(Some(&key)).await?;
+            "Auth key updated"
+        }
+        AuthKeyCommand::Reset => {
+            backend.set_custom_auth_key(None).await?;
+            "Auth key disabled, only client tokens are accepted"
+        }
+    };
+
+    if as_json {
+        print_json(&json!({ "success": true }))
+    } else {
+        println!("{}", message);
+        Ok(())
+    }
+}
+
+async fn execute(args: CliArgs) -> Result<()> {
+    let backend = match &args.url {
+        Some(url) => Backend::remote(url, args.admin_token)?,
+        None => Backend::local(args.database.as_deref()).await?,
+    };
+
+    let result = match args.command {
+        Command::Keys(command) => run_keys(&backend, command, args.json).await,
+        Command::Logs(command) => run_logs(&backend, command, args.json).await,
+        Command::Settings(command) => run_settings(&backend, command).await,
+        Command::Tokens(command) => run_tokens(&backend, command, args.json).await,
+        Command::AuthKey(command) => run_auth_key(&backend, command, args.json).await,
+    };
+    backend.close().await;
+    result
+}
+
+/// 解析命令行参数并执行一条命令，日志只输出警告以上的级别，避免混入命令的输出
+pub fn run() -> Result<()> {
+    let args = CliArgs::parse();
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("warn")))
+        .init();
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(execute(args))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::CommandFactory;
+
+    #[test]
+    fn arguments_are_consistent() {
+        CliArgs::command().debug_assert();
+    }
+
+    #[test]
+    fn global_options_follow_the_subcommand() {
+        let args = CliArgs::try_parse_from([
+            "tjimi-cli", "tokens", "create", "ci", "--expires-at", "2030-01-01T00:00:00Z", "--json", "--url", "http://127.0.0.1:5675",
+        ])
+        .unwrap();
+        assert!(args.json);
+        assert_eq!(args.url.as_deref(), Some("http://127.0.0.1:5675"));
+        let Command::Tokens(TokensCommand::Create { name, expires_at, scopes }) = args.command else {
+            panic!("expected tokens create");
+        };
+        assert_eq!(name, "ci");
+        assert_eq!(expires_at.unwrap().to_rfc3339(), "2030-01-01T00:00:00+00:00");
+        assert_eq!(scopes, None);
+
+        assert!(CliArgs::try_parse_from(["tjimi-cli", "tokens", "create", "ci", "--expires-at", "tomorrow"]).is_err());
+    }
+
+    #[test]
+    fn logs_export_as_csv() {
+        let logs = [json!({
+            "id": "1",
+            "createdAt": "2026-01-01T00:00:00.000Z",
+            "apiKeyName": "main, backup",
+            "clientTokenName": null,
+            "method": "POST",
+            "path": "/v1beta/models/gemini-2.5-flash:generateContent",
+            "statusCode": 200,
+            "responseTimeMs": 120,
+            "totalTokens": 42,
+            "cacheHit": true,
+            "coalesced": false,
+        })];
+        let csv = logs_to_csv(&logs);
+        let mut lines = csv.lines();
+        assert_eq!(lines.next().unwrap(), LOG_CSV_COLUMNS.iter().map(|(column, _)| *column).collect::<Vec<_>>().join(","));
+        assert_eq!(
+            lines.next().unwrap(),
+            "1,2026-01-01T00:00:00.000Z,\"main, backup\",,POST,/v1beta/models/gemini-2.5-flash:generateContent,200,120,42,true,false,"
+        );
+        assert!(format_log_line(&logs[0]).ends_with("key=main, backup client=- [cache]"));
+    }
+}
@@ -0,0 +1,39 @@
+use crate::models::AdminApiStatus;
+use crate::services::AdminApiService;
+use crate::commands::{require_session, record_audit, snapshot};
+use tauri::State;
+use sqlx::SqlitePool;
+
+async fn status_snapshot(pool: &SqlitePool) -> Option<serde_json::Value> {
+    let status = AdminApiService::new(pool.clone()).status().await.ok()?;
+    snapshot(&status)
+}
+
+#[tauri::command]
+pub async fn get_admin_api_status(session_token: String, pool: State<'_, SqlitePool>) -> Result<AdminApiStatus, String> {
+    require_session(pool.inner(), &session_token).await?;
+    AdminApiService::new(pool.inner().clone()).status().await
+        .map_err(|e| e.to_string())
+}
+
+/// 生成新的管理 API 令牌并启用管理 API，旧令牌立即失效；明文只返回这一次
+#[tauri::command]
+pub async fn rotate_admin_api_token(session_token: String, pool: State<'_, SqlitePool>) -> Result<String, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = status_snapshot(pool.inner()).await;
+    let token = AdminApiService::new(pool.inner().clone()).rotate_token().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "rotate_admin_api_token", None, before, status_snapshot(pool.inner()).await).await;
+    Ok(token)
+}
+
+/// 删除令牌，停用管理 API
+#[tauri::command]
+pub async fn revoke_admin_api_token(session_token: String, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = status_snapshot(pool.inner()).await;
+    AdminApiService::new(pool.inner().clone()).revoke_token().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "revoke_admin_api_token", None, before, status_snapshot(pool.inner()).await).await;
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use crate::models::{CreateApiKeyRequest, UpdateApiKeyRequest, ApiKeyResponse};
 use crate::services::ApiKeyService;
+use crate::commands::{require_session, record_audit, snapshot};
 use serde::Serialize;
 use tauri::State;
 use uuid::Uuid;
@@ -25,17 +26,22 @@ pub struct PaginatedApiKeysResponse {
 
 #[tauri::command]
 pub async fn create_api_key(
+    session_token: String,
     request: CreateApiKeyRequest,
     pool: State<'_, SqlitePool>,
 ) -> Result<ApiKeyResult<ApiKeyResponse>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
     let api_key_service = ApiKeyService::new(pool.inner().clone());
     
     match api_key_service.create_api_key(request).await {
-        Ok(api_key) => Ok(ApiKeyResult {
-            success: true,
-            data: Some(api_key),
-            error: None,
-        }),
+        Ok(api_key) => {
+            record_audit(pool.inner(), Some(&session), "create_api_key", Some(&api_key.id.to_string()), None, snapshot(&api_key)).await;
+            Ok(ApiKeyResult {
+                success: true,
+                data: Some(api_key),
+                error: None,
+            })
+        },
         Err(e) => Ok(ApiKeyResult {
             success: false,
             data: None,
@@ -46,8 +52,10 @@ pub async fn create_api_key(
 
 #[tauri::command]
 pub async fn get_all_api_keys(
+    session_token: String,
     pool: State<'_, SqlitePool>,
 ) -> Result<ApiKeyResult<Vec<ApiKeyResponse>>, String> {
+    require_session(pool.inner(), &session_token).await?;
     let api_key_service = ApiKeyService::new(pool.inner().clone());
     
     match api_key_service.get_all_api_keys().await {
@@ -66,10 +74,12 @@ pub async fn get_all_api_keys(
 
 #[tauri::command]
 pub async fn get_api_keys_paginated(
+    session_token: String,
     page: Option<u32>,
     per_page: Option<u32>,
     pool: State<'_, SqlitePool>,
 ) -> Result<ApiKeyResult<PaginatedApiKeysResponse>, String> {
+    require_session(pool.inner(), &session_token).await?;
     let page = page.unwrap_or(1);
     let per_page = per_page.unwrap_or(20).min(100); // 最多100条
     
@@ -77,7 +87,7 @@ pub async fn get_api_keys_paginated(
     
     match api_key_service.get_api_keys_paginated(page, per_page).await {
         Ok((api_keys, total_count)) => {
-            let total_pages = (total_count + per_page - 1) / per_page;
+            let total_pages = total_count.div_ceil(per_page);
             
             Ok(ApiKeyResult {
                 success: true,
@@ -101,19 +111,25 @@ pub async fn get_api_keys_paginated(
 
 #[tauri::command]
 pub async fn update_api_key(
+    session_token: String,
     keyId: String,
     request: UpdateApiKeyRequest,
     pool: State<'_, SqlitePool>,
 ) -> Result<ApiKeyResult<ApiKeyResponse>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
     let api_key_service = ApiKeyService::new(pool.inner().clone());
     let key_uuid = Uuid::parse_str(&keyId).map_err(|e| e.to_string())?;
+    let before = api_key_service.get_api_key_by_id(key_uuid).await.ok().flatten();
     
     match api_key_service.update_api_key(key_uuid, request).await {
-        Ok(Some(api_key)) => Ok(ApiKeyResult {
-            success: true,
-            data: Some(api_key),
-            error: None,
-        }),
+        Ok(Some(api_key)) => {
+            record_audit(pool.inner(), Some(&session), "update_api_key", Some(&keyId), before.as_ref().and_then(snapshot), snapshot(&api_key)).await;
+            Ok(ApiKeyResult {
+                success: true,
+                data: Some(api_key),
+                error: None,
+            })
+        },
         Ok(None) => Ok(ApiKeyResult {
             success: false,
             data: None,
@@ -129,18 +145,26 @@ pub async fn update_api_key(
 
 #[tauri::command]
 pub async fn delete_api_key(
+    session_token: String,
     keyId: String,
     pool: State<'_, SqlitePool>,
 ) -> Result<ApiKeyResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
     let api_key_service = ApiKeyService::new(pool.inner().clone());
     let key_uuid = Uuid::parse_str(&keyId).map_err(|e| e.to_string())?;
+    let before = api_key_service.get_api_key_by_id(key_uuid).await.ok().flatten();
     
     match api_key_service.delete_api_key(key_uuid).await {
-        Ok(deleted) => Ok(ApiKeyResult {
-            success: true,
-            data: Some(deleted),
-            error: None,
-        }),
+        Ok(deleted) => {
+            if deleted {
+                record_audit(pool.inner(), Some(&session), "delete_api_key", Some(&keyId), before.as_ref().and_then(snapshot), None).await;
+            }
+            Ok(ApiKeyResult {
+                success: true,
+                data: Some(deleted),
+                error: None,
+            })
+        },
         Err(e) => Ok(ApiKeyResult {
             success: false,
             data: None,
@@ -0,0 +1,76 @@
+use crate::models::{AdminSession, AuditChannel, AuditEvent, AuditEventFilter, AuditExportFormat};
+pub(crate) use crate::services::snapshot;
+use crate::services::AuditService;
+use crate::commands::require_session;
+use serde::Serialize;
+use serde_json::Value;
+use tauri::State;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaginatedAuditEventsResponse {
+    pub events: Vec<AuditEvent>,
+    pub total_count: u32,
+    pub page: u32,
+    pub per_page: u32,
+    pub total_pages: u32,
+}
+
+/// 记录一次成功的管理操作；写入失败只记录日志，不影响命令的结果
+pub(crate) async fn record_audit(
+    pool: &SqlitePool,
+    session: Option<&AdminSession>,
+    action: &str,
+    target: Option<&str>,
+    before: Option<Value>,
+    after: Option<Value>,
+) {
+    let result = AuditService::new(pool.clone())
+        .record(AuditChannel::Console, session.map(|s| s.id), None, action, target, before.as_ref(), after.as_ref())
+        .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to record audit event for {}: {}", action, e);
+    }
+}
+
+#[tauri::command]
+pub async fn get_audit_events(
+    session_token: String,
+    filter: Option<AuditEventFilter>,
+    page: Option<u32>,
+    per_page: Option<u32>,
+    pool: State<'_, SqlitePool>,
+) -> Result<PaginatedAuditEventsResponse, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let page = page.unwrap_or(1).max(1);
+    let per_page = per_page.unwrap_or(50).clamp(1, 200); // 最多200条
+
+    let (events, total_count) = AuditService::new(pool.inner().clone())
+        .list(&filter.unwrap_or_default(), page, per_page)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(PaginatedAuditEventsResponse {
+        events,
+        total_count,
+        page,
+        per_page,
+        total_pages: total_count.div_ceil(per_page),
+    })
+}
+
+/// 返回导出文件的内容，由前端保存
+#[tauri::command]
+pub async fn export_audit_events(
+    session_token: String,
+    filter: Option<AuditEventFilter>,
+    format: Option<AuditExportFormat>,
+    pool: State<'_, SqlitePool>,
+) -> Result<String, String> {
+    require_session(pool.inner(), &session_token).await?;
+    AuditService::new(pool.inner().clone())
+        .export(&filter.unwrap_or_default(), format.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
@@ -1,5 +1,6 @@
-use crate::models::{LoginRequest, LoginResponse, ChangePasswordRequest};
-use crate::services::AuthService;
+use crate::models::{AdminSession, AuthScope, BruteForceSettings, LoginOutcome, LoginRequest, LoginResponse, ChangePasswordRequest};
+use crate::services::{AdminSessionService, AuthGuardService, AuthLockedError, AuthService, ErrorLoggerService, SettingsService, LOCAL_SOURCE};
+use crate::commands::record_audit;
 use serde::Serialize;
 use tauri::State;
 use sqlx::SqlitePool;
@@ -12,24 +13,97 @@ pub struct AuthResult<T> {
     pub error: Option<String>,
 }
 
+// 会话校验失败时返回给前端的错误前缀，前端据此跳转登录页或修改密码
+pub const UNAUTHENTICATED: &str = "UNAUTHENTICATED";
+pub const PASSWORD_CHANGE_REQUIRED: &str = "PASSWORD_CHANGE_REQUIRED";
+// 登录需要第二因素时的错误前缀，前端据此显示验证码输入框
+pub const TOTP_REQUIRED: &str = "TOTP_REQUIRED";
+
+async fn validate_session(pool: &SqlitePool, session_token: &str) -> Result<AdminSession, String> {
+    AdminSessionService::new(pool.clone())
+        .validate(session_token)
+        .await
+        .map_err(|e| e.to_string())?
+        .ok_or_else(|| format!("{}: 会话无效或已过期，请重新登录", UNAUTHENTICATED))
+}
+
+/// 校验管理会话，所有管理命令执行前调用；仍在使用默认密码时拒绝
+pub async fn require_session(pool: &SqlitePool, session_token: &str) -> Result<AdminSession, String> {
+    let session = validate_session(pool, session_token).await?;
+    if session.password_change_required {
+        return Err(format!("{}: 仍在使用默认密码，请先修改密码", PASSWORD_CHANGE_REQUIRED));
+    }
+    Ok(session)
+}
+
+fn locked_out_message(locked: &AuthLockedError) -> String {
+    format!("密码错误次数过多，请在 {} 秒后重试", locked.retry_after.as_secs().max(1))
+}
+
+/// 管理密码验证失败后计数，触发锁定时写入日志
+pub(crate) async fn record_login_failure(pool: &SqlitePool, settings: &BruteForceSettings) {
+    let error_logger = ErrorLoggerService::new(pool.clone());
+    for locked in AuthGuardService::new().record_failure(AuthScope::AdminLogin, LOCAL_SOURCE, settings) {
+        tracing::warn!("{}", locked);
+        if let Err(e) = error_logger.log_auth_error("INVOKE", "login", &locked.to_string(), 429, None).await {
+            tracing::warn!("Failed to log auth lockout: {}", e);
+        }
+    }
+}
+
 #[tauri::command]
 pub async fn login(
     request: LoginRequest,
     pool: State<'_, SqlitePool>,
 ) -> Result<AuthResult<LoginResponse>, String> {
+    let guard_settings = SettingsService::new(pool.inner().clone()).get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    if let Err(locked) = auth_guard.check(AuthScope::AdminLogin, LOCAL_SOURCE, &guard_settings) {
+        return Ok(AuthResult {
+            success: false,
+            data: None,
+            error: Some(locked_out_message(&locked)),
+        });
+    }
+
     let auth_service = AuthService::new(pool.inner().clone());
     
     match auth_service.login(request).await {
-        Ok(Some(login_response)) => Ok(AuthResult {
-            success: true,
-            data: Some(login_response),
-            error: None,
-        }),
-        Ok(None) => Ok(AuthResult {
+        Ok(LoginOutcome::Success(login_response)) => {
+            auth_guard.record_success(AuthScope::AdminLogin, LOCAL_SOURCE);
+            let session = AdminSessionService::new(pool.inner().clone())
+                .validate(&login_response.session_token)
+                .await
+                .ok()
+                .flatten();
+            record_audit(pool.inner(), session.as_ref(), "login", None, None, None).await;
+            Ok(AuthResult {
+                success: true,
+                data: Some(login_response),
+                error: None,
+            })
+        }
+        Ok(LoginOutcome::SecondFactorRequired) => Ok(AuthResult {
             success: false,
             data: None,
-            error: Some("密码错误".to_string()),
+            error: Some(format!("{}: 请输入验证器应用中的动态验证码或恢复码", TOTP_REQUIRED)),
         }),
+        Ok(LoginOutcome::InvalidPassword) => {
+            record_login_failure(pool.inner(), &guard_settings).await;
+            Ok(AuthResult {
+                success: false,
+                data: None,
+                error: Some("密码错误".to_string()),
+            })
+        }
+        Ok(LoginOutcome::InvalidSecondFactor) => {
+            record_login_failure(pool.inner(), &guard_settings).await;
+            Ok(AuthResult {
+                success: false,
+                data: None,
+                error: Some(format!("{}: 验证码错误或已使用过", TOTP_REQUIRED)),
+            })
+        }
         Err(e) => Ok(AuthResult {
             success: false,
             data: None,
@@ -40,22 +114,48 @@ pub async fn login(
 
 #[tauri::command]
 pub async fn change_password(
+    session_token: String,
     request: ChangePasswordRequest,
     pool: State<'_, SqlitePool>,
 ) -> Result<AuthResult<bool>, String> {
+    // 修改密码本身不受默认密码限制
+    let session = validate_session(pool.inner(), &session_token).await?;
+
+    // 当前密码同样受失败次数限制，避免持有会话的人猜测管理密码
+    let guard_settings = SettingsService::new(pool.inner().clone()).get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    if let Err(locked) = auth_guard.check(AuthScope::AdminLogin, LOCAL_SOURCE, &guard_settings) {
+        return Ok(AuthResult {
+            success: false,
+            data: None,
+            error: Some(locked_out_message(&locked)),
+        });
+    }
+
     let auth_service = AuthService::new(pool.inner().clone());
     
     match auth_service.change_password(request).await {
-        Ok(true) => Ok(AuthResult {
-            success: true,
-            data: Some(true),
-            error: None,
-        }),
-        Ok(false) => Ok(AuthResult {
-            success: false,
-            data: Some(false),
-            error: Some("当前密码错误".to_string()),
-        }),
+        Ok(true) => {
+            auth_guard.record_success(AuthScope::AdminLogin, LOCAL_SOURCE);
+            AdminSessionService::new(pool.inner().clone())
+                .password_changed(session.id)
+                .await
+                .map_err(|e| e.to_string())?;
+            record_audit(pool.inner(), Some(&session), "change_password", None, None, None).await;
+            Ok(AuthResult {
+                success: true,
+                data: Some(true),
+                error: None,
+            })
+        }
+        Ok(false) => {
+            record_login_failure(pool.inner(), &guard_settings).await;
+            Ok(AuthResult {
+                success: false,
+                data: Some(false),
+                error: Some("当前密码错误".to_string()),
+            })
+        }
         Err(e) => Ok(AuthResult {
             success: false,
             data: None,
@@ -66,8 +166,10 @@ pub async fn change_password(
 
 #[tauri::command]
 pub async fn check_default_password(
+    session_token: String,
     pool: State<'_, SqlitePool>,
 ) -> Result<AuthResult<bool>, String> {
+    validate_session(pool.inner(), &session_token).await?;
     let auth_service = AuthService::new(pool.inner().clone());
     
     match auth_service.get_default_password_info().await {
@@ -82,4 +184,44 @@ pub async fn check_default_password(
             error: Some(e.to_string()),
         }),
     }
-}
\ No newline at end of file
+}
+
+/// 当前会话的信息，前端启动时用于确认保存的令牌仍然有效
+#[tauri::command]
+pub async fn get_current_session(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<AuthResult<AdminSession>, String> {
+    let session = validate_session(pool.inner(), &session_token).await?;
+    Ok(AuthResult {
+        success: true,
+        data: Some(session),
+        error: None,
+    })
+}
+
+#[tauri::command]
+pub async fn logout(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<AuthResult<bool>, String> {
+    let session_service = AdminSessionService::new(pool.inner().clone());
+    let session = session_service.validate(&session_token).await.ok().flatten();
+    match session_service.revoke(&session_token).await {
+        Ok(()) => {
+            if session.is_some() {
+                record_audit(pool.inner(), session.as_ref(), "logout", None, None, None).await;
+            }
+            Ok(AuthResult {
+                success: true,
+                data: Some(true),
+                error: None,
+            })
+        }
+        Err(e) => Ok(AuthResult {
+            success: false,
+            data: None,
+            error: Some(e.to_string()),
+        }),
+    }
+}
@@ -0,0 +1,28 @@
+use crate::models::{AuthLockout, AuthScope};
+use crate::services::AuthGuardService;
+use crate::commands::{require_session, record_audit};
+use tauri::State;
+use sqlx::SqlitePool;
+
+/// 当前因认证失败过多被锁定的来源
+#[tauri::command]
+pub async fn get_auth_lockouts(session_token: String, pool: State<'_, SqlitePool>) -> Result<Vec<AuthLockout>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    Ok(AuthGuardService::new().lockouts())
+}
+
+/// 解除锁定并清除失败记录，不指定 scope 和 source 时清除全部，返回清除的条目数
+#[tauri::command]
+pub async fn clear_auth_lockouts(
+    session_token: String,
+    scope: Option<AuthScope>,
+    source: Option<String>,
+    pool: State<'_, SqlitePool>,
+) -> Result<usize, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let cleared = AuthGuardService::new().clear(scope, source.as_deref());
+    tracing::info!("Cleared {} auth lockout record(s)", cleared);
+    let after = serde_json::json!({ "scope": scope, "source": source, "cleared": cleared });
+    record_audit(pool.inner(), Some(&session), "clear_auth_lockouts", None, None, Some(after)).await;
+    Ok(cleared)
+}
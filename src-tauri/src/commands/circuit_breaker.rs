@@ -0,0 +1,21 @@
+use crate::services::{CircuitBreakerService, CircuitBreakerStatus, SettingsService};
+use crate::commands::{require_session, record_audit};
+use tauri::State;
+use sqlx::SqlitePool;
+
+#[tauri::command]
+pub async fn get_circuit_breaker_status(session_token: String, pool: State<'_, SqlitePool>) -> Result<CircuitBreakerStatus, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let breaker_settings = settings_service.get_circuit_breaker_settings().await
+        .map_err(|e| e.to_string())?;
+    Ok(CircuitBreakerService::new().status(&breaker_settings))
+}
+
+#[tauri::command]
+pub async fn reset_circuit_breakers(session_token: String, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    CircuitBreakerService::new().reset();
+    record_audit(pool.inner(), Some(&session), "reset_circuit_breakers", None, None, None).await;
+    Ok(())
+}
\ No newline at end of file
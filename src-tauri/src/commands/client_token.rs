@@ -0,0 +1,142 @@
+use crate::models::{ClientScopes, ClientToken, ClientTokenUsage, CreateClientTokenRequest, IssuedClientToken};
+use crate::services::ClientTokenService;
+use crate::commands::{require_session, record_audit, snapshot};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientTokenResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ClientTokenResult<T> {
+    fn from_result(result: anyhow::Result<T>) -> Self {
+        match result {
+            Ok(data) => ClientTokenResult {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => ClientTokenResult {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    fn not_found() -> Self {
+        ClientTokenResult {
+            success: false,
+            data: None,
+            error: Some("Client token not found".to_string()),
+        }
+    }
+}
+
+/// 创建令牌，返回的明文只显示这一次
+#[tauri::command]
+pub async fn create_client_token(
+    session_token: String,
+    request: CreateClientTokenRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<IssuedClientToken>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    let result = token_service.create_token(request).await;
+    if let Ok(issued) = &result {
+        record_audit(pool.inner(), Some(&session), "create_client_token", Some(&issued.token.id.to_string()), None, snapshot(&issued.token)).await;
+    }
+    Ok(ClientTokenResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn get_all_client_tokens(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<Vec<ClientToken>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    Ok(ClientTokenResult::from_result(token_service.get_all_tokens().await))
+}
+
+#[tauri::command]
+pub async fn revoke_client_token(
+    session_token: String,
+    token_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<ClientToken>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    let token_uuid = Uuid::parse_str(&token_id).map_err(|e| e.to_string())?;
+    let before = token_service.get_token_by_id(token_uuid).await.ok().flatten();
+
+    match token_service.revoke_token(token_uuid).await {
+        Ok(Some(token)) => {
+            record_audit(pool.inner(), Some(&session), "revoke_client_token", Some(&token_id), before.as_ref().and_then(snapshot), snapshot(&token)).await;
+            Ok(ClientTokenResult::from_result(Ok(token)))
+        }
+        Ok(None) => Ok(ClientTokenResult::not_found()),
+        Err(e) => Ok(ClientTokenResult::from_result(Err(e))),
+    }
+}
+
+/// 轮换令牌，返回的新明文只显示这一次
+#[tauri::command]
+pub async fn rotate_client_token(
+    session_token: String,
+    token_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<IssuedClientToken>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    let token_uuid = Uuid::parse_str(&token_id).map_err(|e| e.to_string())?;
+    let before = token_service.get_token_by_id(token_uuid).await.ok().flatten();
+
+    match token_service.rotate_token(token_uuid).await {
+        Ok(Some(issued)) => {
+            record_audit(pool.inner(), Some(&session), "rotate_client_token", Some(&token_id), before.as_ref().and_then(snapshot), snapshot(&issued.token)).await;
+            Ok(ClientTokenResult::from_result(Ok(issued)))
+        }
+        Ok(None) => Ok(ClientTokenResult::not_found()),
+        Err(e) => Ok(ClientTokenResult::from_result(Err(e))),
+    }
+}
+
+#[tauri::command]
+pub async fn update_client_token_scopes(
+    session_token: String,
+    token_id: String,
+    scopes: ClientScopes,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<ClientToken>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    let token_uuid = Uuid::parse_str(&token_id).map_err(|e| e.to_string())?;
+    let before = token_service.get_token_by_id(token_uuid).await.ok().flatten();
+
+    match token_service.update_scopes(token_uuid, scopes).await {
+        Ok(Some(token)) => {
+            record_audit(pool.inner(), Some(&session), "update_client_token_scopes", Some(&token_id), before.as_ref().and_then(snapshot), snapshot(&token)).await;
+            Ok(ClientTokenResult::from_result(Ok(token)))
+        }
+        Ok(None) => Ok(ClientTokenResult::not_found()),
+        Err(e) => Ok(ClientTokenResult::from_result(Err(e))),
+    }
+}
+
+/// 各客户端令牌在当前自然日和自然月内的用量
+#[tauri::command]
+pub async fn get_client_token_usage(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ClientTokenResult<Vec<ClientTokenUsage>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let token_service = ClientTokenService::new(pool.inner().clone());
+    Ok(ClientTokenResult::from_result(token_service.get_all_usage().await))
+}
@@ -1,42 +1,65 @@
 use tauri::State;
+use sqlx::SqlitePool;
 use crate::services::CustomAuthService;
+use crate::commands::{require_session, record_audit};
 
 #[tauri::command]
 pub async fn set_custom_auth_key(
+    session_token: String,
     service: State<'_, CustomAuthService>,
     key: String,
+    pool: State<'_, SqlitePool>,
 ) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = service.audit_snapshot().await;
     service
         .set_custom_key(&key)
         .await
-        .map_err(|e| format!("Failed to set custom auth key: {}", e))
+        .map_err(|e| format!("Failed to set custom auth key: {}", e))?;
+    record_audit(pool.inner(), Some(&session), "set_custom_auth_key", None, before, service.audit_snapshot().await).await;
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn reset_custom_auth_key(
+    session_token: String,
     service: State<'_, CustomAuthService>,
+    pool: State<'_, SqlitePool>,
 ) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = service.audit_snapshot().await;
     service
-        .reset_to_default_key()
+        .disable_custom_key()
         .await
-        .map_err(|e| format!("Failed to reset custom auth key: {}", e))
+        .map_err(|e| format!("Failed to reset custom auth key: {}", e))?;
+    record_audit(pool.inner(), Some(&session), "reset_custom_auth_key", None, before, service.audit_snapshot().await).await;
+    Ok(())
 }
 
-// 保留原命令用于兼容，但现在重置为默认密钥
+// 保留原命令用于兼容，与 reset_custom_auth_key 相同
 #[tauri::command]
 pub async fn clear_custom_auth_key(
+    session_token: String,
     service: State<'_, CustomAuthService>,
+    pool: State<'_, SqlitePool>,
 ) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = service.audit_snapshot().await;
     service
-        .clear_custom_key()
+        .disable_custom_key()
         .await
-        .map_err(|e| format!("Failed to clear custom auth key: {}", e))
+        .map_err(|e| format!("Failed to clear custom auth key: {}", e))?;
+    record_audit(pool.inner(), Some(&session), "clear_custom_auth_key", None, before, service.audit_snapshot().await).await;
+    Ok(())
 }
 
 #[tauri::command]
 pub async fn has_custom_auth_key(
+    session_token: String,
     service: State<'_, CustomAuthService>,
+    pool: State<'_, SqlitePool>,
 ) -> Result<bool, String> {
+    require_session(pool.inner(), &session_token).await?;
     service
         .has_custom_key()
         .await
@@ -45,9 +68,12 @@ pub async fn has_custom_auth_key(
 
 #[tauri::command]
 pub async fn validate_custom_auth_key(
+    session_token: String,
     service: State<'_, CustomAuthService>,
     key: String,
+    pool: State<'_, SqlitePool>,
 ) -> Result<bool, String> {
+    require_session(pool.inner(), &session_token).await?;
     service
         .validate_custom_key(&key)
         .await
@@ -4,10 +4,34 @@ pub mod logs;
 pub mod custom_auth;
 pub mod window;
 pub mod settings;
+pub mod circuit_breaker;
+pub mod provider;
+pub mod model_alias;
+pub mod policy;
+pub mod script_hook;
+pub mod response_cache;
+pub mod client_token;
+pub mod tls;
+pub mod auth_guard;
+pub mod totp;
+pub mod audit;
+pub mod admin_api;
 
 pub use auth::*;
 pub use api_key::*;
 pub use logs::*;
 pub use custom_auth::*;
 pub use window::*;
-pub use settings::*;
\ No newline at end of file
+pub use settings::*;
+pub use circuit_breaker::*;
+pub use provider::*;
+pub use model_alias::*;
+pub use policy::*;
+pub use script_hook::*;
+pub use response_cache::*;
+pub use client_token::*;
+pub use tls::*;
+pub use auth_guard::*;
+pub use totp::*;
+pub use audit::*;
+pub use admin_api::*;
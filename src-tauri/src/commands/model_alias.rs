@@ -0,0 +1,100 @@
+use crate::models::{ModelAlias, CreateModelAliasRequest, UpdateModelAliasRequest};
+use crate::services::ModelAliasService;
+use crate::commands::{require_session, record_audit, snapshot};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelAliasResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ModelAliasResult<T> {
+    fn from_result(result: anyhow::Result<T>) -> Self {
+        match result {
+            Ok(data) => ModelAliasResult {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => ModelAliasResult {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn create_model_alias(
+    session_token: String,
+    request: CreateModelAliasRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ModelAliasResult<ModelAlias>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let alias_service = ModelAliasService::new(pool.inner().clone());
+    let result = alias_service.create_alias(request).await;
+    if let Ok(alias) = &result {
+        record_audit(pool.inner(), Some(&session), "create_model_alias", Some(&alias.id.to_string()), None, snapshot(alias)).await;
+    }
+    Ok(ModelAliasResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn get_all_model_aliases(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ModelAliasResult<Vec<ModelAlias>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let alias_service = ModelAliasService::new(pool.inner().clone());
+    Ok(ModelAliasResult::from_result(alias_service.get_all_aliases().await))
+}
+
+#[tauri::command]
+pub async fn update_model_alias(
+    session_token: String,
+    alias_id: String,
+    request: UpdateModelAliasRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ModelAliasResult<ModelAlias>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let alias_service = ModelAliasService::new(pool.inner().clone());
+    let alias_uuid = Uuid::parse_str(&alias_id).map_err(|e| e.to_string())?;
+    let before = alias_service.get_alias_by_id(alias_uuid).await.ok().flatten();
+
+    match alias_service.update_alias(alias_uuid, request).await {
+        Ok(Some(alias)) => {
+            record_audit(pool.inner(), Some(&session), "update_model_alias", Some(&alias_id), before.as_ref().and_then(snapshot), snapshot(&alias)).await;
+            Ok(ModelAliasResult::from_result(Ok(alias)))
+        }
+        Ok(None) => Ok(ModelAliasResult {
+            success: false,
+            data: None,
+            error: Some("Model alias not found".to_string()),
+        }),
+        Err(e) => Ok(ModelAliasResult::from_result(Err(e))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_model_alias(
+    session_token: String,
+    alias_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ModelAliasResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let alias_service = ModelAliasService::new(pool.inner().clone());
+    let alias_uuid = Uuid::parse_str(&alias_id).map_err(|e| e.to_string())?;
+    let before = alias_service.get_alias_by_id(alias_uuid).await.ok().flatten();
+    let result = alias_service.delete_alias(alias_uuid).await;
+    if matches!(result, Ok(true)) {
+        record_audit(pool.inner(), Some(&session), "delete_model_alias", Some(&alias_id), before.as_ref().and_then(snapshot), None).await;
+    }
+    Ok(ModelAliasResult::from_result(result))
+}
@@ -0,0 +1,100 @@
+use crate::models::{PolicyRule, CreatePolicyRuleRequest, UpdatePolicyRuleRequest};
+use crate::services::PolicyService;
+use crate::commands::{require_session, record_audit, snapshot};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyRuleResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> PolicyRuleResult<T> {
+    fn from_result(result: anyhow::Result<T>) -> Self {
+        match result {
+            Ok(data) => PolicyRuleResult {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => PolicyRuleResult {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn create_policy_rule(
+    session_token: String,
+    request: CreatePolicyRuleRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<PolicyRuleResult<PolicyRule>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let policy_service = PolicyService::new(pool.inner().clone());
+    let result = policy_service.create_rule(request).await;
+    if let Ok(rule) = &result {
+        record_audit(pool.inner(), Some(&session), "create_policy_rule", Some(&rule.id.to_string()), None, snapshot(rule)).await;
+    }
+    Ok(PolicyRuleResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn get_all_policy_rules(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<PolicyRuleResult<Vec<PolicyRule>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let policy_service = PolicyService::new(pool.inner().clone());
+    Ok(PolicyRuleResult::from_result(policy_service.get_all_rules().await))
+}
+
+#[tauri::command]
+pub async fn update_policy_rule(
+    session_token: String,
+    rule_id: String,
+    request: UpdatePolicyRuleRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<PolicyRuleResult<PolicyRule>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let policy_service = PolicyService::new(pool.inner().clone());
+    let rule_uuid = Uuid::parse_str(&rule_id).map_err(|e| e.to_string())?;
+    let before = policy_service.get_rule_by_id(rule_uuid).await.ok().flatten();
+
+    match policy_service.update_rule(rule_uuid, request).await {
+        Ok(Some(rule)) => {
+            record_audit(pool.inner(), Some(&session), "update_policy_rule", Some(&rule_id), before.as_ref().and_then(snapshot), snapshot(&rule)).await;
+            Ok(PolicyRuleResult::from_result(Ok(rule)))
+        }
+        Ok(None) => Ok(PolicyRuleResult {
+            success: false,
+            data: None,
+            error: Some("Policy rule not found".to_string()),
+        }),
+        Err(e) => Ok(PolicyRuleResult::from_result(Err(e))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_policy_rule(
+    session_token: String,
+    rule_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<PolicyRuleResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let policy_service = PolicyService::new(pool.inner().clone());
+    let rule_uuid = Uuid::parse_str(&rule_id).map_err(|e| e.to_string())?;
+    let before = policy_service.get_rule_by_id(rule_uuid).await.ok().flatten();
+    let result = policy_service.delete_rule(rule_uuid).await;
+    if matches!(result, Ok(true)) {
+        record_audit(pool.inner(), Some(&session), "delete_policy_rule", Some(&rule_id), before.as_ref().and_then(snapshot), None).await;
+    }
+    Ok(PolicyRuleResult::from_result(result))
+}
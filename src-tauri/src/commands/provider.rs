@@ -0,0 +1,161 @@
+use crate::models::{Provider, CreateProviderRequest, UpdateProviderRequest, ApiKeyResponse};
+use crate::services::{ProviderService, ApiKeyService};
+use crate::commands::{require_session, record_audit, snapshot};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ProviderResult<T> {
+    fn from_result(result: anyhow::Result<T>) -> Self {
+        match result {
+            Ok(data) => ProviderResult {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => ProviderResult {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+
+    fn not_found() -> Self {
+        ProviderResult {
+            success: false,
+            data: None,
+            error: Some("Provider not found".to_string()),
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn create_provider(
+    session_token: String,
+    request: CreateProviderRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<Provider>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let provider_service = ProviderService::new(pool.inner().clone());
+    let result = provider_service.create_provider(request).await;
+    if let Ok(provider) = &result {
+        record_audit(pool.inner(), Some(&session), "create_provider", Some(&provider.id.to_string()), None, snapshot(provider)).await;
+    }
+    Ok(ProviderResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn get_all_providers(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<Vec<Provider>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let provider_service = ProviderService::new(pool.inner().clone());
+    Ok(ProviderResult::from_result(provider_service.get_all_providers().await))
+}
+
+#[tauri::command]
+pub async fn update_provider(
+    session_token: String,
+    provider_id: String,
+    request: UpdateProviderRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<Provider>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let provider_service = ProviderService::new(pool.inner().clone());
+    let provider_uuid = Uuid::parse_str(&provider_id).map_err(|e| e.to_string())?;
+    let before = provider_service.get_provider_by_id(provider_uuid).await.ok().flatten();
+
+    match provider_service.update_provider(provider_uuid, request).await {
+        Ok(Some(provider)) => {
+            record_audit(pool.inner(), Some(&session), "update_provider", Some(&provider_id), before.as_ref().and_then(snapshot), snapshot(&provider)).await;
+            Ok(ProviderResult::from_result(Ok(provider)))
+        }
+        Ok(None) => Ok(ProviderResult::not_found()),
+        Err(e) => Ok(ProviderResult::from_result(Err(e))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_provider(
+    session_token: String,
+    provider_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let provider_service = ProviderService::new(pool.inner().clone());
+    let provider_uuid = Uuid::parse_str(&provider_id).map_err(|e| e.to_string())?;
+    let before = provider_service.get_provider_by_id(provider_uuid).await.ok().flatten();
+    let result = provider_service.delete_provider(provider_uuid).await;
+    if matches!(result, Ok(true)) {
+        record_audit(pool.inner(), Some(&session), "delete_provider", Some(&provider_id), before.as_ref().and_then(snapshot), None).await;
+    }
+    Ok(ProviderResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn set_default_provider(
+    session_token: String,
+    provider_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let provider_service = ProviderService::new(pool.inner().clone());
+    let provider_uuid = Uuid::parse_str(&provider_id).map_err(|e| e.to_string())?;
+    let before = provider_service.get_provider_by_id(provider_uuid).await.ok().flatten();
+    let result = provider_service.set_default_provider(provider_uuid).await;
+    if matches!(result, Ok(true)) {
+        let after = provider_service.get_provider_by_id(provider_uuid).await.ok().flatten();
+        record_audit(pool.inner(), Some(&session), "set_default_provider", Some(&provider_id), before.as_ref().and_then(snapshot), after.as_ref().and_then(snapshot)).await;
+    }
+    Ok(ProviderResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn set_api_key_provider(
+    session_token: String,
+    key_id: String,
+    provider_id: Option<String>,
+    pool: State<'_, SqlitePool>,
+) -> Result<ProviderResult<ApiKeyResponse>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let api_key_service = ApiKeyService::new(pool.inner().clone());
+    let provider_service = ProviderService::new(pool.inner().clone());
+    let key_uuid = Uuid::parse_str(&key_id).map_err(|e| e.to_string())?;
+    let provider_uuid = provider_id
+        .map(|id| Uuid::parse_str(&id))
+        .transpose()
+        .map_err(|e| e.to_string())?;
+
+    if let Some(provider_uuid) = provider_uuid {
+        match provider_service.get_provider_by_id(provider_uuid).await {
+            Ok(Some(_)) => {}
+            Ok(None) => return Ok(ProviderResult::not_found()),
+            Err(e) => return Ok(ProviderResult::from_result(Err(e))),
+        }
+    }
+
+    let before = api_key_service.get_api_key_by_id(key_uuid).await.ok().flatten();
+    match api_key_service.set_api_key_provider(key_uuid, provider_uuid).await {
+        Ok(Some(api_key)) => {
+            record_audit(pool.inner(), Some(&session), "set_api_key_provider", Some(&key_id), before.as_ref().and_then(snapshot), snapshot(&api_key)).await;
+            Ok(ProviderResult::from_result(Ok(api_key)))
+        }
+        Ok(None) => Ok(ProviderResult {
+            success: false,
+            data: None,
+            error: Some("API key not found".to_string()),
+        }),
+        Err(e) => Ok(ProviderResult::from_result(Err(e))),
+    }
+}
\ No newline at end of file
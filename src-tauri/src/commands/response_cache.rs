@@ -0,0 +1,24 @@
+use crate::models::ResponseCacheStats;
+use crate::services::ResponseCacheService;
+use crate::commands::{require_session, record_audit};
+use tauri::State;
+use sqlx::SqlitePool;
+
+#[tauri::command]
+pub async fn get_response_cache_stats(session_token: String, pool: State<'_, SqlitePool>) -> Result<ResponseCacheStats, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let cache_service = ResponseCacheService::new(pool.inner().clone());
+    cache_service.stats().await
+        .map_err(|e| e.to_string())
+}
+
+/// 清空响应缓存，返回删除的条目数
+#[tauri::command]
+pub async fn purge_response_cache(session_token: String, pool: State<'_, SqlitePool>) -> Result<u64, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let cache_service = ResponseCacheService::new(pool.inner().clone());
+    let purged = cache_service.purge().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "purge_response_cache", None, None, Some(serde_json::json!({ "purged": purged }))).await;
+    Ok(purged)
+}
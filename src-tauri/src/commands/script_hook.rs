@@ -0,0 +1,100 @@
+use crate::models::{ScriptHook, CreateScriptHookRequest, UpdateScriptHookRequest};
+use crate::services::ScriptHookService;
+use crate::commands::{require_session, record_audit, snapshot};
+use serde::Serialize;
+use tauri::State;
+use uuid::Uuid;
+use sqlx::SqlitePool;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptHookResult<T> {
+    pub success: bool,
+    pub data: Option<T>,
+    pub error: Option<String>,
+}
+
+impl<T> ScriptHookResult<T> {
+    fn from_result(result: anyhow::Result<T>) -> Self {
+        match result {
+            Ok(data) => ScriptHookResult {
+                success: true,
+                data: Some(data),
+                error: None,
+            },
+            Err(e) => ScriptHookResult {
+                success: false,
+                data: None,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn create_script_hook(
+    session_token: String,
+    request: CreateScriptHookRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ScriptHookResult<ScriptHook>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let hook_service = ScriptHookService::new(pool.inner().clone());
+    let result = hook_service.create_hook(request).await;
+    if let Ok(hook) = &result {
+        record_audit(pool.inner(), Some(&session), "create_script_hook", Some(&hook.id.to_string()), None, snapshot(hook)).await;
+    }
+    Ok(ScriptHookResult::from_result(result))
+}
+
+#[tauri::command]
+pub async fn get_all_script_hooks(
+    session_token: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ScriptHookResult<Vec<ScriptHook>>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let hook_service = ScriptHookService::new(pool.inner().clone());
+    Ok(ScriptHookResult::from_result(hook_service.get_all_hooks().await))
+}
+
+#[tauri::command]
+pub async fn update_script_hook(
+    session_token: String,
+    hook_id: String,
+    request: UpdateScriptHookRequest,
+    pool: State<'_, SqlitePool>,
+) -> Result<ScriptHookResult<ScriptHook>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let hook_service = ScriptHookService::new(pool.inner().clone());
+    let hook_uuid = Uuid::parse_str(&hook_id).map_err(|e| e.to_string())?;
+    let before = hook_service.get_hook_by_id(hook_uuid).await.ok().flatten();
+
+    match hook_service.update_hook(hook_uuid, request).await {
+        Ok(Some(hook)) => {
+            record_audit(pool.inner(), Some(&session), "update_script_hook", Some(&hook_id), before.as_ref().and_then(snapshot), snapshot(&hook)).await;
+            Ok(ScriptHookResult::from_result(Ok(hook)))
+        }
+        Ok(None) => Ok(ScriptHookResult {
+            success: false,
+            data: None,
+            error: Some("Script hook not found".to_string()),
+        }),
+        Err(e) => Ok(ScriptHookResult::from_result(Err(e))),
+    }
+}
+
+#[tauri::command]
+pub async fn delete_script_hook(
+    session_token: String,
+    hook_id: String,
+    pool: State<'_, SqlitePool>,
+) -> Result<ScriptHookResult<bool>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let hook_service = ScriptHookService::new(pool.inner().clone());
+    let hook_uuid = Uuid::parse_str(&hook_id).map_err(|e| e.to_string())?;
+    let before = hook_service.get_hook_by_id(hook_uuid).await.ok().flatten();
+    let result = hook_service.delete_hook(hook_uuid).await;
+    if matches!(result, Ok(true)) {
+        record_audit(pool.inner(), Some(&session), "delete_script_hook", Some(&hook_id), before.as_ref().and_then(snapshot), None).await;
+    }
+    Ok(ScriptHookResult::from_result(result))
+}
@@ -1,17 +1,289 @@
-use crate::services::SettingsService;
+use crate::models::{CircuitBreakerSettings, HedgingSettings, ModelFallbackSettings, ResponseCacheSettings, CoalescingSettings, RateLimitSettings, SchedulerSettings, NetworkSettings, TlsSettings, SessionSettings, BruteForceSettings, ValidationMode};
+use crate::services::{SettingsService, TlsService};
+use crate::commands::{require_session, record_audit, snapshot};
 use tauri::State;
 use sqlx::SqlitePool;
 
 #[tauri::command]
-pub async fn get_retry_count(pool: State<'_, SqlitePool>) -> Result<i32, String> {
+pub async fn get_retry_count(session_token: String, pool: State<'_, SqlitePool>) -> Result<i32, String> {
+    require_session(pool.inner(), &session_token).await?;
     let settings_service = SettingsService::new(pool.inner().clone());
     settings_service.get_retry_count().await
         .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn set_retry_count(retry_count: i32, pool: State<'_, SqlitePool>) -> Result<(), String> {
+pub async fn set_retry_count(session_token: String, retry_count: i32, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
     let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_retry_count().await.ok();
+    let after = snapshot(&retry_count);
     settings_service.set_retry_count(retry_count).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_retry_count", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_circuit_breaker_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<CircuitBreakerSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_circuit_breaker_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_circuit_breaker_settings(session_token: String, settings: CircuitBreakerSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_circuit_breaker_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_circuit_breaker_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_circuit_breaker_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_hedging_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<HedgingSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_hedging_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_hedging_settings(session_token: String, settings: HedgingSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_hedging_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_hedging_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_hedging_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_model_fallback_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<ModelFallbackSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_model_fallback_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_model_fallback_settings(session_token: String, settings: ModelFallbackSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_model_fallback_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_model_fallback_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_model_fallback_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_validation_mode(session_token: String, pool: State<'_, SqlitePool>) -> Result<ValidationMode, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_validation_mode().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_validation_mode(session_token: String, mode: ValidationMode, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_validation_mode().await.ok();
+    let after = snapshot(&mode);
+    settings_service.set_validation_mode(mode).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_validation_mode", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_response_cache_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<ResponseCacheSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_response_cache_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_response_cache_settings(session_token: String, settings: ResponseCacheSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_response_cache_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_response_cache_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_response_cache_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_coalescing_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<CoalescingSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_coalescing_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_coalescing_settings(session_token: String, settings: CoalescingSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_coalescing_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_coalescing_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_coalescing_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_rate_limit_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<RateLimitSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_rate_limit_settings().await
         .map_err(|e| e.to_string())
-}
\ No newline at end of file
+}
+
+#[tauri::command]
+pub async fn set_rate_limit_settings(session_token: String, settings: RateLimitSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_rate_limit_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_rate_limit_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_rate_limit_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_scheduler_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<SchedulerSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_scheduler_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_scheduler_settings(session_token: String, settings: SchedulerSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_scheduler_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_scheduler_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_scheduler_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_network_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<NetworkSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_network_settings().await
+        .map_err(|e| e.to_string())
+}
+
+/// 保存网络设置；监听地址变化时立即重新绑定代理端口，访问控制列表对下一个请求生效
+#[tauri::command]
+pub async fn set_network_settings(session_token: String, settings: NetworkSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let previous = settings_service.get_network_settings().await.unwrap_or_default();
+    let listener_changed = previous.listener_changed(&settings);
+    let after = snapshot(&settings);
+
+    settings_service.set_network_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_network_settings", None, snapshot(&previous), after).await;
+
+    if listener_changed {
+        crate::server::start_server(pool.inner().clone()).await
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// 代理服务实际监听的地址，端口回退后可能与设置不同
+#[tauri::command]
+pub async fn get_proxy_listen_address(session_token: String, pool: State<'_, SqlitePool>) -> Result<Option<String>, String> {
+    require_session(pool.inner(), &session_token).await?;
+    Ok(crate::server::listening_addr().await.map(|addr| addr.to_string()))
+}
+
+#[tauri::command]
+pub async fn get_tls_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<TlsSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_tls_settings().await
+        .map_err(|e| e.to_string())
+}
+
+/// 保存 HTTPS 设置；先确认证书可用，切换 HTTPS 时重新绑定，仅更换证书时原地替换
+#[tauri::command]
+pub async fn set_tls_settings(session_token: String, settings: TlsSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    if settings.enabled {
+        TlsService::new(pool.inner().clone()).load_certificate(&settings).await
+            .map_err(|e| e.to_string())?;
+    }
+
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_tls_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_tls_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_tls_settings", None, before.as_ref().and_then(snapshot), after).await;
+
+    crate::server::apply_tls_settings(pool.inner().clone()).await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn get_session_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<SessionSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_session_settings().await
+        .map_err(|e| e.to_string())
+}
+
+/// 保存会话有效期设置，新的空闲超时对现有会话立即生效，总有效期从下次登录开始生效
+#[tauri::command]
+pub async fn set_session_settings(session_token: String, settings: SessionSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_session_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_session_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_session_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_brute_force_settings(session_token: String, pool: State<'_, SqlitePool>) -> Result<BruteForceSettings, String> {
+    require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    settings_service.get_brute_force_settings().await
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_brute_force_settings(session_token: String, settings: BruteForceSettings, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings_service = SettingsService::new(pool.inner().clone());
+    let before = settings_service.get_brute_force_settings().await.ok();
+    let after = snapshot(&settings);
+    settings_service.set_brute_force_settings(settings).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "set_brute_force_settings", None, before.as_ref().and_then(snapshot), after).await;
+    Ok(())
+}
@@ -0,0 +1,56 @@
+use crate::models::TlsCertificateInfo;
+use crate::services::{SettingsService, TlsService};
+use crate::commands::{require_session, record_audit, snapshot};
+use tauri::State;
+use sqlx::SqlitePool;
+
+async fn certificate_info(pool: &SqlitePool) -> Result<TlsCertificateInfo, String> {
+    let settings = SettingsService::new(pool.clone()).get_tls_settings().await
+        .map_err(|e| e.to_string())?;
+    let pem = TlsService::new(pool.clone()).load_certificate(&settings).await
+        .map_err(|e| e.to_string())?;
+
+    Ok(TlsCertificateInfo {
+        active: crate::server::tls_active().await,
+        ..pem.info
+    })
+}
+
+/// 当前 HTTPS 证书的来源和 SHA-256 指纹；未生成过自签名证书时会先生成
+#[tauri::command]
+pub async fn get_tls_certificate_info(session_token: String, pool: State<'_, SqlitePool>) -> Result<TlsCertificateInfo, String> {
+    require_session(pool.inner(), &session_token).await?;
+    certificate_info(pool.inner()).await
+}
+
+/// 重新读取证书文件（例如续期后），不重启代理
+#[tauri::command]
+pub async fn reload_tls_certificate(session_token: String, pool: State<'_, SqlitePool>) -> Result<TlsCertificateInfo, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = certificate_info(pool.inner()).await.ok();
+    crate::server::apply_tls_settings(pool.inner().clone()).await
+        .map_err(|e| e.to_string())?;
+    let info = certificate_info(pool.inner()).await?;
+    record_audit(pool.inner(), Some(&session), "reload_tls_certificate", None, before.as_ref().and_then(snapshot), snapshot(&info)).await;
+    Ok(info)
+}
+
+/// 重新生成自签名证书并立即生效，客户端需要更新固定的指纹
+#[tauri::command]
+pub async fn regenerate_tls_certificate(session_token: String, pool: State<'_, SqlitePool>) -> Result<TlsCertificateInfo, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let settings = SettingsService::new(pool.inner().clone()).get_tls_settings().await
+        .map_err(|e| e.to_string())?;
+    if settings.file_paths().is_some() {
+        return Err("A certificate file is configured; clear the certificate paths to use a self-signed certificate".to_string());
+    }
+    let before = certificate_info(pool.inner()).await.ok();
+
+    TlsService::new(pool.inner().clone()).regenerate_self_signed(&settings.subject_alt_names).await
+        .map_err(|e| e.to_string())?;
+    crate::server::apply_tls_settings(pool.inner().clone()).await
+        .map_err(|e| e.to_string())?;
+    let info = certificate_info(pool.inner()).await?;
+    record_audit(pool.inner(), Some(&session), "regenerate_tls_certificate", None, before.as_ref().and_then(snapshot), snapshot(&info)).await;
+    Ok(info)
+}
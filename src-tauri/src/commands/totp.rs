@@ -0,0 +1,93 @@
+use crate::models::{AuthScope, TotpEnrollment, TotpStatus};
+use crate::services::{AuthGuardService, AuthService, SettingsService, TotpService, LOCAL_SOURCE};
+use crate::commands::{record_login_failure, require_session, record_audit, snapshot};
+use tauri::State;
+use sqlx::SqlitePool;
+
+/// 两步验证状态变化前后的快照，只包含是否启用和剩余恢复码数量
+async fn status_snapshot(pool: &SqlitePool) -> Option<serde_json::Value> {
+    let status = TotpService::new(pool.clone()).status().await.ok()?;
+    snapshot(&status)
+}
+
+/// 敏感操作前再次确认管理员身份：可选的管理密码加当前的动态验证码或恢复码，失败计入登录失败次数
+async fn confirm_admin(pool: &SqlitePool, password: Option<&str>, code: &str) -> Result<(), String> {
+    let guard_settings = SettingsService::new(pool.clone()).get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    auth_guard.check(AuthScope::AdminLogin, LOCAL_SOURCE, &guard_settings)
+        .map_err(|locked| format!("密码错误次数过多，请在 {} 秒后重试", locked.retry_after.as_secs().max(1)))?;
+
+    if let Some(password) = password {
+        let valid = AuthService::new(pool.clone()).verify_password(password).await
+            .map_err(|e| e.to_string())?;
+        if !valid {
+            record_login_failure(pool, &guard_settings).await;
+            return Err("密码错误".to_string());
+        }
+    }
+
+    let valid = TotpService::new(pool.clone()).verify(code).await
+        .map_err(|e| e.to_string())?;
+    if !valid {
+        record_login_failure(pool, &guard_settings).await;
+        return Err("验证码错误或已使用过".to_string());
+    }
+
+    auth_guard.record_success(AuthScope::AdminLogin, LOCAL_SOURCE);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_totp_status(session_token: String, pool: State<'_, SqlitePool>) -> Result<TotpStatus, String> {
+    require_session(pool.inner(), &session_token).await?;
+    TotpService::new(pool.inner().clone()).status().await
+        .map_err(|e| e.to_string())
+}
+
+/// 生成待确认的密钥和 otpauth 地址，扫码后用 confirm_totp_enrollment 确认
+#[tauri::command]
+pub async fn begin_totp_enrollment(session_token: String, pool: State<'_, SqlitePool>) -> Result<TotpEnrollment, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = status_snapshot(pool.inner()).await;
+    let enrollment = TotpService::new(pool.inner().clone()).begin_enrollment().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "begin_totp_enrollment", None, before, status_snapshot(pool.inner()).await).await;
+    Ok(enrollment)
+}
+
+/// 确认绑定并启用两步验证，返回只显示一次的恢复码
+#[tauri::command]
+pub async fn confirm_totp_enrollment(session_token: String, code: String, pool: State<'_, SqlitePool>) -> Result<Vec<String>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    let before = status_snapshot(pool.inner()).await;
+    let codes = TotpService::new(pool.inner().clone()).confirm_enrollment(&code).await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "confirm_totp_enrollment", None, before, status_snapshot(pool.inner()).await).await;
+    tracing::info!("TOTP two-factor authentication enabled for the admin console");
+    Ok(codes)
+}
+
+/// 停用两步验证，需要管理密码和当前验证码（或恢复码）
+#[tauri::command]
+pub async fn disable_totp(session_token: String, password: String, code: String, pool: State<'_, SqlitePool>) -> Result<(), String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    confirm_admin(pool.inner(), Some(&password), &code).await?;
+    let before = status_snapshot(pool.inner()).await;
+    TotpService::new(pool.inner().clone()).disable().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "disable_totp", None, before, status_snapshot(pool.inner()).await).await;
+    tracing::warn!("TOTP two-factor authentication disabled for the admin console");
+    Ok(())
+}
+
+/// 重新生成恢复码，旧的恢复码全部作废
+#[tauri::command]
+pub async fn regenerate_totp_recovery_codes(session_token: String, code: String, pool: State<'_, SqlitePool>) -> Result<Vec<String>, String> {
+    let session = require_session(pool.inner(), &session_token).await?;
+    confirm_admin(pool.inner(), None, &code).await?;
+    let before = status_snapshot(pool.inner()).await;
+    let codes = TotpService::new(pool.inner().clone()).regenerate_recovery_codes().await
+        .map_err(|e| e.to_string())?;
+    record_audit(pool.inner(), Some(&session), "regenerate_totp_recovery_codes", None, before, status_snapshot(pool.inner()).await).await;
+    Ok(codes)
+}
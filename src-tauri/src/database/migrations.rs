@@ -90,6 +90,68 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await.ok(); // 忽略错误，可能列已存在
 
+    // Add circuit_breaker_config column (JSON) to app_settings table
+    sqlx::query(
+        r#"
+        ALTER TABLE app_settings ADD COLUMN circuit_breaker_config TEXT;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // 早期版本把熔断设置存成三个独立的列，存在时迁移到 JSON 中
+    sqlx::query(
+        r#"
+        UPDATE app_settings SET circuit_breaker_config = json_object(
+            'failureThreshold', circuit_failure_threshold,
+            'openDurationSecs', circuit_open_seconds,
+            'halfOpenMaxRequests', circuit_half_open_requests
+        )
+        WHERE circuit_breaker_config IS NULL
+          AND circuit_failure_threshold IS NOT NULL
+          AND circuit_open_seconds IS NOT NULL
+          AND circuit_half_open_requests IS NOT NULL
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，旧列不存在
+
+    // Add hedging_config column (JSON) to app_settings table
+    sqlx::query(
+        r#"
+        ALTER TABLE app_settings ADD COLUMN hedging_config TEXT;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // Add model_fallback_config column (JSON) to app_settings table
+    sqlx::query(
+        r#"
+        ALTER TABLE app_settings ADD COLUMN model_fallback_config TEXT;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // Add validation_mode column to app_settings table ("strict" or "passthrough")
+    sqlx::query(
+        r#"
+        ALTER TABLE app_settings ADD COLUMN validation_mode TEXT DEFAULT 'strict';
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // Add fallback_from column to request_logs table (originally requested model when a fallback served the request)
+    sqlx::query(
+        r#"
+        ALTER TABLE request_logs ADD COLUMN fallback_from TEXT;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
     // Add request_body and response_body columns to request_logs table
     sqlx::query(
         r#"
@@ -107,18 +169,344 @@ pub async fn run_migrations(pool: &SqlitePool) -> Result<()> {
     .execute(pool)
     .await.ok(); // 忽略错误，可能列已存在
 
-    // Initialize default custom auth key if not set
+    // Create providers table (upstream endpoints that API keys are sent to)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS providers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            base_url TEXT NOT NULL,
+            api_version_map TEXT,
+            extra_headers TEXT,
+            timeout_secs INTEGER NOT NULL DEFAULT 60,
+            is_default INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Seed the default Google AI Studio provider
+    let providers_exist: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM providers")
+        .fetch_one(pool)
+        .await?;
+
+    if providers_exist.0 == 0 {
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+
+        sqlx::query(
+            r#"
+            INSERT INTO providers (id, name, base_url, api_version_map, extra_headers, timeout_secs, is_default, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, 1, ?, ?)
+            "#,
+        )
+        .bind(uuid::Uuid::new_v4().to_string())
+        .bind("Google AI Studio")
+        .bind(crate::services::DEFAULT_PROVIDER_BASE_URL)
+        .bind(r#"{"v1":"v1beta"}"#)
+        .bind("{}")
+        .bind(crate::services::DEFAULT_PROVIDER_TIMEOUT_SECS as i64)
+        .bind(&now)
+        .bind(&now)
+        .execute(pool)
+        .await?;
+    }
+
+    // Add provider_id column to api_keys table (NULL means the default provider)
+    sqlx::query(
+        r#"
+        ALTER TABLE api_keys ADD COLUMN provider_id TEXT;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // Add provider_type and token_url columns to providers table (Vertex AI support)
+    for column in [
+        "provider_type TEXT NOT NULL DEFAULT 'gemini'",
+        "token_url TEXT",
+    ] {
+        sqlx::query(&format!("ALTER TABLE providers ADD COLUMN {}", column))
+            .execute(pool)
+            .await.ok(); // 忽略错误，可能列已存在
+    }
+
+    // Add model_prefixes column to providers table (OpenAI-compatible routing)
+    sqlx::query("ALTER TABLE providers ADD COLUMN model_prefixes TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add credential columns to api_keys table (Vertex AI service accounts)
+    for column in [
+        "credential_type TEXT NOT NULL DEFAULT 'api_key'",
+        "vertex_project TEXT",
+        "vertex_location TEXT",
+    ] {
+        sqlx::query(&format!("ALTER TABLE api_keys ADD COLUMN {}", column))
+            .execute(pool)
+            .await.ok(); // 忽略错误，可能列已存在
+    }
+
+    // Create model_aliases table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS model_aliases (
+            id TEXT PRIMARY KEY,
+            alias TEXT NOT NULL UNIQUE,
+            target_model TEXT NOT NULL,
+            description TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create policy_rules table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS policy_rules (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            priority INTEGER NOT NULL DEFAULT 0,
+            model_patterns TEXT,
+            client_tokens TEXT,
+            actions TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create script_hooks table
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS script_hooks (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            stage TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            priority INTEGER NOT NULL DEFAULT 0,
+            source TEXT NOT NULL,
+            time_budget_ms INTEGER,
+            memory_budget_kb INTEGER,
+            fail_closed INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Add fail_closed column to script_hooks table
+    sqlx::query(
+        r#"
+        ALTER TABLE script_hooks ADD COLUMN fail_closed INTEGER NOT NULL DEFAULT 0;
+        "#,
+    )
+    .execute(pool)
+    .await.ok(); // 忽略错误，可能列已存在
+
+    // Create response_cache table (exact-match cache for non-streaming generateContent)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS response_cache (
+            cache_key TEXT PRIMARY KEY,
+            response_body TEXT NOT NULL,
+            served_model TEXT,
+            api_key_id TEXT NOT NULL,
+            size_bytes INTEGER NOT NULL,
+            hit_count INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            last_hit_at TEXT
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create client_tokens table (named client access tokens, only the secret hash is stored)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS client_tokens (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            secret_hash TEXT NOT NULL UNIQUE,
+            token_prefix TEXT NOT NULL,
+            enabled INTEGER NOT NULL DEFAULT 1,
+            expires_at TEXT,
+            last_used_at TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create admin_sessions table (management UI sessions, only the token hash is stored)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS admin_sessions (
+            id TEXT PRIMARY KEY,
+            token_hash TEXT NOT NULL UNIQUE,
+            password_change_required INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            last_seen_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    // Create audit_events table (administrative actions, secrets in changes are masked)
+    sqlx::query(
+        r#"
+        CREATE TABLE IF NOT EXISTS audit_events (
+            id TEXT PRIMARY KEY,
+            session_id TEXT,
+            action TEXT NOT NULL,
+            target TEXT,
+            changes TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )
+        "#,
+    )
+    .execute(pool)
+    .await?;
+
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_audit_events_created_at ON audit_events (created_at)")
+        .execute(pool)
+        .await?;
+
+    // Add response_cache_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN response_cache_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add cache_hit column to request_logs table
+    sqlx::query("ALTER TABLE request_logs ADD COLUMN cache_hit INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add coalescing_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN coalescing_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add coalesced column to request_logs table
+    sqlx::query("ALTER TABLE request_logs ADD COLUMN coalesced INTEGER NOT NULL DEFAULT 0")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add rate_limit_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN rate_limit_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add scheduler_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN scheduler_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add client_token_id column to request_logs table (NULL for the global custom auth key)
+    sqlx::query("ALTER TABLE request_logs ADD COLUMN client_token_id TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add scopes column (JSON) to client_tokens table
+    sqlx::query("ALTER TABLE client_tokens ADD COLUMN scopes TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add total_tokens column to request_logs table (usageMetadata.totalTokenCount of successful responses)
+    sqlx::query("ALTER TABLE request_logs ADD COLUMN total_tokens INTEGER")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Index for per-client usage queries
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_request_logs_client_token ON request_logs (client_token_id, created_at)")
+        .execute(pool)
+        .await?;
+
+    // Add network_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN network_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add TLS columns to app_settings table: JSON config plus the persisted self-signed certificate
+    for column in ["tls_config", "tls_generated_cert", "tls_generated_key", "tls_generated_sans", "tls_generated_at"] {
+        sqlx::query(&format!("ALTER TABLE app_settings ADD COLUMN {} TEXT", column))
+            .execute(pool)
+            .await.ok(); // 忽略错误，可能列已存在
+    }
+
+    // Add session_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN session_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add brute_force_config column (JSON) to app_settings table
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN brute_force_config TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add TOTP two-factor columns to app_settings table
+    for column in [
+        "totp_enabled INTEGER NOT NULL DEFAULT 0",
+        "totp_secret TEXT",
+        "totp_pending_secret TEXT",
+        "totp_last_step INTEGER",
+        "totp_recovery_codes TEXT",
+    ] {
+        sqlx::query(&format!("ALTER TABLE app_settings ADD COLUMN {}", column))
+            .execute(pool)
+            .await.ok(); // 忽略错误，可能列已存在
+    }
+
+    // Add channel column to audit_events table (console or admin_api)
+    sqlx::query("ALTER TABLE audit_events ADD COLUMN channel TEXT NOT NULL DEFAULT 'console'")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add admin API token columns to app_settings table (only the token hash is stored)
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN admin_api_token_hash TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN admin_api_token_prefix TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    sqlx::query("ALTER TABLE app_settings ADD COLUMN admin_api_token_created_at TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add actor column to audit_events table (admin API caller or CLI user)
+    sqlx::query("ALTER TABLE audit_events ADD COLUMN actor TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // Add applied_policies column (JSON array of rule names) to request_logs table
+    sqlx::query("ALTER TABLE request_logs ADD COLUMN applied_policies TEXT")
+        .execute(pool)
+        .await.ok(); // 忽略错误，可能列已存在
+
+    // 旧版本会自动设置公开的默认验证密钥，升级后停用，需要时由管理员重新设置
     use crate::services::CustomAuthService;
-    let custom_auth_service = CustomAuthService::new(pool.clone());
-    
-    // Check if custom auth key is already set
-    if !custom_auth_service.has_custom_key().await.unwrap_or(false) {
-        // Set default custom auth key to "123456"
-        if let Err(e) = custom_auth_service.set_custom_key("123456").await {
-            tracing::warn!("Failed to set default custom auth key: {}", e);
-        } else {
-            tracing::info!("Default custom auth key initialized: 123456");
-        }
+    match CustomAuthService::new(pool.clone()).disable_legacy_default_key().await {
+        Ok(true) => tracing::warn!("Disabled the built-in default custom auth key; create client tokens or set a new key"),
+        Ok(false) => {}
+        Err(e) => tracing::warn!("Failed to check for the default custom auth key: {}", e),
     }
 
     Ok(())
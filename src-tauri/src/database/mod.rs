@@ -1,11 +1,11 @@
 use sqlx::{SqlitePool, migrate::MigrateDatabase};
 use anyhow::Result;
-use std::path::PathBuf;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
 
 pub mod migrations;
 
-fn get_database_path(_app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf> {
+/// 未指定数据库文件时使用的位置，无界面服务和命令行工具默认与桌面应用使用同一个数据库
+pub fn default_database_path() -> Result<PathBuf> {
     #[cfg(target_os = "windows")]
     {
         // Windows: Use AppData/Local directory
@@ -18,72 +18,79 @@ fn get_database_path(_app_handle: Option<&tauri::AppHandle>) -> Result<PathBuf>
             Ok(std::env::temp_dir().join("gemini_proxy.db"))
         }
     }
-    
+
+    #[cfg(not(target_os = "windows"))]
+    {
+        // Other platforms: Use temp directory as fallback
+        Ok(std::env::temp_dir().join("gemini_proxy.db"))
+    }
+}
+
+#[cfg(feature = "desktop")]
+fn get_database_path(_app_handle: &tauri::AppHandle) -> Result<PathBuf> {
     #[cfg(target_os = "android")]
     {
+        use tauri::Manager;
+
         // Android: Use app data directory for persistent storage
         // This is typically /data/data/com.wye.tjimi/files/
-        if let Some(handle) = _app_handle {
-            let app_data_dir = handle.path().app_data_dir()
-                .map_err(|e| anyhow::anyhow!("Failed to get app data directory: {}", e))?;
-            let db_path = app_data_dir.join("gemini_proxy.db");
-            
-            // Ensure the directory exists
-            if let Some(parent) = db_path.parent() {
-                std::fs::create_dir_all(parent)?;
-            }
-            
-            Ok(db_path)
-        } else {
-            // Fallback to temp directory if no app handle provided
-            let temp_dir = std::env::temp_dir();
-            let db_path = temp_dir.join("gemini_proxy.db");
-            
-            if let Some(parent) = db_path.parent() {
-                std::fs::create_dir_all(parent).unwrap_or_default();
-            }
-            
-            Ok(db_path)
-        }
+        let app_data_dir = _app_handle.path().app_data_dir()
+            .map_err(|e| anyhow::anyhow!("Failed to get app data directory: {}", e))?;
+        Ok(app_data_dir.join("gemini_proxy.db"))
     }
-    
-    #[cfg(not(any(target_os = "windows", target_os = "android")))]
+
+    #[cfg(not(target_os = "android"))]
     {
-        // Other platforms: Use temp directory as fallback
-        Ok(std::env::temp_dir().join("gemini_proxy.db"))
+        default_database_path()
     }
 }
 
-pub async fn init_database() -> Result<SqlitePool> {
-    let db_path = get_database_path(None)?;
+async fn connect(db_path: &Path) -> Result<SqlitePool> {
+    tracing::info!("Initializing database at: {}", db_path.display());
+
+    // Ensure the directory exists
+    if let Some(parent) = db_path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)?;
+    }
+
     let database_url = format!("sqlite:{}", db_path.display());
-    
     if !sqlx::Sqlite::database_exists(&database_url).await? {
         sqlx::Sqlite::create_database(&database_url).await?;
     }
-    
+
     let pool = SqlitePool::connect(&database_url).await?;
-    
+
     // Run migrations
     migrations::run_migrations(&pool).await?;
-    
+
     Ok(pool)
 }
 
+/// 打开指定的数据库文件并执行迁移，未指定时使用 `default_database_path`
+pub async fn init_database(db_path: Option<&Path>) -> Result<SqlitePool> {
+    let db_path = match db_path {
+        Some(path) => path.to_path_buf(),
+        None => default_database_path()?,
+    };
+    connect(&db_path).await
+}
+
+/// 测试用的内存数据库，已执行迁移；只用一个连接，否则每个连接各有一个数据库
+#[cfg(test)]
+pub async fn test_pool() -> SqlitePool {
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .idle_timeout(None)
+        .max_lifetime(None)
+        .connect("sqlite::memory:")
+        .await
+        .unwrap();
+    migrations::run_migrations(&pool).await.unwrap();
+    pool
+}
+
+#[cfg(feature = "desktop")]
 pub async fn init_database_with_app_handle(app_handle: &tauri::AppHandle) -> Result<SqlitePool> {
-    let db_path = get_database_path(Some(app_handle))?;
-    let database_url = format!("sqlite:{}", db_path.display());
-    
-    tracing::info!("Initializing database at: {}", db_path.display());
-    
-    if !sqlx::Sqlite::database_exists(&database_url).await? {
-        sqlx::Sqlite::create_database(&database_url).await?;
-    }
-    
-    let pool = SqlitePool::connect(&database_url).await?;
-    
-    // Run migrations
-    migrations::run_migrations(&pool).await?;
-    
-    Ok(pool)
-}
\ No newline at end of file
+    let db_path = get_database_path(app_handle)?;
+    connect(&db_path).await
+}
@@ -0,0 +1,146 @@
+use clap::{Parser, ValueEnum};
+use serde::Deserialize;
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DEFAULT_LOG_FILTER: &str = "info";
+const DEFAULT_SHUTDOWN_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// 便于阅读的单行文本
+    #[default]
+    Text,
+    /// 每行一个 JSON 对象，便于日志系统采集
+    Json,
+}
+
+/// 命令行参数，未指定的参数依次读取环境变量和配置文件
+#[derive(Debug, Parser)]
+#[command(name = "tjimi-server", version, about = "Run the Gemini proxy without the desktop app")]
+pub struct ServerArgs {
+    /// TOML 配置文件
+    #[arg(short, long, env = "TJIMI_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// SQLite 数据库文件，不存在时自动创建
+    #[arg(long, env = "TJIMI_DATABASE")]
+    pub database: Option<PathBuf>,
+    /// 监听地址，只在本次运行中覆盖网络设置，不写回数据库
+    #[arg(long, env = "TJIMI_HOST")]
+    pub host: Option<String>,
+    /// 监听端口，只在本次运行中覆盖网络设置，不写回数据库
+    #[arg(long, env = "TJIMI_PORT")]
+    pub port: Option<u16>,
+    /// 管理 API 令牌（至少 24 个字符），建议通过环境变量或配置文件提供
+    #[arg(long, env = "TJIMI_ADMIN_TOKEN", hide_env_values = true)]
+    pub admin_token: Option<String>,
+    /// 日志过滤规则，语法同 RUST_LOG，例如 `info,tjimi_lib=debug`
+    #[arg(long, env = "TJIMI_LOG")]
+    pub log: Option<String>,
+    #[arg(long, env = "TJIMI_LOG_FORMAT", value_enum)]
+    pub log_format: Option<LogFormat>,
+    /// 收到 SIGTERM 后等待进行中请求完成的最长秒数
+    #[arg(long, env = "TJIMI_SHUTDOWN_TIMEOUT")]
+    pub shutdown_timeout: Option<u64>,
+}
+
+/// 配置文件的内容，字段与命令行参数同名
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    database: Option<PathBuf>,
+    host: Option<String>,
+    port: Option<u16>,
+    admin_token: Option<String>,
+    log: Option<String>,
+    log_format: Option<LogFormat>,
+    shutdown_timeout: Option<u64>,
+}
+
+/// 合并后的配置，优先级：命令行参数 > 环境变量 > 配置文件 > 默认值
+#[derive(Debug)]
+pub struct ServerConfig {
+    /// 为空时使用桌面应用的默认数据库位置
+    pub database: Option<PathBuf>,
+    pub host: Option<String>,
+    pub port: Option<u16>,
+    pub admin_token: Option<String>,
+    pub log: String,
+    pub log_format: LogFormat,
+    pub shutdown_timeout: Duration,
+}
+
+impl ServerConfig {
+    pub fn load(args: ServerArgs) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read config file {}", path.display()))?;
+                toml::from_str::<ConfigFile>(&content)
+                    .with_context(|| format!("Invalid config file {}", path.display()))?
+            }
+            None => ConfigFile::default(),
+        };
+
+        Ok(Self {
+            database: args.database.or(file.database),
+            host: args.host.or(file.host),
+            port: args.port.or(file.port),
+            admin_token: args.admin_token.or(file.admin_token),
+            log: args.log
+                .or(file.log)
+                .or_else(|| std::env::var("RUST_LOG").ok())
+                .unwrap_or_else(|| DEFAULT_LOG_FILTER.to_string()),
+            log_format: args.log_format.or(file.log_format).unwrap_or_default(),
+            shutdown_timeout: Duration::from_secs(
+                args.shutdown_timeout.or(file.shutdown_timeout).unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT_SECS),
+            ),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("tjimi-server-{}-{}.toml", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn command_line_overrides_the_config_file() {
+        let path = write_config("precedence", "host = \"0.0.0.0\"\nport = 8000\nlog_format = \"json\"\nshutdown_timeout = 5\n");
+        let args = ServerArgs::try_parse_from(["tjimi-server", "--config", path.to_str().unwrap(), "--port", "9000"]).unwrap();
+        let config = ServerConfig::load(args).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(config.host.as_deref(), Some("0.0.0.0"));
+        assert_eq!(config.port, Some(9000));
+        assert_eq!(config.log_format, LogFormat::Json);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(5));
+    }
+
+    #[test]
+    fn defaults_without_a_config_file() {
+        let config = ServerConfig::load(ServerArgs::try_parse_from(["tjimi-server"]).unwrap()).unwrap();
+        assert_eq!(config.host, None);
+        assert_eq!(config.port, None);
+        assert_eq!(config.log_format, LogFormat::Text);
+        assert_eq!(config.shutdown_timeout, Duration::from_secs(DEFAULT_SHUTDOWN_TIMEOUT_SECS));
+    }
+
+    #[test]
+    fn rejects_unknown_config_keys_and_bad_ports() {
+        let path = write_config("unknown", "listen = \"0.0.0.0\"\n");
+        let args = ServerArgs::try_parse_from(["tjimi-server", "--config", path.to_str().unwrap()]).unwrap();
+        let error = ServerConfig::load(args).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(format!("{:#}", error).contains("unknown field `listen`"));
+
+        assert!(ServerArgs::try_parse_from(["tjimi-server", "--port", "70000"]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+pub mod config;
+
+use clap::Parser;
+use anyhow::{Context, Result, anyhow};
+use tracing_subscriber::EnvFilter;
+use crate::database::init_database;
+use crate::server::ListenOverride;
+use crate::services::AdminApiService;
+use config::{LogFormat, ServerArgs, ServerConfig};
+
+fn init_tracing(config: &ServerConfig) -> Result<()> {
+    let filter = EnvFilter::try_new(&config.log)
+        .with_context(|| format!("Invalid log filter '{}'", config.log))?;
+    let builder = tracing_subscriber::fmt().with_env_filter(filter);
+    let result = match config.log_format {
+        LogFormat::Text => builder.try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+    result.map_err(|e| anyhow!("Failed to initialize logging: {}", e))
+}
+
+/// 配置中的监听地址只在本进程内覆盖网络设置，不写入数据库；
+/// 运行期间通过管理 API 修改的地址保存在数据库中，但不会替换这里覆盖的部分
+fn apply_listen_overrides(config: &ServerConfig) {
+    if config.host.is_none() && config.port.is_none() {
+        return;
+    }
+
+    tracing::info!(host = ?config.host, port = ?config.port, "Overriding listen address from configuration");
+    crate::server::set_listen_override(ListenOverride {
+        host: config.host.clone(),
+        port: config.port,
+    });
+}
+
+/// 等待 SIGTERM 或 Ctrl-C，返回收到的信号名称
+async fn shutdown_signal() -> Result<&'static str> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result.map(|_| "SIGINT").map_err(Into::into),
+            _ = terminate.recv() => Ok("SIGTERM"),
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        tokio::signal::ctrl_c().await?;
+        Ok("Ctrl-C")
+    }
+}
+
+async fn serve(config: ServerConfig) -> Result<()> {
+    let pool = init_database(config.database.as_deref()).await
+        .context("Failed to initialize database")?;
+
+    apply_listen_overrides(&config);
+    if let Some(token) = &config.admin_token {
+        AdminApiService::new(pool.clone()).set_token(token).await?;
+        tracing::info!("Admin API enabled with the configured token");
+    }
+
+    let addr = crate::server::start_server(pool.clone()).await?;
+    tracing::info!(%addr, tls = crate::server::tls_active().await, "Proxy server started");
+
+    let signal = shutdown_signal().await?;
+    tracing::info!(
+        signal,
+        timeout_secs = config.shutdown_timeout.as_secs(),
+        "Shutting down, waiting for in-flight requests"
+    );
+    crate::server::shutdown_server(config.shutdown_timeout).await;
+    pool.close().await;
+    tracing::info!("Proxy server stopped");
+    Ok(())
+}
+
+/// 解析命令行参数和配置文件，运行代理服务直到收到 SIGTERM 或 Ctrl-C
+pub fn run() -> Result<()> {
+    let config = ServerConfig::load(ServerArgs::parse())?;
+    init_tracing(&config)?;
+
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()?
+        .block_on(serve(config))
+}
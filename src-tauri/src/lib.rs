@@ -1,15 +1,28 @@
+
 mod models;
 mod services;
 mod database;
+#[cfg(feature = "desktop")]
 mod commands;
 mod server;
+mod utils;
+// 不依赖 Tauri 的代理服务入口（tjimi-server）
+pub mod headless;
+// 命令行管理工具入口（tjimi-cli）
+pub mod cli;
 
+#[cfg(feature = "desktop")]
 use database::init_database_with_app_handle;
-use server::create_app;
+#[cfg(feature = "desktop")]
+use server::start_server;
+#[cfg(feature = "desktop")]
 use commands::*;
+#[cfg(feature = "desktop")]
 use services::CustomAuthService;
+#[cfg(feature = "desktop")]
 use tauri::Manager;
 
+#[cfg(feature = "desktop")]
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize logging
@@ -32,16 +45,9 @@ pub fn run() {
                         // Start HTTP server in background
                         let server_pool = pool.clone();
                         tauri::async_runtime::spawn(async move {
-                            let server_app = create_app(server_pool).await;
-                            let listener = tokio::net::TcpListener::bind("0.0.0.0:5675")
-                                .await
-                                .expect("Failed to bind to port 5675");
-                            
-                            tracing::info!("Gemini proxy server listening on http://0.0.0.0:5675");
-                            
-                            axum::serve(listener, server_app)
-                                .await
-                                .expect("Failed to start server");
+                            if let Err(e) = start_server(server_pool).await {
+                                tracing::error!("Failed to start proxy server: {}", e);
+                            }
                         });
                     }
                     Err(e) => {
@@ -57,6 +63,8 @@ pub fn run() {
             login,
             change_password,
             check_default_password,
+            get_current_session,
+            logout,
             create_api_key,
             get_all_api_keys,
             get_api_keys_paginated,
@@ -80,7 +88,75 @@ pub fn run() {
             start_drag,
             is_desktop,
             get_retry_count,
-            set_retry_count
+            set_retry_count,
+            get_circuit_breaker_settings,
+            set_circuit_breaker_settings,
+            get_circuit_breaker_status,
+            reset_circuit_breakers,
+            get_hedging_settings,
+            set_hedging_settings,
+            get_model_fallback_settings,
+            set_model_fallback_settings,
+            get_validation_mode,
+            set_validation_mode,
+            get_response_cache_settings,
+            set_response_cache_settings,
+            get_response_cache_stats,
+            purge_response_cache,
+            get_coalescing_settings,
+            set_coalescing_settings,
+            get_rate_limit_settings,
+            set_rate_limit_settings,
+            get_scheduler_settings,
+            set_scheduler_settings,
+            get_network_settings,
+            set_network_settings,
+            get_proxy_listen_address,
+            get_tls_settings,
+            set_tls_settings,
+            get_session_settings,
+            set_session_settings,
+            get_brute_force_settings,
+            set_brute_force_settings,
+            create_provider,
+            get_all_providers,
+            update_provider,
+            delete_provider,
+            set_default_provider,
+            set_api_key_provider,
+            create_model_alias,
+            get_all_model_aliases,
+            update_model_alias,
+            delete_model_alias,
+            create_policy_rule,
+            get_all_policy_rules,
+            update_policy_rule,
+            delete_policy_rule,
+            create_script_hook,
+            get_all_script_hooks,
+            update_script_hook,
+            delete_script_hook,
+            create_client_token,
+            get_all_client_tokens,
+            revoke_client_token,
+            rotate_client_token,
+            update_client_token_scopes,
+            get_client_token_usage,
+            get_tls_certificate_info,
+            reload_tls_certificate,
+            regenerate_tls_certificate,
+            get_auth_lockouts,
+            clear_auth_lockouts,
+            get_totp_status,
+            begin_totp_enrollment,
+            confirm_totp_enrollment,
+            disable_totp,
+            regenerate_totp_recovery_codes,
+            get_audit_events,
+            export_audit_events,
+            get_admin_api_status,
+            rotate_admin_api_token,
+            revoke_admin_api_token
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,12 @@
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+
+/// `/admin` 管理 API 的状态，配置了令牌才会启用
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminApiStatus {
+    pub enabled: bool,
+    /// 令牌开头几位，用于辨认正在使用的令牌
+    pub token_prefix: Option<String>,
+    pub token_created_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,48 @@
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 管理界面的登录会话，令牌明文只在登录时返回
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminSession {
+    pub id: Uuid,
+    /// 仍在使用默认密码登录，修改密码前只能调用修改密码和登出
+    pub password_change_required: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+fn parse_timestamp(row: &sqlx::sqlite::SqliteRow, column: &str) -> Result<DateTime<Utc>, sqlx::Error> {
+    use sqlx::Row;
+
+    let value: String = row.try_get(column)?;
+    DateTime::parse_from_rfc3339(&value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .map_err(|e| sqlx::Error::ColumnDecode {
+            index: column.to_string(),
+            source: Box::new(e),
+        })
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for AdminSession {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(AdminSession {
+            id,
+            password_change_required: row.try_get::<i32, _>("password_change_required")? != 0,
+            created_at: parse_timestamp(row, "created_at")?,
+            last_seen_at: parse_timestamp(row, "last_seen_at")?,
+            expires_at: parse_timestamp(row, "expires_at")?,
+        })
+    }
+}
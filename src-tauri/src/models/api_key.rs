@@ -1,6 +1,33 @@
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
+use crate::utils::mask_key_value;
+
+/// 密钥的凭据类型：AI Studio API 密钥或 Vertex AI 服务账号 JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CredentialType {
+    #[default]
+    ApiKey,
+    VertexServiceAccount,
+}
+
+impl CredentialType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CredentialType::ApiKey => "api_key",
+            CredentialType::VertexServiceAccount => "vertex_service_account",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "api_key" => Some(CredentialType::ApiKey),
+            "vertex_service_account" => Some(CredentialType::VertexServiceAccount),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ApiKey {
@@ -10,6 +37,10 @@ pub struct ApiKey {
     pub is_active: bool,
     pub usage_count: i64,
     pub last_used: Option<DateTime<Utc>>,
+    pub provider_id: Option<Uuid>,
+    pub credential_type: CredentialType,
+    pub vertex_project: Option<String>,
+    pub vertex_location: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -38,6 +69,22 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ApiKey {
                 }))
             .transpose()?;
         
+        let provider_id_str: Option<String> = row.try_get("provider_id")?;
+        let provider_id = provider_id_str
+            .map(|s| Uuid::parse_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "provider_id".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?;
+        
+        let credential_type_str: String = row.try_get("credential_type")?;
+        let credential_type = CredentialType::parse(&credential_type_str)
+            .ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "credential_type".to_string(),
+                source: format!("unknown credential type: {}", credential_type_str).into(),
+            })?;
+        
         let created_at_str: String = row.try_get("created_at")?;
         let created_at = DateTime::parse_from_rfc3339(&created_at_str)
             .map(|dt| dt.with_timezone(&Utc))
@@ -61,6 +108,10 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ApiKey {
             is_active,
             usage_count: row.try_get("usage_count")?,
             last_used,
+            provider_id,
+            credential_type,
+            vertex_project: row.try_get("vertex_project")?,
+            vertex_location: row.try_get("vertex_location")?,
             created_at,
             updated_at,
         })
@@ -72,6 +123,11 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ApiKey {
 pub struct CreateApiKeyRequest {
     pub name: String,
     pub key_value: String,
+    pub provider_id: Option<Uuid>,
+    /// 不填写时根据密钥内容自动识别（服务账号 JSON 识别为 Vertex AI）
+    pub credential_type: Option<CredentialType>,
+    pub vertex_project: Option<String>,
+    pub vertex_location: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -90,5 +146,20 @@ pub struct ApiKeyResponse {
     pub is_active: bool,
     pub usage_count: i64,
     pub last_used: Option<DateTime<Utc>>,
+    pub provider_id: Option<Uuid>,
+    pub credential_type: CredentialType,
+    pub vertex_project: Option<String>,
+    pub vertex_location: Option<String>,
     pub created_at: DateTime<Utc>,
-}
\ No newline at end of file
+}
+
+impl ApiKeyResponse {
+    /// 通过管理 API 和命令行工具输出时隐藏密钥，服务账号 JSON 整体隐藏
+    pub fn masked(mut self) -> Self {
+        self.key_value = match self.credential_type {
+            CredentialType::VertexServiceAccount => "****".to_string(),
+            CredentialType::ApiKey => mask_key_value(&self.key_value),
+        };
+        self
+    }
+}
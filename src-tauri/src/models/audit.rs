@@ -0,0 +1,115 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 管理操作的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditChannel {
+    /// 管理界面（Tauri 命令）
+    #[default]
+    Console,
+    /// `/admin` 管理 API
+    AdminApi,
+    /// 直接操作数据库的命令行工具
+    Cli,
+}
+
+impl AuditChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Console => "console",
+            Self::AdminApi => "admin_api",
+            Self::Cli => "cli",
+        }
+    }
+
+    fn parse(value: &str) -> Self {
+        match value {
+            "admin_api" => Self::AdminApi,
+            "cli" => Self::Cli,
+            _ => Self::Console,
+        }
+    }
+}
+
+/// 一次管理操作的审计记录
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub channel: AuditChannel,
+    /// 执行操作的管理会话，登录前的操作以及管理 API 和命令行工具的操作为空
+    pub session_id: Option<Uuid>,
+    /// 管理 API 和命令行工具的操作者，例如 `admin-api@10.0.0.5`、`cli@deploy`
+    pub actor: Option<String>,
+    /// 执行的命令名，例如 `update_api_key`
+    pub action: String,
+    /// 被操作对象的 ID
+    pub target: Option<String>,
+    /// 变更的字段，格式为 `{"字段": {"before": 旧值, "after": 新值}}`，密钥类字段已脱敏
+    pub changes: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for AuditEvent {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let session_id = row
+            .try_get::<Option<String>, _>("session_id")?
+            .and_then(|value| Uuid::parse_str(&value).ok());
+
+        let channel: Option<String> = row.try_get("channel")?;
+        let changes_str: String = row.try_get("changes")?;
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(AuditEvent {
+            id,
+            channel: channel.as_deref().map(AuditChannel::parse).unwrap_or_default(),
+            session_id,
+            actor: row.try_get("actor")?,
+            action: row.try_get("action")?,
+            target: row.try_get("target")?,
+            changes: serde_json::from_str(&changes_str).unwrap_or(Value::Null),
+            created_at,
+        })
+    }
+}
+
+/// 审计记录的查询条件，为空的条件不限制
+#[cfg(feature = "desktop")]
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct AuditEventFilter {
+    /// 命令名前缀，例如 `set_` 匹配所有设置修改
+    pub action: Option<String>,
+    pub channel: Option<AuditChannel>,
+    pub session_id: Option<Uuid>,
+    pub target: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditExportFormat {
+    #[default]
+    Json,
+    Csv,
+}
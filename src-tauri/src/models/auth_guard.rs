@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "desktop")]
+use chrono::{DateTime, Utc};
+
+/// 分别统计失败次数的认证入口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AuthScope {
+    /// 管理界面登录
+    AdminLogin,
+    /// 代理接口的客户端密钥
+    Proxy,
+    /// `/admin` 管理 API 的令牌
+    AdminApi,
+}
+
+impl AuthScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::AdminLogin => "admin login",
+            Self::Proxy => "proxy auth",
+            Self::AdminApi => "admin API auth",
+        }
+    }
+}
+
+/// 被锁定的来源
+#[cfg(feature = "desktop")]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthLockout {
+    pub scope: AuthScope,
+    /// 来源 IP；管理界面登录来自本机时为 `local`，全局锁定时为 `*`
+    pub source: String,
+    /// 当前统计窗口内的失败次数
+    pub failures: u32,
+    /// 连续被锁定的次数，决定下一次锁定的时长
+    pub lockouts: u32,
+    pub locked_until: DateTime<Utc>,
+}
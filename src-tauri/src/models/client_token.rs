@@ -0,0 +1,172 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+use crate::utils::glob_match;
+
+/// 客户端令牌的权限范围，列表为空或上限为空表示不限制；
+/// 代理只转发生成内容、计数和模型列表接口，因此按模型而不按路由限制
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ClientScopes {
+    /// 允许的模型名通配符，例如 `gemini-*-flash*`
+    pub allowed_models: Vec<String>,
+    /// `generationConfig.maxOutputTokens` 的上限，请求未指定时按上限填入
+    pub max_output_tokens: Option<u32>,
+    /// 请求数和 token 数上限，按 UTC 自然日和自然月统计成功的请求；
+    /// 上限衡量的是客户端得到的响应，命中缓存和被合并的请求也按响应的 token 数计入
+    pub daily_request_limit: Option<u64>,
+    pub monthly_request_limit: Option<u64>,
+    pub daily_token_limit: Option<u64>,
+    pub monthly_token_limit: Option<u64>,
+}
+
+impl ClientScopes {
+    pub fn allows_model(&self, model: &str) -> bool {
+        self.allowed_models.is_empty() || self.allowed_models.iter().any(|pattern| glob_match(pattern, model))
+    }
+
+    pub fn has_caps(&self) -> bool {
+        self.daily_request_limit.is_some()
+            || self.monthly_request_limit.is_some()
+            || self.daily_token_limit.is_some()
+            || self.monthly_token_limit.is_some()
+    }
+}
+
+/// 客户端在当前自然日和自然月内的用量
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientUsage {
+    pub daily_requests: u64,
+    pub daily_tokens: u64,
+    pub monthly_requests: u64,
+    pub monthly_tokens: u64,
+}
+
+impl ClientUsage {
+    /// 返回第一个已达到的上限的说明
+    pub fn exceeded_cap(&self, scopes: &ClientScopes) -> Option<String> {
+        let checks = [
+            ("daily request", self.daily_requests, scopes.daily_request_limit),
+            ("monthly request", self.monthly_requests, scopes.monthly_request_limit),
+            ("daily token", self.daily_tokens, scopes.daily_token_limit),
+            ("monthly token", self.monthly_tokens, scopes.monthly_token_limit),
+        ];
+        checks.into_iter().find_map(|(name, used, limit)| {
+            limit.filter(|&limit| used >= limit).map(|limit| format!("{} cap reached ({}/{})", name, used, limit))
+        })
+    }
+}
+
+/// 令牌及其用量，供管理界面查询各客户端的消耗
+#[cfg(feature = "desktop")]
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientTokenUsage {
+    pub token: ClientToken,
+    pub usage: ClientUsage,
+}
+
+/// 有名称的客户端访问令牌，数据库只保存密文的哈希
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientToken {
+    pub id: Uuid,
+    pub name: String,
+    /// 令牌开头几位，用于在列表中辨认
+    pub token_prefix: String,
+    pub enabled: bool,
+    pub scopes: ClientScopes,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl ClientToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| expires_at <= Utc::now())
+    }
+}
+
+fn parse_optional_timestamp(row: &sqlx::sqlite::SqliteRow, column: &str) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+    use sqlx::Row;
+
+    let value: Option<String> = row.try_get(column)?;
+    value
+        .map(|s| {
+            DateTime::parse_from_rfc3339(&s)
+                .map(|dt| dt.with_timezone(&Utc))
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: column.to_string(),
+                    source: Box::new(e),
+                })
+        })
+        .transpose()
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ClientToken {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let updated_at_str: String = row.try_get("updated_at")?;
+        let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "updated_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let scopes = row.try_get::<Option<String>, _>("scopes")?
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "scopes".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        Ok(ClientToken {
+            id,
+            name: row.try_get("name")?,
+            token_prefix: row.try_get("token_prefix")?,
+            enabled: row.try_get::<i32, _>("enabled")? != 0,
+            scopes,
+            expires_at: parse_optional_timestamp(row, "expires_at")?,
+            last_used_at: parse_optional_timestamp(row, "last_used_at")?,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateClientTokenRequest {
+    pub name: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub scopes: Option<ClientScopes>,
+}
+
+/// 新建或轮换后的令牌，明文只在这里返回一次
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedClientToken {
+    pub token: ClientToken,
+    pub secret: String,
+}
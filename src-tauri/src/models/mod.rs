@@ -1,7 +1,45 @@
+#[cfg(feature = "desktop")]
 pub mod user;
 pub mod api_key;
 pub mod request_log;
+pub mod settings;
+pub mod provider;
+pub mod model_alias;
+pub mod policy;
+pub mod script_hook;
+#[cfg(feature = "desktop")]
+pub mod response_cache;
+pub mod request_scheduler;
+pub mod client_token;
+pub mod tls;
+#[cfg(feature = "desktop")]
+pub mod admin_session;
+pub mod auth_guard;
+#[cfg(feature = "desktop")]
+pub mod totp;
+pub mod audit;
+#[cfg(feature = "desktop")]
+pub mod admin_api;
 
+#[cfg(feature = "desktop")]
 pub use user::*;
 pub use api_key::*;
-pub use request_log::*;
\ No newline at end of file
+pub use request_log::*;
+pub use settings::*;
+pub use provider::*;
+pub use model_alias::*;
+pub use policy::*;
+pub use script_hook::*;
+#[cfg(feature = "desktop")]
+pub use response_cache::*;
+pub use request_scheduler::*;
+pub use client_token::*;
+pub use tls::*;
+#[cfg(feature = "desktop")]
+pub use admin_session::*;
+pub use auth_guard::*;
+#[cfg(feature = "desktop")]
+pub use totp::*;
+pub use audit::*;
+#[cfg(feature = "desktop")]
+pub use admin_api::*;
@@ -0,0 +1,71 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 面向客户端的模型别名，转发前替换为真实模型名
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelAlias {
+    pub id: Uuid,
+    pub alias: String,
+    pub target_model: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ModelAlias {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let updated_at_str: String = row.try_get("updated_at")?;
+        let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "updated_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(ModelAlias {
+            id,
+            alias: row.try_get("alias")?,
+            target_model: row.try_get("target_model")?,
+            description: row.try_get("description")?,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateModelAliasRequest {
+    pub alias: String,
+    pub target_model: String,
+    pub description: Option<String>,
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateModelAliasRequest {
+    pub alias: Option<String>,
+    pub target_model: Option<String>,
+    pub description: Option<String>,
+}
@@ -0,0 +1,134 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 策略动作，`path` 为点分隔的请求体字段路径，例如 `generationConfig.maxOutputTokens`；
+/// 字段同时匹配 camelCase 和 snake_case 写法
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum PolicyAction {
+    /// 字段缺失时设置默认值
+    SetDefault { path: String, value: Value },
+    /// 无论客户端是否提供都覆盖为指定值
+    Force { path: String, value: Value },
+    /// 把数值字段限制在范围内
+    Clamp { path: String, min: Option<f64>, max: Option<f64> },
+    /// 删除字段
+    Strip { path: String },
+    /// 在 systemInstruction 前插入一段文本
+    PrependSystemInstruction { text: String },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PolicyRule {
+    pub id: Uuid,
+    pub name: String,
+    pub enabled: bool,
+    /// 数值小的先应用
+    pub priority: i32,
+    /// 匹配的模型，支持 `*` 通配符；为空时匹配所有模型
+    pub model_patterns: Vec<String>,
+    /// 匹配的客户端令牌名称，支持 `*` 通配符；为空时匹配所有客户端
+    pub client_tokens: Vec<String>,
+    pub actions: Vec<PolicyAction>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for PolicyRule {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let model_patterns = row.try_get::<Option<String>, _>("model_patterns")?
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "model_patterns".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let client_tokens = row.try_get::<Option<String>, _>("client_tokens")?
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "client_tokens".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let actions = row.try_get::<Option<String>, _>("actions")?
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "actions".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let enabled_int: i32 = row.try_get("enabled")?;
+
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let updated_at_str: String = row.try_get("updated_at")?;
+        let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "updated_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(PolicyRule {
+            id,
+            name: row.try_get("name")?,
+            enabled: enabled_int != 0,
+            priority: row.try_get("priority")?,
+            model_patterns,
+            client_tokens,
+            actions,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePolicyRuleRequest {
+    pub name: String,
+    pub enabled: Option<bool>,
+    pub priority: Option<i32>,
+    #[serde(default)]
+    pub model_patterns: Vec<String>,
+    #[serde(default)]
+    pub client_tokens: Vec<String>,
+    pub actions: Vec<PolicyAction>,
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePolicyRuleRequest {
+    pub name: Option<String>,
+    pub enabled: Option<bool>,
+    pub priority: Option<i32>,
+    pub model_patterns: Option<Vec<String>>,
+    pub client_tokens: Option<Vec<String>>,
+    pub actions: Option<Vec<PolicyAction>>,
+}
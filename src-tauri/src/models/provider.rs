@@ -0,0 +1,239 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+/// 供应商使用的上游 API 形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ProviderType {
+    /// Google AI Studio（generativelanguage）风格的 API，使用 `?key=` 认证
+    #[default]
+    Gemini,
+    /// Vertex AI，使用服务账号换取的 OAuth2 访问令牌认证
+    Vertex,
+    /// OpenAI 兼容的 Chat Completions API，请求和响应在代理中转换格式
+    #[serde(rename = "openai")]
+    OpenAi,
+}
+
+impl ProviderType {
+    #[cfg(feature = "desktop")]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderType::Gemini => "gemini",
+            ProviderType::Vertex => "vertex",
+            ProviderType::OpenAi => "openai",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "gemini" => Some(ProviderType::Gemini),
+            "vertex" => Some(ProviderType::Vertex),
+            "openai" => Some(ProviderType::OpenAi),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provider {
+    pub id: Uuid,
+    pub name: String,
+    pub provider_type: ProviderType,
+    pub base_url: String,
+    /// 客户端 API 版本到上游 API 版本的映射，例如 `{"v1": "v1beta"}`
+    pub api_version_map: HashMap<String, String>,
+    pub extra_headers: HashMap<String, String>,
+    pub timeout_secs: u64,
+    /// Vertex AI 的令牌交换地址，为空时使用服务账号 JSON 中的 token_uri
+    pub token_url: Option<String>,
+    /// OpenAI 兼容供应商处理的模型名前缀，例如 `gpt-`
+    pub model_prefixes: Vec<String>,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Provider {
+    /// 熔断器按上游主机统计，同一主机上的多个供应商共享一个熔断器
+    pub fn upstream_host(&self) -> String {
+        match url::Url::parse(&self.base_url) {
+            Ok(url) => match (url.host_str(), url.port()) {
+                (Some(host), Some(port)) => format!("{}:{}", host, port),
+                (Some(host), None) => host.to_string(),
+                _ => self.base_url.clone(),
+            },
+            Err(_) => self.base_url.clone(),
+        }
+    }
+
+    /// 把客户端路径转换为上游路径，按映射替换开头的 API 版本段
+    pub fn upstream_path(&self, path: &str) -> String {
+        let trimmed = path.trim_start_matches('/');
+        let (version, rest) = trimmed.split_once('/').unwrap_or((trimmed, ""));
+
+        match self.api_version_map.get(version) {
+            Some(mapped) if rest.is_empty() => format!("/{}", mapped),
+            Some(mapped) => format!("/{}/{}", mapped, rest),
+            None => format!("/{}", trimmed),
+        }
+    }
+
+    pub fn upstream_url(&self, path: &str) -> String {
+        format!("{}{}", self.base_url.trim_end_matches('/'), self.upstream_path(path))
+    }
+
+    /// OpenAI 兼容供应商的基础地址包含版本段，例如 `https://api.openai.com/v1`
+    pub fn chat_completions_url(&self) -> String {
+        format!("{}/chat/completions", self.base_url.trim_end_matches('/'))
+    }
+
+    pub fn serves_model(&self, model: &str) -> bool {
+        self.model_prefixes
+            .iter()
+            .any(|prefix| !prefix.is_empty() && model.starts_with(prefix.as_str()))
+    }
+
+    /// 把 `/v1beta/models/{model}:{method}` 改写为 Vertex AI 的发布者模型路径；
+    /// 不是针对单个模型的调用时返回 None
+    pub fn vertex_url(&self, path: &str, project: &str, location: &str) -> Option<String> {
+        let trimmed = path.trim_start_matches('/');
+        let (client_version, _) = trimmed.split_once('/')?;
+        let model_call = trimmed.split("/models/").nth(1)?;
+        if !model_call.contains(':') {
+            return None;
+        }
+
+        let version = self.api_version_map
+            .get(client_version)
+            .map(String::as_str)
+            .unwrap_or("v1");
+
+        Some(format!(
+            "{}/{}/projects/{}/locations/{}/publishers/google/models/{}",
+            self.base_url.trim_end_matches('/'),
+            version,
+            project,
+            location,
+            model_call
+        ))
+    }
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for Provider {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let api_version_map_str: Option<String> = row.try_get("api_version_map")?;
+        let api_version_map = api_version_map_str
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "api_version_map".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let extra_headers_str: Option<String> = row.try_get("extra_headers")?;
+        let extra_headers = extra_headers_str
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "extra_headers".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let model_prefixes_str: Option<String> = row.try_get("model_prefixes")?;
+        let model_prefixes = model_prefixes_str
+            .map(|s| serde_json::from_str(&s)
+                .map_err(|e| sqlx::Error::ColumnDecode {
+                    index: "model_prefixes".to_string(),
+                    source: Box::new(e),
+                }))
+            .transpose()?
+            .unwrap_or_default();
+
+        let provider_type_str: String = row.try_get("provider_type")?;
+        let provider_type = ProviderType::parse(&provider_type_str)
+            .ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "provider_type".to_string(),
+                source: format!("unknown provider type: {}", provider_type_str).into(),
+            })?;
+
+        let timeout_secs: i64 = row.try_get("timeout_secs")?;
+        let is_default_int: i32 = row.try_get("is_default")?;
+
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let updated_at_str: String = row.try_get("updated_at")?;
+        let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "updated_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(Provider {
+            id,
+            name: row.try_get("name")?,
+            provider_type,
+            base_url: row.try_get("base_url")?,
+            api_version_map,
+            extra_headers,
+            timeout_secs: timeout_secs.max(1) as u64,
+            token_url: row.try_get("token_url")?,
+            model_prefixes,
+            is_default: is_default_int != 0,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProviderRequest {
+    pub name: String,
+    #[serde(default)]
+    pub provider_type: ProviderType,
+    pub base_url: String,
+    #[serde(default)]
+    pub api_version_map: HashMap<String, String>,
+    #[serde(default)]
+    pub extra_headers: HashMap<String, String>,
+    pub timeout_secs: Option<u64>,
+    pub token_url: Option<String>,
+    #[serde(default)]
+    pub model_prefixes: Vec<String>,
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProviderRequest {
+    pub name: Option<String>,
+    pub base_url: Option<String>,
+    pub api_version_map: Option<HashMap<String, String>>,
+    pub extra_headers: Option<HashMap<String, String>>,
+    pub timeout_secs: Option<u64>,
+    pub token_url: Option<String>,
+    pub model_prefixes: Option<Vec<String>>,
+}
@@ -1,60 +1,7 @@
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use chrono::{DateTime, Utc};
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct RequestLog {
-    pub id: Uuid,
-    pub api_key_id: Uuid,
-    pub method: String,
-    pub path: String,
-    pub status_code: i32,
-    pub response_time_ms: i64,
-    pub request_body: Option<String>,
-    pub response_body: Option<String>,
-    pub created_at: DateTime<Utc>,
-}
-
-impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for RequestLog {
-    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
-        use sqlx::Row;
-        
-        let id_str: String = row.try_get("id")?;
-        let id = Uuid::parse_str(&id_str)
-            .map_err(|e| sqlx::Error::ColumnDecode {
-                index: "id".to_string(),
-                source: Box::new(e),
-            })?;
-        
-        let api_key_id_str: String = row.try_get("api_key_id")?;
-        let api_key_id = Uuid::parse_str(&api_key_id_str)
-            .map_err(|e| sqlx::Error::ColumnDecode {
-                index: "api_key_id".to_string(),
-                source: Box::new(e),
-            })?;
-        
-        let created_at_str: String = row.try_get("created_at")?;
-        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
-            .map(|dt| dt.with_timezone(&Utc))
-            .map_err(|e| sqlx::Error::ColumnDecode {
-                index: "created_at".to_string(),
-                source: Box::new(e),
-            })?;
-        
-        Ok(RequestLog {
-            id,
-            api_key_id,
-            method: row.try_get("method")?,
-            path: row.try_get("path")?,
-            status_code: row.try_get("status_code")?,
-            response_time_ms: row.try_get("response_time_ms")?,
-            request_body: row.try_get("request_body").ok(),
-            response_body: row.try_get("response_body").ok(),
-            created_at,
-        })
-    }
-}
-
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct RequestLogResponse {
@@ -66,6 +13,18 @@ pub struct RequestLogResponse {
     pub response_time_ms: i64,
     pub request_body: Option<String>,
     pub response_body: Option<String>,
+    /// 发生模型降级时客户端原本请求的模型
+    pub fallback_from: Option<String>,
+    /// 本次请求应用的策略规则名称（JSON 数组）
+    pub applied_policies: Option<String>,
+    /// 响应来自缓存，没有请求上游
+    pub cache_hit: bool,
+    /// 与相同的并发请求合并，没有单独请求上游
+    pub coalesced: bool,
+    /// 发起请求的客户端令牌名称，使用全局自定义密钥时为空
+    pub client_token_name: Option<String>,
+    /// 成功响应的 usageMetadata.totalTokenCount
+    pub total_tokens: Option<i64>,
     pub created_at: DateTime<Utc>,
 }
 
@@ -97,6 +56,12 @@ impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for RequestLogResponse {
             response_time_ms: row.try_get("response_time_ms")?,
             request_body: row.try_get("request_body").ok(),
             response_body: row.try_get("response_body").ok(),
+            fallback_from: row.try_get("fallback_from").ok().flatten(),
+            applied_policies: row.try_get("applied_policies").ok().flatten(),
+            cache_hit: row.try_get::<Option<i32>, _>("cache_hit").ok().flatten().unwrap_or(0) != 0,
+            coalesced: row.try_get::<Option<i32>, _>("coalesced").ok().flatten().unwrap_or(0) != 0,
+            client_token_name: row.try_get("client_token_name").ok().flatten(),
+            total_tokens: row.try_get("total_tokens").ok().flatten(),
             created_at,
         })
     }
@@ -0,0 +1,24 @@
+use crate::models::RequestPriority;
+use serde::Serialize;
+
+/// 某个优先级的排队情况，累计值从进程启动开始计算
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PriorityQueueStats {
+    pub priority: RequestPriority,
+    /// 当前排队的请求数
+    pub depth: usize,
+    /// 排队后取得密钥的请求数
+    pub served: u64,
+    /// 超过最长等待时间被拒绝的请求数
+    pub timed_out: u64,
+    pub average_wait_ms: f64,
+    pub max_wait_ms: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchedulerStats {
+    pub depth: usize,
+    pub priorities: Vec<PriorityQueueStats>,
+}
@@ -0,0 +1,16 @@
+use serde::Serialize;
+use chrono::{DateTime, Utc};
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResponseCacheStats {
+    pub entries: i64,
+    pub expired_entries: i64,
+    pub size_bytes: i64,
+    /// 当前条目的命中次数之和
+    pub entry_hits: i64,
+    /// 请求日志中记录的缓存命中总数
+    pub logged_hits: i64,
+    pub oldest_entry_at: Option<DateTime<Utc>>,
+    pub newest_entry_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+/// 脚本钩子的执行时机
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HookStage {
+    /// 转发前，`body` 为请求体
+    PreRequest,
+    /// 非流式请求返回后，`body` 为响应体
+    PostResponse,
+    /// 流式响应的每个数据块，`body` 为该块的 JSON
+    StreamChunk,
+}
+
+impl HookStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HookStage::PreRequest => "pre_request",
+            HookStage::PostResponse => "post_response",
+            HookStage::StreamChunk => "stream_chunk",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "pre_request" => Some(HookStage::PreRequest),
+            "post_response" => Some(HookStage::PostResponse),
+            "stream_chunk" => Some(HookStage::StreamChunk),
+            _ => None,
+        }
+    }
+}
+
+/// 每个脚本默认的执行时间预算（毫秒）
+pub const DEFAULT_HOOK_TIME_BUDGET_MS: i64 = 50;
+/// 每个脚本默认的大小预算（KB）。Rhai 无法统计脚本占用的总内存，该预算只限制单个字符串的字节数，
+/// 以及数组和对象的元素个数（按每个元素约 32 字节折算）；需要容纳请求体中的内联数据
+pub const DEFAULT_HOOK_MEMORY_BUDGET_KB: i64 = 8192;
+
+/// 用 Rhai 编写的脚本钩子。脚本中可读写 `body`，并可读取 `meta`（model、path、method、client、stream、status_code）
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScriptHook {
+    pub id: Uuid,
+    pub name: String,
+    pub stage: HookStage,
+    pub enabled: bool,
+    /// 数值小的先执行
+    pub priority: i32,
+    pub source: String,
+    pub time_budget_ms: i64,
+    pub memory_budget_kb: i64,
+    /// 脚本出错或超出预算时拒绝请求；默认跳过该钩子继续处理
+    pub fail_closed: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl sqlx::FromRow<'_, sqlx::sqlite::SqliteRow> for ScriptHook {
+    fn from_row(row: &sqlx::sqlite::SqliteRow) -> Result<Self, sqlx::Error> {
+        use sqlx::Row;
+
+        let id_str: String = row.try_get("id")?;
+        let id = Uuid::parse_str(&id_str)
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "id".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let stage_str: String = row.try_get("stage")?;
+        let stage = HookStage::parse(&stage_str)
+            .ok_or_else(|| sqlx::Error::ColumnDecode {
+                index: "stage".to_string(),
+                source: format!("unknown hook stage: {}", stage_str).into(),
+            })?;
+
+        let enabled_int: i32 = row.try_get("enabled")?;
+
+        let created_at_str: String = row.try_get("created_at")?;
+        let created_at = DateTime::parse_from_rfc3339(&created_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "created_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        let updated_at_str: String = row.try_get("updated_at")?;
+        let updated_at = DateTime::parse_from_rfc3339(&updated_at_str)
+            .map(|dt| dt.with_timezone(&Utc))
+            .map_err(|e| sqlx::Error::ColumnDecode {
+                index: "updated_at".to_string(),
+                source: Box::new(e),
+            })?;
+
+        Ok(ScriptHook {
+            id,
+            name: row.try_get("name")?,
+            stage,
+            enabled: enabled_int != 0,
+            priority: row.try_get("priority")?,
+            source: row.try_get("source")?,
+            time_budget_ms: row.try_get::<Option<i64>, _>("time_budget_ms")?.unwrap_or(DEFAULT_HOOK_TIME_BUDGET_MS),
+            memory_budget_kb: row.try_get::<Option<i64>, _>("memory_budget_kb")?.unwrap_or(DEFAULT_HOOK_MEMORY_BUDGET_KB),
+            fail_closed: row.try_get::<Option<i32>, _>("fail_closed")?.unwrap_or(0) != 0,
+            created_at,
+            updated_at,
+        })
+    }
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScriptHookRequest {
+    pub name: String,
+    pub stage: HookStage,
+    pub enabled: Option<bool>,
+    pub priority: Option<i32>,
+    pub source: String,
+    pub time_budget_ms: Option<i64>,
+    pub memory_budget_kb: Option<i64>,
+    pub fail_closed: Option<bool>,
+}
+
+#[cfg(feature = "desktop")]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateScriptHookRequest {
+    pub name: Option<String>,
+    pub stage: Option<HookStage>,
+    pub enabled: Option<bool>,
+    pub priority: Option<i32>,
+    pub source: Option<String>,
+    pub time_budget_ms: Option<i64>,
+    pub memory_budget_kb: Option<i64>,
+    pub fail_closed: Option<bool>,
+}
+
+/// 传给脚本的请求元数据（脚本中的 `meta`）
+#[derive(Debug, Clone, Serialize)]
+pub struct HookMetadata {
+    pub model: String,
+    pub path: String,
+    pub method: String,
+    pub client: String,
+    pub stream: bool,
+    pub status_code: Option<i32>,
+}
@@ -0,0 +1,491 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CircuitBreakerSettings {
+    pub failure_threshold: u32,
+    pub open_duration_secs: u64,
+    pub half_open_max_requests: u32,
+}
+
+impl Default for CircuitBreakerSettings {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 5,
+            open_duration_secs: 30,
+            half_open_max_requests: 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HedgingSettings {
+    pub enabled: bool,
+    /// 以该分位数的历史延迟作为发送对冲请求前的等待时间
+    pub percentile: f64,
+    pub min_delay_ms: u64,
+    pub max_delay_ms: u64,
+    /// 启用对冲的模型，支持 `*` 通配符；与 routes 都为空时对所有 generateContent 请求生效
+    pub models: Vec<String>,
+    /// 启用对冲的请求路径，支持 `*` 通配符
+    pub routes: Vec<String>,
+}
+
+impl Default for HedgingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            percentile: 95.0,
+            min_delay_ms: 300,
+            max_delay_ms: 5000,
+            models: Vec::new(),
+            routes: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ModelFallbackSettings {
+    pub enabled: bool,
+    /// 降级链，按顺序尝试，例如 `["gemini-2.5-pro", "gemini-2.5-flash", "gemini-2.5-flash-lite"]`
+    pub chains: Vec<Vec<String>>,
+}
+
+impl ModelFallbackSettings {
+    /// 请求模型之后依次尝试的降级模型，未启用或模型不在任何链中时为空
+    pub fn fallbacks_for(&self, model: &str) -> Vec<String> {
+        if !self.enabled {
+            return Vec::new();
+        }
+
+        self.chains
+            .iter()
+            .find_map(|chain| {
+                let position = chain.iter().position(|m| m == model)?;
+                Some(chain[position + 1..].to_vec())
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// 请求体校验模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "camelCase")]
+pub enum ValidationMode {
+    /// 按 GenerateContentRequest 结构校验，不合法时返回 400
+    #[default]
+    Strict,
+    /// 不做校验，原样转发给上游
+    Passthrough,
+}
+
+impl ValidationMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ValidationMode::Strict => "strict",
+            ValidationMode::Passthrough => "passthrough",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "passthrough" => ValidationMode::Passthrough,
+            _ => ValidationMode::Strict,
+        }
+    }
+}
+
+/// 非流式 generateContent 的精确匹配响应缓存
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ResponseCacheSettings {
+    pub enabled: bool,
+    pub ttl_secs: u64,
+    /// 缓存总大小上限，超出时淘汰最旧的条目
+    pub max_size_mb: u64,
+}
+
+impl Default for ResponseCacheSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ttl_secs: 3600,
+            max_size_mb: 100,
+        }
+    }
+}
+
+/// 合并相同的并发请求
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CoalescingSettings {
+    pub enabled: bool,
+    /// 只合并 `temperature` 为 0 的请求，避免需要多样结果的客户端拿到相同的回答
+    pub deterministic_only: bool,
+}
+
+impl Default for CoalescingSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            deterministic_only: true,
+        }
+    }
+}
+
+/// 一组限流规则，各项为 0 表示不限制
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimitRule {
+    /// 令牌桶每分钟补充的请求数
+    pub requests_per_minute: u32,
+    /// 令牌桶容量（允许的突发请求数），为 0 时等于 requests_per_minute
+    pub burst: u32,
+    /// 同时处理的最大请求数
+    pub max_concurrent: u32,
+}
+
+impl RateLimitRule {
+    pub fn capacity(&self) -> f64 {
+        if self.burst > 0 { self.burst as f64 } else { self.requests_per_minute as f64 }
+    }
+}
+
+/// 受保护路由的入站限流
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RateLimitSettings {
+    pub enabled: bool,
+    /// 所有客户端共享的限制
+    pub global: RateLimitRule,
+    /// 每个客户端凭据各自的默认限制
+    pub per_client: RateLimitRule,
+    /// 按客户端名称覆盖 per_client
+    pub client_overrides: HashMap<String, RateLimitRule>,
+    /// 超出限制时最多排队等待的毫秒数，0 表示直接拒绝
+    pub queue_timeout_ms: u64,
+    /// 同时排队的最大请求数，超出时直接拒绝；为 0 时不排队
+    pub max_queued: u32,
+}
+
+impl RateLimitSettings {
+    pub fn rule_for(&self, client: &str) -> &RateLimitRule {
+        self.client_overrides.get(client).unwrap_or(&self.per_client)
+    }
+}
+
+/// 请求等待空闲密钥时的优先级，排队时按权重轮流取得密钥，高优先级得到的份额更多
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RequestPriority {
+    Interactive,
+    #[default]
+    Normal,
+    Batch,
+}
+
+impl RequestPriority {
+    pub const ALL: [RequestPriority; 3] = [Self::Interactive, Self::Normal, Self::Batch];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Interactive => "interactive",
+            Self::Normal => "normal",
+            Self::Batch => "batch",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "interactive" | "high" => Some(Self::Interactive),
+            "normal" | "default" => Some(Self::Normal),
+            "batch" | "low" => Some(Self::Batch),
+            _ => None,
+        }
+    }
+}
+
+/// 所有密钥都在冷却时的排队调度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SchedulerSettings {
+    pub enabled: bool,
+    /// 各优先级最多排队等待的毫秒数，超过后按熔断错误返回
+    pub interactive_max_wait_ms: u64,
+    pub normal_max_wait_ms: u64,
+    pub batch_max_wait_ms: u64,
+    /// 按客户端名称指定的默认优先级，请求头可以覆盖
+    pub client_priorities: HashMap<String, RequestPriority>,
+}
+
+impl Default for SchedulerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interactive_max_wait_ms: 5_000,
+            normal_max_wait_ms: 30_000,
+            batch_max_wait_ms: 120_000,
+            client_priorities: HashMap::new(),
+        }
+    }
+}
+
+impl SchedulerSettings {
+    pub fn max_wait(&self, priority: RequestPriority) -> std::time::Duration {
+        let ms = match priority {
+            RequestPriority::Interactive => self.interactive_max_wait_ms,
+            RequestPriority::Normal => self.normal_max_wait_ms,
+            RequestPriority::Batch => self.batch_max_wait_ms,
+        };
+        std::time::Duration::from_millis(ms)
+    }
+
+    /// 请求头优先，其次是客户端的默认优先级
+    pub fn priority_for(&self, client: &str, header: Option<&str>) -> RequestPriority {
+        header
+            .and_then(RequestPriority::parse)
+            .or_else(|| self.client_priorities.get(client).copied())
+            .unwrap_or_default()
+    }
+}
+
+/// 代理监听地址和来源 IP 访问控制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NetworkSettings {
+    pub host: String,
+    pub port: u16,
+    /// 端口被占用时依次尝试后面的端口个数，0 表示不尝试
+    pub port_fallback_attempts: u16,
+    /// 允许访问的来源（CIDR 或单个 IP），为空表示允许所有来源
+    pub allow_cidrs: Vec<String>,
+    /// 拒绝访问的来源，优先于允许列表
+    pub deny_cidrs: Vec<String>,
+}
+
+impl Default for NetworkSettings {
+    /// 默认只监听本机，对局域网开放需要显式把 host 改为 0.0.0.0 或网卡地址
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 5675,
+            port_fallback_attempts: 10,
+            allow_cidrs: Vec::new(),
+            deny_cidrs: Vec::new(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// 监听地址相关的配置是否不同，只有这些变化需要重新绑定端口
+    pub fn listener_changed(&self, other: &NetworkSettings) -> bool {
+        self.host != other.host || self.port != other.port || self.port_fallback_attempts != other.port_fallback_attempts
+    }
+
+    /// 按这些设置绑定时可能尝试的地址中，是否有与 `addr` 冲突的；
+    /// 任一方监听 0.0.0.0 或 :: 时与同端口的其它地址也冲突
+    pub fn conflicts_with(&self, addr: SocketAddr) -> bool {
+        let Ok(host) = self.host.parse::<IpAddr>() else {
+            return false;
+        };
+        let last_port = self.port.saturating_add(self.port_fallback_attempts);
+        (self.port..=last_port).contains(&addr.port())
+            && (host == addr.ip() || host.is_unspecified() || addr.ip().is_unspecified())
+    }
+}
+
+/// 代理监听器的 HTTPS 设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TlsSettings {
+    pub enabled: bool,
+    /// PEM 证书链文件路径，与 key_path 同时配置时使用，否则使用自签名证书
+    pub cert_path: Option<String>,
+    pub key_path: Option<String>,
+    /// 自签名证书的 SAN（域名或 IP），修改后重新生成证书
+    pub subject_alt_names: Vec<String>,
+}
+
+impl Default for TlsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cert_path: None,
+            key_path: None,
+            subject_alt_names: vec!["localhost".to_string(), "127.0.0.1".to_string()],
+        }
+    }
+}
+
+impl TlsSettings {
+    /// 证书和私钥文件路径，两者都配置且非空时返回
+    pub fn file_paths(&self) -> Option<(&str, &str)> {
+        let cert = self.cert_path.as_deref().map(str::trim).filter(|p| !p.is_empty())?;
+        let key = self.key_path.as_deref().map(str::trim).filter(|p| !p.is_empty())?;
+        Some((cert, key))
+    }
+}
+
+/// 管理界面会话的有效期
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct SessionSettings {
+    /// 登录后会话的最长有效时间
+    pub ttl_minutes: u64,
+    /// 超过该时间没有调用任何管理命令时会话失效
+    pub idle_timeout_minutes: u64,
+}
+
+impl Default for SessionSettings {
+    fn default() -> Self {
+        Self {
+            ttl_minutes: 12 * 60,
+            idle_timeout_minutes: 30,
+        }
+    }
+}
+
+/// 管理登录和代理认证的失败次数限制
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BruteForceSettings {
+    pub enabled: bool,
+    /// 同一来源在统计窗口内允许的失败次数，超过后锁定该来源
+    pub max_failures_per_ip: u32,
+    /// 所有来源合计在统计窗口内允许的失败次数，超过后锁定全部来源，0 表示不限制
+    pub global_max_failures: u32,
+    pub window_secs: u64,
+    /// 第一次锁定的时长，之后每次锁定翻倍
+    pub base_lockout_secs: u64,
+    pub max_lockout_secs: u64,
+}
+
+impl Default for BruteForceSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            max_failures_per_ip: 5,
+            global_max_failures: 100,
+            window_secs: 300,
+            base_lockout_secs: 30,
+            max_lockout_secs: 3600,
+        }
+    }
+}
+
+impl BruteForceSettings {
+    /// 第 n 次（从 0 开始）锁定的时长
+    pub fn lockout_duration(&self, previous_lockouts: u32) -> std::time::Duration {
+        let secs = self.base_lockout_secs.max(1)
+            .saturating_mul(1u64 << previous_lockouts.min(32))
+            .min(self.max_lockout_secs.max(1));
+        std::time::Duration::from_secs(secs)
+    }
+}
+
+/// 可以按名称读写的设置名称，用于管理 API 的 `/admin/settings/{name}` 和命令行工具
+pub const SETTING_NAMES: &[&str] = &[
+    "retry-count",
+    "circuit-breaker",
+    "hedging",
+    "model-fallback",
+    "validation-mode",
+    "response-cache",
+    "coalescing",
+    "rate-limit",
+    "scheduler",
+    "network",
+    "tls",
+    "session",
+    "brute-force",
+];
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingValueError {
+    #[error("Unknown setting '{0}'")]
+    Unknown(String),
+    #[error("Invalid settings: {0}")]
+    Invalid(#[from] serde_json::Error),
+}
+
+/// 按名称读写的一项设置，序列化结果与对应的设置结构相同
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+pub enum SettingValue {
+    RetryCount(i32),
+    CircuitBreaker(CircuitBreakerSettings),
+    Hedging(HedgingSettings),
+    ModelFallback(ModelFallbackSettings),
+    ValidationMode(ValidationMode),
+    ResponseCache(ResponseCacheSettings),
+    Coalescing(CoalescingSettings),
+    RateLimit(RateLimitSettings),
+    Scheduler(SchedulerSettings),
+    Network(NetworkSettings),
+    Tls(TlsSettings),
+    Session(SessionSettings),
+    BruteForce(BruteForceSettings),
+}
+
+impl SettingValue {
+    /// 按设置名称解析 JSON，格式与读取结果相同
+    pub fn from_json(name: &str, value: serde_json::Value) -> Result<Self, SettingValueError> {
+        Ok(match name {
+            "retry-count" => Self::RetryCount(serde_json::from_value(value)?),
+            "circuit-breaker" => Self::CircuitBreaker(serde_json::from_value(value)?),
+            "hedging" => Self::Hedging(serde_json::from_value(value)?),
+            "model-fallback" => Self::ModelFallback(serde_json::from_value(value)?),
+            "validation-mode" => Self::ValidationMode(serde_json::from_value(value)?),
+            "response-cache" => Self::ResponseCache(serde_json::from_value(value)?),
+            "coalescing" => Self::Coalescing(serde_json::from_value(value)?),
+            "rate-limit" => Self::RateLimit(serde_json::from_value(value)?),
+            "scheduler" => Self::Scheduler(serde_json::from_value(value)?),
+            "network" => Self::Network(serde_json::from_value(value)?),
+            "tls" => Self::Tls(serde_json::from_value(value)?),
+            "session" => Self::Session(serde_json::from_value(value)?),
+            "brute-force" => Self::BruteForce(serde_json::from_value(value)?),
+            _ => return Err(SettingValueError::Unknown(name.to_string())),
+        })
+    }
+
+    /// 修改这项设置时记录的审计操作名，与管理界面的命令名相同
+    pub fn audit_action(&self) -> &'static str {
+        match self {
+            Self::RetryCount(_) => "set_retry_count",
+            Self::CircuitBreaker(_) => "set_circuit_breaker_settings",
+            Self::Hedging(_) => "set_hedging_settings",
+            Self::ModelFallback(_) => "set_model_fallback_settings",
+            Self::ValidationMode(_) => "set_validation_mode",
+            Self::ResponseCache(_) => "set_response_cache_settings",
+            Self::Coalescing(_) => "set_coalescing_settings",
+            Self::RateLimit(_) => "set_rate_limit_settings",
+            Self::Scheduler(_) => "set_scheduler_settings",
+            Self::Network(_) => "set_network_settings",
+            Self::Tls(_) => "set_tls_settings",
+            Self::Session(_) => "set_session_settings",
+            Self::BruteForce(_) => "set_brute_force_settings",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn held_fallback_port_conflicts_with_the_new_listener() {
+        let settings = NetworkSettings { port: 5675, port_fallback_attempts: 2, ..NetworkSettings::default() };
+        assert!(settings.conflicts_with("127.0.0.1:5675".parse().unwrap()));
+        assert!(settings.conflicts_with("127.0.0.1:5677".parse().unwrap()));
+        assert!(settings.conflicts_with("0.0.0.0:5676".parse().unwrap()));
+        assert!(!settings.conflicts_with("127.0.0.1:5678".parse().unwrap()));
+        assert!(!settings.conflicts_with("192.168.1.5:5675".parse().unwrap()));
+
+        let lan = NetworkSettings { host: "0.0.0.0".to_string(), ..settings };
+        assert!(lan.conflicts_with("127.0.0.1:5676".parse().unwrap()));
+    }
+}
@@ -0,0 +1,26 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// 证书来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CertificateSource {
+    /// 从配置的 PEM 文件读取
+    File,
+    /// 首次启动时生成并保存在数据库中的自签名证书
+    SelfSigned,
+}
+
+/// 当前使用的 HTTPS 证书，客户端可用指纹固定自签名证书
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsCertificateInfo {
+    pub source: CertificateSource,
+    /// 叶子证书 DER 的 SHA-256，冒号分隔的大写十六进制
+    pub fingerprint_sha256: String,
+    /// 自签名证书的 SAN，文件证书为空
+    pub subject_alt_names: Vec<String>,
+    pub generated_at: Option<DateTime<Utc>>,
+    /// 代理当前是否以 HTTPS 提供服务
+    pub active: bool,
+}
@@ -0,0 +1,20 @@
+use serde::Serialize;
+
+/// 开始绑定两步验证时返回的密钥，确认验证码之前不会生效
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpEnrollment {
+    /// Base32 编码的密钥，无法扫码时手动输入
+    pub secret: String,
+    /// 用于生成二维码的 `otpauth://totp/...` 地址
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TotpStatus {
+    pub enabled: bool,
+    /// 已开始绑定但尚未确认
+    pub pending: bool,
+    pub recovery_codes_remaining: usize,
+}
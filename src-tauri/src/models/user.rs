@@ -14,6 +14,9 @@ pub struct AppSettings {
 #[serde(rename_all = "camelCase")]
 pub struct LoginRequest {
     pub password: String,
+    /// 启用两步验证后需要的动态验证码或恢复码
+    #[serde(default)]
+    pub totp_code: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -28,4 +31,16 @@ pub struct ChangePasswordRequest {
 pub struct LoginResponse {
     pub success: bool,
     pub session_token: String,
-}
\ No newline at end of file
+    pub expires_at: DateTime<Utc>,
+    /// 仍在使用默认密码，需要先修改密码才能进行其他管理操作
+    pub password_change_required: bool,
+}
+
+/// 登录结果，密码正确但缺少或填错第二因素时不签发会话
+#[derive(Debug)]
+pub enum LoginOutcome {
+    Success(LoginResponse),
+    InvalidPassword,
+    SecondFactorRequired,
+    InvalidSecondFactor,
+}
@@ -0,0 +1,465 @@
+use axum::{
+    extract::{ConnectInfo, Extension, Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Json, Response},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::models::{
+    ApiKeyResponse, AuditChannel, AuthScope, ClientToken, CreateApiKeyRequest, CreateClientTokenRequest, IssuedClientToken,
+    RequestLogResponse, SettingValue, SettingValueError, UpdateApiKeyRequest, SETTING_NAMES,
+};
+use crate::server::middleware::locked_out_response;
+use crate::services::{peer_source, snapshot, ApiKeyService, AuditService, AuthGuardService, ClientTokenService, CustomAuthService, RequestLogService, SettingsService, TlsService};
+use crate::utils::ValidationError;
+
+/// 管理 API 的错误，响应体与代理接口的错误格式一致
+#[derive(Debug, thiserror::Error)]
+pub enum AdminApiError {
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("{0}")]
+    NotFound(String),
+    #[error("{0}")]
+    AlreadyExists(String),
+    #[error("{0}")]
+    Unauthenticated(String),
+    #[error("{0}")]
+    PermissionDenied(String),
+    #[error(transparent)]
+    Internal(#[from] anyhow::Error),
+}
+
+impl AdminApiError {
+    pub fn status(&self) -> StatusCode {
+        match self {
+            Self::InvalidArgument(_) => StatusCode::BAD_REQUEST,
+            Self::NotFound(_) => StatusCode::NOT_FOUND,
+            Self::AlreadyExists(_) => StatusCode::CONFLICT,
+            Self::Unauthenticated(_) => StatusCode::UNAUTHORIZED,
+            Self::PermissionDenied(_) => StatusCode::FORBIDDEN,
+            Self::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidArgument(_) => "INVALID_ARGUMENT",
+            Self::NotFound(_) => "NOT_FOUND",
+            Self::AlreadyExists(_) => "ALREADY_EXISTS",
+            Self::Unauthenticated(_) => "UNAUTHENTICATED",
+            Self::PermissionDenied(_) => "PERMISSION_DENIED",
+            Self::Internal(_) => "INTERNAL",
+        }
+    }
+}
+
+impl IntoResponse for AdminApiError {
+    fn into_response(self) -> Response {
+        if let Self::Internal(e) = &self {
+            tracing::error!("Admin API request failed: {}", e);
+        }
+        let body = json!({
+            "error": {
+                "code": self.code(),
+                "message": self.to_string(),
+                "status": self.code()
+            }
+        });
+        (self.status(), Json(body)).into_response()
+    }
+}
+
+pub type AdminResult<T> = Result<Json<T>, AdminApiError>;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageQuery {
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+impl PageQuery {
+    fn resolve(&self, default_per_page: u32, max_per_page: u32) -> (u32, u32) {
+        (self.page.unwrap_or(1).max(1), self.per_page.unwrap_or(default_per_page).clamp(1, max_per_page))
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total_count: u32,
+    pub page: u32,
+    pub per_page: u32,
+    pub total_pages: u32,
+}
+
+impl<T> Page<T> {
+    fn new(items: Vec<T>, total_count: u32, page: u32, per_page: u32) -> Self {
+        Self { items, total_count, page, per_page, total_pages: total_count.div_ceil(per_page) }
+    }
+}
+
+/// 通过认证的管理 API 调用方，由认证中间件放入请求扩展，记入审计日志
+#[derive(Debug, Clone)]
+pub struct AdminApiActor(pub String);
+
+#[derive(Debug, Deserialize)]
+pub struct CustomAuthKeyRequest {
+    pub key: String,
+}
+
+fn parse_id(id: &str) -> Result<Uuid, AdminApiError> {
+    Uuid::parse_str(id).map_err(|_| AdminApiError::InvalidArgument(format!("Invalid id '{}'", id)))
+}
+
+fn to_json<T: Serialize>(value: T) -> Result<Value, AdminApiError> {
+    serde_json::to_value(value).map_err(|e| AdminApiError::Internal(e.into()))
+}
+
+/// 服务返回的错误：只有参数校验错误返回 400，其余都是内部错误
+fn service_error(e: anyhow::Error) -> AdminApiError {
+    let e = match e.downcast::<ValidationError>() {
+        Ok(invalid) => return AdminApiError::InvalidArgument(invalid.to_string()),
+        Err(e) => e,
+    };
+    match e.downcast::<SettingValueError>() {
+        Ok(e) => e.into(),
+        Err(e) => AdminApiError::Internal(e),
+    }
+}
+
+impl From<SettingValueError> for AdminApiError {
+    fn from(e: SettingValueError) -> Self {
+        match e {
+            SettingValueError::Unknown(_) => Self::NotFound(e.to_string()),
+            SettingValueError::Invalid(_) => Self::InvalidArgument(e.to_string()),
+        }
+    }
+}
+
+/// 记录通过管理 API 执行的操作，写入失败只记录日志
+async fn record_audit(
+    pool: &SqlitePool,
+    actor: &AdminApiActor,
+    action: &str,
+    target: Option<&str>,
+    before: Option<Value>,
+    after: Option<Value>,
+) {
+    let result = AuditService::new(pool.clone())
+        .record(AuditChannel::AdminApi, None, Some(&actor.0), action, target, before.as_ref(), after.as_ref())
+        .await;
+    if let Err(e) = result {
+        tracing::warn!("Failed to record audit event for {}: {}", action, e);
+    }
+}
+
+// ---- API 密钥 ----
+// 返回的密钥都经过脱敏，完整密钥只能通过 `GET /admin/api-keys/{id}/secret` 单独读取
+
+pub async fn list_api_keys(
+    State(pool): State<Arc<SqlitePool>>,
+    Query(query): Query<PageQuery>,
+) -> AdminResult<Page<ApiKeyResponse>> {
+    let (page, per_page) = query.resolve(20, 100);
+    let (api_keys, total_count) = ApiKeyService::new(pool.as_ref().clone())
+        .get_api_keys_paginated(page, per_page)
+        .await?;
+    let api_keys = api_keys.into_iter().map(ApiKeyResponse::masked).collect();
+    Ok(Json(Page::new(api_keys, total_count, page, per_page)))
+}
+
+/// 添加密钥，已经保存过相同的密钥时返回 409
+pub async fn create_api_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<(StatusCode, Json<ApiKeyResponse>), AdminApiError> {
+    let api_key_service = ApiKeyService::new(pool.as_ref().clone());
+    if api_key_service.key_value_exists(request.key_value.trim()).await? {
+        return Err(AdminApiError::AlreadyExists("API key already exists".to_string()));
+    }
+
+    let api_key = api_key_service.create_api_key(request).await.map_err(service_error)?;
+    record_audit(&pool, &actor, "create_api_key", Some(&api_key.id.to_string()), None, snapshot(&api_key)).await;
+    Ok((StatusCode::CREATED, Json(api_key.masked())))
+}
+
+pub async fn get_api_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(key_id): Path<String>,
+) -> AdminResult<ApiKeyResponse> {
+    ApiKeyService::new(pool.as_ref().clone())
+        .get_api_key_by_id(parse_id(&key_id)?)
+        .await?
+        .map(|api_key| Json(api_key.masked()))
+        .ok_or_else(|| AdminApiError::NotFound("API key not found".to_string()))
+}
+
+/// 读取完整的密钥（服务账号为 JSON 原文），每次读取都记入审计日志
+pub async fn get_api_key_secret(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Path(key_id): Path<String>,
+) -> AdminResult<Value> {
+    let api_key = ApiKeyService::new(pool.as_ref().clone())
+        .get_api_key_by_id(parse_id(&key_id)?)
+        .await?
+        .ok_or_else(|| AdminApiError::NotFound("API key not found".to_string()))?;
+    record_audit(&pool, &actor, "reveal_api_key", Some(&key_id), None, None).await;
+    Ok(Json(json!({ "keyValue": api_key.key_value })))
+}
+
+pub async fn update_api_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Path(key_id): Path<String>,
+    Json(request): Json<UpdateApiKeyRequest>,
+) -> AdminResult<ApiKeyResponse> {
+    let key_uuid = parse_id(&key_id)?;
+    let api_key_service = ApiKeyService::new(pool.as_ref().clone());
+    let before = api_key_service.get_api_key_by_id(key_uuid).await?;
+
+    let api_key = api_key_service.update_api_key(key_uuid, request).await.map_err(service_error)?
+        .ok_or_else(|| AdminApiError::NotFound("API key not found".to_string()))?;
+    record_audit(&pool, &actor, "update_api_key", Some(&key_id), before.as_ref().and_then(snapshot), snapshot(&api_key)).await;
+    Ok(Json(api_key.masked()))
+}
+
+pub async fn delete_api_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Path(key_id): Path<String>,
+) -> Result<StatusCode, AdminApiError> {
+    let key_uuid = parse_id(&key_id)?;
+    let api_key_service = ApiKeyService::new(pool.as_ref().clone());
+    let before = api_key_service.get_api_key_by_id(key_uuid).await?;
+
+    if !api_key_service.delete_api_key(key_uuid).await? {
+        return Err(AdminApiError::NotFound("API key not found".to_string()));
+    }
+    record_audit(&pool, &actor, "delete_api_key", Some(&key_id), before.as_ref().and_then(snapshot), None).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_api_key_today_requests(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(key_id): Path<String>,
+) -> AdminResult<Value> {
+    parse_id(&key_id)?;
+    let today_requests = RequestLogService::new(pool.as_ref().clone())
+        .api_key_today_requests(&key_id)
+        .await?;
+    Ok(Json(json!({ "todayRequests": today_requests })))
+}
+
+// ---- 请求日志 ----
+
+pub async fn list_request_logs(
+    State(pool): State<Arc<SqlitePool>>,
+    Query(query): Query<PageQuery>,
+) -> AdminResult<Page<RequestLogResponse>> {
+    let (page, per_page) = query.resolve(50, 200);
+    let (logs, total_count) = RequestLogService::new(pool.as_ref().clone())
+        .paginated(page, per_page)
+        .await?;
+    Ok(Json(Page::new(logs, total_count, page, per_page)))
+}
+
+pub async fn get_usage_stats(
+    State(pool): State<Arc<SqlitePool>>,
+) -> AdminResult<Value> {
+    Ok(Json(RequestLogService::new(pool.as_ref().clone()).usage_stats().await?))
+}
+
+// ---- 设置 ----
+
+async fn read_setting(service: &SettingsService, name: &str) -> Result<Value, AdminApiError> {
+    if !SETTING_NAMES.contains(&name) {
+        return Err(SettingValueError::Unknown(name.to_string()).into());
+    }
+    to_json(service.get_named(name).await?)
+}
+
+pub async fn list_settings(
+    State(pool): State<Arc<SqlitePool>>,
+) -> AdminResult<Value> {
+    let service = SettingsService::new(pool.as_ref().clone());
+    let mut settings = serde_json::Map::new();
+    for name in SETTING_NAMES {
+        settings.insert(name.to_string(), read_setting(&service, name).await?);
+    }
+    Ok(Json(Value::Object(settings)))
+}
+
+pub async fn get_setting(
+    State(pool): State<Arc<SqlitePool>>,
+    Path(name): Path<String>,
+) -> AdminResult<Value> {
+    let service = SettingsService::new(pool.as_ref().clone());
+    Ok(Json(read_setting(&service, &name).await?))
+}
+
+/// 保存设置；监听地址或 HTTPS 的变化在响应发出后再应用，避免重新绑定时中断当前请求
+pub async fn put_setting(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Path(name): Path<String>,
+    Json(value): Json<Value>,
+) -> AdminResult<Value> {
+    let value = SettingValue::from_json(&name, value)?;
+    let action = value.audit_action();
+    let service = SettingsService::new(pool.as_ref().clone());
+    let before = read_setting(&service, &name).await.ok();
+
+    let listener_changed = match &value {
+        SettingValue::Network(settings) => service.get_network_settings().await
+            .unwrap_or_default()
+            .listener_changed(settings),
+        SettingValue::Tls(settings) if settings.enabled => {
+            TlsService::new(pool.as_ref().clone()).load_certificate(settings).await
+                .map_err(|e| AdminApiError::InvalidArgument(e.to_string()))?;
+            false
+        }
+        _ => false,
+    };
+    let tls_changed = matches!(value, SettingValue::Tls(_));
+
+    service.set_named(value).await.map_err(service_error)?;
+    let after = read_setting(&service, &name).await?;
+    record_audit(&pool, &actor, action, None, before, Some(after.clone())).await;
+
+    if listener_changed {
+        crate::server::start_server_in_background(pool.as_ref().clone());
+    }
+    if tls_changed {
+        crate::server::apply_tls_settings_in_background(pool.as_ref().clone());
+    }
+    Ok(Json(after))
+}
+
+// ---- 客户端令牌 ----
+
+pub async fn list_client_tokens(
+    State(pool): State<Arc<SqlitePool>>,
+) -> AdminResult<Vec<ClientToken>> {
+    Ok(Json(ClientTokenService::new(pool.as_ref().clone()).get_all_tokens().await?))
+}
+
+/// 创建客户端令牌，返回的明文只显示这一次
+pub async fn create_client_token(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Json(request): Json<CreateClientTokenRequest>,
+) -> Result<(StatusCode, Json<IssuedClientToken>), AdminApiError> {
+    let issued = ClientTokenService::new(pool.as_ref().clone())
+        .create_token(request)
+        .await
+        .map_err(service_error)?;
+    record_audit(&pool, &actor, "create_client_token", Some(&issued.token.id.to_string()), None, snapshot(&issued.token)).await;
+    Ok((StatusCode::CREATED, Json(issued)))
+}
+
+// ---- 全局自定义验证密钥 ----
+
+pub async fn get_custom_auth_key_status(
+    State(pool): State<Arc<SqlitePool>>,
+) -> AdminResult<Value> {
+    let has_custom_key = CustomAuthService::new(pool.as_ref().clone()).has_custom_key().await?;
+    Ok(Json(json!({ "hasCustomKey": has_custom_key })))
+}
+
+pub async fn set_custom_auth_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+    Json(request): Json<CustomAuthKeyRequest>,
+) -> Result<StatusCode, AdminApiError> {
+    let service = CustomAuthService::new(pool.as_ref().clone());
+    let before = service.audit_snapshot().await;
+    service.set_custom_key(&request.key).await.map_err(service_error)?;
+    record_audit(&pool, &actor, "set_custom_auth_key", None, before, service.audit_snapshot().await).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 停用全局密钥，之后只接受客户端令牌
+pub async fn reset_custom_auth_key(
+    State(pool): State<Arc<SqlitePool>>,
+    Extension(actor): Extension<AdminApiActor>,
+) -> Result<StatusCode, AdminApiError> {
+    let service = CustomAuthService::new(pool.as_ref().clone());
+    let before = service.audit_snapshot().await;
+    service.disable_custom_key().await?;
+    record_audit(&pool, &actor, "reset_custom_auth_key", None, before, service.audit_snapshot().await).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 检查结果可以用来猜测密钥，与管理登录一样按对端地址计数并锁定
+pub async fn validate_custom_auth_key(
+    State(pool): State<Arc<SqlitePool>>,
+    remote: Option<ConnectInfo<SocketAddr>>,
+    Json(request): Json<CustomAuthKeyRequest>,
+) -> Result<Response, AdminApiError> {
+    let source = peer_source(remote.map(|ConnectInfo(remote)| remote));
+    let guard_settings = SettingsService::new(pool.as_ref().clone()).get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    if let Err(locked) = auth_guard.check(AuthScope::AdminLogin, &source, &guard_settings) {
+        return Ok(locked_out_response(&locked));
+    }
+
+    let valid = CustomAuthService::new(pool.as_ref().clone()).validate_custom_key(&request.key).await?;
+    if valid {
+        auth_guard.record_success(AuthScope::AdminLogin, &source);
+    } else {
+        for locked in auth_guard.record_failure(AuthScope::AdminLogin, &source, &guard_settings) {
+            tracing::warn!("{}", locked);
+        }
+    }
+    Ok(Json(json!({ "valid": valid })).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_use_the_proxy_status_codes() {
+        let cases = [
+            (AdminApiError::InvalidArgument(String::new()), StatusCode::BAD_REQUEST, "INVALID_ARGUMENT"),
+            (AdminApiError::NotFound(String::new()), StatusCode::NOT_FOUND, "NOT_FOUND"),
+            (AdminApiError::AlreadyExists(String::new()), StatusCode::CONFLICT, "ALREADY_EXISTS"),
+            (AdminApiError::Unauthenticated(String::new()), StatusCode::UNAUTHORIZED, "UNAUTHENTICATED"),
+            (AdminApiError::PermissionDenied(String::new()), StatusCode::FORBIDDEN, "PERMISSION_DENIED"),
+            (AdminApiError::Internal(anyhow::anyhow!("boom")), StatusCode::INTERNAL_SERVER_ERROR, "INTERNAL"),
+        ];
+        for (error, status, code) in cases {
+            assert_eq!((error.status(), error.code()), (status, code));
+        }
+    }
+
+    #[test]
+    fn page_query_is_clamped() {
+        let query = PageQuery { page: Some(0), per_page: Some(1000) };
+        assert_eq!(query.resolve(50, 200), (1, 200));
+        let query = PageQuery { page: None, per_page: None };
+        assert_eq!(query.resolve(50, 200), (1, 50));
+    }
+
+    #[test]
+    fn only_typed_validation_errors_are_invalid_argument() {
+        let invalid = service_error(ValidationError("Listen port must be between 1 and 65535".to_string()).into());
+        assert_eq!(invalid.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(invalid.to_string(), "Listen port must be between 1 and 65535");
+
+        let unknown = service_error(SettingValueError::Unknown("nope".to_string()).into());
+        assert_eq!(unknown.status(), StatusCode::NOT_FOUND);
+
+        assert_eq!(service_error(anyhow::anyhow!("Failed to hash custom auth key")).status(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(service_error(sqlx::Error::RowNotFound.into()).status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
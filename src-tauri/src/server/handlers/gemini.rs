@@ -1,15 +1,19 @@
 use axum::{
-    extract::{Path, State},
-    http::StatusCode,
+    extract::{Extension, Path, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{Json, Response, Sse, IntoResponse},
     response::sse::Event,
 };
-use crate::services::{GeminiProxyService, ErrorLoggerService};
+use crate::models::{HookMetadata, HookStage, RequestPriority};
+use crate::services::{GeminiProxyService, ErrorLoggerService, CircuitOpenError, HookFailedError, ProxyResponse, ModelAliasService, PolicyService, RequestContext, RequestValidationError, ScriptHookService, HookChain, SettingsService, CACHE_OPT_IN_HEADER, PRIORITY_HEADER, cache_opt_in};
+use crate::server::middleware::{ClientIdentity, permission_denied_response};
 use serde_json::Value;
 use std::sync::Arc;
 use std::time::Instant;
 use sqlx::SqlitePool;
-use tokio_stream::StreamExt;
+use tokio_stream::{Stream, StreamExt};
+use futures::stream::BoxStream;
+use bytes::Bytes;
 
 pub async fn api_info() -> Result<Json<Value>, StatusCode> {
     let info = serde_json::json!({
@@ -27,50 +31,276 @@ pub async fn api_info() -> Result<Json<Value>, StatusCode> {
     Ok(Json(info))
 }
 
-pub async fn list_models(
-    State(pool): State<Arc<SqlitePool>>,
-) -> Result<Json<Value>, StatusCode> {
-    let start_time = Instant::now();
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
-    let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
-    
-    match proxy_service.forward_request("GET", "/v1/models", serde_json::json!({})).await {
-        Ok(response) => Ok(Json(response)),
-        Err(e) => {
-            let error_msg = format!("Failed to list models: {}", e);
-            if let Err(log_err) = error_logger.log_handler_error(
-                None,
-                "GET",
-                "/v1/models",
-                &error_msg,
-                500,
-                Some(start_time),
-                None,
-            ).await {
-                tracing::warn!("Failed to log handler error: {}", log_err);
+/// 熔断器打开时快速失败，返回 503 并告知客户端何时重试
+async fn circuit_open_response(
+    error_logger: &ErrorLoggerService,
+    method: &str,
+    path: &str,
+    error: &CircuitOpenError,
+    start_time: Instant,
+    request_body: Option<&str>,
+) -> Response {
+    let error_msg = error.to_string();
+    if let Err(log_err) = error_logger.log_handler_error(
+        None,
+        method,
+        path,
+        &error_msg,
+        503,
+        Some(start_time),
+        request_body,
+    ).await {
+        tracing::warn!("Failed to log handler error: {}", log_err);
+    }
+
+    let error_response = serde_json::json!({
+        "error": {
+            "code": "UNAVAILABLE",
+            "message": error_msg,
+            "status": "UNAVAILABLE"
+        }
+    });
+
+    let mut response = (StatusCode::SERVICE_UNAVAILABLE, Json(error_response)).into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(error.retry_after.as_secs().max(1)),
+    );
+    response
+}
+
+/// 设置为出错时拒绝请求的脚本钩子执行失败，返回 500
+async fn hook_failed_response(
+    error_logger: &ErrorLoggerService,
+    path: &str,
+    error: &HookFailedError,
+    start_time: Instant,
+    request_body: Option<&str>,
+) -> Response {
+    let error_msg = error.to_string();
+    if let Err(log_err) = error_logger.log_handler_error(
+        None,
+        "POST",
+        path,
+        &error_msg,
+        500,
+        Some(start_time),
+        request_body,
+    ).await {
+        tracing::warn!("Failed to log handler error: {}", log_err);
+    }
+
+    let error_response = serde_json::json!({
+        "error": {
+            "code": "INTERNAL",
+            "message": error_msg,
+            "status": "INTERNAL"
+        }
+    });
+
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response)).into_response()
+}
+
+/// 构造 INVALID_ARGUMENT 错误体，结构校验失败时附带 Google 风格的字段错误列表
+fn invalid_argument_body(error: &anyhow::Error) -> Value {
+    let mut body = serde_json::json!({
+        "error": {
+            "code": "INVALID_ARGUMENT",
+            "message": error.to_string(),
+            "status": "INVALID_ARGUMENT"
+        }
+    });
+
+    if let Some(validation) = error.downcast_ref::<RequestValidationError>() {
+        body["error"]["details"] = serde_json::json!([{
+            "@type": "type.googleapis.com/google.rpc.BadRequest",
+            "fieldViolations": validation.violations,
+        }]);
+    }
+    body
+}
+
+/// 返回响应体，并用 `X-Served-Model` 头标明实际处理请求的模型（发生降级时与请求的模型不同），缓存命中时带 `X-Proxy-Cache: HIT`
+fn served_model_response(response: ProxyResponse) -> Response {
+    let mut http_response = Json(response.body).into_response();
+    if let Some(model) = response.served_model.and_then(|m| HeaderValue::from_str(&m).ok()) {
+        http_response.headers_mut().insert("x-served-model", model);
+    }
+    if response.cache_hit {
+        http_response.headers_mut().insert(CACHE_OPT_IN_HEADER, HeaderValue::from_static("HIT"));
+    }
+    http_response
+}
+
+/// 把上游数据块转成 SSE 事件，流式钩子在阻塞线程池中逐块执行；
+/// 设置为出错时拒绝请求的钩子失败后发送错误事件并结束流
+fn sse_events(
+    stream: BoxStream<'static, anyhow::Result<Bytes>>,
+    chunk_hooks: HookChain,
+    meta: HookMetadata,
+) -> impl Stream<Item = Result<Event, axum::Error>> {
+    futures::stream::unfold(Some((stream, chunk_hooks, meta)), |state| async move {
+        let (mut stream, chunk_hooks, meta) = state?;
+        let event = match stream.next().await? {
+            Ok(bytes) => {
+                let text = String::from_utf8_lossy(&bytes);
+                let data = text
+                    .lines()
+                    .filter_map(|line| line.strip_prefix("data: "))
+                    .find(|data| !data.is_empty() && *data != "[DONE]");
+                match data {
+                    Some(data) => match chunk_hooks.run_on_chunk(data, &meta).await {
+                        Ok(data) => Event::default().data(data),
+                        Err(e) => {
+                            tracing::error!("{}", e);
+                            let event = Event::default().data(serde_json::json!({ "error": e.to_string() }).to_string());
+                            return Some((Ok(event), None));
+                        }
+                    },
+                    None => Event::default().data(text.trim()),
+                }
             }
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(e) => {
+                tracing::error!("Stream error: {}", e);
+                Event::default().data(format!(r#"{{"error": "{}"}}"#, e))
+            }
+        };
+        Some((Ok(event), Some((stream, chunk_hooks, meta))))
+    })
+}
+
+/// 按模型和客户端对请求体应用策略规则，规则读取失败时原样转发
+async fn apply_policies(pool: &SqlitePool, path: &str, client: &ClientIdentity, mut payload: Value) -> (Value, RequestContext) {
+    let model = path.split(':').next().unwrap_or(path);
+    let policy_service = PolicyService::new(pool.clone());
+
+    match policy_service.apply(model, &client.token_name, &mut payload).await {
+        Ok(applied_policies) => {
+            if !applied_policies.is_empty() {
+                tracing::info!("Applied policies for {}: {}", model, applied_policies.join(", "));
+            }
+            (payload, RequestContext { applied_policies, ..RequestContext::default() })
         }
+        Err(e) => {
+            tracing::warn!("Failed to apply policies: {}", e);
+            (payload, RequestContext::default())
+        }
+    }
+}
+
+/// 请求排队等待密钥时的优先级：请求头优先，其次是客户端的默认优先级
+async fn request_priority(pool: &SqlitePool, client: &ClientIdentity, headers: &HeaderMap) -> RequestPriority {
+    let settings = SettingsService::new(pool.clone()).get_scheduler_settings().await.unwrap_or_default();
+    settings.priority_for(&client.token_name, headers.get(PRIORITY_HEADER).and_then(|v| v.to_str().ok()))
+}
+
+/// 请求体中 generation config 对象的键，兼容 snake_case 写法
+fn generation_config_key(body: &serde_json::Map<String, Value>) -> &'static str {
+    if body.contains_key("generation_config") { "generation_config" } else { "generationConfig" }
+}
+
+/// 按客户端权限范围检查模型和客户端自己请求的 `maxOutputTokens`；
+/// 需在应用策略和钩子之前调用，由它们注入的值交给 [`clamp_output_tokens`] 处理
+fn check_scopes(client: &ClientIdentity, path: &str, payload: &Value) -> Result<(), String> {
+    let model = path.split(':').next().unwrap_or(path);
+    if !client.scopes.allows_model(model) {
+        return Err(format!("Client token '{}' is not allowed to use model {}", client.token_name, model));
+    }
+
+    let Some(limit) = client.scopes.max_output_tokens else {
+        return Ok(());
+    };
+    let requested = payload.as_object()
+        .and_then(|body| body.get(generation_config_key(body)))
+        .and_then(|config| config.get("maxOutputTokens").or_else(|| config.get("max_output_tokens")))
+        .and_then(Value::as_u64);
+    match requested {
+        Some(requested) if requested > limit as u64 => Err(format!(
+            "maxOutputTokens {} exceeds the limit of {} for client token '{}'",
+            requested, limit, client.token_name
+        )),
+        _ => Ok(()),
     }
 }
 
-pub async fn get_model(
-    Path(model): Path<String>,
+/// 把应用策略和钩子之后的 `maxOutputTokens` 压到客户端上限内，未指定时填入上限
+fn clamp_output_tokens(client: &ClientIdentity, payload: &mut Value) {
+    let (Some(limit), Some(body)) = (client.scopes.max_output_tokens, payload.as_object_mut()) else {
+        return;
+    };
+    let config_key = generation_config_key(body);
+    let Some(config) = body
+        .entry(config_key)
+        .or_insert_with(|| serde_json::json!({}))
+        .as_object_mut()
+    else {
+        return;
+    };
+
+    let field = if config.contains_key("max_output_tokens") { "max_output_tokens" } else { "maxOutputTokens" };
+    let clamped = config.get(field)
+        .and_then(Value::as_u64)
+        .map_or(limit as u64, |current| current.min(limit as u64));
+    config.insert(field.to_string(), Value::from(clamped));
+}
+
+/// 只带客户端信息的请求附加信息，用于不应用策略的请求
+fn client_context(client: &ClientIdentity) -> RequestContext {
+    RequestContext {
+        client_token_id: client.token_id,
+        client_scopes: client.scopes.clone(),
+        ..RequestContext::default()
+    }
+}
+
+/// 传给脚本钩子的请求元数据
+fn hook_metadata(path: &str, client: &ClientIdentity) -> HookMetadata {
+    HookMetadata {
+        model: path.split(':').next().unwrap_or(path).to_string(),
+        path: format!("/v1beta/models/{}", path),
+        method: "POST".to_string(),
+        client: client.token_name.clone(),
+        stream: path.ends_with(":streamGenerateContent"),
+        status_code: None,
+    }
+}
+
+/// 加载某个阶段启用的脚本钩子，读取失败时不执行任何钩子
+async fn load_hooks(pool: &SqlitePool, stage: HookStage) -> HookChain {
+    ScriptHookService::new(pool.clone())
+        .load_chain(stage)
+        .await
+        .unwrap_or_else(|e| {
+            tracing::warn!("Failed to load script hooks: {}", e);
+            HookChain::default()
+        })
+}
+
+pub async fn list_models(
     State(pool): State<Arc<SqlitePool>>,
-) -> Result<Json<Value>, StatusCode> {
+    Extension(client): Extension<ClientIdentity>,
+) -> Result<Response, StatusCode> {
     let start_time = Instant::now();
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
+    let proxy_service = GeminiProxyService::new(pool.as_ref().clone()).with_context(client_context(&client));
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
-    let path = format!("/v1beta/models/{}", model);
     
-    match proxy_service.forward_request("GET", &path, serde_json::json!({})).await {
-        Ok(response) => Ok(Json(response)),
+    match proxy_service.forward_request("GET", "/v1/models", serde_json::json!({})).await {
+        Ok(mut response) => {
+            let alias_service = ModelAliasService::new(pool.as_ref().clone());
+            if let Err(e) = alias_service.append_to_model_list(&mut response).await {
+                tracing::warn!("Failed to append model aliases: {}", e);
+            }
+            Ok(Json(response).into_response())
+        }
         Err(e) => {
-            let error_msg = format!("Failed to get model {}: {}", model, e);
+            if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                return Ok(circuit_open_response(&error_logger, "GET", "/v1/models", open, start_time, None).await);
+            }
+            let error_msg = format!("Failed to list models: {}", e);
             if let Err(log_err) = error_logger.log_handler_error(
                 None,
                 "GET",
-                &path,
+                "/v1/models",
                 &error_msg,
                 500,
                 Some(start_time),
@@ -86,9 +316,11 @@ pub async fn get_model(
 pub async fn get_model_by_path(
     Path(path): Path<String>,
     State(pool): State<Arc<SqlitePool>>,
-) -> Result<Json<Value>, StatusCode> {
+    Extension(client): Extension<ClientIdentity>,
+) -> Result<Response, StatusCode> {
     let start_time = Instant::now();
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
+    let path = ModelAliasService::new(pool.as_ref().clone()).apply_to_path(&path).await;
     let full_path = format!("/v1beta/models/{}", path);
     
     // 只处理单个模型名的路径，不处理带有 : 的路径
@@ -108,11 +340,14 @@ pub async fn get_model_by_path(
         return Err(StatusCode::NOT_FOUND);
     }
     
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
+    let proxy_service = GeminiProxyService::new(pool.as_ref().clone()).with_context(client_context(&client));
     
     match proxy_service.forward_request("GET", &full_path, serde_json::json!({})).await {
-        Ok(response) => Ok(Json(response)),
+        Ok(response) => Ok(Json(response).into_response()),
         Err(e) => {
+            if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                return Ok(circuit_open_response(&error_logger, "GET", &full_path, open, start_time, None).await);
+            }
             let error_msg = format!("Failed to get model {}: {}", path, e);
             if let Err(log_err) = error_logger.log_handler_error(
                 None,
@@ -133,30 +368,61 @@ pub async fn get_model_by_path(
 pub async fn generate_content(
     Path(path): Path<String>,
     State(pool): State<Arc<SqlitePool>>,
+    Extension(client): Extension<ClientIdentity>,
+    headers: HeaderMap,
     Json(payload): Json<Value>,
 ) -> Result<Response, StatusCode> {
     let start_time = Instant::now();
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
     let request_body = ErrorLoggerService::extract_request_body_string(&payload);
+    let path = ModelAliasService::new(pool.as_ref().clone()).apply_to_path(&path).await;
+    // 权限范围按客户端原始请求检查，策略和钩子注入的值随后压到上限内
+    if let Err(error_msg) = check_scopes(&client, &path, &payload) {
+        if let Err(log_err) = error_logger.log_handler_error(
+            None,
+            "POST",
+            &format!("/v1beta/models/{}", path),
+            &error_msg,
+            403,
+            Some(start_time),
+            Some(&request_body),
+        ).await {
+            tracing::warn!("Failed to log handler error: {}", log_err);
+        }
+        return Ok(permission_denied_response(&error_msg));
+    }
+    let (mut payload, mut context) = apply_policies(&pool, &path, &client, payload).await;
+    context.cache_opt_in = cache_opt_in(headers.get(CACHE_OPT_IN_HEADER).and_then(|v| v.to_str().ok()));
+    context.priority = request_priority(&pool, &client, &headers).await;
+    context.client_token_id = client.token_id;
+    context.client_scopes = client.scopes.clone();
+    let meta = hook_metadata(&path, &client);
+    if let Err(e) = load_hooks(&pool, HookStage::PreRequest).await.run(&mut payload, &meta).await {
+        return Ok(hook_failed_response(&error_logger, &format!("/v1beta/models/{}", path), &e, start_time, Some(&request_body)).await);
+    }
+    clamp_output_tokens(&client, &mut payload);
+    let proxy_service = GeminiProxyService::new(pool.as_ref().clone()).with_context(context);
     
     // 处理 generateContent 和 streamGenerateContent 路径
     if path.ends_with(":generateContent") {
         let full_path = format!("/v1beta/models/{}", path);
-        match proxy_service.forward_request("POST", &full_path, payload).await {
-            Ok(response) => Ok(Json(response).into_response()),
+        match proxy_service.forward_request_with_fallback("POST", &full_path, payload).await {
+            Ok(mut response) => {
+                let meta = HookMetadata { status_code: Some(200), ..meta };
+                if let Err(e) = load_hooks(&pool, HookStage::PostResponse).await.run(&mut response.body, &meta).await {
+                    return Ok(hook_failed_response(&error_logger, &full_path, &e, start_time, Some(&request_body)).await);
+                }
+                Ok(served_model_response(response))
+            }
             Err(e) => {
+                if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                    return Ok(circuit_open_response(&error_logger, "POST", &full_path, open, start_time, Some(&request_body)).await);
+                }
                 let error_msg = e.to_string();
                 
                 // Return 400 for validation errors, 500 for other errors
                 if error_msg.contains("Invalid request format") {
-                    let error_response = serde_json::json!({
-                        "error": {
-                            "code": "INVALID_ARGUMENT",
-                            "message": error_msg,
-                            "status": "INVALID_ARGUMENT"
-                        }
-                    });
+                    let error_response = invalid_argument_body(&e);
                     
                     if let Err(log_err) = error_logger.log_handler_error(
                         None,
@@ -191,27 +457,8 @@ pub async fn generate_content(
         let full_path = format!("/v1beta/models/{}", path);
         match proxy_service.forward_streaming_request("POST", &full_path, payload).await {
             Ok(stream) => {
-                let sse_stream = stream.map(|chunk| -> Result<Event, axum::Error> {
-                    match chunk {
-                        Ok(bytes) => {
-                            let text = String::from_utf8_lossy(&bytes);
-                            let lines: Vec<&str> = text.lines().collect();
-                            for line in lines {
-                                if line.starts_with("data: ") {
-                                    let data = &line[6..];
-                                    if !data.is_empty() && data != "[DONE]" {
-                                        return Ok(Event::default().data(data.to_string()));
-                                    }
-                                }
-                            }
-                            Ok(Event::default().data(text.trim().to_string()))
-                        }
-                        Err(e) => {
-                            tracing::error!("Stream error: {}", e);
-                            Ok(Event::default().data(format!(r#"{{"error": "{}"}}"#, e)))
-                        }
-                    }
-                });
+                let chunk_hooks = load_hooks(&pool, HookStage::StreamChunk).await;
+                let sse_stream = sse_events(stream, chunk_hooks, meta);
                 
                 let response = Sse::new(sse_stream)
                     .keep_alive(
@@ -224,6 +471,9 @@ pub async fn generate_content(
                 Ok(response)
             }
             Err(e) => {
+                if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                    return Ok(circuit_open_response(&error_logger, "POST", &full_path, open, start_time, Some(&request_body)).await);
+                }
                 let error_msg = e.to_string();
                 
                 // Return 400 for validation errors, 500 for other errors
@@ -239,7 +489,7 @@ pub async fn generate_content(
                     ).await {
                         tracing::warn!("Failed to log handler error: {}", log_err);
                     }
-                    Err(StatusCode::BAD_REQUEST)
+                    Ok((StatusCode::BAD_REQUEST, Json(invalid_argument_body(&e))).into_response())
                 } else {
                     if let Err(log_err) = error_logger.log_handler_error(
                         None,
@@ -276,30 +526,61 @@ pub async fn generate_content(
 pub async fn generate_content_v1(
     Path(path): Path<String>,
     State(pool): State<Arc<SqlitePool>>,
+    Extension(client): Extension<ClientIdentity>,
+    headers: HeaderMap,
     Json(payload): Json<Value>,
 ) -> Result<Response, StatusCode> {
     let start_time = Instant::now();
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
     let request_body = ErrorLoggerService::extract_request_body_string(&payload);
+    let path = ModelAliasService::new(pool.as_ref().clone()).apply_to_path(&path).await;
+    // 权限范围按客户端原始请求检查，策略和钩子注入的值随后压到上限内
+    if let Err(error_msg) = check_scopes(&client, &path, &payload) {
+        if let Err(log_err) = error_logger.log_handler_error(
+            None,
+            "POST",
+            &format!("/v1beta/models/{}", path),
+            &error_msg,
+            403,
+            Some(start_time),
+            Some(&request_body),
+        ).await {
+            tracing::warn!("Failed to log handler error: {}", log_err);
+        }
+        return Ok(permission_denied_response(&error_msg));
+    }
+    let (mut payload, mut context) = apply_policies(&pool, &path, &client, payload).await;
+    context.cache_opt_in = cache_opt_in(headers.get(CACHE_OPT_IN_HEADER).and_then(|v| v.to_str().ok()));
+    context.priority = request_priority(&pool, &client, &headers).await;
+    context.client_token_id = client.token_id;
+    context.client_scopes = client.scopes.clone();
+    let meta = hook_metadata(&path, &client);
+    if let Err(e) = load_hooks(&pool, HookStage::PreRequest).await.run(&mut payload, &meta).await {
+        return Ok(hook_failed_response(&error_logger, &format!("/v1beta/models/{}", path), &e, start_time, Some(&request_body)).await);
+    }
+    clamp_output_tokens(&client, &mut payload);
+    let proxy_service = GeminiProxyService::new(pool.as_ref().clone()).with_context(context);
     
     // 处理 generateContent 和 streamGenerateContent 路径，但使用 v1beta 转发
     if path.ends_with(":generateContent") {
         let full_path = format!("/v1beta/models/{}", path);
-        match proxy_service.forward_request("POST", &full_path, payload).await {
-            Ok(response) => Ok(Json(response).into_response()),
+        match proxy_service.forward_request_with_fallback("POST", &full_path, payload).await {
+            Ok(mut response) => {
+                let meta = HookMetadata { status_code: Some(200), ..meta };
+                if let Err(e) = load_hooks(&pool, HookStage::PostResponse).await.run(&mut response.body, &meta).await {
+                    return Ok(hook_failed_response(&error_logger, &full_path, &e, start_time, Some(&request_body)).await);
+                }
+                Ok(served_model_response(response))
+            }
             Err(e) => {
+                if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                    return Ok(circuit_open_response(&error_logger, "POST", &full_path, open, start_time, Some(&request_body)).await);
+                }
                 let error_msg = e.to_string();
                 
                 // Return 400 for validation errors, 500 for other errors
                 if error_msg.contains("Invalid request format") {
-                    let error_response = serde_json::json!({
-                        "error": {
-                            "code": "INVALID_ARGUMENT",
-                            "message": error_msg,
-                            "status": "INVALID_ARGUMENT"
-                        }
-                    });
+                    let error_response = invalid_argument_body(&e);
                     
                     if let Err(log_err) = error_logger.log_handler_error(
                         None,
@@ -334,27 +615,8 @@ pub async fn generate_content_v1(
         let full_path = format!("/v1beta/models/{}", path);
         match proxy_service.forward_streaming_request("POST", &full_path, payload).await {
             Ok(stream) => {
-                let sse_stream = stream.map(|chunk| -> Result<Event, axum::Error> {
-                    match chunk {
-                        Ok(bytes) => {
-                            let text = String::from_utf8_lossy(&bytes);
-                            let lines: Vec<&str> = text.lines().collect();
-                            for line in lines {
-                                if line.starts_with("data: ") {
-                                    let data = &line[6..];
-                                    if !data.is_empty() && data != "[DONE]" {
-                                        return Ok(Event::default().data(data.to_string()));
-                                    }
-                                }
-                            }
-                            Ok(Event::default().data(text.trim().to_string()))
-                        }
-                        Err(e) => {
-                            tracing::error!("Stream error: {}", e);
-                            Ok(Event::default().data(format!(r#"{{"error": "{}"}}"#, e)))
-                        }
-                    }
-                });
+                let chunk_hooks = load_hooks(&pool, HookStage::StreamChunk).await;
+                let sse_stream = sse_events(stream, chunk_hooks, meta);
                 
                 let response = Sse::new(sse_stream)
                     .keep_alive(
@@ -367,6 +629,9 @@ pub async fn generate_content_v1(
                 Ok(response)
             }
             Err(e) => {
+                if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                    return Ok(circuit_open_response(&error_logger, "POST", &full_path, open, start_time, Some(&request_body)).await);
+                }
                 let error_msg = e.to_string();
                 
                 // Return 400 for validation errors, 500 for other errors
@@ -382,7 +647,7 @@ pub async fn generate_content_v1(
                     ).await {
                         tracing::warn!("Failed to log handler error: {}", log_err);
                     }
-                    Err(StatusCode::BAD_REQUEST)
+                    Ok((StatusCode::BAD_REQUEST, Json(invalid_argument_body(&e))).into_response())
                 } else {
                     if let Err(log_err) = error_logger.log_handler_error(
                         None,
@@ -419,9 +684,11 @@ pub async fn generate_content_v1(
 pub async fn get_model_by_path_v1(
     Path(path): Path<String>,
     State(pool): State<Arc<SqlitePool>>,
-) -> Result<Json<Value>, StatusCode> {
+    Extension(client): Extension<ClientIdentity>,
+) -> Result<Response, StatusCode> {
     let start_time = Instant::now();
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
+    let path = ModelAliasService::new(pool.as_ref().clone()).apply_to_path(&path).await;
     let full_path = format!("/v1beta/models/{}", path);
     
     // 只处理单个模型名的路径，不处理带有 : 的路径
@@ -441,11 +708,14 @@ pub async fn get_model_by_path_v1(
         return Err(StatusCode::NOT_FOUND);
     }
     
-    let proxy_service = GeminiProxyService::new(pool.as_ref().clone());
+    let proxy_service = GeminiProxyService::new(pool.as_ref().clone()).with_context(client_context(&client));
     
     match proxy_service.forward_request("GET", &full_path, serde_json::json!({})).await {
-        Ok(response) => Ok(Json(response)),
+        Ok(response) => Ok(Json(response).into_response()),
         Err(e) => {
+            if let Some(open) = e.downcast_ref::<CircuitOpenError>() {
+                return Ok(circuit_open_response(&error_logger, "GET", &full_path, open, start_time, None).await);
+            }
             let error_msg = format!("Failed to get model {}: {}", path, e);
             if let Err(log_err) = error_logger.log_handler_error(
                 None,
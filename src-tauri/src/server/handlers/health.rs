@@ -1,10 +1,23 @@
-use axum::{http::StatusCode, response::Json};
+use axum::{extract::State, http::StatusCode, response::Json};
+use crate::services::{CircuitBreakerService, SettingsService};
 use serde_json::{json, Value};
+use sqlx::SqlitePool;
+use std::sync::Arc;
+
+pub async fn health_check(
+    State(pool): State<Arc<SqlitePool>>,
+) -> Result<Json<Value>, StatusCode> {
+    let settings_service = SettingsService::new(pool.as_ref().clone());
+    let breaker_settings = settings_service.get_circuit_breaker_settings().await.unwrap_or_default();
+    let circuit_breaker = CircuitBreakerService::new();
+
+    // 上游熔断时服务仍可访问，但请求会被快速拒绝
+    let status = if circuit_breaker.any_upstream_open() { "degraded" } else { "healthy" };
 
-pub async fn health_check() -> Result<Json<Value>, StatusCode> {
     Ok(Json(json!({
-        "status": "healthy",
+        "status": status,
         "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
-        "service": "Gemini Proxy"
+        "service": "Gemini Proxy",
+        "circuitBreakers": circuit_breaker.status(&breaker_settings)
     })))
 }
\ No newline at end of file
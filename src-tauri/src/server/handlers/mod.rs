@@ -1,2 +1,4 @@
 pub mod gemini;
-pub mod health;
\ No newline at end of file
+pub mod health;
+pub mod admin;
+pub mod openapi;
\ No newline at end of file
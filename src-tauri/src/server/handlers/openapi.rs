@@ -0,0 +1,346 @@
+use axum::response::Json;
+use serde_json::{json, Value};
+use crate::models::SETTING_NAMES;
+
+fn error_response(description: &str) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": { "$ref": "#/components/schemas/Error" } } }
+    })
+}
+
+fn json_response(description: &str, schema: Value) -> Value {
+    json!({
+        "description": description,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
+
+fn json_body(schema: Value) -> Value {
+    json!({
+        "required": true,
+        "content": { "application/json": { "schema": schema } }
+    })
+}
+
+fn page_of(item: &str) -> Value {
+    json!({
+        "allOf": [
+            { "$ref": "#/components/schemas/Page" },
+            {
+                "type": "object",
+                "properties": { "items": { "type": "array", "items": { "$ref": format!("#/components/schemas/{}", item) } } }
+            }
+        ]
+    })
+}
+
+/// 管理 API 的 OpenAPI 3.0 描述，路径与 `create_app` 中注册的 `/admin` 路由一致
+pub fn admin_openapi_document() -> Value {
+    let page_params = json!([
+        { "name": "page", "in": "query", "schema": { "type": "integer", "minimum": 1, "default": 1 } },
+        { "name": "perPage", "in": "query", "schema": { "type": "integer", "minimum": 1 } }
+    ]);
+    let id_param = json!({ "name": "id", "in": "path", "required": true, "schema": { "type": "string", "format": "uuid" } });
+    let name_param = json!({ "name": "name", "in": "path", "required": true, "schema": { "type": "string", "enum": SETTING_NAMES } });
+    let key_body = json_body(json!({
+        "type": "object",
+        "required": ["key"],
+        "properties": { "key": { "type": "string" } }
+    }));
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "Tjimi Proxy Admin API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "管理 API 密钥、客户端令牌、请求日志、设置和全局验证密钥。修改操作会以 admin_api 渠道记入审计日志。"
+        },
+        "security": [ { "adminToken": [] }, { "bearerAuth": [] } ],
+        "paths": {
+            "/admin/api-keys": {
+                "get": {
+                    "summary": "分页列出 API 密钥",
+                    "parameters": page_params,
+                    "responses": {
+                        "200": json_response("API 密钥列表", page_of("ApiKey")),
+                        "401": error_response("管理 API 令牌无效")
+                    }
+                },
+                "post": {
+                    "summary": "添加 API 密钥",
+                    "requestBody": json_body(json!({ "$ref": "#/components/schemas/CreateApiKey" })),
+                    "responses": {
+                        "201": json_response("新建的 API 密钥", json!({ "$ref": "#/components/schemas/ApiKey" })),
+                        "400": error_response("请求参数无效"),
+                        "409": error_response("已经保存过相同的密钥")
+                    }
+                }
+            },
+            "/admin/api-keys/{id}": {
+                "parameters": [id_param],
+                "get": {
+                    "summary": "查看 API 密钥",
+                    "responses": {
+                        "200": json_response("API 密钥", json!({ "$ref": "#/components/schemas/ApiKey" })),
+                        "404": error_response("密钥不存在")
+                    }
+                },
+                "patch": {
+                    "summary": "修改 API 密钥，未提供的字段保持不变",
+                    "requestBody": json_body(json!({ "$ref": "#/components/schemas/UpdateApiKey" })),
+                    "responses": {
+                        "200": json_response("修改后的 API 密钥", json!({ "$ref": "#/components/schemas/ApiKey" })),
+                        "404": error_response("密钥不存在")
+                    }
+                },
+                "delete": {
+                    "summary": "删除 API 密钥",
+                    "responses": {
+                        "204": { "description": "已删除" },
+                        "404": error_response("密钥不存在")
+                    }
+                }
+            },
+            "/admin/api-keys/{id}/secret": {
+                "parameters": [id_param],
+                "get": {
+                    "summary": "读取完整的密钥，每次读取都记入审计日志",
+                    "responses": {
+                        "200": json_response("完整密钥", json!({
+                            "type": "object",
+                            "properties": { "keyValue": { "type": "string" } }
+                        })),
+                        "404": error_response("密钥不存在")
+                    }
+                }
+            },
+            "/admin/api-keys/{id}/today-requests": {
+                "parameters": [id_param],
+                "get": {
+                    "summary": "密钥今日发往上游的请求数",
+                    "responses": {
+                        "200": json_response("今日请求数", json!({
+                            "type": "object",
+                            "properties": { "todayRequests": { "type": "integer" } }
+                        }))
+                    }
+                }
+            },
+            "/admin/logs": {
+                "get": {
+                    "summary": "按时间倒序分页列出请求日志",
+                    "parameters": page_params,
+                    "responses": {
+                        "200": json_response("请求日志", page_of("RequestLog"))
+                    }
+                }
+            },
+            "/admin/logs/stats": {
+                "get": {
+                    "summary": "总体和今日的使用统计",
+                    "responses": {
+                        "200": json_response("使用统计", json!({ "type": "object" }))
+                    }
+                }
+            },
+            "/admin/settings": {
+                "get": {
+                    "summary": "全部设置，按设置名称分组",
+                    "responses": {
+                        "200": json_response("全部设置", json!({ "type": "object" }))
+                    }
+                }
+            },
+            "/admin/settings/{name}": {
+                "parameters": [name_param],
+                "get": {
+                    "summary": "读取一项设置",
+                    "responses": {
+                        "200": json_response("设置的当前值", json!({})),
+                        "404": error_response("未知的设置名称")
+                    }
+                },
+                "put": {
+                    "summary": "保存一项设置，格式与读取结果相同；监听地址和 HTTPS 的修改在响应后生效",
+                    "requestBody": json_body(json!({})),
+                    "responses": {
+                        "200": json_response("保存后的设置", json!({})),
+                        "400": error_response("设置格式无效或证书不可用"),
+                        "404": error_response("未知的设置名称")
+                    }
+                }
+            },
+            "/admin/client-tokens": {
+                "get": {
+                    "summary": "列出客户端令牌（不含明文）",
+                    "responses": {
+                        "200": json_response("客户端令牌", json!({ "type": "array", "items": { "$ref": "#/components/schemas/ClientToken" } }))
+                    }
+                },
+                "post": {
+                    "summary": "创建客户端令牌，明文只在响应中返回一次",
+                    "requestBody": json_body(json!({
+                        "type": "object",
+                        "required": ["name"],
+                        "properties": {
+                            "name": { "type": "string" },
+                            "expiresAt": { "type": "string", "format": "date-time" },
+                            "scopes": { "type": "object" }
+                        }
+                    })),
+                    "responses": {
+                        "201": json_response("新建的令牌和明文", json!({
+                            "type": "object",
+                            "properties": {
+                                "token": { "$ref": "#/components/schemas/ClientToken" },
+                                "secret": { "type": "string" }
+                            }
+                        })),
+                        "400": error_response("请求参数无效")
+                    }
+                }
+            },
+            "/admin/custom-auth-key": {
+                "get": {
+                    "summary": "是否设置了全局验证密钥，未设置时停用",
+                    "responses": {
+                        "200": json_response("状态", json!({
+                            "type": "object",
+                            "properties": { "hasCustomKey": { "type": "boolean" } }
+                        }))
+                    }
+                },
+                "put": {
+                    "summary": "设置全局验证密钥；已有客户端令牌时代理不再接受该密钥",
+                    "requestBody": key_body,
+                    "responses": {
+                        "204": { "description": "已保存" },
+                        "400": error_response("密钥无效")
+                    }
+                },
+                "delete": {
+                    "summary": "停用全局验证密钥，之后只接受客户端令牌",
+                    "responses": {
+                        "204": { "description": "已停用" }
+                    }
+                }
+            },
+            "/admin/custom-auth-key/validate": {
+                "post": {
+                    "summary": "检查密钥是否与全局验证密钥一致",
+                    "requestBody": key_body,
+                    "responses": {
+                        "200": json_response("检查结果", json!({
+                            "type": "object",
+                            "properties": { "valid": { "type": "boolean" } }
+                        })),
+                        "429": error_response("来源失败次数过多，已暂时锁定")
+                    }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": {
+                "adminToken": { "type": "apiKey", "in": "header", "name": "X-Admin-Token" },
+                "bearerAuth": { "type": "http", "scheme": "bearer" }
+            },
+            "schemas": {
+                "Error": {
+                    "type": "object",
+                    "properties": {
+                        "error": {
+                            "type": "object",
+                            "properties": {
+                                "code": { "type": "string" },
+                                "message": { "type": "string" },
+                                "status": { "type": "string" }
+                            }
+                        }
+                    }
+                },
+                "Page": {
+                    "type": "object",
+                    "properties": {
+                        "items": { "type": "array", "items": {} },
+                        "totalCount": { "type": "integer" },
+                        "page": { "type": "integer" },
+                        "perPage": { "type": "integer" },
+                        "totalPages": { "type": "integer" }
+                    }
+                },
+                "ApiKey": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "name": { "type": "string" },
+                        "keyValue": { "type": "string", "description": "脱敏后的密钥，服务账号固定为 ****" },
+                        "isActive": { "type": "boolean" },
+                        "usageCount": { "type": "integer" },
+                        "lastUsed": { "type": "string", "format": "date-time", "nullable": true },
+                        "providerId": { "type": "string", "format": "uuid", "nullable": true },
+                        "credentialType": { "type": "string" },
+                        "vertexProject": { "type": "string", "nullable": true },
+                        "vertexLocation": { "type": "string", "nullable": true },
+                        "createdAt": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "CreateApiKey": {
+                    "type": "object",
+                    "required": ["name", "keyValue"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "keyValue": { "type": "string" },
+                        "providerId": { "type": "string", "format": "uuid" },
+                        "credentialType": { "type": "string", "description": "不填写时根据密钥内容自动识别" },
+                        "vertexProject": { "type": "string" },
+                        "vertexLocation": { "type": "string" }
+                    }
+                },
+                "UpdateApiKey": {
+                    "type": "object",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "isActive": { "type": "boolean" }
+                    }
+                },
+                "ClientToken": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "name": { "type": "string" },
+                        "tokenPrefix": { "type": "string" },
+                        "enabled": { "type": "boolean" },
+                        "scopes": { "type": "object" },
+                        "expiresAt": { "type": "string", "format": "date-time", "nullable": true },
+                        "lastUsedAt": { "type": "string", "format": "date-time", "nullable": true },
+                        "createdAt": { "type": "string", "format": "date-time" },
+                        "updatedAt": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "RequestLog": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string", "format": "uuid" },
+                        "apiKeyName": { "type": "string" },
+                        "method": { "type": "string" },
+                        "path": { "type": "string" },
+                        "statusCode": { "type": "integer" },
+                        "responseTimeMs": { "type": "integer" },
+                        "fallbackFrom": { "type": "string", "nullable": true },
+                        "cacheHit": { "type": "boolean" },
+                        "coalesced": { "type": "boolean" },
+                        "clientTokenName": { "type": "string", "nullable": true },
+                        "totalTokens": { "type": "integer", "nullable": true },
+                        "createdAt": { "type": "string", "format": "date-time" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// 无需令牌即可读取，便于生成客户端
+pub async fn admin_openapi() -> Json<Value> {
+    Json(admin_openapi_document())
+}
@@ -0,0 +1,77 @@
+use axum::{
+    extract::{ConnectInfo, State},
+    http::{header, Request},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    body::Body,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use crate::models::AuthScope;
+use crate::server::handlers::admin::{AdminApiActor, AdminApiError};
+use crate::server::middleware::locked_out_response;
+use crate::services::{peer_source, AdminApiService, AuthGuardService, ErrorLoggerService, SettingsService};
+use sqlx::SqlitePool;
+
+/// 管理 API 令牌请求头，也可以使用 `Authorization: Bearer`
+pub const ADMIN_TOKEN_HEADER: &str = "x-admin-token";
+
+fn admin_token(req: &Request<Body>) -> Option<String> {
+    let headers = req.headers();
+    headers
+        .get(ADMIN_TOKEN_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            headers
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .map(str::to_string)
+        })
+}
+
+/// 校验 `/admin` 请求的管理令牌；未配置令牌时管理 API 处于停用状态
+pub async fn admin_auth_middleware(
+    State(pool): State<Arc<SqlitePool>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Response {
+    let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
+    let method = req.method().to_string();
+    let path = req.uri().path().to_string();
+    let source = peer_source(req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(remote)| *remote));
+
+    let guard_settings = SettingsService::new(pool.as_ref().clone()).get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    if let Err(locked) = auth_guard.check(AuthScope::AdminApi, &source, &guard_settings) {
+        if let Err(e) = error_logger.log_auth_error(&method, &path, &locked.to_string(), 429, None).await {
+            tracing::warn!("Failed to log auth error: {}", e);
+        }
+        return locked_out_response(&locked);
+    }
+
+    let token = admin_token(&req).unwrap_or_default();
+    let rejection = match AdminApiService::new(pool.as_ref().clone()).verify(&token).await {
+        Ok(Some(true)) => None,
+        Ok(Some(false)) => Some(AdminApiError::Unauthenticated("Missing or invalid admin API token".to_string())),
+        Ok(None) => Some(AdminApiError::PermissionDenied("Admin API is disabled; generate an admin API token in the settings first".to_string())),
+        Err(e) => return AdminApiError::Internal(e).into_response(),
+    };
+
+    if let Some(error) = rejection {
+        if matches!(error, AdminApiError::Unauthenticated(_)) {
+            for locked in auth_guard.record_failure(AuthScope::AdminApi, &source, &guard_settings) {
+                tracing::warn!("{}", locked);
+            }
+        }
+        if let Err(e) = error_logger.log_auth_error(&method, &path, &error.to_string(), error.status().as_u16() as i32, None).await {
+            tracing::warn!("Failed to log auth error: {}", e);
+        }
+        return error.into_response();
+    }
+
+    auth_guard.record_success(AuthScope::AdminApi, &source);
+    req.extensions_mut().insert(AdminApiActor(format!("admin-api@{}", source)));
+    next.run(req).await
+}
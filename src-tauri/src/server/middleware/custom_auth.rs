@@ -1,14 +1,91 @@
 use axum::{
-    extract::State,
-    http::{Request, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{header, HeaderValue, Request, StatusCode},
     middleware::Next,
-    response::Response,
+    response::{IntoResponse, Json, Response},
     body::to_bytes,
 };
 use std::sync::Arc;
 use std::collections::HashMap;
-use crate::services::{CustomAuthService, ErrorLoggerService};
+use std::net::SocketAddr;
+use crate::models::{AuthScope, BruteForceSettings, ClientScopes, ClientToken};
+use crate::services::{peer_source, AuthGuardService, AuthLockedError, ClientTokenService, CustomAuthService, ErrorLoggerService, SettingsService};
+use crate::utils::mask_key_value;
 use sqlx::SqlitePool;
+use uuid::Uuid;
+
+/// 使用全局自定义密钥认证的客户端名称
+pub const DEFAULT_CLIENT_NAME: &str = "default";
+
+/// 通过认证的客户端身份，中间件把它放入请求扩展供处理器使用
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    /// 客户端令牌名称，策略规则等按名称匹配客户端
+    pub token_name: String,
+    /// client_tokens 中的令牌 ID，使用全局自定义密钥时为 None
+    pub token_id: Option<Uuid>,
+    /// 令牌的权限范围；全局自定义密钥只在还没有任何客户端令牌时可用，不受限制
+    pub scopes: ClientScopes,
+}
+
+/// 客户端超出权限范围时的 403 PERMISSION_DENIED 响应
+pub fn permission_denied_response(message: &str) -> Response {
+    let error_response = serde_json::json!({
+        "error": {
+            "code": "PERMISSION_DENIED",
+            "message": message,
+            "status": "PERMISSION_DENIED"
+        }
+    });
+
+    (StatusCode::FORBIDDEN, Json(error_response)).into_response()
+}
+
+/// 来源因认证失败过多被锁定时的 429 响应
+pub fn locked_out_response(locked: &AuthLockedError) -> Response {
+    let error_response = serde_json::json!({
+        "error": {
+            "code": "RESOURCE_EXHAUSTED",
+            "message": locked.to_string(),
+            "status": "RESOURCE_EXHAUSTED"
+        }
+    });
+
+    let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error_response)).into_response();
+    response.headers_mut().insert(
+        header::RETRY_AFTER,
+        HeaderValue::from(locked.retry_after.as_secs_f64().ceil().max(1.0) as u64),
+    );
+    response
+}
+
+/// 记录一次密钥错误，触发锁定时写入日志
+async fn record_auth_failure(
+    error_logger: &ErrorLoggerService,
+    settings: &BruteForceSettings,
+    source: &str,
+    method: &str,
+    path: &str,
+) {
+    for locked in AuthGuardService::new().record_failure(AuthScope::Proxy, source, settings) {
+        tracing::warn!("{}", locked);
+        if let Err(e) = error_logger.log_auth_error(method, path, &locked.to_string(), 429, None).await {
+            tracing::warn!("Failed to log auth lockout: {}", e);
+        }
+    }
+}
+
+/// 检查令牌是否已用完日或月上限，返回拒绝原因
+async fn scope_violation(token_service: &ClientTokenService, token: &ClientToken) -> anyhow::Result<Option<String>> {
+    if token.scopes.has_caps() {
+        let usage = token_service.usage(token.id).await?;
+        if let Some(cap) = usage.exceeded_cap(&token.scopes) {
+            return Ok(Some(format!("Client token '{}' has reached its {}", token.name, cap)));
+        }
+    }
+
+    Ok(None)
+}
 
 pub async fn custom_auth_middleware(
     State(pool): State<Arc<SqlitePool>>,
@@ -18,6 +95,18 @@ pub async fn custom_auth_middleware(
     let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
     let method = req.method().to_string();
     let path = req.uri().path().to_string();
+
+    // 来源 IP 或全局处于锁定状态时，不再验证密钥
+    let source = peer_source(req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(remote)| *remote));
+    let settings_service = SettingsService::new(pool.as_ref().clone());
+    let guard_settings = settings_service.get_brute_force_settings().await.unwrap_or_default();
+    let auth_guard = AuthGuardService::new();
+    if let Err(locked) = auth_guard.check(AuthScope::Proxy, &source, &guard_settings) {
+        if let Err(e) = error_logger.log_auth_error(&method, &path, &locked.to_string(), 429, None).await {
+            tracing::warn!("Failed to log auth error: {}", e);
+        }
+        return Ok(locked_out_response(&locked));
+    }
     
     // 提取请求体用于日志记录（如果是POST请求）
     let request_body = if req.method() == "POST" {
@@ -42,11 +131,7 @@ pub async fn custom_auth_middleware(
     // 从请求头获取自定义验证密钥
     let auth_header_key = if let Some(auth_header) = req.headers().get("authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
-            if auth_str.starts_with("Bearer ") {
-                Some(auth_str[7..].to_string())
-            } else {
-                None
-            }
+            auth_str.strip_prefix("Bearer ").map(str::to_string)
         } else {
             None
         }
@@ -82,12 +167,19 @@ pub async fn custom_auth_middleware(
         }
     };
 
-    // 验证自定义密钥
-    let custom_auth_service = CustomAuthService::new(pool.as_ref().clone());
-    match custom_auth_service.validate_custom_key(&key_value).await {
-        Ok(is_valid) => {
-            if !is_valid {
-                let error_msg = format!("Invalid custom key provided: {}", mask_key(&key_value));
+    // 先按客户端令牌验证，找不到时再与全局自定义密钥比对；
+    // 创建过客户端令牌后不再接受全局密钥，避免绕过令牌的权限范围
+    let token_service = ClientTokenService::new(pool.as_ref().clone());
+    let identity = match token_service.find_by_secret(&key_value).await {
+        Ok(Some(token)) => {
+            let rejection = if !token.enabled {
+                Some(format!("Client token '{}' has been revoked", token.name))
+            } else if token.is_expired() {
+                Some(format!("Client token '{}' has expired", token.name))
+            } else {
+                None
+            };
+            if let Some(error_msg) = rejection {
                 if let Err(e) = error_logger.log_auth_error(
                     &method,
                     &path,
@@ -99,9 +191,69 @@ pub async fn custom_auth_middleware(
                 }
                 return Err(StatusCode::FORBIDDEN);
             }
+
+            match scope_violation(&token_service, &token).await {
+                Ok(None) => {}
+                Ok(Some(error_msg)) => {
+                    if let Err(e) = error_logger.log_auth_error(
+                        &method,
+                        &path,
+                        &error_msg,
+                        403,
+                        request_body.as_deref(),
+                    ).await {
+                        tracing::warn!("Failed to log auth error: {}", e);
+                    }
+                    return Ok(permission_denied_response(&error_msg));
+                }
+                Err(e) => tracing::warn!("Failed to check client token usage: {}", e),
+            }
+
+            if let Err(e) = token_service.touch(token.id).await {
+                tracing::warn!("Failed to update client token last used time: {}", e);
+            }
+            ClientIdentity {
+                token_name: token.name,
+                token_id: Some(token.id),
+                scopes: token.scopes,
+            }
+        }
+        Ok(None) => {
+            match token_service.has_tokens().await {
+                Ok(false) => {}
+                Ok(true) => {
+                    let error_msg = "Unknown client token (the shared custom auth key is not accepted once client tokens exist)";
+                    if let Err(e) = error_logger.log_auth_error(
+                        &method,
+                        &path,
+                        error_msg,
+                        403,
+                        request_body.as_deref(),
+                    ).await {
+                        tracing::warn!("Failed to log auth error: {}", e);
+                    }
+                    record_auth_failure(&error_logger, &guard_settings, &source, &method, &path).await;
+                    return Err(StatusCode::FORBIDDEN);
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to check for client tokens: {}", e);
+                    return Err(StatusCode::INTERNAL_SERVER_ERROR);
+                }
+            }
+            if let Err(status) = validate_custom_key(&pool, &error_logger, &key_value, &method, &path, request_body.as_deref()).await {
+                if status == StatusCode::FORBIDDEN {
+                    record_auth_failure(&error_logger, &guard_settings, &source, &method, &path).await;
+                }
+                return Err(status);
+            }
+            ClientIdentity {
+                token_name: DEFAULT_CLIENT_NAME.to_string(),
+                token_id: None,
+                scopes: ClientScopes::default(),
+            }
         }
         Err(e) => {
-            let error_msg = format!("Failed to validate custom key: {}", e);
+            let error_msg = format!("Failed to validate client token: {}", e);
             if let Err(log_err) = error_logger.log_auth_error(
                 &method,
                 &path,
@@ -113,16 +265,54 @@ pub async fn custom_auth_middleware(
             }
             return Err(StatusCode::INTERNAL_SERVER_ERROR);
         }
-    }
+    };
 
     // 如果验证通过，继续处理请求
+    auth_guard.record_success(AuthScope::Proxy, &source);
+    req.extensions_mut().insert(identity);
     Ok(next.run(req).await)
 }
 
-fn mask_key(key: &str) -> String {
-    if key.len() > 8 {
-        format!("{}****{}", &key[..4], &key[key.len()-4..])
-    } else {
-        "****".to_string()
+/// 与全局自定义密钥比对，不匹配时记录日志并返回对应的状态码
+async fn validate_custom_key(
+    pool: &SqlitePool,
+    error_logger: &ErrorLoggerService,
+    key_value: &str,
+    method: &str,
+    path: &str,
+    request_body: Option<&str>,
+) -> Result<(), StatusCode> {
+    let custom_auth_service = CustomAuthService::new(pool.clone());
+    match custom_auth_service.validate_custom_key(key_value).await {
+        Ok(is_valid) => {
+            if !is_valid {
+                let error_msg = format!("Invalid custom key provided: {}", mask_key_value(key_value));
+                if let Err(e) = error_logger.log_auth_error(
+                    method,
+                    path,
+                    &error_msg,
+                    403,
+                    request_body,
+                ).await {
+                    tracing::warn!("Failed to log auth error: {}", e);
+                }
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+        Err(e) => {
+            let error_msg = format!("Failed to validate custom key: {}", e);
+            if let Err(log_err) = error_logger.log_auth_error(
+                method,
+                path,
+                &error_msg,
+                500,
+                request_body,
+            ).await {
+                tracing::warn!("Failed to log auth error: {}", log_err);
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
     }
+
+    Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,45 @@
+use axum::{
+    extract::{ConnectInfo, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+    body::Body,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use crate::server::middleware::permission_denied_response;
+use crate::services::{ErrorLoggerService, SettingsService, is_ip_allowed};
+use sqlx::SqlitePool;
+
+/// 按来源 IP 的允许和拒绝列表过滤请求，放在最外层，在认证之前执行
+pub async fn ip_filter_middleware(
+    State(pool): State<Arc<SqlitePool>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    // 取不到来源地址时无法判断，按拒绝处理
+    let remote = req.extensions().get::<ConnectInfo<SocketAddr>>().map(|ConnectInfo(remote)| remote.ip());
+    let error_msg = match remote {
+        Some(ip) => {
+            let settings_service = SettingsService::new(pool.as_ref().clone());
+            let settings = settings_service.get_network_settings().await.unwrap_or_default();
+            if is_ip_allowed(&settings, ip) {
+                return next.run(req).await;
+            }
+            format!("Client IP {} is not allowed to access the proxy", ip)
+        }
+        None => "Client IP is unknown, request rejected by the IP filter".to_string(),
+    };
+    let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
+    if let Err(e) = error_logger.log_auth_error(
+        req.method().as_str(),
+        req.uri().path(),
+        &error_msg,
+        403,
+        None,
+    ).await {
+        tracing::warn!("Failed to log IP filter rejection: {}", e);
+    }
+
+    permission_denied_response(&error_msg)
+}
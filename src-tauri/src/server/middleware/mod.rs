@@ -1,3 +1,9 @@
 pub mod custom_auth;
+pub mod rate_limit;
+pub mod ip_filter;
+pub mod admin_auth;
 
-pub use custom_auth::*;
\ No newline at end of file
+pub use custom_auth::*;
+pub use rate_limit::*;
+pub use ip_filter::*;
+pub use admin_auth::*;
\ No newline at end of file
@@ -0,0 +1,72 @@
+use axum::{
+    extract::State,
+    http::{header, HeaderValue, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Json, Response},
+    body::Body,
+};
+use std::sync::Arc;
+use crate::server::middleware::{ClientIdentity, DEFAULT_CLIENT_NAME};
+use crate::services::{ErrorLoggerService, RateLimiterService, SettingsService};
+use sqlx::SqlitePool;
+use tokio_stream::StreamExt;
+
+/// 按全局和客户端凭据限流，需要放在认证中间件之后（内层）以取得客户端身份
+pub async fn rate_limit_middleware(
+    State(pool): State<Arc<SqlitePool>>,
+    req: Request<Body>,
+    next: Next,
+) -> Response {
+    let settings_service = SettingsService::new(pool.as_ref().clone());
+    let settings = settings_service.get_rate_limit_settings().await.unwrap_or_default();
+    if !settings.enabled {
+        return next.run(req).await;
+    }
+
+    let client = req.extensions()
+        .get::<ClientIdentity>()
+        .map(|identity| identity.token_name.clone())
+        .unwrap_or_else(|| DEFAULT_CLIENT_NAME.to_string());
+
+    match RateLimiterService::new().acquire(&client, &settings).await {
+        Ok(permit) => {
+            let response = next.run(req).await;
+
+            // 流式响应在响应体发送完之前一直占用并发名额
+            let (parts, body) = response.into_parts();
+            let body = Body::from_stream(body.into_data_stream().map(move |chunk| {
+                let _ = &permit;
+                chunk
+            }));
+            Response::from_parts(parts, body)
+        }
+        Err(limited) => {
+            let error_msg = format!("{} (client: {})", limited, client);
+            let error_logger = ErrorLoggerService::new(pool.as_ref().clone());
+            if let Err(e) = error_logger.log_auth_error(
+                req.method().as_str(),
+                req.uri().path(),
+                &error_msg,
+                429,
+                None,
+            ).await {
+                tracing::warn!("Failed to log rate limit error: {}", e);
+            }
+
+            let error_response = serde_json::json!({
+                "error": {
+                    "code": "RESOURCE_EXHAUSTED",
+                    "message": error_msg,
+                    "status": "RESOURCE_EXHAUSTED"
+                }
+            });
+
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, Json(error_response)).into_response();
+            response.headers_mut().insert(
+                header::RETRY_AFTER,
+                HeaderValue::from(limited.retry_after.as_secs_f64().ceil().max(1.0) as u64),
+            );
+            response
+        }
+    }
+}
@@ -7,8 +7,21 @@ use axum::{
     middleware::from_fn_with_state,
 };
 use sqlx::SqlitePool;
-use std::sync::Arc;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, LazyLock, OnceLock};
+use std::time::Duration;
+use axum_server::Handle;
+use axum_server::tls_rustls::RustlsConfig;
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
 use tower_http::cors::CorsLayer;
+use anyhow::{Result, anyhow};
+use crate::models::NetworkSettings;
+use crate::services::{SettingsService, TlsService};
+
+// 重新绑定时等待旧监听器释放端口的最长时间，进行中的流式响应不会阻塞重启
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
 
 pub async fn create_app(pool: SqlitePool) -> Router {
     let app_state = Arc::new(pool);
@@ -20,12 +33,272 @@ pub async fn create_app(pool: SqlitePool) -> Router {
         .route("/v1beta/models", get(handlers::gemini::list_models))
         .route("/v1beta/models/*path", post(handlers::gemini::generate_content))
         .route("/v1beta/models/*path", get(handlers::gemini::get_model_by_path))
+        .layer(from_fn_with_state(app_state.clone(), middleware::rate_limit_middleware))
         .layer(from_fn_with_state(app_state.clone(), middleware::custom_auth_middleware));
 
+    // 管理 API 使用独立的管理令牌，不经过代理的验证和限流
+    let admin_routes = Router::new()
+        .route("/admin/api-keys", get(handlers::admin::list_api_keys).post(handlers::admin::create_api_key))
+        .route(
+            "/admin/api-keys/:id",
+            get(handlers::admin::get_api_key)
+                .patch(handlers::admin::update_api_key)
+                .delete(handlers::admin::delete_api_key),
+        )
+        .route("/admin/api-keys/:id/secret", get(handlers::admin::get_api_key_secret))
+        .route("/admin/api-keys/:id/today-requests", get(handlers::admin::get_api_key_today_requests))
+        .route("/admin/logs", get(handlers::admin::list_request_logs))
+        .route("/admin/logs/stats", get(handlers::admin::get_usage_stats))
+        .route("/admin/settings", get(handlers::admin::list_settings))
+        .route("/admin/settings/:name", get(handlers::admin::get_setting).put(handlers::admin::put_setting))
+        .route("/admin/client-tokens", get(handlers::admin::list_client_tokens).post(handlers::admin::create_client_token))
+        .route(
+            "/admin/custom-auth-key",
+            get(handlers::admin::get_custom_auth_key_status)
+                .put(handlers::admin::set_custom_auth_key)
+                .delete(handlers::admin::reset_custom_auth_key),
+        )
+        .route("/admin/custom-auth-key/validate", post(handlers::admin::validate_custom_auth_key))
+        .layer(from_fn_with_state(app_state.clone(), middleware::admin_auth_middleware));
+
     Router::new()
         .route("/health", get(handlers::health::health_check))
         .route("/v1", get(handlers::gemini::api_info))
+        .route("/admin/openapi.json", get(handlers::openapi::admin_openapi))
         .merge(protected_routes)
+        .merge(admin_routes)
         .layer(CorsLayer::permissive())
+        .layer(from_fn_with_state(app_state.clone(), middleware::ip_filter_middleware))
         .with_state(app_state)
-}
\ No newline at end of file
+}
+
+struct RunningServer {
+    addr: SocketAddr,
+    handle: Handle,
+    /// 以 HTTPS 提供服务时的证书配置，替换证书时原地更新，无需重新绑定
+    tls: Option<RustlsConfig>,
+    task: JoinHandle<()>,
+}
+
+// 当前运行的代理监听器，修改监听地址或切换 HTTPS 时停止并重新绑定
+static RUNNING: LazyLock<Mutex<Option<RunningServer>>> = LazyLock::new(|| Mutex::new(None));
+
+/// 启动参数中指定的监听地址，只在本进程内覆盖网络设置，不写入数据库
+#[derive(Debug, Clone, Default)]
+pub struct ListenOverride {
+    pub host: Option<String>,
+    pub port: Option<u16>,
+}
+
+static LISTEN_OVERRIDE: OnceLock<ListenOverride> = OnceLock::new();
+
+/// 设置本进程的监听地址覆盖，需在启动代理前调用；之后每次重新绑定都使用覆盖后的地址
+pub fn set_listen_override(listen: ListenOverride) {
+    if LISTEN_OVERRIDE.set(listen).is_err() {
+        tracing::warn!("Listen address override is already set, ignoring");
+    }
+}
+
+/// 数据库中的网络设置，叠加启动参数中的监听地址
+async fn effective_network_settings(pool: &SqlitePool) -> NetworkSettings {
+    let mut settings = SettingsService::new(pool.clone()).get_network_settings().await.unwrap_or_default();
+    if let Some(listen) = LISTEN_OVERRIDE.get() {
+        if let Some(host) = &listen.host {
+            settings.host = host.clone();
+        }
+        if let Some(port) = listen.port {
+            settings.port = port;
+        }
+    }
+    settings
+}
+
+/// 绑定配置的地址；端口被占用时依次尝试后面的端口
+async fn bind_with_fallback(settings: &NetworkSettings) -> Result<TcpListener> {
+    let host: IpAddr = settings.host.parse()
+        .map_err(|_| anyhow!("Invalid listen host '{}'", settings.host))?;
+
+    let mut last_error = None;
+    for offset in 0..=settings.port_fallback_attempts {
+        let Some(port) = settings.port.checked_add(offset) else {
+            break;
+        };
+        match TcpListener::bind(SocketAddr::new(host, port)).await {
+            Ok(listener) => {
+                if offset > 0 {
+                    tracing::warn!("Port {} is busy, proxy server fell back to port {}", settings.port, port);
+                }
+                return Ok(listener);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(anyhow!(
+        "Failed to bind proxy server to {}:{} (tried {} fallback ports): {}",
+        settings.host,
+        settings.port,
+        settings.port_fallback_attempts,
+        last_error.map(|e| e.to_string()).unwrap_or_default()
+    ))
+}
+
+/// 停止监听器，已建立的连接（包括流式响应）在后台继续完成
+async fn stop_server(server: RunningServer) {
+    server.handle.graceful_shutdown(None);
+    if tokio::time::timeout(SHUTDOWN_GRACE, server.task).await.is_err() {
+        tracing::info!("Previous proxy listener on {} is still draining connections", server.addr);
+    }
+}
+
+/// 按 HTTPS 设置加载证书，未启用时返回 None
+async fn load_tls_config(pool: &SqlitePool) -> Result<Option<RustlsConfig>> {
+    let settings = SettingsService::new(pool.clone()).get_tls_settings().await.unwrap_or_default();
+    if !settings.enabled {
+        return Ok(None);
+    }
+
+    // 只启用了 ring 作为 rustls 的加密实现，重复安装会返回错误，可以忽略
+    let _ = rustls::crypto::ring::default_provider().install_default();
+
+    let pem = TlsService::new(pool.clone()).load_certificate(&settings).await?;
+    let config = RustlsConfig::from_pem(pem.cert, pem.key).await
+        .map_err(|e| anyhow!("Failed to load TLS certificate: {}", e))?;
+    tracing::info!("Using TLS certificate with SHA-256 fingerprint {}", pem.info.fingerprint_sha256);
+    Ok(Some(config))
+}
+
+/// 在已绑定的监听器上启动代理服务
+async fn serve(listener: std::net::TcpListener, pool: SqlitePool, tls: Option<RustlsConfig>) -> Result<RunningServer> {
+    let addr = listener.local_addr()?;
+    let service = create_app(pool).await.into_make_service_with_connect_info::<SocketAddr>();
+
+    let handle = Handle::new();
+    let task = match tls.clone() {
+        Some(config) => {
+            let server = axum_server::from_tcp_rustls(listener, config).handle(handle.clone());
+            tokio::spawn(async move {
+                if let Err(e) = server.serve(service).await {
+                    tracing::error!("Proxy server stopped with error: {}", e);
+                }
+            })
+        }
+        None => {
+            let server = axum_server::from_tcp(listener).handle(handle.clone());
+            tokio::spawn(async move {
+                if let Err(e) = server.serve(service).await {
+                    tracing::error!("Proxy server stopped with error: {}", e);
+                }
+            })
+        }
+    };
+
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    tracing::info!("Gemini proxy server listening on {}://{}", scheme, addr);
+    Ok(RunningServer { addr, handle, tls, task })
+}
+
+/// 按网络设置启动代理服务；已在运行时用于修改监听地址或切换 HTTPS 后重新绑定，
+/// 新的地址绑定成功后才停止旧的监听器，失败时旧的监听器继续提供服务
+pub async fn start_server(pool: SqlitePool) -> Result<SocketAddr> {
+    let mut running = RUNNING.lock().await;
+
+    let tls = load_tls_config(&pool).await?;
+    let settings = effective_network_settings(&pool).await;
+
+    let listener = match running.take() {
+        // 旧监听器占着新配置可能绑定的地址时只能先释放，绑定失败则在原地址上恢复
+        Some(previous) if settings.conflicts_with(previous.addr) => {
+            let (previous_addr, previous_tls) = (previous.addr, previous.tls.clone());
+            stop_server(previous).await;
+            match bind_with_fallback(&settings).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    match TcpListener::bind(previous_addr).await {
+                        Ok(listener) => *running = Some(serve(listener.into_std()?, pool, previous_tls).await?),
+                        Err(restore_error) => tracing::error!("Failed to restore proxy listener on {}: {}", previous_addr, restore_error),
+                    }
+                    return Err(e);
+                }
+            }
+        }
+        previous => match bind_with_fallback(&settings).await {
+            Ok(listener) => {
+                if let Some(previous) = previous {
+                    stop_server(previous).await;
+                }
+                listener
+            }
+            Err(e) => {
+                *running = previous;
+                return Err(e);
+            }
+        },
+    };
+
+    let server = serve(listener.into_std()?, pool, tls).await?;
+    let addr = server.addr;
+    *running = Some(server);
+    Ok(addr)
+}
+
+/// 应用 HTTPS 设置：启用状态变化时重新绑定，否则原地替换证书，已建立的连接不受影响
+pub async fn apply_tls_settings(pool: SqlitePool) -> Result<()> {
+    let enabled = SettingsService::new(pool.clone()).get_tls_settings().await.unwrap_or_default().enabled;
+    let current = RUNNING.lock().await.as_ref().map(|server| server.tls.clone());
+
+    match current {
+        // 代理尚未启动，启动时会读取最新设置
+        None => Ok(()),
+        Some(Some(config)) if enabled => {
+            let settings = SettingsService::new(pool.clone()).get_tls_settings().await.unwrap_or_default();
+            let pem = TlsService::new(pool).load_certificate(&settings).await?;
+            config.reload_from_pem(pem.cert, pem.key).await
+                .map_err(|e| anyhow!("Failed to reload TLS certificate: {}", e))?;
+            tracing::info!("Reloaded TLS certificate, SHA-256 fingerprint {}", pem.info.fingerprint_sha256);
+            Ok(())
+        }
+        Some(None) if !enabled => Ok(()),
+        Some(_) => start_server(pool).await.map(|_| ()),
+    }
+}
+
+/// 停止代理服务，等待进行中的请求（包括流式响应）完成，超过 timeout 后强制关闭剩余连接
+pub async fn shutdown_server(timeout: Duration) {
+    let Some(server) = RUNNING.lock().await.take() else {
+        return;
+    };
+    server.handle.graceful_shutdown(Some(timeout));
+    if let Err(e) = server.task.await {
+        tracing::warn!("Proxy listener on {} did not stop cleanly: {}", server.addr, e);
+    }
+}
+
+/// 在后台重新绑定；管理 API 在代理自身的请求中修改监听地址时使用，不必等待当前连接结束
+pub fn start_server_in_background(pool: SqlitePool) {
+    tokio::spawn(async move {
+        if let Err(e) = start_server(pool).await {
+            tracing::error!("Failed to rebind proxy server: {}", e);
+        }
+    });
+}
+
+/// 在后台应用 HTTPS 设置，用法同 `start_server_in_background`
+pub fn apply_tls_settings_in_background(pool: SqlitePool) {
+    tokio::spawn(async move {
+        if let Err(e) = apply_tls_settings(pool).await {
+            tracing::error!("Failed to apply TLS settings: {}", e);
+        }
+    });
+}
+
+/// 代理当前是否以 HTTPS 提供服务
+pub async fn tls_active() -> bool {
+    RUNNING.lock().await.as_ref().is_some_and(|server| server.tls.is_some())
+}
+
+/// 代理服务实际监听的地址（可能因端口回退与设置不同）
+#[cfg(feature = "desktop")]
+pub async fn listening_addr() -> Option<SocketAddr> {
+    RUNNING.lock().await.as_ref().map(|server| server.addr)
+}
@@ -0,0 +1,118 @@
+#[cfg(feature = "desktop")]
+use crate::models::AdminApiStatus;
+use crate::utils::constant_time_eq;
+use sqlx::SqlitePool;
+use anyhow::{Result, anyhow};
+use chrono::{Utc, SecondsFormat};
+#[cfg(feature = "desktop")]
+use chrono::DateTime;
+use sha2::{Sha256, Digest};
+#[cfg(feature = "desktop")]
+use uuid::Uuid;
+
+// 令牌明文的固定前缀，与客户端令牌 `tjm_` 区分
+const TOKEN_PREFIX: &str = "tjadm_";
+// 状态中显示的令牌开头长度（含前缀）
+const DISPLAY_PREFIX_LEN: usize = 14;
+// 手动指定的令牌（例如无界面部署的配置文件）至少需要的长度
+const MIN_TOKEN_LEN: usize = 24;
+
+/// 生成 256 位随机数的令牌明文
+#[cfg(feature = "desktop")]
+fn generate_token() -> String {
+    format!("{}{}{}", TOKEN_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 管理 API 的令牌，与管理界面的登录会话和代理的客户端令牌相互独立
+pub struct AdminApiService {
+    pool: SqlitePool,
+}
+
+impl AdminApiService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn status(&self) -> Result<AdminApiStatus> {
+        let result: (Option<String>, Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT admin_api_token_hash, admin_api_token_prefix, admin_api_token_created_at FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(AdminApiStatus {
+            enabled: result.0.is_some(),
+            token_prefix: result.1,
+            token_created_at: result.2
+                .and_then(|value| DateTime::parse_from_rfc3339(&value).ok())
+                .map(|dt| dt.with_timezone(&Utc)),
+        })
+    }
+
+    /// 生成新令牌并启用管理 API，旧令牌立即失效；返回的明文只显示这一次
+    #[cfg(feature = "desktop")]
+    pub async fn rotate_token(&self) -> Result<String> {
+        let token = generate_token();
+        self.store(&token).await?;
+        Ok(token)
+    }
+
+    /// 使用指定的令牌，用于通过配置文件或环境变量部署；与当前令牌相同时不做修改
+    pub async fn set_token(&self, token: &str) -> Result<()> {
+        let token = token.trim();
+        if token.len() < MIN_TOKEN_LEN {
+            return Err(anyhow!("Admin API token must be at least {} characters", MIN_TOKEN_LEN));
+        }
+        if self.verify(token).await? == Some(true) {
+            return Ok(());
+        }
+        self.store(token).await
+    }
+
+    /// 删除令牌并停用管理 API
+    #[cfg(feature = "desktop")]
+    pub async fn revoke_token(&self) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET admin_api_token_hash = NULL, admin_api_token_prefix = NULL, admin_api_token_created_at = NULL, updated_at = ? WHERE id = 1"
+        )
+        .bind(Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 未配置令牌时返回 None，否则返回令牌是否正确
+    pub async fn verify(&self, token: &str) -> Result<Option<bool>> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT admin_api_token_hash FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.0.map(|stored| constant_time_eq(stored.as_bytes(), hash_token(token).as_bytes())))
+    }
+
+    async fn store(&self, token: &str) -> Result<()> {
+        let now = Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true);
+        // 手动指定的令牌没有随机前缀，只显示开头 4 位
+        let shown = if token.starts_with(TOKEN_PREFIX) { DISPLAY_PREFIX_LEN } else { 4 };
+        let prefix: String = token.chars().take(shown).collect();
+        sqlx::query(
+            "UPDATE app_settings SET admin_api_token_hash = ?, admin_api_token_prefix = ?, admin_api_token_created_at = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(hash_token(token))
+        .bind(prefix)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,207 @@
+use crate::models::{AdminSession, SessionSettings};
+use crate::services::SettingsService;
+use sqlx::SqlitePool;
+use anyhow::Result;
+use chrono::{Duration, Utc, SecondsFormat};
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 生成 256 位随机数的会话令牌
+fn generate_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+fn hash_token(token: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(token.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct AdminSessionService {
+    pool: SqlitePool,
+}
+
+impl AdminSessionService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    async fn settings(&self) -> SessionSettings {
+        SettingsService::new(self.pool.clone()).get_session_settings().await.unwrap_or_default()
+    }
+
+    /// 登录成功后创建会话，返回会话和令牌明文；顺便清理已过期的会话
+    pub async fn create_session(&self, password_change_required: bool) -> Result<(AdminSession, String)> {
+        let settings = self.settings().await;
+        let now = Utc::now();
+        self.purge_expired(&settings).await?;
+
+        let token = generate_token();
+        let session = AdminSession {
+            id: Uuid::new_v4(),
+            password_change_required,
+            created_at: now,
+            last_seen_at: now,
+            expires_at: now + Duration::minutes(settings.ttl_minutes as i64),
+        };
+
+        sqlx::query(
+            "INSERT INTO admin_sessions (id, token_hash, password_change_required, created_at, last_seen_at, expires_at) VALUES (?, ?, ?, ?, ?, ?)"
+        )
+        .bind(session.id.to_string())
+        .bind(hash_token(&token))
+        .bind(session.password_change_required as i32)
+        .bind(to_js_compatible_timestamp(session.created_at))
+        .bind(to_js_compatible_timestamp(session.last_seen_at))
+        .bind(to_js_compatible_timestamp(session.expires_at))
+        .execute(&self.pool)
+        .await?;
+
+        Ok((session, token))
+    }
+
+    /// 校验令牌并刷新最近活动时间；令牌不存在、已过期或空闲超时时返回 None
+    pub async fn validate(&self, token: &str) -> Result<Option<AdminSession>> {
+        if token.is_empty() {
+            return Ok(None);
+        }
+
+        let session: Option<AdminSession> = sqlx::query_as(
+            "SELECT id, password_change_required, created_at, last_seen_at, expires_at FROM admin_sessions WHERE token_hash = ?"
+        )
+        .bind(hash_token(token))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some(mut session) = session else {
+            return Ok(None);
+        };
+
+        let settings = self.settings().await;
+        let now = Utc::now();
+        let idle_deadline = session.last_seen_at + Duration::minutes(settings.idle_timeout_minutes as i64);
+        if now >= session.expires_at || now >= idle_deadline {
+            self.delete_session(session.id).await?;
+            return Ok(None);
+        }
+
+        sqlx::query("UPDATE admin_sessions SET last_seen_at = ? WHERE id = ?")
+            .bind(to_js_compatible_timestamp(now))
+            .bind(session.id.to_string())
+            .execute(&self.pool)
+            .await?;
+        session.last_seen_at = now;
+
+        Ok(Some(session))
+    }
+
+    /// 登出，令牌不存在时也视为成功
+    pub async fn revoke(&self, token: &str) -> Result<()> {
+        sqlx::query("DELETE FROM admin_sessions WHERE token_hash = ?")
+            .bind(hash_token(token))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    /// 修改密码后调用：当前会话解除修改密码的限制，其他会话全部失效
+    pub async fn password_changed(&self, current: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM admin_sessions WHERE id != ?")
+            .bind(current.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        sqlx::query("UPDATE admin_sessions SET password_change_required = 0 WHERE id = ?")
+            .bind(current.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn delete_session(&self, id: Uuid) -> Result<()> {
+        sqlx::query("DELETE FROM admin_sessions WHERE id = ?")
+            .bind(id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
+    async fn purge_expired(&self, settings: &SessionSettings) -> Result<()> {
+        let now = Utc::now();
+        let idle_cutoff = now - Duration::minutes(settings.idle_timeout_minutes as i64);
+
+        sqlx::query("DELETE FROM admin_sessions WHERE expires_at <= ? OR last_seen_at <= ?")
+            .bind(to_js_compatible_timestamp(now))
+            .bind(to_js_compatible_timestamp(idle_cutoff))
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_pool;
+
+    async fn set_timestamp(service: &AdminSessionService, column: &str, id: Uuid, value: chrono::DateTime<Utc>) {
+        sqlx::query(&format!("UPDATE admin_sessions SET {} = ? WHERE id = ?", column))
+            .bind(to_js_compatible_timestamp(value))
+            .bind(id.to_string())
+            .execute(&service.pool)
+            .await
+            .unwrap();
+    }
+
+    async fn session_count(service: &AdminSessionService) -> i64 {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM admin_sessions")
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+        count
+    }
+
+    #[tokio::test]
+    async fn valid_session_is_returned_until_revoked() {
+        let service = AdminSessionService::new(test_pool().await);
+        let (session, token) = service.create_session(false).await.unwrap();
+
+        let validated = service.validate(&token).await.unwrap().unwrap();
+        assert_eq!(validated.id, session.id);
+        assert!(service.validate("").await.unwrap().is_none());
+        assert!(service.validate("not-a-session").await.unwrap().is_none());
+
+        service.revoke(&token).await.unwrap();
+        assert!(service.validate(&token).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn expired_and_idle_sessions_are_rejected_and_deleted() {
+        let service = AdminSessionService::new(test_pool().await);
+        let (expired, expired_token) = service.create_session(false).await.unwrap();
+        let (idle, idle_token) = service.create_session(false).await.unwrap();
+        set_timestamp(&service, "expires_at", expired.id, Utc::now() - Duration::seconds(1)).await;
+        let idle_timeout = SessionSettings::default().idle_timeout_minutes as i64;
+        set_timestamp(&service, "last_seen_at", idle.id, Utc::now() - Duration::minutes(idle_timeout + 1)).await;
+
+        assert!(service.validate(&expired_token).await.unwrap().is_none());
+        assert!(service.validate(&idle_token).await.unwrap().is_none());
+        assert_eq!(session_count(&service).await, 0);
+    }
+
+    #[tokio::test]
+    async fn password_change_keeps_only_the_current_session() {
+        let service = AdminSessionService::new(test_pool().await);
+        let (current, current_token) = service.create_session(true).await.unwrap();
+        let (_, other_token) = service.create_session(true).await.unwrap();
+
+        service.password_changed(current.id).await.unwrap();
+        assert!(!service.validate(&current_token).await.unwrap().unwrap().password_change_required);
+        assert!(service.validate(&other_token).await.unwrap().is_none());
+    }
+}
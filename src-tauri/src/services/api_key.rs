@@ -1,4 +1,6 @@
-use crate::models::{ApiKey, CreateApiKeyRequest, UpdateApiKeyRequest, ApiKeyResponse};
+use crate::models::{ApiKey, CredentialType, CreateApiKeyRequest, UpdateApiKeyRequest, ApiKeyResponse};
+use crate::services::{validate_vertex_location, ServiceAccountKey, DEFAULT_VERTEX_LOCATION};
+use crate::utils::ValidationError;
 use sqlx::SqlitePool;
 use anyhow::Result;
 use chrono::{Utc, SecondsFormat};
@@ -22,13 +24,33 @@ impl ApiKeyService {
         let key_id = Uuid::new_v4();
         let now = Utc::now();
 
-        tracing::info!("Creating API key: name={}, key_prefix={}", 
-            request.name, &request.key_value[..10]);
+        let credential_type = request.credential_type
+            .unwrap_or_else(|| Self::detect_credential_type(&request.key_value));
+        let (vertex_project, vertex_location) = match credential_type {
+            CredentialType::ApiKey => (None, None),
+            CredentialType::VertexServiceAccount => {
+                let account = ServiceAccountKey::parse(&request.key_value)
+                    .map_err(|e| ValidationError(e.to_string()))?;
+                let project = request.vertex_project
+                    .filter(|p| !p.trim().is_empty())
+                    .or(account.project_id)
+                    .ok_or_else(|| ValidationError("Vertex AI project is required when the service account JSON has no project_id".to_string()))?;
+                let location = request.vertex_location
+                    .map(|l| l.trim().to_string())
+                    .filter(|l| !l.is_empty())
+                    .unwrap_or_else(|| DEFAULT_VERTEX_LOCATION.to_string());
+                validate_vertex_location(&location).map_err(|e| ValidationError(e.to_string()))?;
+                (Some(project), Some(location))
+            }
+        };
+
+        tracing::info!("Creating API key: name={}, type={}, key_prefix={}", 
+            request.name, credential_type.as_str(), request.key_value.get(..10).unwrap_or(""));
 
         let result = sqlx::query(
             r#"
-            INSERT INTO api_keys (id, name, key_value, is_active, usage_count, created_at, updated_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO api_keys (id, name, key_value, is_active, usage_count, provider_id, credential_type, vertex_project, vertex_location, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(key_id.to_string())
@@ -36,6 +58,10 @@ impl ApiKeyService {
         .bind(&request.key_value)
         .bind(1)
         .bind(0)
+        .bind(request.provider_id.map(|id| id.to_string()))
+        .bind(credential_type.as_str())
+        .bind(&vertex_project)
+        .bind(&vertex_location)
         .bind(to_js_compatible_timestamp(now))
         .bind(to_js_compatible_timestamp(now))
         .execute(&self.pool)
@@ -59,14 +85,32 @@ impl ApiKeyService {
             is_active: true,
             usage_count: 0,
             last_used: None,
+            provider_id: request.provider_id,
+            credential_type,
+            vertex_project,
+            vertex_location,
             created_at: now,
         })
     }
 
+    /// 服务账号 JSON 识别为 Vertex AI 凭据，其余按 AI Studio API 密钥处理
+    fn detect_credential_type(key_value: &str) -> CredentialType {
+        let is_service_account = serde_json::from_str::<serde_json::Value>(key_value)
+            .ok()
+            .and_then(|v| v.get("type").and_then(|t| t.as_str()).map(|t| t == "service_account"))
+            .unwrap_or(false);
+
+        if is_service_account {
+            CredentialType::VertexServiceAccount
+        } else {
+            CredentialType::ApiKey
+        }
+    }
+
     pub async fn get_all_api_keys(&self) -> Result<Vec<ApiKeyResponse>> {
         let keys: Vec<ApiKey> = sqlx::query_as(
             r#"
-            SELECT id, name, key_value, is_active, usage_count, last_used, created_at, updated_at
+            SELECT id, name, key_value, is_active, usage_count, last_used, provider_id, credential_type, vertex_project, vertex_location, created_at, updated_at
             FROM api_keys ORDER BY created_at DESC
             "#,
         )
@@ -80,6 +124,10 @@ impl ApiKeyService {
             is_active: k.is_active,
             usage_count: k.usage_count,
             last_used: k.last_used,
+            provider_id: k.provider_id,
+            credential_type: k.credential_type,
+            vertex_project: k.vertex_project,
+            vertex_location: k.vertex_location,
             created_at: k.created_at,
         }).collect())
     }
@@ -96,7 +144,7 @@ impl ApiKeyService {
         // 获取分页数据
         let keys: Vec<ApiKey> = sqlx::query_as(
             r#"
-            SELECT id, name, key_value, is_active, usage_count, last_used, created_at, updated_at
+            SELECT id, name, key_value, is_active, usage_count, last_used, provider_id, credential_type, vertex_project, vertex_location, created_at, updated_at
             FROM api_keys ORDER BY created_at DESC
             LIMIT ? OFFSET ?
             "#,
@@ -113,6 +161,10 @@ impl ApiKeyService {
             is_active: k.is_active,
             usage_count: k.usage_count,
             last_used: k.last_used,
+            provider_id: k.provider_id,
+            credential_type: k.credential_type,
+            vertex_project: k.vertex_project,
+            vertex_location: k.vertex_location,
             created_at: k.created_at,
         }).collect();
 
@@ -158,6 +210,21 @@ impl ApiKeyService {
         self.get_api_key_by_id(key_id).await
     }
 
+    /// 绑定密钥到指定供应商，传入 None 时回退到默认供应商
+    #[cfg(feature = "desktop")]
+    pub async fn set_api_key_provider(&self, key_id: Uuid, provider_id: Option<Uuid>) -> Result<Option<ApiKeyResponse>> {
+        sqlx::query(
+            "UPDATE api_keys SET provider_id = ?, updated_at = ? WHERE id = ?"
+        )
+        .bind(provider_id.map(|id| id.to_string()))
+        .bind(to_js_compatible_timestamp(Utc::now()))
+        .bind(key_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        self.get_api_key_by_id(key_id).await
+    }
+
     pub async fn delete_api_key(&self, key_id: Uuid) -> Result<bool> {
         let result = sqlx::query(
             "DELETE FROM api_keys WHERE id = ?"
@@ -169,10 +236,19 @@ impl ApiKeyService {
         Ok(result.rows_affected() > 0)
     }
 
+    /// 是否已经保存了相同的密钥
+    pub async fn key_value_exists(&self, key_value: &str) -> Result<bool> {
+        let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM api_keys WHERE key_value = ?")
+            .bind(key_value)
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count.0 > 0)
+    }
+
     pub async fn get_api_key_by_id(&self, key_id: Uuid) -> Result<Option<ApiKeyResponse>> {
         let key: Option<ApiKey> = sqlx::query_as(
             r#"
-            SELECT id, name, key_value, is_active, usage_count, last_used, created_at, updated_at
+            SELECT id, name, key_value, is_active, usage_count, last_used, provider_id, credential_type, vertex_project, vertex_location, created_at, updated_at
             FROM api_keys WHERE id = ?
             "#,
         )
@@ -187,6 +263,10 @@ impl ApiKeyService {
             is_active: k.is_active,
             usage_count: k.usage_count,
             last_used: k.last_used,
+            provider_id: k.provider_id,
+            credential_type: k.credential_type,
+            vertex_project: k.vertex_project,
+            vertex_location: k.vertex_location,
             created_at: k.created_at,
         }))
     }
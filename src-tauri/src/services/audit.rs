@@ -0,0 +1,294 @@
+use crate::models::{AuditChannel, AuditEvent};
+use sqlx::SqlitePool;
+#[cfg(feature = "desktop")]
+use crate::models::{AuditEventFilter, AuditExportFormat};
+#[cfg(feature = "desktop")]
+use crate::utils::csv_field;
+#[cfg(feature = "desktop")]
+use sqlx::{QueryBuilder, Sqlite};
+use serde_json::{Map, Value};
+use anyhow::Result;
+use uuid::Uuid;
+use std::collections::BTreeSet;
+
+const MASK: &str = "******";
+
+/// 去掉下划线和连字符后按小写比较，同时匹配 camelCase、snake_case 和 HTTP 头
+const SECRET_FIELDS: &[&str] = &[
+    "key",
+    "keyvalue",
+    "apikey",
+    "xapikey",
+    "xgoogapikey",
+    "customkey",
+    "password",
+    "currentpassword",
+    "newpassword",
+    "token",
+    "sessiontoken",
+    "secret",
+    "privatekey",
+    "authorization",
+    "proxyauthorization",
+    "code",
+    "recoverycodes",
+];
+
+/// 每次修改都会变化的字段，不计入变更
+const IGNORED_FIELDS: &[&str] = &["updatedAt"];
+
+fn is_secret_field(name: &str) -> bool {
+    let normalized: String = name
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    SECRET_FIELDS.contains(&normalized.as_str())
+}
+
+/// 递归替换密钥类字段的值，空值保留以便区分设置和清除
+fn mask_secrets(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(name, value)| {
+                    let masked = if is_secret_field(name) && !value.is_null() {
+                        Value::String(MASK.to_string())
+                    } else {
+                        mask_secrets(value)
+                    };
+                    (name.clone(), masked)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(mask_secrets).collect()),
+        other => other.clone(),
+    }
+}
+
+/// 按顶层字段比较前后状态；不是对象的值（例如重试次数）记在 `value` 下
+fn diff(before: Option<&Value>, after: Option<&Value>) -> Value {
+    let as_object = |value: Option<&Value>| -> Map<String, Value> {
+        match value {
+            Some(Value::Object(map)) => map.clone(),
+            Some(Value::Null) | None => Map::new(),
+            Some(other) => Map::from_iter([("value".to_string(), other.clone())]),
+        }
+    };
+    let before = as_object(before);
+    let after = as_object(after);
+
+    let fields: BTreeSet<&String> = before.keys().chain(after.keys()).collect();
+    let mut changes = Map::new();
+    for field in fields {
+        if IGNORED_FIELDS.contains(&field.as_str()) {
+            continue;
+        }
+        let old = before.get(field).unwrap_or(&Value::Null);
+        let new = after.get(field).unwrap_or(&Value::Null);
+        if old == new {
+            continue;
+        }
+
+        let (old, new) = if is_secret_field(field) {
+            // 密钥类字段只表明发生了变化，不记录内容
+            let mask = |value: &Value| if value.is_null() { Value::Null } else { Value::String(MASK.to_string()) };
+            (mask(old), mask(new))
+        } else {
+            (mask_secrets(old), mask_secrets(new))
+        };
+        changes.insert(field.clone(), serde_json::json!({ "before": old, "after": new }));
+    }
+    Value::Object(changes)
+}
+
+/// 序列化操作前后的状态，供 `AuditService::record` 比较
+pub fn snapshot<T: serde::Serialize>(value: &T) -> Option<Value> {
+    serde_json::to_value(value).ok()
+}
+
+#[cfg(feature = "desktop")]
+fn push_filter<'a>(builder: &mut QueryBuilder<'a, Sqlite>, filter: &'a AuditEventFilter) {
+    builder.push(" WHERE 1 = 1");
+    if let Some(action) = filter.action.as_deref().filter(|action| !action.is_empty()) {
+        builder.push(" AND action LIKE ").push_bind(format!("{}%", action.replace('%', "")));
+    }
+    if let Some(channel) = filter.channel {
+        builder.push(" AND channel = ").push_bind(channel.as_str());
+    }
+    if let Some(session_id) = filter.session_id {
+        builder.push(" AND session_id = ").push_bind(session_id.to_string());
+    }
+    if let Some(target) = filter.target.as_deref().filter(|target| !target.is_empty()) {
+        builder.push(" AND target = ").push_bind(target);
+    }
+    if let Some(since) = filter.since {
+        builder.push(" AND created_at >= ").push_bind(since.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    }
+    if let Some(until) = filter.until {
+        builder.push(" AND created_at < ").push_bind(until.to_rfc3339_opts(chrono::SecondsFormat::Millis, true));
+    }
+}
+
+pub struct AuditService {
+    pool: SqlitePool,
+}
+
+impl AuditService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 记录一次管理操作，before/after 为操作前后的状态，新建时 before 为空，删除时 after 为空
+    #[allow(clippy::too_many_arguments)]
+    pub async fn record(
+        &self,
+        channel: AuditChannel,
+        session_id: Option<Uuid>,
+        actor: Option<&str>,
+        action: &str,
+        target: Option<&str>,
+        before: Option<&Value>,
+        after: Option<&Value>,
+    ) -> Result<AuditEvent> {
+        let event = AuditEvent {
+            id: Uuid::new_v4(),
+            channel,
+            session_id,
+            actor: actor.map(str::to_string),
+            action: action.to_string(),
+            target: target.map(str::to_string),
+            changes: diff(before, after),
+            created_at: chrono::Utc::now(),
+        };
+
+        sqlx::query(
+            "INSERT INTO audit_events (id, channel, session_id, actor, action, target, changes, created_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?)"
+        )
+        .bind(event.id.to_string())
+        .bind(event.channel.as_str())
+        .bind(event.session_id.map(|id| id.to_string()))
+        .bind(&event.actor)
+        .bind(&event.action)
+        .bind(&event.target)
+        .bind(event.changes.to_string())
+        .bind(event.created_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(event)
+    }
+
+    /// 按时间倒序分页查询，返回当前页和符合条件的总数
+    #[cfg(feature = "desktop")]
+    pub async fn list(&self, filter: &AuditEventFilter, page: u32, per_page: u32) -> Result<(Vec<AuditEvent>, u32)> {
+        let mut count_query = QueryBuilder::new("SELECT COUNT(*) FROM audit_events");
+        push_filter(&mut count_query, filter);
+        let (total_count,): (i64,) = count_query.build_query_as().fetch_one(&self.pool).await?;
+
+        let offset = page.saturating_sub(1) * per_page;
+        let mut query = QueryBuilder::new("SELECT id, channel, session_id, actor, action, target, changes, created_at FROM audit_events");
+        push_filter(&mut query, filter);
+        query
+            .push(" ORDER BY created_at DESC LIMIT ")
+            .push_bind(per_page)
+            .push(" OFFSET ")
+            .push_bind(offset);
+        let events = query.build_query_as::<AuditEvent>().fetch_all(&self.pool).await?;
+
+        Ok((events, total_count as u32))
+    }
+
+    /// 导出全部符合条件的记录
+    #[cfg(feature = "desktop")]
+    pub async fn export(&self, filter: &AuditEventFilter, format: AuditExportFormat) -> Result<String> {
+        let mut query = QueryBuilder::new("SELECT id, channel, session_id, actor, action, target, changes, created_at FROM audit_events");
+        push_filter(&mut query, filter);
+        query.push(" ORDER BY created_at DESC");
+        let events = query.build_query_as::<AuditEvent>().fetch_all(&self.pool).await?;
+
+        match format {
+            AuditExportFormat::Json => Ok(serde_json::to_string_pretty(&events)?),
+            AuditExportFormat::Csv => {
+                let mut csv = String::from("id,created_at,channel,session_id,actor,action,target,changes\n");
+                for event in &events {
+                    let row = [
+                        event.id.to_string(),
+                        event.created_at.to_rfc3339_opts(chrono::SecondsFormat::Millis, true),
+                        event.channel.as_str().to_string(),
+                        event.session_id.map(|id| id.to_string()).unwrap_or_default(),
+                        event.actor.clone().unwrap_or_default(),
+                        event.action.clone(),
+                        event.target.clone().unwrap_or_default(),
+                        event.changes.to_string(),
+                    ];
+                    csv.push_str(&row.iter().map(|field| csv_field(field)).collect::<Vec<_>>().join(","));
+                    csv.push('\n');
+                }
+                Ok(csv)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn secrets_are_masked_at_any_depth_and_in_any_case_style() {
+        let masked = mask_secrets(&json!({
+            "name": "prod",
+            "keyValue": "AIzaSy-secret",
+            "headers": { "X-Goog-Api-Key": "AIzaSy-secret", "Accept": "text/plain" },
+            "tokens": [{ "private_key": "-----BEGIN", "client_email": "a@b" }],
+            "customKey": null
+        }));
+        assert_eq!(masked, json!({
+            "name": "prod",
+            "keyValue": MASK,
+            "headers": { "X-Goog-Api-Key": MASK, "Accept": "text/plain" },
+            "tokens": [{ "private_key": MASK, "client_email": "a@b" }],
+            "customKey": null
+        }));
+    }
+
+    #[test]
+    fn diff_keeps_only_changed_fields() {
+        let before = json!({ "name": "a", "isActive": true, "updatedAt": "1" });
+        let after = json!({ "name": "b", "isActive": true, "updatedAt": "2" });
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            json!({ "name": { "before": "a", "after": "b" } })
+        );
+    }
+
+    #[test]
+    fn diff_of_create_and_delete_compares_against_null() {
+        let value = json!({ "name": "a" });
+        assert_eq!(diff(None, Some(&value)), json!({ "name": { "before": null, "after": "a" } }));
+        assert_eq!(diff(Some(&value), None), json!({ "name": { "before": "a", "after": null } }));
+    }
+
+    #[test]
+    fn diff_records_scalars_under_value() {
+        assert_eq!(
+            diff(Some(&json!(3)), Some(&json!(5))),
+            json!({ "value": { "before": 3, "after": 5 } })
+        );
+    }
+
+    #[test]
+    fn diff_masks_changed_secrets_but_shows_that_they_changed() {
+        let before = json!({ "customKey": null, "settings": { "password": "old" } });
+        let after = json!({ "customKey": "new-key", "settings": { "password": "new" } });
+        assert_eq!(
+            diff(Some(&before), Some(&after)),
+            json!({
+                "customKey": { "before": null, "after": MASK },
+                "settings": { "before": { "password": MASK }, "after": { "password": MASK } }
+            })
+        );
+    }
+}
@@ -1,9 +1,9 @@
-use crate::models::{AppSettings, LoginRequest, LoginResponse, ChangePasswordRequest};
+use crate::models::{AppSettings, LoginOutcome, LoginRequest, LoginResponse, ChangePasswordRequest};
+use crate::services::{AdminSessionService, TotpService};
 use sqlx::SqlitePool;
 use anyhow::Result;
 use bcrypt::{hash, verify, DEFAULT_COST};
 use chrono::{Utc, SecondsFormat};
-use uuid::Uuid;
 
 fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
     // 生成 JavaScript 兼容的时间戳（毫秒精度）
@@ -19,48 +19,83 @@ impl AuthService {
         Self { pool }
     }
 
-    pub async fn login(&self, request: LoginRequest) -> Result<Option<LoginResponse>> {
+    pub async fn login(&self, request: LoginRequest) -> Result<LoginOutcome> {
         let settings: Option<AppSettings> = sqlx::query_as(
             "SELECT id, password_hash, created_at, updated_at FROM app_settings WHERE id = 1"
         )
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(settings) = settings {
-            if verify(&request.password, &settings.password_hash)? {
-                let session_token = Uuid::new_v4().to_string();
-                return Ok(Some(LoginResponse {
-                    success: true,
-                    session_token,
-                }));
+        let Some(settings) = settings else {
+            return Ok(LoginOutcome::InvalidPassword);
+        };
+        if !verify(&request.password, &settings.password_hash)? {
+            return Ok(LoginOutcome::InvalidPassword);
+        }
+
+        // 启用两步验证后，密码正确还需要动态验证码或恢复码
+        let totp_service = TotpService::new(self.pool.clone());
+        if totp_service.is_enabled().await? {
+            let Some(code) = request.totp_code.as_deref().filter(|code| !code.trim().is_empty()) else {
+                return Ok(LoginOutcome::SecondFactorRequired);
+            };
+            if !totp_service.verify(code).await? {
+                return Ok(LoginOutcome::InvalidSecondFactor);
             }
         }
 
-        Ok(None)
+        let password_change_required = verify("admin123", &settings.password_hash).unwrap_or(false);
+        let (session, session_token) = AdminSessionService::new(self.pool.clone())
+            .create_session(password_change_required)
+            .await?;
+        Ok(LoginOutcome::Success(LoginResponse {
+            success: true,
+            session_token,
+            expires_at: session.expires_at,
+            password_change_required,
+        }))
+    }
+
+    /// 校验管理密码，用于停用两步验证等敏感操作的二次确认
+    pub async fn verify_password(&self, password: &str) -> Result<bool> {
+        let settings: Option<AppSettings> = sqlx::query_as(
+            "SELECT id, password_hash, created_at, updated_at FROM app_settings WHERE id = 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(match settings {
+            Some(settings) => verify(password, &settings.password_hash)?,
+            None => false,
+        })
     }
 
     pub async fn change_password(&self, request: ChangePasswordRequest) -> Result<bool> {
+        if request.new_password == "admin123" {
+            return Err(anyhow::anyhow!("新密码不能与默认密码相同"));
+        }
+
         let settings: Option<AppSettings> = sqlx::query_as(
             "SELECT id, password_hash, created_at, updated_at FROM app_settings WHERE id = 1"
         )
         .fetch_optional(&self.pool)
         .await?;
 
-        if let Some(settings) = settings {
-            if verify(&request.current_password, &settings.password_hash)? {
-                let new_password_hash = hash(request.new_password, DEFAULT_COST)?;
-                let now = Utc::now();
-
-                sqlx::query(
-                    "UPDATE app_settings SET password_hash = ?, updated_at = ? WHERE id = 1"
-                )
-                .bind(new_password_hash)
-                .bind(to_js_compatible_timestamp(now))
-                .execute(&self.pool)
-                .await?;
-
-                return Ok(true);
-            }
+        if let Some(settings) = settings
+            && verify(&request.current_password, &settings.password_hash)?
+        {
+            let new_password_hash = hash(request.new_password, DEFAULT_COST)?;
+            let now = Utc::now();
+
+            sqlx::query(
+                "UPDATE app_settings SET password_hash = ?, updated_at = ? WHERE id = 1"
+            )
+            .bind(new_password_hash)
+            .bind(to_js_compatible_timestamp(now))
+            .execute(&self.pool)
+            .await?;
+
+            return Ok(true);
         }
 
         Ok(false)
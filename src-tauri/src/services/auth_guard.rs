@@ -0,0 +1,297 @@
+use crate::models::{AuthScope, BruteForceSettings};
+#[cfg(feature = "desktop")]
+use crate::models::AuthLockout;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+/// 全局计数使用的来源名称
+pub const GLOBAL_SOURCE: &str = "*";
+/// Tauri 命令没有来源 IP，只有本机管理界面通过 IPC 登录时使用这个来源名称；HTTP 请求按对端地址计数
+#[cfg(feature = "desktop")]
+pub const LOCAL_SOURCE: &str = "local";
+
+#[derive(Default)]
+struct FailureRecord {
+    failures: u32,
+    window_start: Option<Instant>,
+    lockouts: u32,
+    locked_until: Option<Instant>,
+    last_failure: Option<Instant>,
+}
+
+impl FailureRecord {
+    fn remaining_lock(&self, now: Instant) -> Option<Duration> {
+        self.locked_until
+            .filter(|until| *until > now)
+            .map(|until| until - now)
+    }
+
+    /// 记录一次失败，达到上限时锁定并返回锁定时长
+    fn fail(&mut self, limit: u32, settings: &BruteForceSettings, now: Instant) -> Option<Duration> {
+        let window = Duration::from_secs(settings.window_secs.max(1));
+        if self.window_start.is_none_or(|start| now.duration_since(start) > window) {
+            self.window_start = Some(now);
+            self.failures = 0;
+        }
+        self.failures += 1;
+        self.last_failure = Some(now);
+
+        if self.failures < limit {
+            return None;
+        }
+
+        let duration = settings.lockout_duration(self.lockouts);
+        self.lockouts = self.lockouts.saturating_add(1);
+        self.locked_until = Some(now + duration);
+        self.failures = 0;
+        self.window_start = None;
+        Some(duration)
+    }
+}
+
+// 失败计数只保存在内存中，重启后清零
+static STATE: LazyLock<Mutex<HashMap<(AuthScope, String), FailureRecord>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// 来源被锁定时的错误，认证入口据此拒绝请求
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("Too many failed {} attempts from {}, locked for {}s", scope.as_str(), if origin == GLOBAL_SOURCE { "all sources" } else { origin.as_str() }, retry_after.as_secs().max(1))]
+pub struct AuthLockedError {
+    pub scope: AuthScope,
+    /// 被锁定的来源，全局锁定时为 `*`
+    pub origin: String,
+    pub retry_after: Duration,
+}
+
+/// 按来源和全局统计认证失败次数，超过上限后按指数增长的时长锁定
+pub struct AuthGuardService;
+
+impl Default for AuthGuardService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// HTTP 请求的来源名称，按对端 IP 计数；取不到连接信息时归入同一个来源
+pub fn peer_source(remote: Option<SocketAddr>) -> String {
+    remote.map_or_else(|| "unknown".to_string(), |remote| remote.ip().to_string())
+}
+
+impl AuthGuardService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 验证凭据之前调用，来源或全局处于锁定状态时返回错误
+    pub fn check(&self, scope: AuthScope, source: &str, settings: &BruteForceSettings) -> Result<(), AuthLockedError> {
+        if !settings.enabled {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        let state = STATE.lock().unwrap();
+        for key in [source, GLOBAL_SOURCE] {
+            if let Some(retry_after) = state.get(&(scope, key.to_string())).and_then(|record| record.remaining_lock(now)) {
+                return Err(AuthLockedError {
+                    scope,
+                    origin: key.to_string(),
+                    retry_after,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录一次认证失败，返回这次失败触发的锁定（来源和全局各一个）
+    pub fn record_failure(&self, scope: AuthScope, source: &str, settings: &BruteForceSettings) -> Vec<AuthLockedError> {
+        if !settings.enabled {
+            return Vec::new();
+        }
+
+        let now = Instant::now();
+        let mut state = STATE.lock().unwrap();
+        Self::prune(&mut state, settings, now);
+
+        let mut triggered = Vec::new();
+        let mut limits = vec![(source, settings.max_failures_per_ip.max(1))];
+        if settings.global_max_failures > 0 {
+            limits.push((GLOBAL_SOURCE, settings.global_max_failures));
+        }
+
+        for (key, limit) in limits {
+            let record = state.entry((scope, key.to_string())).or_default();
+            if let Some(retry_after) = record.fail(limit, settings, now) {
+                triggered.push(AuthLockedError {
+                    scope,
+                    origin: key.to_string(),
+                    retry_after,
+                });
+            }
+        }
+        triggered
+    }
+
+    /// 认证成功后清除该来源的失败记录，全局计数不受影响
+    pub fn record_success(&self, scope: AuthScope, source: &str) {
+        let mut state = STATE.lock().unwrap();
+        let key = (scope, source.to_string());
+        if state.get(&key).is_some_and(|record| record.remaining_lock(Instant::now()).is_none()) {
+            state.remove(&key);
+        }
+    }
+
+    /// 当前处于锁定状态的来源
+    #[cfg(feature = "desktop")]
+    pub fn lockouts(&self) -> Vec<AuthLockout> {
+        let now = Instant::now();
+        let wall_now = chrono::Utc::now();
+        let state = STATE.lock().unwrap();
+
+        let mut lockouts: Vec<AuthLockout> = state
+            .iter()
+            .filter_map(|((scope, source), record)| {
+                let remaining = record.remaining_lock(now)?;
+                Some(AuthLockout {
+                    scope: *scope,
+                    source: source.clone(),
+                    failures: record.failures,
+                    lockouts: record.lockouts,
+                    locked_until: wall_now + chrono::Duration::from_std(remaining).unwrap_or_default(),
+                })
+            })
+            .collect();
+        lockouts.sort_by_key(|lockout| std::cmp::Reverse(lockout.locked_until));
+        lockouts
+    }
+
+    /// 解除锁定并清除失败记录；scope 和 source 为空时匹配全部，返回清除的条目数
+    #[cfg(feature = "desktop")]
+    pub fn clear(&self, scope: Option<AuthScope>, source: Option<&str>) -> usize {
+        let mut state = STATE.lock().unwrap();
+        let before = state.len();
+        state.retain(|(entry_scope, entry_source), _| {
+            let scope_matches = scope.is_none_or(|s| s == *entry_scope);
+            let source_matches = source.is_none_or(|s| s == entry_source);
+            !(scope_matches && source_matches)
+        });
+        before - state.len()
+    }
+
+    /// 丢弃已解锁且长时间没有失败的记录，避免大量来源占用内存
+    fn prune(state: &mut HashMap<(AuthScope, String), FailureRecord>, settings: &BruteForceSettings, now: Instant) {
+        let idle = Duration::from_secs(settings.window_secs.max(settings.max_lockout_secs).max(1) * 2);
+        state.retain(|_, record| {
+            record.remaining_lock(now).is_some()
+                || record.last_failure.is_some_and(|last| now.duration_since(last) < idle)
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 失败记录在所有测试之间共享，每个测试使用自己的来源；全局计数只在 AdminLogin 的测试中开启
+    fn settings() -> BruteForceSettings {
+        BruteForceSettings {
+            enabled: true,
+            max_failures_per_ip: 3,
+            global_max_failures: 0,
+            window_secs: 300,
+            base_lockout_secs: 30,
+            max_lockout_secs: 100,
+        }
+    }
+
+    #[test]
+    fn locks_a_source_after_max_failures() {
+        let guard = AuthGuardService::new();
+        let settings = settings();
+        for _ in 0..2 {
+            assert!(guard.record_failure(AuthScope::Proxy, "10.0.0.1", &settings).is_empty());
+        }
+        assert!(guard.check(AuthScope::Proxy, "10.0.0.1", &settings).is_ok());
+
+        let triggered = guard.record_failure(AuthScope::Proxy, "10.0.0.1", &settings);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].origin, "10.0.0.1");
+        assert_eq!(triggered[0].retry_after, Duration::from_secs(30));
+
+        let locked = guard.check(AuthScope::Proxy, "10.0.0.1", &settings).unwrap_err();
+        assert!(locked.retry_after <= Duration::from_secs(30));
+        // 其他来源不受影响
+        assert!(guard.check(AuthScope::Proxy, "10.0.0.2", &settings).is_ok());
+        // 成功也不能解除进行中的锁定
+        guard.record_success(AuthScope::Proxy, "10.0.0.1");
+        assert!(guard.check(AuthScope::Proxy, "10.0.0.1", &settings).is_err());
+    }
+
+    #[test]
+    fn success_resets_the_failure_count() {
+        let guard = AuthGuardService::new();
+        let settings = settings();
+        for _ in 0..2 {
+            guard.record_failure(AuthScope::Proxy, "10.0.1.1", &settings);
+        }
+        guard.record_success(AuthScope::Proxy, "10.0.1.1");
+        for _ in 0..2 {
+            assert!(guard.record_failure(AuthScope::Proxy, "10.0.1.1", &settings).is_empty());
+        }
+        assert!(guard.check(AuthScope::Proxy, "10.0.1.1", &settings).is_ok());
+    }
+
+    #[test]
+    fn lockouts_double_up_to_the_maximum() {
+        let settings = settings();
+        let durations: Vec<u64> = (0..4).map(|n| settings.lockout_duration(n).as_secs()).collect();
+        assert_eq!(durations, [30, 60, 100, 100]);
+        assert_eq!(settings.lockout_duration(u32::MAX).as_secs(), 100);
+
+        let mut record = FailureRecord::default();
+        let now = Instant::now();
+        let mut lockouts = Vec::new();
+        for round in 0..3u64 {
+            let at = now + Duration::from_secs(round * 1000);
+            let locked = (0..3).filter_map(|_| record.fail(3, &settings, at)).collect::<Vec<_>>();
+            lockouts.extend(locked);
+        }
+        assert_eq!(lockouts, [Duration::from_secs(30), Duration::from_secs(60), Duration::from_secs(100)]);
+    }
+
+    #[test]
+    fn failures_outside_the_window_are_forgotten() {
+        let settings = settings();
+        let mut record = FailureRecord::default();
+        let now = Instant::now();
+        assert_eq!(record.fail(3, &settings, now), None);
+        assert_eq!(record.fail(3, &settings, now), None);
+        let later = now + Duration::from_secs(settings.window_secs + 1);
+        assert_eq!(record.fail(3, &settings, later), None);
+        assert_eq!(record.failures, 1);
+    }
+
+    #[test]
+    fn global_limit_locks_every_source() {
+        let guard = AuthGuardService::new();
+        let settings = BruteForceSettings { global_max_failures: 2, ..settings() };
+        assert!(guard.record_failure(AuthScope::AdminLogin, "global-a", &settings).is_empty());
+        let triggered = guard.record_failure(AuthScope::AdminLogin, "global-b", &settings);
+        assert_eq!(triggered.len(), 1);
+        assert_eq!(triggered[0].origin, GLOBAL_SOURCE);
+
+        let locked = guard.check(AuthScope::AdminLogin, "global-c", &settings).unwrap_err();
+        assert_eq!(locked.origin, GLOBAL_SOURCE);
+        assert!(locked.to_string().contains("all sources"));
+    }
+
+    #[test]
+    fn disabled_guard_never_locks() {
+        let guard = AuthGuardService::new();
+        let settings = BruteForceSettings { enabled: false, ..settings() };
+        for _ in 0..10 {
+            assert!(guard.record_failure(AuthScope::Proxy, "10.0.2.1", &settings).is_empty());
+        }
+        assert!(guard.check(AuthScope::Proxy, "10.0.2.1", &settings).is_ok());
+    }
+}
@@ -0,0 +1,376 @@
+use crate::models::CircuitBreakerSettings;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// 熔断器状态在进程内共享，HTTP 服务和 Tauri 命令看到的是同一份数据
+static REGISTRY: LazyLock<Mutex<BreakerRegistry>> = LazyLock::new(|| Mutex::new(BreakerRegistry::default()));
+
+#[derive(Default)]
+struct BreakerRegistry {
+    upstreams: HashMap<String, Breaker>,
+    keys: HashMap<Uuid, Breaker>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CircuitState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+/// 熔断器打开时的快速失败错误，处理器据此返回 503 和 Retry-After
+#[derive(Debug, thiserror::Error)]
+#[error("Circuit breaker open for {scope}, retry after {}s", retry_after.as_secs().max(1))]
+pub struct CircuitOpenError {
+    pub scope: String,
+    pub retry_after: Duration,
+}
+
+/// 一次上游请求的结果分类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpstreamOutcome {
+    /// 上游正常应答（包括 400 这类客户端错误）
+    Healthy,
+    /// 单个密钥被限流（429），上游本身可用
+    KeyExhausted,
+    /// 5xx 或网络错误
+    Failed,
+}
+
+impl UpstreamOutcome {
+    pub fn from_status(status_code: i32) -> Self {
+        match status_code {
+            429 => UpstreamOutcome::KeyExhausted,
+            500..=599 => UpstreamOutcome::Failed,
+            _ => UpstreamOutcome::Healthy,
+        }
+    }
+}
+
+struct Breaker {
+    state: CircuitState,
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    half_open_since: Option<Instant>,
+    half_open_in_flight: u32,
+    last_failure_at: Option<DateTime<Utc>>,
+}
+
+impl Default for Breaker {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            consecutive_failures: 0,
+            opened_at: None,
+            half_open_since: None,
+            half_open_in_flight: 0,
+            last_failure_at: None,
+        }
+    }
+}
+
+impl Breaker {
+    fn try_acquire(&mut self, settings: &CircuitBreakerSettings) -> Result<(), Duration> {
+        let open_duration = Duration::from_secs(settings.open_duration_secs);
+
+        if self.state == CircuitState::Open {
+            let elapsed = self.opened_at.map(|t| t.elapsed()).unwrap_or(open_duration);
+            if elapsed < open_duration {
+                return Err(open_duration - elapsed);
+            }
+            self.state = CircuitState::HalfOpen;
+            self.half_open_since = Some(Instant::now());
+            self.half_open_in_flight = 0;
+        }
+
+        if self.state == CircuitState::HalfOpen {
+            // 试探请求一直没有回报结果时（例如客户端中途断开），不让熔断器永远卡在半开状态
+            let stale = self.half_open_since.map(|t| t.elapsed() >= open_duration).unwrap_or(true);
+            if stale {
+                self.half_open_since = Some(Instant::now());
+                self.half_open_in_flight = 0;
+            }
+
+            if self.half_open_in_flight >= settings.half_open_max_requests.max(1) {
+                return Err(Duration::from_secs(1));
+            }
+            self.half_open_in_flight += 1;
+        }
+
+        Ok(())
+    }
+
+    fn release(&mut self) {
+        if self.state == CircuitState::HalfOpen {
+            self.half_open_in_flight = self.half_open_in_flight.saturating_sub(1);
+        }
+    }
+
+    fn record_success(&mut self) {
+        match self.state {
+            CircuitState::Closed => self.consecutive_failures = 0,
+            CircuitState::HalfOpen => {
+                self.state = CircuitState::Closed;
+                self.consecutive_failures = 0;
+                self.opened_at = None;
+                self.half_open_since = None;
+                self.half_open_in_flight = 0;
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    fn record_failure(&mut self, settings: &CircuitBreakerSettings) {
+        self.last_failure_at = Some(Utc::now());
+
+        match self.state {
+            CircuitState::Closed => {
+                self.consecutive_failures += 1;
+                if self.consecutive_failures >= settings.failure_threshold.max(1) {
+                    self.trip();
+                }
+            }
+            CircuitState::HalfOpen => {
+                self.consecutive_failures += 1;
+                self.trip();
+            }
+            CircuitState::Open => {}
+        }
+    }
+
+    fn trip(&mut self) {
+        self.state = CircuitState::Open;
+        self.opened_at = Some(Instant::now());
+        self.half_open_since = None;
+        self.half_open_in_flight = 0;
+    }
+
+    fn snapshot(&self, settings: &CircuitBreakerSettings) -> BreakerSnapshot {
+        let retry_after_secs = match (self.state, self.opened_at) {
+            (CircuitState::Open, Some(opened_at)) => Duration::from_secs(settings.open_duration_secs)
+                .saturating_sub(opened_at.elapsed())
+                .as_secs(),
+            _ => 0,
+        };
+
+        BreakerSnapshot {
+            state: self.state,
+            consecutive_failures: self.consecutive_failures,
+            retry_after_secs,
+            last_failure_at: self.last_failure_at
+                .map(|dt| dt.to_rfc3339_opts(SecondsFormat::Millis, true)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BreakerSnapshot {
+    pub state: CircuitState,
+    pub consecutive_failures: u32,
+    pub retry_after_secs: u64,
+    pub last_failure_at: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CircuitBreakerStatus {
+    pub upstreams: HashMap<String, BreakerSnapshot>,
+    pub keys: HashMap<Uuid, BreakerSnapshot>,
+}
+
+pub struct CircuitBreakerService;
+
+impl CircuitBreakerService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn try_acquire_upstream(&self, host: &str, settings: &CircuitBreakerSettings) -> Result<(), CircuitOpenError> {
+        let mut registry = REGISTRY.lock().unwrap();
+        registry.upstreams
+            .entry(host.to_string())
+            .or_default()
+            .try_acquire(settings)
+            .map_err(|retry_after| CircuitOpenError {
+                scope: format!("upstream {}", host),
+                retry_after,
+            })
+    }
+
+    /// 尝试占用某个密钥，熔断器打开时返回剩余的等待时间
+    pub fn try_acquire_key(&self, key_id: Uuid, settings: &CircuitBreakerSettings) -> Result<(), Duration> {
+        let mut registry = REGISTRY.lock().unwrap();
+        registry.keys.entry(key_id).or_default().try_acquire(settings)
+    }
+
+    pub fn release_upstream(&self, host: &str) {
+        let mut registry = REGISTRY.lock().unwrap();
+        if let Some(breaker) = registry.upstreams.get_mut(host) {
+            breaker.release();
+        }
+    }
+
+    pub fn record(&self, host: &str, key_id: Uuid, outcome: UpstreamOutcome, settings: &CircuitBreakerSettings) {
+        let mut registry = REGISTRY.lock().unwrap();
+
+        let upstream = registry.upstreams.entry(host.to_string()).or_default();
+        match outcome {
+            UpstreamOutcome::Failed => upstream.record_failure(settings),
+            UpstreamOutcome::Healthy | UpstreamOutcome::KeyExhausted => upstream.record_success(),
+        }
+
+        let key = registry.keys.entry(key_id).or_default();
+        match outcome {
+            UpstreamOutcome::Healthy => key.record_success(),
+            UpstreamOutcome::KeyExhausted | UpstreamOutcome::Failed => key.record_failure(settings),
+        }
+    }
+
+    pub fn any_upstream_open(&self) -> bool {
+        let registry = REGISTRY.lock().unwrap();
+        registry.upstreams
+            .values()
+            .any(|b| b.state == CircuitState::Open)
+    }
+
+    pub fn status(&self, settings: &CircuitBreakerSettings) -> CircuitBreakerStatus {
+        let registry = REGISTRY.lock().unwrap();
+        CircuitBreakerStatus {
+            upstreams: registry.upstreams
+                .iter()
+                .map(|(host, b)| (host.clone(), b.snapshot(settings)))
+                .collect(),
+            keys: registry.keys
+                .iter()
+                .map(|(id, b)| (*id, b.snapshot(settings)))
+                .collect(),
+        }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub fn reset(&self) {
+        let mut registry = REGISTRY.lock().unwrap();
+        registry.upstreams.clear();
+        registry.keys.clear();
+    }
+}
+
+impl Default for CircuitBreakerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings() -> CircuitBreakerSettings {
+        CircuitBreakerSettings {
+            failure_threshold: 2,
+            open_duration_secs: 30,
+            half_open_max_requests: 1,
+        }
+    }
+
+    /// 让打开的熔断器看起来已经过了打开时长
+    fn expire_open(breaker: &mut Breaker) {
+        breaker.opened_at = Some(Instant::now() - Duration::from_secs(31));
+    }
+
+    #[test]
+    fn closed_open_half_open_closed() {
+        let settings = settings();
+        let mut breaker = Breaker::default();
+
+        breaker.record_failure(&settings);
+        assert_eq!(breaker.state, CircuitState::Closed);
+        breaker.record_failure(&settings);
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert!(breaker.try_acquire(&settings).is_err());
+
+        expire_open(&mut breaker);
+        assert!(breaker.try_acquire(&settings).is_ok());
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+
+        breaker.record_success();
+        assert_eq!(breaker.state, CircuitState::Closed);
+        assert_eq!(breaker.consecutive_failures, 0);
+        assert!(breaker.try_acquire(&settings).is_ok());
+    }
+
+    #[test]
+    fn half_open_failure_reopens() {
+        let settings = settings();
+        let mut breaker = Breaker::default();
+        breaker.trip();
+        expire_open(&mut breaker);
+
+        assert!(breaker.try_acquire(&settings).is_ok());
+        breaker.record_failure(&settings);
+        assert_eq!(breaker.state, CircuitState::Open);
+        assert!(breaker.try_acquire(&settings).is_err());
+    }
+
+    #[test]
+    fn half_open_limits_probes_until_released() {
+        let settings = settings();
+        let mut breaker = Breaker::default();
+        breaker.trip();
+        expire_open(&mut breaker);
+
+        assert!(breaker.try_acquire(&settings).is_ok());
+        assert!(breaker.try_acquire(&settings).is_err());
+
+        // 占用的试探名额没有发出请求（例如密钥级熔断拒绝）时归还，下一个请求可以试探
+        breaker.release();
+        assert_eq!(breaker.half_open_in_flight, 0);
+        assert!(breaker.try_acquire(&settings).is_ok());
+        assert_eq!(breaker.state, CircuitState::HalfOpen);
+    }
+
+    #[test]
+    fn release_without_acquire_does_not_underflow() {
+        let settings = settings();
+        let mut breaker = Breaker::default();
+        breaker.release();
+        breaker.trip();
+        expire_open(&mut breaker);
+        assert!(breaker.try_acquire(&settings).is_ok());
+        breaker.release();
+        breaker.release();
+        assert_eq!(breaker.half_open_in_flight, 0);
+    }
+
+    #[test]
+    fn key_rejection_returns_the_upstream_probe_slot() {
+        // 与 acquire_key 相同的顺序：先占上游名额，密钥被拒绝时归还
+        let settings = settings();
+        let service = CircuitBreakerService::new();
+        let host = format!("test-{}.invalid", Uuid::new_v4());
+        let (open_key, other_key) = (Uuid::new_v4(), Uuid::new_v4());
+
+        for _ in 0..2 {
+            service.record(&host, open_key, UpstreamOutcome::Failed, &settings);
+        }
+        {
+            let mut registry = REGISTRY.lock().unwrap();
+            expire_open(registry.upstreams.get_mut(&host).unwrap());
+        }
+
+        assert!(service.try_acquire_upstream(&host, &settings).is_ok());
+        assert!(service.try_acquire_key(open_key, &settings).is_err());
+        service.release_upstream(&host);
+
+        assert!(service.try_acquire_upstream(&host, &settings).is_ok());
+        assert!(service.try_acquire_key(other_key, &settings).is_ok());
+        service.record(&host, other_key, UpstreamOutcome::Healthy, &settings);
+        assert_eq!(REGISTRY.lock().unwrap().upstreams[&host].state, CircuitState::Closed);
+    }
+}
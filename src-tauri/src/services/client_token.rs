@@ -0,0 +1,282 @@
+use crate::models::{ClientToken, ClientUsage, CreateClientTokenRequest, IssuedClientToken};
+#[cfg(feature = "desktop")]
+use crate::models::{ClientScopes, ClientTokenUsage};
+use crate::server::middleware::DEFAULT_CLIENT_NAME;
+use crate::utils::ValidationError;
+use sqlx::SqlitePool;
+use anyhow::{Result, anyhow};
+use chrono::{Datelike, Utc, SecondsFormat};
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+
+// 令牌明文的固定前缀，便于在配置文件和日志中识别
+const SECRET_PREFIX: &str = "tjm_";
+// 列表中显示的令牌开头长度（含前缀）
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 生成含 244 位随机数（两个 UUID v4）的令牌明文
+fn generate_secret() -> String {
+    format!("{}{}{}", SECRET_PREFIX, Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+/// 令牌明文的 SHA-256，用作数据库中的查找键
+///
+/// 明文由服务端生成，含 244 位随机数，无法字典或暴力破解，因此不加盐也不用 Argon2：
+/// 每个代理请求都要验证令牌，慢哈希会成为瓶颈。按哈希等值查询的耗时只取决于哈希值，
+/// 攻击者无法借此逐位猜出明文。用户自行设置的全局验证密钥熵不可控，仍使用 Argon2
+fn hash_secret(secret: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(secret.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct ClientTokenService {
+    pool: SqlitePool,
+}
+
+impl ClientTokenService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn create_token(&self, request: CreateClientTokenRequest) -> Result<IssuedClientToken> {
+        let name = request.name.trim();
+        if name.is_empty() {
+            return Err(ValidationError("Client token name is required".to_string()).into());
+        }
+        if name == DEFAULT_CLIENT_NAME {
+            return Err(ValidationError(format!("Client token name '{}' is reserved for the global custom auth key", DEFAULT_CLIENT_NAME)).into());
+        }
+
+        let token_id = Uuid::new_v4();
+        let secret = generate_secret();
+        let now = to_js_compatible_timestamp(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO client_tokens (id, name, secret_hash, token_prefix, enabled, scopes, expires_at, created_at, updated_at)
+            VALUES (?, ?, ?, ?, 1, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(token_id.to_string())
+        .bind(name)
+        .bind(hash_secret(&secret))
+        .bind(&secret[..DISPLAY_PREFIX_LEN])
+        .bind(serde_json::to_string(&request.scopes.unwrap_or_default())?)
+        .bind(request.expires_at.map(to_js_compatible_timestamp))
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        let token = self.get_token_by_id(token_id).await?
+            .ok_or_else(|| anyhow!("Client token not found after creation"))?;
+
+        Ok(IssuedClientToken { token, secret })
+    }
+
+    /// 是否创建过客户端令牌（包括已吊销的）；创建后不再接受全局自定义密钥
+    pub async fn has_tokens(&self) -> Result<bool> {
+        let (count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM client_tokens")
+            .fetch_one(&self.pool)
+            .await?;
+        Ok(count > 0)
+    }
+
+    pub async fn get_all_tokens(&self) -> Result<Vec<ClientToken>> {
+        let tokens: Vec<ClientToken> = sqlx::query_as(
+            r#"
+            SELECT id, name, token_prefix, enabled, scopes, expires_at, last_used_at, created_at, updated_at
+            FROM client_tokens ORDER BY created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(tokens)
+    }
+
+    pub async fn get_token_by_id(&self, token_id: Uuid) -> Result<Option<ClientToken>> {
+        let token: Option<ClientToken> = sqlx::query_as(
+            r#"
+            SELECT id, name, token_prefix, enabled, scopes, expires_at, last_used_at, created_at, updated_at
+            FROM client_tokens WHERE id = ?
+            "#,
+        )
+        .bind(token_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// 吊销令牌：保留记录以便日志仍能对应到它，但不再允许认证
+    #[cfg(feature = "desktop")]
+    pub async fn revoke_token(&self, token_id: Uuid) -> Result<Option<ClientToken>> {
+        sqlx::query("UPDATE client_tokens SET enabled = 0, updated_at = ? WHERE id = ?")
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .bind(token_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        self.get_token_by_id(token_id).await
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn update_scopes(&self, token_id: Uuid, scopes: ClientScopes) -> Result<Option<ClientToken>> {
+        sqlx::query("UPDATE client_tokens SET scopes = ?, updated_at = ? WHERE id = ?")
+            .bind(serde_json::to_string(&scopes)?)
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .bind(token_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        self.get_token_by_id(token_id).await
+    }
+
+    /// 为令牌生成新的明文，旧明文立即失效，名称和日志归属不变
+    #[cfg(feature = "desktop")]
+    pub async fn rotate_token(&self, token_id: Uuid) -> Result<Option<IssuedClientToken>> {
+        let secret = generate_secret();
+
+        let result = sqlx::query("UPDATE client_tokens SET secret_hash = ?, token_prefix = ?, updated_at = ? WHERE id = ?")
+            .bind(hash_secret(&secret))
+            .bind(&secret[..DISPLAY_PREFIX_LEN])
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .bind(token_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Ok(None);
+        }
+
+        Ok(self.get_token_by_id(token_id).await?.map(|token| IssuedClientToken { token, secret }))
+    }
+
+    /// 按明文查找令牌，不检查是否启用或过期；只按哈希查找的理由见 [`hash_secret`]
+    pub async fn find_by_secret(&self, secret: &str) -> Result<Option<ClientToken>> {
+        let token: Option<ClientToken> = sqlx::query_as(
+            r#"
+            SELECT id, name, token_prefix, enabled, scopes, expires_at, last_used_at, created_at, updated_at
+            FROM client_tokens WHERE secret_hash = ?
+            "#,
+        )
+        .bind(hash_secret(secret))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn touch(&self, token_id: Uuid) -> Result<()> {
+        sqlx::query("UPDATE client_tokens SET last_used_at = ? WHERE id = ?")
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .bind(token_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 令牌在当前 UTC 自然日和自然月内成功的请求数和 token 数
+    pub async fn usage(&self, token_id: Uuid) -> Result<ClientUsage> {
+        let now = Utc::now();
+        let day_start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+        let month_start = now.date_naive().with_day(1).unwrap().and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+        let (daily_requests, daily_tokens, monthly_requests, monthly_tokens): (i64, i64, i64, i64) = sqlx::query_as(
+            r#"
+            SELECT
+                COALESCE(SUM(CASE WHEN created_at >= ? THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(CASE WHEN created_at >= ? THEN COALESCE(total_tokens, 0) ELSE 0 END), 0),
+                COUNT(*),
+                COALESCE(SUM(COALESCE(total_tokens, 0)), 0)
+            FROM request_logs
+            WHERE client_token_id = ? AND created_at >= ? AND status_code BETWEEN 200 AND 299
+            "#,
+        )
+        .bind(to_js_compatible_timestamp(day_start))
+        .bind(to_js_compatible_timestamp(day_start))
+        .bind(token_id.to_string())
+        .bind(to_js_compatible_timestamp(month_start))
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(ClientUsage {
+            daily_requests: daily_requests as u64,
+            daily_tokens: daily_tokens as u64,
+            monthly_requests: monthly_requests as u64,
+            monthly_tokens: monthly_tokens as u64,
+        })
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_all_usage(&self) -> Result<Vec<ClientTokenUsage>> {
+        let mut result = Vec::new();
+        for token in self.get_all_tokens().await? {
+            let usage = self.usage(token.id).await?;
+            result.push(ClientTokenUsage { token, usage });
+        }
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_pool;
+
+    fn request(name: &str) -> CreateClientTokenRequest {
+        CreateClientTokenRequest { name: name.to_string(), expires_at: None, scopes: None }
+    }
+
+    #[tokio::test]
+    async fn secrets_are_stored_hashed_and_found_by_secret() {
+        let service = ClientTokenService::new(test_pool().await);
+        assert!(!service.has_tokens().await.unwrap());
+
+        let issued = service.create_token(request("ci")).await.unwrap();
+        assert!(issued.secret.starts_with(SECRET_PREFIX));
+        assert_eq!(issued.token.token_prefix, issued.secret[..DISPLAY_PREFIX_LEN]);
+        assert!(service.has_tokens().await.unwrap());
+
+        let (stored,): (String,) = sqlx::query_as("SELECT secret_hash FROM client_tokens WHERE id = ?")
+            .bind(issued.token.id.to_string())
+            .fetch_one(&service.pool)
+            .await
+            .unwrap();
+        assert_eq!(stored, hash_secret(&issued.secret));
+        assert!(!stored.contains(&issued.secret[SECRET_PREFIX.len()..]));
+
+        let found = service.find_by_secret(&issued.secret).await.unwrap().unwrap();
+        assert_eq!(found.id, issued.token.id);
+        assert!(service.find_by_secret("tjm_wrong").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn rejects_empty_and_reserved_names() {
+        let service = ClientTokenService::new(test_pool().await);
+        for name in ["  ", DEFAULT_CLIENT_NAME] {
+            let error = service.create_token(request(name)).await.unwrap_err();
+            assert!(error.is::<ValidationError>(), "{name}");
+        }
+    }
+
+    #[tokio::test]
+    async fn expiry_is_reported_by_the_token() {
+        let service = ClientTokenService::new(test_pool().await);
+        let expired = CreateClientTokenRequest { expires_at: Some(Utc::now() - chrono::Duration::minutes(1)), ..request("expired") };
+        let current = CreateClientTokenRequest { expires_at: Some(Utc::now() + chrono::Duration::days(1)), ..request("current") };
+
+        assert!(service.create_token(expired).await.unwrap().token.is_expired());
+        assert!(!service.create_token(current).await.unwrap().token.is_expired());
+        assert!(!service.create_token(request("forever")).await.unwrap().token.is_expired());
+    }
+}
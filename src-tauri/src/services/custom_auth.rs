@@ -1,6 +1,49 @@
+use crate::utils::constant_time_eq;
 use sqlx::SqlitePool;
-use anyhow::Result;
+use anyhow::{Result, anyhow};
+use argon2::{Argon2, PasswordHash, PasswordHasher, PasswordVerifier};
+use argon2::password_hash::SaltString;
 use sha2::{Sha256, Digest};
+use std::sync::{LazyLock, Mutex};
+use uuid::Uuid;
+
+// 最近一次验证通过的密钥，命中时跳过耗时的 Argon2 计算
+struct VerifiedKey {
+    stored_hash: String,
+    digest: [u8; 32],
+}
+
+static VERIFIED: LazyLock<Mutex<Option<VerifiedKey>>> = LazyLock::new(|| Mutex::new(None));
+
+// 旧版本在未设置密钥时自动写入的公开默认值，升级时清除
+const LEGACY_DEFAULT_KEY: &str = "123456";
+
+fn sha256(key: &str) -> [u8; 32] {
+    Sha256::digest(key.as_bytes()).into()
+}
+
+/// 用随机盐的 Argon2id 计算密钥哈希，返回 PHC 格式字符串
+fn hash_key(key: &str) -> Result<String> {
+    // v4 UUID 的 16 字节来自系统随机数，用作盐
+    let salt = SaltString::encode_b64(Uuid::new_v4().as_bytes())
+        .map_err(|e| anyhow!("Failed to generate salt: {}", e))?;
+    Argon2::default()
+        .hash_password(key.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash custom auth key: {}", e))
+}
+
+/// 与保存的哈希比对；旧版本保存的是不加盐的 SHA-256 十六进制串
+fn verify_key(key: &str, stored_hash: &str) -> bool {
+    match PasswordHash::new(stored_hash) {
+        Ok(parsed) => Argon2::default().verify_password(key.as_bytes(), &parsed).is_ok(),
+        Err(_) => constant_time_eq(format!("{:x}", Sha256::digest(key.as_bytes())).as_bytes(), stored_hash.as_bytes()),
+    }
+}
+
+fn is_legacy_hash(stored_hash: &str) -> bool {
+    !stored_hash.starts_with('$')
+}
 
 pub struct CustomAuthService {
     pool: SqlitePool,
@@ -12,7 +55,8 @@ impl CustomAuthService {
     }
 
     pub async fn set_custom_key(&self, key: &str) -> Result<()> {
-        let key_hash = self.hash_key(key);
+        let key = key.to_string();
+        let key_hash = tokio::task::spawn_blocking(move || hash_key(&key)).await??;
         let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
         
         sqlx::query(
@@ -36,52 +80,70 @@ impl CustomAuthService {
         Ok(result.and_then(|r| r.0))
     }
 
+    /// 审计比较用的当前密钥哈希，记录时会被脱敏
+    pub async fn audit_snapshot(&self) -> Option<serde_json::Value> {
+        let stored = self.get_custom_key().await.ok()?;
+        Some(serde_json::json!({ "customKey": stored }))
+    }
+
+    /// 全局密钥未设置时停用，任何密钥都不能通过
     pub async fn validate_custom_key(&self, key: &str) -> Result<bool> {
-        let key_hash = self.hash_key(key);
-        
         let stored_key = self.get_custom_key().await?;
         
         match stored_key {
-            Some(stored_hash) => Ok(stored_hash == key_hash),
-            None => {
-                // 如果没有设置自定义秘钥，自动设置默认密钥并验证
-                tracing::warn!("No custom auth key found, initializing default key");
-                if let Err(e) = self.set_custom_key("123456").await {
-                    tracing::error!("Failed to set default custom auth key: {}", e);
+            Some(stored_hash) => {
+                let digest = sha256(key);
+                if let Some(verified) = VERIFIED.lock().unwrap().as_ref()
+                    && verified.stored_hash == stored_hash
+                {
+                    return Ok(constant_time_eq(&verified.digest, &digest));
+                }
+
+                let candidate = key.to_string();
+                let hash = stored_hash.clone();
+                let is_valid = tokio::task::spawn_blocking(move || verify_key(&candidate, &hash)).await?;
+                if !is_valid {
                     return Ok(false);
                 }
-                // 验证是否匹配默认密钥
-                Ok(key == "123456")
+
+                // 旧版本的 SHA-256 哈希在第一次验证通过时升级为 Argon2
+                if is_legacy_hash(&stored_hash) {
+                    if let Err(e) = self.set_custom_key(key).await {
+                        tracing::warn!("Failed to upgrade custom auth key hash: {}", e);
+                    }
+                } else {
+                    *VERIFIED.lock().unwrap() = Some(VerifiedKey { stored_hash, digest });
+                }
+                Ok(true)
             }
+            None => Ok(false),
         }
     }
 
-    pub async fn reset_to_default_key(&self) -> Result<()> {
-        // 重置为默认密钥而不是清除
-        self.set_custom_key("123456").await?;
+    /// 清除全局密钥，之后只能使用客户端令牌访问代理
+    pub async fn disable_custom_key(&self) -> Result<()> {
+        let now = chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true);
+        sqlx::query("UPDATE app_settings SET custom_auth_key = NULL, updated_at = ? WHERE id = 1")
+            .bind(now)
+            .execute(&self.pool)
+            .await?;
+        *VERIFIED.lock().unwrap() = None;
         Ok(())
     }
 
-    // 保留原方法用于兼容，但现在重置为默认密钥
-    pub async fn clear_custom_key(&self) -> Result<()> {
-        self.reset_to_default_key().await
-    }
-
     pub async fn has_custom_key(&self) -> Result<bool> {
-        let result = self.get_custom_key().await?;
-        if result.is_none() {
-            // 如果没有密钥，设置默认密钥
-            if let Err(e) = self.set_custom_key("123456").await {
-                tracing::error!("Failed to set default custom auth key: {}", e);
-                return Ok(false);
-            }
-        }
-        Ok(true) // 现在总是有自定义密钥
+        Ok(self.get_custom_key().await?.is_some())
     }
 
-    fn hash_key(&self, key: &str) -> String {
-        let mut hasher = Sha256::new();
-        hasher.update(key.as_bytes());
-        format!("{:x}", hasher.finalize())
+    /// 仍在使用旧版本写入的默认密钥时停用它，返回是否停用
+    pub async fn disable_legacy_default_key(&self) -> Result<bool> {
+        let Some(stored_hash) = self.get_custom_key().await? else {
+            return Ok(false);
+        };
+        let is_default = tokio::task::spawn_blocking(move || verify_key(LEGACY_DEFAULT_KEY, &stored_hash)).await?;
+        if is_default {
+            self.disable_custom_key().await?;
+        }
+        Ok(is_default)
     }
 }
\ No newline at end of file
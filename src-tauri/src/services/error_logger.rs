@@ -32,9 +32,9 @@ impl ErrorLoggerService {
         
         let error_response = serde_json::json!({
             "error": {
-                "code": if status_code == 401 { "UNAUTHENTICATED" } else if status_code == 403 { "PERMISSION_DENIED" } else { "INTERNAL_ERROR" },
+                "code": match status_code { 401 => "UNAUTHENTICATED", 403 => "PERMISSION_DENIED", 429 => "RESOURCE_EXHAUSTED", _ => "INTERNAL_ERROR" },
                 "message": error_msg,
-                "status": if status_code == 401 { "UNAUTHENTICATED" } else if status_code == 403 { "PERMISSION_DENIED" } else { "INTERNAL" }
+                "status": match status_code { 401 => "UNAUTHENTICATED", 403 => "PERMISSION_DENIED", 429 => "RESOURCE_EXHAUSTED", _ => "INTERNAL" }
             }
         });
 
@@ -63,6 +63,7 @@ impl ErrorLoggerService {
     }
 
     /// 记录处理器级别的错误
+    #[allow(clippy::too_many_arguments)]
     pub async fn log_handler_error(
         &self,
         api_key_id: Option<Uuid>,
@@ -84,7 +85,9 @@ impl ErrorLoggerService {
                     401 => "UNAUTHENTICATED", 
                     403 => "PERMISSION_DENIED",
                     404 => "NOT_FOUND",
+                    429 => "RESOURCE_EXHAUSTED",
                     500 => "INTERNAL",
+                    503 => "UNAVAILABLE",
                     _ => "UNKNOWN"
                 },
                 "message": error_msg,
@@ -93,7 +96,9 @@ impl ErrorLoggerService {
                     401 => "UNAUTHENTICATED",
                     403 => "PERMISSION_DENIED", 
                     404 => "NOT_FOUND",
+                    429 => "RESOURCE_EXHAUSTED",
                     500 => "INTERNAL",
+                    503 => "UNAVAILABLE",
                     _ => "UNKNOWN"
                 }
             }
@@ -1,14 +1,156 @@
-use crate::services::{KeyRotationService, ApiKeyService, SettingsService};
+use crate::models::{ApiKey, CircuitBreakerSettings, ClientScopes, CredentialType, Provider, ProviderType, RequestPriority, ValidationMode};
+use crate::services::{KeyRotationService, ApiKeyService, SettingsService, CircuitBreakerService, CircuitOpenError, UpstreamOutcome, HedgingService, ProviderService, ProviderDirectory, VertexAuthService, DEFAULT_VERTEX_LOCATION, validate_vertex_location, ChatStreamTranslator, gemini_to_chat_request, chat_response_to_gemini, validate_generate_content_request, ResponseCacheService, is_deterministic, SingleFlightService, Flight, FlightResponse, SharedResult, StreamRole, RequestSchedulerService};
+use crate::utils::{model_from_path, replace_model_in_path, canonical_request_hash};
 use anyhow::{Result, anyhow};
 use reqwest::Client;
 use serde_json::Value;
-use std::time::Instant;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use futures::stream::BoxStream;
 use tokio_stream::StreamExt;
 use uuid::Uuid;
 use chrono::{Utc, SecondsFormat};
 use sqlx::SqlitePool;
 use bytes::Bytes;
 
+// 被对冲请求取代而取消的尝试在日志中使用的状态码（沿用 nginx 的 499 Client Closed Request）
+const HEDGE_CANCELLED_STATUS: i32 = 499;
+// 队首请求重新尝试取密钥的最短间隔
+const MIN_QUEUE_POLL: Duration = Duration::from_millis(10);
+
+struct AttemptOutcome {
+    api_key_id: Uuid,
+    status_code: i32,
+    response_text: String,
+}
+
+impl AttemptOutcome {
+    fn is_success(&self) -> bool {
+        (200..300).contains(&self.status_code)
+    }
+
+    fn succeeded(result: &Result<AttemptOutcome>) -> bool {
+        matches!(result, Ok(outcome) if outcome.is_success())
+    }
+}
+
+/// 处理器在转发前填写、随每条日志写入的请求附加信息
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    /// 本次请求应用的策略规则名称
+    pub applied_policies: Vec<String>,
+    /// 客户端通过请求头要求启用响应缓存
+    pub cache_opt_in: bool,
+    /// 所有密钥都在冷却时排队等待的优先级
+    pub priority: RequestPriority,
+    /// 发起请求的客户端令牌，使用全局自定义密钥时为 None
+    pub client_token_id: Option<Uuid>,
+    /// 客户端令牌的权限范围，降级时跳过不允许的模型
+    pub client_scopes: ClientScopes,
+}
+
+impl RequestContext {
+    fn applied_policies_json(&self) -> Option<String> {
+        if self.applied_policies.is_empty() {
+            None
+        } else {
+            serde_json::to_string(&self.applied_policies).ok()
+        }
+    }
+}
+
+/// 一次上游调用的请求信息，重试、对冲和日志共用
+struct UpstreamCall<'a> {
+    method: &'a str,
+    path: &'a str,
+    body: &'a Value,
+    /// 模型降级时客户端原本请求的模型
+    fallback_from: Option<&'a str>,
+}
+
+/// 写入 request_logs 的一行，请求级的附加信息取自 RequestContext
+#[derive(Default)]
+struct LogEntry<'a> {
+    api_key_id: Uuid,
+    method: &'a str,
+    path: &'a str,
+    status_code: i32,
+    response_time_ms: i64,
+    request_body: Option<&'a str>,
+    response_body: Option<&'a str>,
+    fallback_from: Option<&'a str>,
+    cache_hit: bool,
+    /// 与相同的并发请求合并，没有单独请求上游
+    coalesced: bool,
+}
+
+/// 同一模型的所有重试都失败时的上游错误，模型降级据此判断是否换用下一个模型
+#[derive(Debug, thiserror::Error)]
+#[error("Gemini API error after {attempts} attempts ({status_code}): {body}")]
+pub struct UpstreamStatusError {
+    pub attempts: i32,
+    pub status_code: i32,
+    pub body: String,
+}
+
+/// 非流式请求的响应和实际处理请求的模型
+pub struct ProxyResponse {
+    pub body: Value,
+    pub served_model: Option<String>,
+    /// 响应来自缓存，没有请求上游
+    pub cache_hit: bool,
+}
+
+/// 响应中 `usageMetadata.totalTokenCount` 的值
+fn usage_total_tokens(body: &Value) -> Option<i64> {
+    body.get("usageMetadata")?.get("totalTokenCount")?.as_i64()
+}
+
+/// 从 SSE 数据块中读取最新的 usageMetadata，数据流结束或被客户端断开时把 token 数写回日志
+struct StreamUsage {
+    pool: SqlitePool,
+    log_id: Uuid,
+    pending: Vec<u8>,
+    total_tokens: Option<i64>,
+}
+
+impl StreamUsage {
+    fn observe(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        while let Some(pos) = self.pending.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.pending.drain(..=pos).collect();
+            let tokens = std::str::from_utf8(&line).ok()
+                .and_then(|line| line.trim().strip_prefix("data:"))
+                .and_then(|data| serde_json::from_str::<Value>(data.trim()).ok())
+                .and_then(|event| usage_total_tokens(&event));
+            if tokens.is_some() {
+                self.total_tokens = tokens;
+            }
+        }
+    }
+}
+
+impl Drop for StreamUsage {
+    fn drop(&mut self) {
+        let (Some(total_tokens), Ok(runtime)) = (self.total_tokens, tokio::runtime::Handle::try_current()) else {
+            return;
+        };
+        let pool = self.pool.clone();
+        let log_id = self.log_id;
+        runtime.spawn(async move {
+            if let Err(e) = sqlx::query("UPDATE request_logs SET total_tokens = ? WHERE id = ?")
+                .bind(total_tokens)
+                .bind(log_id.to_string())
+                .execute(&pool)
+                .await
+            {
+                tracing::warn!("Failed to record streaming token usage: {}", e);
+            }
+        });
+    }
+}
+
 fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
     // 生成 JavaScript 兼容的时间戳（毫秒精度）
     dt.to_rfc3339_opts(SecondsFormat::Millis, true)
@@ -19,6 +161,14 @@ pub struct GeminiProxyService {
     key_rotation: KeyRotationService,
     api_key_service: ApiKeyService,
     settings_service: SettingsService,
+    provider_service: ProviderService,
+    circuit_breaker: CircuitBreakerService,
+    hedging: HedgingService,
+    vertex_auth: VertexAuthService,
+    response_cache: ResponseCacheService,
+    single_flight: SingleFlightService,
+    scheduler: RequestSchedulerService,
+    context: RequestContext,
     pool: SqlitePool,
 }
 
@@ -32,154 +182,353 @@ impl GeminiProxyService {
         let key_rotation = KeyRotationService::new(pool.clone());
         let api_key_service = ApiKeyService::new(pool.clone());
         let settings_service = SettingsService::new(pool.clone());
+        let provider_service = ProviderService::new(pool.clone());
 
         Self {
+            vertex_auth: VertexAuthService::new(client.clone()),
+            response_cache: ResponseCacheService::new(pool.clone()),
+            single_flight: SingleFlightService::new(),
+            scheduler: RequestSchedulerService::new(),
             client,
             key_rotation,
             api_key_service,
             settings_service,
+            provider_service,
+            circuit_breaker: CircuitBreakerService::new(),
+            hedging: HedgingService::new(),
+            context: RequestContext::default(),
             pool,
         }
     }
 
+    pub fn with_context(mut self, context: RequestContext) -> Self {
+        self.context = context;
+        self
+    }
+
     pub async fn forward_request(&self, method: &str, path: &str, body: Value) -> Result<Value> {
+        Ok(self.forward_request_with_fallback(method, path, body).await?.body)
+    }
+
+    /// 转发非流式请求；请求模型的所有密钥都因配额耗尽失败时，按降级链依次改用下一个模型
+    pub async fn forward_request_with_fallback(&self, method: &str, path: &str, body: Value) -> Result<ProxyResponse> {
         // Validate request body for generateContent endpoints
-        if method == "POST" && (path.contains(":generateContent") || path.contains("generateContent")) {
-            if let Err(e) = self.validate_generate_content_request(&body) {
-                return Err(anyhow!("Invalid request format: {}", e));
+        if method == "POST" && path.contains("generateContent") {
+            self.validate_request_body(&body).await?;
+        }
+
+        let requested_model = model_from_path(path).map(str::to_string);
+
+        let cache_settings = self.settings_service.get_response_cache_settings().await.unwrap_or_default();
+        let cache_key = match &requested_model {
+            Some(model) if cache_settings.enabled
+                && method == "POST"
+                && path.ends_with(":generateContent")
+                && (self.context.cache_opt_in || is_deterministic(&body)) => Some(self.request_key(model, path, &body).await?),
+            _ => None,
+        };
+
+        if let Some(cache_key) = &cache_key {
+            match self.response_cache.get(cache_key).await {
+                Ok(Some(cached)) => {
+                    tracing::info!("Response cache hit for {}", path);
+                    let request_body = body.to_string();
+                    let response_body = cached.body.to_string();
+                    if let Err(e) = self.log_request_with_body(&LogEntry {
+                        api_key_id: cached.api_key_id,
+                        method,
+                        path,
+                        status_code: 200,
+                        response_time_ms: 0,
+                        request_body: Some(&request_body),
+                        response_body: Some(&response_body),
+                        cache_hit: true,
+                        ..LogEntry::default()
+                    }).await {
+                        tracing::warn!("Failed to log cache hit: {}", e);
+                    }
+                    return Ok(ProxyResponse { body: cached.body, served_model: cached.served_model, cache_hit: true });
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("Failed to read response cache: {}", e),
             }
         }
 
-        let retry_count = self.settings_service.get_retry_count().await.unwrap_or(3);
-        
-        for attempt in 0..retry_count {
-            let start_time = Instant::now();
-            
-            let api_key = self.key_rotation.get_next_active_key().await?
-                .ok_or_else(|| anyhow!("No active API keys available"))?;
+        let coalescing = self.settings_service.get_coalescing_settings().await.unwrap_or_default();
+        let flight_key = match &requested_model {
+            Some(model) if coalescing.enabled
+                && method == "POST"
+                && path.ends_with(":generateContent")
+                && (!coalescing.deterministic_only || is_deterministic(&body)) => match &cache_key {
+                Some(cache_key) => Some(cache_key.clone()),
+                None => Some(self.request_key(model, path, &body).await?),
+            },
+            _ => None,
+        };
+
+        let leader = match flight_key.as_deref().map(|key| self.single_flight.join(key)) {
+            Some(Flight::Follower(mut receiver)) => {
+                let start_time = Instant::now();
+                match receiver.recv().await {
+                    Ok(shared) => return self.coalesced_response(method, path, &body, &shared, start_time).await,
+                    Err(_) => {
+                        tracing::warn!("Coalesced request for {} lost its leader, sending it upstream", path);
+                        None
+                    }
+                }
+            }
+            Some(Flight::Leader(leader)) => Some(leader),
+            None => None,
+        };
+
+        let result = self.forward_with_fallbacks(method, path, &body, requested_model).await;
+        let response = match leader {
+            Some(leader) => {
+                let shared = Arc::new(result);
+                leader.finish(shared.clone());
+                Self::unshare(&shared)?
+            }
+            None => result?,
+        };
 
-            // Convert v1 paths to v1beta and add API key as query parameter
-            let converted_path = path.replace("/v1/", "/v1beta/");
-            let gemini_url = format!("https://generativelanguage.googleapis.com{}?key={}", converted_path, api_key.key_value);
-            
-            let mut request = match method {
-                "GET" => self.client.get(&gemini_url),
-                "POST" => self.client.post(&gemini_url),
-                "PUT" => self.client.put(&gemini_url),
-                "DELETE" => self.client.delete(&gemini_url),
-                _ => return Err(anyhow!("Unsupported HTTP method: {}", method)),
-            };
+        if let Some(cache_key) = &cache_key
+            && let Err(e) = self.response_cache.put(cache_key, &response.body, response.served_model.as_deref(), response.api_key_id, &cache_settings).await
+        {
+            tracing::warn!("Failed to write response cache: {}", e);
+        }
+
+        Ok(ProxyResponse { body: response.body, served_model: response.served_model, cache_hit: false })
+    }
 
-            request = request.header("Content-Type", "application/json");
+    /// 响应缓存和请求合并的键；同一模型名按前缀路由到不同供应商时结果不能共用，
+    /// 因此包含当前处理该路径的供应商
+    async fn request_key(&self, model: &str, path: &str, body: &Value) -> Result<String> {
+        let providers = self.provider_service.load_directory().await?;
+        Ok(canonical_request_hash(&providers.route_for_path(path).id.to_string(), model, body))
+    }
 
-            if method != "GET" {
-                request = request.json(&body);
+    /// 合并的请求直接使用领头请求的结果，日志标记为合并请求
+    async fn coalesced_response(&self, method: &str, path: &str, body: &Value, shared: &SharedResult, start_time: Instant) -> Result<ProxyResponse> {
+        let response = Self::unshare(shared)?;
+        tracing::info!("Coalesced {} with an identical in-flight request", path);
+
+        let request_body = body.to_string();
+        let response_body = response.body.to_string();
+        if let Err(e) = self.log_request_with_body(&LogEntry {
+            api_key_id: response.api_key_id,
+            method,
+            path,
+            status_code: 200,
+            response_time_ms: start_time.elapsed().as_millis() as i64,
+            request_body: Some(&request_body),
+            response_body: Some(&response_body),
+            coalesced: true,
+            ..LogEntry::default()
+        }).await {
+            tracing::warn!("Failed to log coalesced request: {}", e);
+        }
+
+        Ok(ProxyResponse { body: response.body, served_model: response.served_model, cache_hit: false })
+    }
+
+    fn unshare(shared: &SharedResult) -> Result<FlightResponse> {
+        match shared.as_ref() {
+            Ok(response) => Ok(response.clone()),
+            Err(e) => Err(Self::clone_error(e)),
+        }
+    }
+
+    /// 复制领头请求的错误，保留处理器和模型降级需要识别的错误类型
+    fn clone_error(error: &anyhow::Error) -> anyhow::Error {
+        if let Some(e) = error.downcast_ref::<CircuitOpenError>() {
+            return CircuitOpenError { scope: e.scope.clone(), retry_after: e.retry_after }.into();
+        }
+        if let Some(e) = error.downcast_ref::<UpstreamStatusError>() {
+            return UpstreamStatusError { attempts: e.attempts, status_code: e.status_code, body: e.body.clone() }.into();
+        }
+        anyhow!("{:#}", error)
+    }
+
+    /// 依次尝试请求模型和降级链中的模型
+    async fn forward_with_fallbacks(&self, method: &str, path: &str, body: &Value, requested_model: Option<String>) -> Result<FlightResponse> {
+        let fallbacks = match &requested_model {
+            Some(model) => self.settings_service.get_model_fallback_settings().await
+                .unwrap_or_default()
+                .fallbacks_for(model)
+                .into_iter()
+                .filter(|fallback| {
+                    let allowed = self.context.client_scopes.allows_model(fallback);
+                    if !allowed {
+                        tracing::debug!("Skipping fallback model {} outside the client's scopes", fallback);
+                    }
+                    allowed
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+
+        let mut served_model = requested_model.clone();
+        let mut result = self.forward_model(&UpstreamCall { method, path, body, fallback_from: None }).await;
+
+        for fallback in fallbacks {
+            match &result {
+                Err(e) if Self::is_quota_exhausted(e) => {}
+                _ => break,
             }
 
-            let response = request.send().await?;
-            let status_code = response.status().as_u16() as i32;
-            let response_time = start_time.elapsed().as_millis() as i64;
+            let requested = requested_model.as_deref().unwrap_or_default();
+            tracing::warn!("Model {} is exhausted, falling back to {}", requested, fallback);
+
+            let fallback_path = replace_model_in_path(path, &fallback);
+            result = self.forward_model(&UpstreamCall {
+                method,
+                path: &fallback_path,
+                body,
+                fallback_from: Some(requested),
+            }).await;
+            served_model = Some(fallback);
+        }
 
-            // Initial log (will be updated later with body content)
+        let (body, api_key_id) = result?;
+        Ok(FlightResponse { body, served_model, api_key_id })
+    }
 
-            // Update API key usage
-            self.api_key_service.increment_usage(api_key.id).await?;
+    /// 配额耗尽（429/503 或所有密钥都被熔断）时才值得降级到其他模型
+    fn is_quota_exhausted(error: &anyhow::Error) -> bool {
+        if error.downcast_ref::<CircuitOpenError>().is_some() {
+            return true;
+        }
+        matches!(error.downcast_ref::<UpstreamStatusError>(), Some(e) if e.status_code == 429 || e.status_code == 503)
+    }
 
-            if response.status().is_success() {
-                let response_text = response.text().await?;
-                let json_response: Value = serde_json::from_str(&response_text)?;
-                
-                // Update log with request and response body
-                let request_body_str = if method != "GET" { Some(body.to_string()) } else { None };
-                if let Err(e) = self.log_request_with_body(
-                    api_key.id, 
-                    method, 
-                    path, 
-                    status_code, 
-                    response_time, 
-                    request_body_str.as_deref(), 
-                    Some(&response_text)
-                ).await {
-                    tracing::warn!("Failed to update log with body: {}", e);
-                }
-                
-                return Ok(json_response);
-            } else {
-                // If the API key is invalid, mark it as failed
-                if status_code == 401 || status_code == 403 {
-                    self.key_rotation.mark_key_as_failed(api_key.id).await?;
-                }
-                
-                let error_text = response.text().await?;
-                
-                // Update log with request and error response body
-                let request_body_str = if method != "GET" { Some(body.to_string()) } else { None };
-                if let Err(e) = self.log_request_with_body(
-                    api_key.id, 
-                    method, 
-                    path, 
-                    status_code, 
-                    response_time, 
-                    request_body_str.as_deref(), 
-                    Some(&error_text)
-                ).await {
-                    tracing::warn!("Failed to update log with body: {}", e);
-                }
-                
-                // If this is the last attempt, return the error
-                if attempt == retry_count - 1 {
-                    return Err(anyhow!("Gemini API error after {} attempts ({}): {}", retry_count, status_code, error_text));
-                }
-                
-                // Log retry attempt
-                tracing::warn!("Request failed (attempt {}/{}): {} - {}", attempt + 1, retry_count, status_code, error_text);
-                
-                // Wait a bit before retrying (exponential backoff)
-                let delay = std::time::Duration::from_millis(100 * (2_u64.pow(attempt as u32)));
-                tokio::time::sleep(delay).await;
+    /// 针对单个模型按重试次数轮换密钥转发，返回响应体和最终成功的密钥
+    async fn forward_model(&self, call: &UpstreamCall<'_>) -> Result<(Value, Uuid)> {
+        let (method, path) = (call.method, call.path);
+        let retry_count = self.settings_service.get_retry_count().await.unwrap_or(3);
+        let breaker_settings = self.settings_service.get_circuit_breaker_settings().await.unwrap_or_default();
+        let hedging_settings = self.settings_service.get_hedging_settings().await.unwrap_or_default();
+        let hedge_delay = self.hedging.hedge_delay(&hedging_settings, method, path);
+        let providers = self.provider_service.load_directory().await?;
+        
+        for attempt in 0..retry_count {
+            let api_key = self.acquire_key_queued(&providers, path, &breaker_settings).await?;
+
+            let outcome = match hedge_delay {
+                Some(delay) => self.execute_hedged(call, api_key, &providers, delay, &breaker_settings).await?,
+                None => self.execute_attempt(call, &api_key, &providers.for_key(&api_key), &breaker_settings, &AtomicBool::new(false)).await?,
+            };
+
+            if outcome.is_success() {
+                let json_response: Value = serde_json::from_str(&outcome.response_text)?;
+                return Ok((json_response, outcome.api_key_id));
             }
+
+            // If this is the last attempt, return the error
+            if attempt == retry_count - 1 {
+                return Err(UpstreamStatusError {
+                    attempts: retry_count,
+                    status_code: outcome.status_code,
+                    body: outcome.response_text,
+                }.into());
+            }
+            
+            // Log retry attempt
+            tracing::warn!("Request failed (attempt {}/{}): {} - {}", attempt + 1, retry_count, outcome.status_code, outcome.response_text);
+            
+            // Wait a bit before retrying (exponential backoff)
+            let delay = std::time::Duration::from_millis(100 * (2_u64.pow(attempt as u32)));
+            tokio::time::sleep(delay).await;
         }
         
         Err(anyhow!("Request failed after all retry attempts"))
     }
 
-    pub async fn forward_streaming_request(&self, method: &str, path: &str, body: Value) -> Result<impl tokio_stream::Stream<Item = Result<Bytes>> + use<>> {
+    pub async fn forward_streaming_request(&self, method: &str, path: &str, body: Value) -> Result<BoxStream<'static, Result<Bytes>>> {
         // Validate request body for streaming generateContent endpoints
-        if method == "POST" && (path.contains(":streamGenerateContent") || path.contains("streamGenerateContent")) {
-            if let Err(e) = self.validate_generate_content_request(&body) {
-                return Err(anyhow!("Invalid request format: {}", e));
+        if method == "POST" && path.contains("streamGenerateContent") {
+            self.validate_request_body(&body).await?;
+        }
+
+        let coalescing = self.settings_service.get_coalescing_settings().await.unwrap_or_default();
+        let flight_key = match model_from_path(path) {
+            Some(model) if coalescing.enabled
+                && method == "POST"
+                && path.contains("streamGenerateContent")
+                && (!coalescing.deterministic_only || is_deterministic(&body)) => Some(self.request_key(model, path, &body).await?),
+            _ => None,
+        };
+
+        match flight_key.as_deref().map(|key| self.single_flight.join_stream(key)) {
+            Some(StreamRole::Leader(leader)) => match self.open_stream(method, path, &body).await {
+                Ok((stream, api_key_id)) => Ok(leader.start(api_key_id, stream)),
+                Err(e) => {
+                    leader.fail(Self::clone_error(&e));
+                    Err(e)
+                }
+            },
+            Some(StreamRole::Follower(flight)) => {
+                let start_time = Instant::now();
+                match flight.wait_started().await {
+                    Some(Ok(api_key_id)) => {
+                        tracing::info!("Coalesced stream {} with an identical in-flight request", path);
+                        let request_body = body.to_string();
+                        let log_id = self.log_request_with_body(&LogEntry {
+                            api_key_id,
+                            method,
+                            path,
+                            status_code: 200,
+                            response_time_ms: start_time.elapsed().as_millis() as i64,
+                            request_body: Some(&request_body),
+                            response_body: Some("[Streaming Response]"),
+                            coalesced: true,
+                            ..LogEntry::default()
+                        }).await;
+                        if let Err(e) = &log_id {
+                            tracing::warn!("Failed to log coalesced stream: {}", e);
+                        }
+                        Ok(self.track_stream_usage(log_id.ok(), flight.subscribe()))
+                    }
+                    Some(Err(e)) => Err(Self::clone_error(&e)),
+                    None => {
+                        tracing::warn!("Coalesced stream for {} lost its leader, sending it upstream", path);
+                        Ok(self.open_stream(method, path, &body).await?.0)
+                    }
+                }
             }
+            None => Ok(self.open_stream(method, path, &body).await?.0),
         }
+    }
 
+    /// 按重试次数轮换密钥建立流式连接，返回数据流和所用的密钥
+    async fn open_stream(&self, method: &str, path: &str, body: &Value) -> Result<(BoxStream<'static, Result<Bytes>>, Uuid)> {
         let retry_count = self.settings_service.get_retry_count().await.unwrap_or(3);
+        let breaker_settings = self.settings_service.get_circuit_breaker_settings().await.unwrap_or_default();
+        let providers = self.provider_service.load_directory().await?;
+
+        if method != "GET" && method != "POST" {
+            return Err(anyhow!("Unsupported HTTP method for streaming: {}", method));
+        }
         
         for attempt in 0..retry_count {
             let start_time = Instant::now();
-            let api_key = self.key_rotation.get_next_active_key().await?
-                .ok_or_else(|| anyhow!("No active API keys available"))?;
+            let api_key = self.acquire_key_queued(&providers, path, &breaker_settings).await?;
+            let provider = providers.for_key(&api_key);
+            let provider = provider.as_ref();
 
-            // Convert v1 paths to v1beta, add API key and alt=sse for streaming
-            let converted_path = path.replace("/v1/", "/v1beta/");
-            let gemini_url = format!("https://generativelanguage.googleapis.com{}?key={}&alt=sse", converted_path, api_key.key_value);
-            
-            tracing::info!("Starting streaming request (attempt {}/{}): {}", attempt + 1, retry_count, &gemini_url[..100]);
+            tracing::info!("Starting streaming request (attempt {}/{}) via {}: {}", attempt + 1, retry_count, provider.name, provider.upstream_path(path));
             
-            let mut request = match method {
-                "GET" => self.client.get(&gemini_url),
-                "POST" => self.client.post(&gemini_url),
-                _ => return Err(anyhow!("Unsupported HTTP method for streaming: {}", method)),
-            };
+            // Add alt=sse for streaming
+            let mut request = self.build_request(method, path, &api_key, provider, Some("alt=sse")).await?;
 
             request = request
-                .header("Content-Type", "application/json")
                 .header("Accept", "text/event-stream")
                 .header("Cache-Control", "no-cache");
 
             if method != "GET" {
-                request = request.json(&body);
+                request = request.json(&self.upstream_body(provider, path, body, true)?);
             }
 
-            let response = request.send().await?;
+            let response = self.send_tracked(request, &api_key, provider, &breaker_settings).await?;
             let status_code = response.status().as_u16() as i32;
             
             tracing::info!("Streaming response status: {}", status_code);
@@ -193,15 +542,17 @@ impl GeminiProxyService {
                 // Log successful streaming start with request body
                 let response_time = start_time.elapsed().as_millis() as i64;
                 let request_body_str = if method != "GET" { Some(body.to_string()) } else { None };
-                if let Err(e) = self.log_request_with_body(
-                    api_key.id, 
-                    method, 
-                    path, 
-                    status_code, 
-                    response_time, 
-                    request_body_str.as_deref(), 
-                    Some("[Streaming Response]")
-                ).await {
+                let log_id = self.log_request_with_body(&LogEntry {
+                    api_key_id: api_key.id,
+                    method,
+                    path,
+                    status_code,
+                    response_time_ms: response_time,
+                    request_body: request_body_str.as_deref(),
+                    response_body: Some("[Streaming Response]"),
+                    ..LogEntry::default()
+                }).await;
+                if let Err(e) = &log_id {
                     tracing::warn!("Failed to log streaming request: {}", e);
                 }
 
@@ -219,7 +570,18 @@ impl GeminiProxyService {
                         }
                     });
 
-                return Ok(stream);
+                if provider.provider_type == ProviderType::OpenAi {
+                    // 每个 Chat Completions 数据块转换为零到多个 Gemini 事件
+                    let mut translator = ChatStreamTranslator::new(model_from_path(path).unwrap_or_default());
+                    let translated = stream.map(move |result| match result.and_then(|bytes| translator.push(&bytes)) {
+                        Ok(events) => events.into_iter().map(Ok).collect::<Vec<_>>(),
+                        Err(e) => vec![Err(e)],
+                    });
+                    let translated = Box::pin(futures::StreamExt::flat_map(translated, futures::stream::iter));
+                    return Ok((self.track_stream_usage(log_id.ok(), translated), api_key.id));
+                }
+
+                return Ok((self.track_stream_usage(log_id.ok(), Box::pin(stream)), api_key.id));
             } else {
                 let response_time = start_time.elapsed().as_millis() as i64;
                 
@@ -227,23 +589,24 @@ impl GeminiProxyService {
                 
                 // Log the failed request with bodies
                 let request_body_str = if method != "GET" { Some(body.to_string()) } else { None };
-                if let Err(e) = self.log_request_with_body(
-                    api_key.id, 
-                    method, 
-                    path, 
-                    status_code, 
-                    response_time, 
-                    request_body_str.as_deref(), 
-                    Some(&error_text)
-                ).await {
+                if let Err(e) = self.log_request_with_body(&LogEntry {
+                    api_key_id: api_key.id,
+                    method,
+                    path,
+                    status_code,
+                    response_time_ms: response_time,
+                    request_body: request_body_str.as_deref(),
+                    response_body: Some(&error_text),
+                    ..LogEntry::default()
+                }).await {
                     tracing::warn!("Failed to log streaming request: {}", e);
                 }
                 
                 // If the API key is invalid, mark it as failed
-                if status_code == 401 || status_code == 403 {
-                    if let Err(e) = self.key_rotation.mark_key_as_failed(api_key.id).await {
-                        tracing::warn!("Failed to mark key as failed: {}", e);
-                    }
+                if (status_code == 401 || status_code == 403)
+                    && let Err(e) = self.key_rotation.mark_key_as_failed(api_key.id).await
+                {
+                    tracing::warn!("Failed to mark key as failed: {}", e);
                 }
                 
                 // If this is the last attempt, return the error
@@ -263,84 +626,381 @@ impl GeminiProxyService {
         Err(anyhow!("Streaming request failed after all retry attempts"))
     }
 
-    async fn log_request(&self, api_key_id: Uuid, method: &str, path: &str, status_code: i32, response_time_ms: i64) -> Result<()> {
-        self.log_request_with_body(api_key_id, method, path, status_code, response_time_ms, None, None).await
+    /// 在数据流经过时记录 token 用量，日志写入失败时原样返回
+    fn track_stream_usage(&self, log_id: Option<Uuid>, stream: BoxStream<'static, Result<Bytes>>) -> BoxStream<'static, Result<Bytes>> {
+        let Some(log_id) = log_id else {
+            return stream;
+        };
+        let mut usage = StreamUsage { pool: self.pool.clone(), log_id, pending: Vec::new(), total_tokens: None };
+        Box::pin(stream.map(move |item| {
+            if let Ok(bytes) = &item {
+                usage.observe(bytes);
+            }
+            item
+        }))
     }
 
-    async fn log_request_with_body(&self, api_key_id: Uuid, method: &str, path: &str, status_code: i32, response_time_ms: i64, request_body: Option<&str>, response_body: Option<&str>) -> Result<()> {
+    /// 使用指定密钥向上游发送一次非流式请求，记录日志并计入密钥用量
+    ///
+    /// `usage_counted` 标记这次请求是否已经计入用量，被取消的对冲请求据此避免重复计数
+    async fn execute_attempt(&self, call: &UpstreamCall<'_>, api_key: &ApiKey, provider: &Provider, breaker_settings: &CircuitBreakerSettings, usage_counted: &AtomicBool) -> Result<AttemptOutcome> {
+        let (method, path, body) = (call.method, call.path, call.body);
+        let start_time = Instant::now();
+
+        let mut request = self.build_request(method, path, api_key, provider, None).await?;
+
+        if method != "GET" {
+            request = request.json(&self.upstream_body(provider, path, body, false)?);
+        }
+
+        let response = self.send_tracked(request, api_key, provider, breaker_settings).await?;
+        let status_code = response.status().as_u16() as i32;
+        let response_time = start_time.elapsed().as_millis() as i64;
+
+        // Update API key usage
+        if !usage_counted.swap(true, Ordering::SeqCst) {
+            self.api_key_service.increment_usage(api_key.id).await?;
+        }
+
+        // If the API key is invalid, mark it as failed
+        if status_code == 401 || status_code == 403 {
+            self.key_rotation.mark_key_as_failed(api_key.id).await?;
+        }
+
+        let mut response_text = response.text().await?;
+        if provider.provider_type == ProviderType::OpenAi && (200..300).contains(&status_code) {
+            response_text = chat_response_to_gemini(model_from_path(path).unwrap_or_default(), &response_text)?;
+        }
+        let outcome = AttemptOutcome { api_key_id: api_key.id, status_code, response_text };
+
+        if outcome.is_success() {
+            self.hedging.record_latency(path, response_time as u64);
+        }
+
+        // Update log with request and response body
+        let request_body_str = if method != "GET" { Some(body.to_string()) } else { None };
+        if let Err(e) = self.log_request_with_body(&LogEntry {
+            api_key_id: api_key.id,
+            method,
+            path,
+            status_code,
+            response_time_ms: response_time,
+            request_body: request_body_str.as_deref(),
+            response_body: Some(&outcome.response_text),
+            fallback_from: call.fallback_from,
+            ..LogEntry::default()
+        }).await {
+            tracing::warn!("Failed to update log with body: {}", e);
+        }
+
+        Ok(outcome)
+    }
+
+    /// 主请求在等待时间内没有返回时，换一个密钥发送相同请求，先成功的一方胜出，另一方被取消
+    async fn execute_hedged(&self, call: &UpstreamCall<'_>, primary_key: ApiKey, providers: &ProviderDirectory, delay: std::time::Duration, breaker_settings: &CircuitBreakerSettings) -> Result<AttemptOutcome> {
+        let primary_start = Instant::now();
+        let primary_provider = providers.for_key(&primary_key);
+        let primary_counted = AtomicBool::new(false);
+        let primary = self.execute_attempt(call, &primary_key, &primary_provider, breaker_settings, &primary_counted);
+        tokio::pin!(primary);
+
+        tokio::select! {
+            result = &mut primary => return result,
+            _ = tokio::time::sleep(delay) => {}
+        }
+
+        let hedge_key = match self.acquire_key(providers, call.path, breaker_settings, Some(primary_key.id)).await {
+            Ok(key) => key,
+            Err(e) => {
+                tracing::debug!("No spare key for hedged request: {}", e);
+                return primary.await;
+            }
+        };
+
+        tracing::info!("Hedging {} on a second key after {}ms", call.path, delay.as_millis());
+        let hedge_start = Instant::now();
+        let hedge_provider = providers.for_key(&hedge_key);
+        let hedge_counted = AtomicBool::new(false);
+        let hedge = self.execute_attempt(call, &hedge_key, &hedge_provider, breaker_settings, &hedge_counted);
+        tokio::pin!(hedge);
+
+        tokio::select! {
+            result = &mut primary => {
+                if AttemptOutcome::succeeded(&result) {
+                    self.log_cancelled_attempt(hedge_key.id, call, hedge_start, &hedge_counted).await;
+                    return result;
+                }
+                hedge.await
+            }
+            result = &mut hedge => {
+                if AttemptOutcome::succeeded(&result) {
+                    self.log_cancelled_attempt(primary_key.id, call, primary_start, &primary_counted).await;
+                    return result;
+                }
+                primary.await
+            }
+        }
+    }
+
+    /// 被取消的对冲请求已经发往上游，同样记入日志，取消前还没有计入密钥用量时补记一次
+    async fn log_cancelled_attempt(&self, api_key_id: Uuid, call: &UpstreamCall<'_>, start_time: Instant, usage_counted: &AtomicBool) {
+        if !usage_counted.swap(true, Ordering::SeqCst)
+            && let Err(e) = self.api_key_service.increment_usage(api_key_id).await
+        {
+            tracing::warn!("Failed to increment API key usage: {}", e);
+        }
+
+        let response_time = start_time.elapsed().as_millis() as i64;
+        let request_body = call.body.to_string();
+        if let Err(e) = self.log_request_with_body(&LogEntry {
+            api_key_id,
+            method: call.method,
+            path: call.path,
+            status_code: HEDGE_CANCELLED_STATUS,
+            response_time_ms: response_time,
+            request_body: Some(&request_body),
+            response_body: Some("[Hedged request cancelled]"),
+            fallback_from: call.fallback_from,
+            ..LogEntry::default()
+        }).await {
+            tracing::warn!("Failed to log cancelled hedged request: {}", e);
+        }
+    }
+
+    /// 在上游和密钥两级熔断器都允许时，从能处理该路径的密钥中取出下一个
+    async fn acquire_key(&self, providers: &ProviderDirectory, path: &str, breaker_settings: &CircuitBreakerSettings, exclude: Option<Uuid>) -> Result<ApiKey> {
+        let mut shortest_wait: Option<std::time::Duration> = None;
+        let mut note_wait = |wait: std::time::Duration| {
+            shortest_wait = Some(shortest_wait.map_or(wait, |w| w.min(wait)));
+        };
+
+        let key = self.key_rotation.get_next_active_key_where(|key| {
+            if Some(key.id) == exclude {
+                return false;
+            }
+
+            if !providers.accepts(key, path) {
+                return false;
+            }
+
+            let host = providers.for_key(key).upstream_host();
+            if let Err(open) = self.circuit_breaker.try_acquire_upstream(&host, breaker_settings) {
+                note_wait(open.retry_after);
+                return false;
+            }
+
+            match self.circuit_breaker.try_acquire_key(key.id, breaker_settings) {
+                Ok(()) => true,
+                Err(wait) => {
+                    self.circuit_breaker.release_upstream(&host);
+                    note_wait(wait);
+                    false
+                }
+            }
+        }).await?;
+
+        match (key, shortest_wait) {
+            (Some(key), _) => Ok(key),
+            (None, Some(retry_after)) => Err(CircuitOpenError {
+                scope: "all API keys".to_string(),
+                retry_after,
+            }.into()),
+            (None, None) => Err(anyhow!("No active API keys available")),
+        }
+    }
+
+    /// 取密钥；所有密钥都在冷却时排队，各优先级按权重轮流，直到有密钥可用或超过该优先级的最长等待时间。
+    /// 已有请求在排队时新请求直接入队，避免抢在等待更久的请求前面；
+    /// 排在前面的请求暂时取不到密钥时，后面可能使用其他密钥的请求可以先取
+    async fn acquire_key_queued(&self, providers: &ProviderDirectory, path: &str, breaker_settings: &CircuitBreakerSettings) -> Result<ApiKey> {
+        let settings = self.settings_service.get_scheduler_settings().await.unwrap_or_default();
+        if !settings.enabled {
+            return self.acquire_key(providers, path, breaker_settings, None).await;
+        }
+
+        if self.scheduler.is_idle() {
+            match self.acquire_key(providers, path, breaker_settings, None).await {
+                Err(e) if e.downcast_ref::<CircuitOpenError>().is_some() => {}
+                result => return result,
+            }
+        }
+
+        let priority = self.context.priority;
+        let max_wait = settings.max_wait(priority);
+        let ticket = self.scheduler.enqueue(priority);
+        tracing::info!("Queued {} request for an API key, waiting up to {}ms: {}", priority.as_str(), max_wait.as_millis(), path);
+
+        let mut retry_after = None;
+        loop {
+            let notified = self.scheduler.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            // 没轮到时一直等到排队状态变化、自己的冷却结束或超时
+            let mut wait = ticket.blocked_remaining();
+            if ticket.may_acquire() {
+                match self.acquire_key(providers, path, breaker_settings, None).await {
+                    Ok(key) => {
+                        tracing::info!("Queued {} request got API key after {}ms", priority.as_str(), ticket.waited().as_millis());
+                        ticket.finish(false);
+                        return Ok(key);
+                    }
+                    Err(e) => match e.downcast_ref::<CircuitOpenError>() {
+                        Some(open) => {
+                            let blocked = open.retry_after.max(MIN_QUEUE_POLL);
+                            ticket.block_for(blocked);
+                            wait = Some(blocked);
+                            retry_after = wait;
+                        }
+                        None => return Err(e),
+                    },
+                }
+            }
+
+            let remaining = max_wait.saturating_sub(ticket.waited());
+            if remaining.is_zero() {
+                ticket.finish(true);
+                return Err(CircuitOpenError {
+                    scope: format!("all API keys ({} queue wait exceeded)", priority.as_str()),
+                    retry_after: retry_after.unwrap_or(Duration::from_secs(1)),
+                }.into());
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(wait.map_or(remaining, |w| w.min(remaining))) => {}
+                _ = &mut notified => {}
+            }
+        }
+    }
+
+    /// 按供应商配置构造上游请求：基础地址、API 版本映射、附加请求头和超时；
+    /// Vertex AI 凭据改写为发布者模型路径，OpenAI 兼容供应商改为 Chat Completions 地址，二者都使用 Bearer 令牌认证
+    async fn build_request(&self, method: &str, path: &str, api_key: &ApiKey, provider: &Provider, extra_query: Option<&str>) -> Result<reqwest::RequestBuilder> {
+        if provider.provider_type == ProviderType::OpenAi {
+            if !path.ends_with(":generateContent") && !path.ends_with(":streamGenerateContent") {
+                return Err(anyhow!("Only generateContent is supported for OpenAI-compatible providers: {}", path));
+            }
+            // 流式请求通过请求体中的 stream 字段开启，不需要 alt=sse
+            return self.finish_request(method, provider.chat_completions_url(), Some(api_key.key_value.clone()), provider);
+        }
+
+        let (mut url, bearer_token) = match api_key.credential_type {
+            CredentialType::ApiKey => (format!("{}?key={}", provider.upstream_url(path), api_key.key_value), None),
+            CredentialType::VertexServiceAccount => {
+                let project = api_key.vertex_project.as_deref()
+                    .ok_or_else(|| anyhow!("Vertex AI key {} has no project configured", api_key.name))?;
+                let location = api_key.vertex_location.as_deref().unwrap_or(DEFAULT_VERTEX_LOCATION);
+                validate_vertex_location(location)?;
+                let url = provider.vertex_url(path, project, location)
+                    .ok_or_else(|| anyhow!("Path is not supported by Vertex AI: {}", path))?;
+                let token = self.vertex_auth.access_token(api_key, provider).await?;
+                (url, Some(token))
+            }
+        };
+        if let Some(query) = extra_query {
+            url.push(if url.contains('?') { '&' } else { '?' });
+            url.push_str(query);
+        }
+
+        self.finish_request(method, url, bearer_token, provider)
+    }
+
+    fn finish_request(&self, method: &str, url: String, bearer_token: Option<String>, provider: &Provider) -> Result<reqwest::RequestBuilder> {
+        let mut request = match method {
+            "GET" => self.client.get(&url),
+            "POST" => self.client.post(&url),
+            "PUT" => self.client.put(&url),
+            "DELETE" => self.client.delete(&url),
+            _ => return Err(anyhow!("Unsupported HTTP method: {}", method)),
+        };
+
+        request = request
+            .timeout(std::time::Duration::from_secs(provider.timeout_secs))
+            .header("Content-Type", "application/json");
+
+        if let Some(token) = bearer_token {
+            request = request.bearer_auth(token);
+        }
+
+        for (name, value) in &provider.extra_headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        Ok(request)
+    }
+
+    /// 发往上游的请求体，OpenAI 兼容供应商需要转换为 Chat Completions 格式
+    fn upstream_body(&self, provider: &Provider, path: &str, body: &Value, stream: bool) -> Result<Value> {
+        match provider.provider_type {
+            ProviderType::OpenAi => {
+                let model = model_from_path(path)
+                    .ok_or_else(|| anyhow!("Cannot determine model from path: {}", path))?;
+                gemini_to_chat_request(model, body, stream)
+            }
+            ProviderType::Gemini | ProviderType::Vertex => Ok(body.clone()),
+        }
+    }
+
+    /// 发送请求并把结果记入熔断器
+    async fn send_tracked(&self, request: reqwest::RequestBuilder, api_key: &ApiKey, provider: &Provider, breaker_settings: &CircuitBreakerSettings) -> Result<reqwest::Response> {
+        let host = provider.upstream_host();
+        match request.send().await {
+            Ok(response) => {
+                let outcome = UpstreamOutcome::from_status(response.status().as_u16() as i32);
+                self.circuit_breaker.record(&host, api_key.id, outcome, breaker_settings);
+                Ok(response)
+            }
+            Err(e) => {
+                self.circuit_breaker.record(&host, api_key.id, UpstreamOutcome::Failed, breaker_settings);
+                Err(e.into())
+            }
+        }
+    }
+
+    /// 写入一条请求日志并返回日志 ID；成功的响应体带有 usageMetadata 时一并记录 token 数
+    async fn log_request_with_body(&self, entry: &LogEntry<'_>) -> Result<Uuid> {
         let log_id = Uuid::new_v4();
         let now = Utc::now();
+        let total_tokens = entry.response_body
+            .filter(|_| (200..300).contains(&entry.status_code))
+            .and_then(|body| serde_json::from_str::<Value>(body).ok())
+            .and_then(|body| usage_total_tokens(&body));
 
         sqlx::query(
             r#"
-            INSERT INTO request_logs (id, api_key_id, method, path, status_code, response_time_ms, request_body, response_body, created_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO request_logs (id, api_key_id, method, path, status_code, response_time_ms, request_body, response_body, fallback_from, applied_policies, cache_hit, coalesced, client_token_id, total_tokens, created_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(log_id.to_string())
-        .bind(api_key_id.to_string())
-        .bind(method)
-        .bind(path)
-        .bind(status_code)
-        .bind(response_time_ms)
-        .bind(request_body)
-        .bind(response_body)
+        .bind(entry.api_key_id.to_string())
+        .bind(entry.method)
+        .bind(entry.path)
+        .bind(entry.status_code)
+        .bind(entry.response_time_ms)
+        .bind(entry.request_body)
+        .bind(entry.response_body)
+        .bind(entry.fallback_from)
+        .bind(self.context.applied_policies_json())
+        .bind(entry.cache_hit as i32)
+        .bind(entry.coalesced as i32)
+        .bind(self.context.client_token_id.map(|id| id.to_string()))
+        .bind(total_tokens)
         .bind(to_js_compatible_timestamp(now))
         .execute(&self.pool)
         .await?;
 
-        Ok(())
+        Ok(log_id)
     }
 
-    fn validate_generate_content_request(&self, body: &Value) -> Result<()> {
-        // Check if body is an object
-        let obj = body.as_object()
-            .ok_or_else(|| anyhow!("Request body must be a JSON object"))?;
-
-        // Check if contents field exists and is not null
-        let contents = obj.get("contents")
-            .ok_or_else(|| anyhow!("Missing required field 'contents'"))?;
-
-        // Check if contents is an array
-        let contents_array = contents.as_array()
-            .ok_or_else(|| anyhow!("Field 'contents' must be an array"))?;
-
-        // Check if contents array is not empty
-        if contents_array.is_empty() {
-            return Err(anyhow!("Field 'contents' cannot be empty"));
-        }
-
-        // Validate each content item has parts
-        for (index, content) in contents_array.iter().enumerate() {
-            let content_obj = content.as_object()
-                .ok_or_else(|| anyhow!("Content item {} must be an object", index))?;
-            
-            let parts = content_obj.get("parts")
-                .ok_or_else(|| anyhow!("Content item {} missing required field 'parts'", index))?;
-            
-            let parts_array = parts.as_array()
-                .ok_or_else(|| anyhow!("Field 'parts' in content item {} must be an array", index))?;
-            
-            if parts_array.is_empty() {
-                return Err(anyhow!("Field 'parts' in content item {} cannot be empty", index));
-            }
-
-            // Validate each part has at least one content field (text, inline_data, etc.)
-            for (part_index, part) in parts_array.iter().enumerate() {
-                let part_obj = part.as_object()
-                    .ok_or_else(|| anyhow!("Part {} in content item {} must be an object", part_index, index))?;
-                
-                let has_content = part_obj.contains_key("text") || 
-                                 part_obj.contains_key("inline_data") ||
-                                 part_obj.contains_key("function_call") ||
-                                 part_obj.contains_key("function_response");
-                
-                if !has_content {
-                    return Err(anyhow!("Part {} in content item {} must contain at least one content field (text, inline_data, function_call, or function_response)", part_index, index));
-                }
-            }
+    /// 按设置的校验模式检查请求体，透传模式下不做任何检查
+    async fn validate_request_body(&self, body: &Value) -> Result<()> {
+        let mode = self.settings_service.get_validation_mode().await.unwrap_or_default();
+        if mode == ValidationMode::Passthrough {
+            return Ok(());
         }
 
+        validate_generate_content_request(body)?;
         Ok(())
     }
 }
\ No newline at end of file
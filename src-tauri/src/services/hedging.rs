@@ -0,0 +1,145 @@
+use crate::models::HedgingSettings;
+use crate::utils::{glob_match, model_from_path};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+
+// 每个模型最近的成功延迟样本（毫秒），用于计算对冲等待时间
+static LATENCIES: LazyLock<Mutex<HashMap<String, VecDeque<u64>>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+const WINDOW_SIZE: usize = 200;
+const MIN_SAMPLES: usize = 20;
+
+pub struct HedgingService;
+
+impl HedgingService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 返回发送对冲请求前的等待时间；该请求不适用对冲时返回 None
+    pub fn hedge_delay(&self, settings: &HedgingSettings, method: &str, path: &str) -> Option<Duration> {
+        if !settings.enabled || method != "POST" || !path.ends_with(":generateContent") {
+            return None;
+        }
+
+        let model = model_from_path(path)?;
+        let unrestricted = settings.models.is_empty() && settings.routes.is_empty();
+        let selected = unrestricted
+            || settings.models.iter().any(|pattern| glob_match(pattern, model))
+            || settings.routes.iter().any(|pattern| glob_match(pattern, path));
+        if !selected {
+            return None;
+        }
+
+        let min_delay = settings.min_delay_ms;
+        let max_delay = settings.max_delay_ms.max(min_delay);
+        // 样本不足时保守地使用最大等待时间
+        let delay = self.latency_percentile(model, settings.percentile).unwrap_or(max_delay);
+
+        Some(Duration::from_millis(delay.clamp(min_delay, max_delay)))
+    }
+
+    pub fn record_latency(&self, path: &str, latency_ms: u64) {
+        let Some(model) = model_from_path(path) else {
+            return;
+        };
+
+        let mut latencies = LATENCIES.lock().unwrap();
+        let samples = latencies.entry(model.to_string()).or_default();
+        if samples.len() >= WINDOW_SIZE {
+            samples.pop_front();
+        }
+        samples.push_back(latency_ms);
+    }
+
+    fn latency_percentile(&self, model: &str, percentile: f64) -> Option<u64> {
+        let latencies = LATENCIES.lock().unwrap();
+        let samples = latencies.get(model)?;
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+
+        let mut sorted: Vec<u64> = samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        sorted.get(rank.saturating_sub(1)).copied()
+    }
+}
+
+impl Default for HedgingService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 延迟样本按模型共享，每个测试使用自己的模型名称
+    fn settings() -> HedgingSettings {
+        HedgingSettings { enabled: true, ..HedgingSettings::default() }
+    }
+
+    #[test]
+    fn only_unary_generate_content_is_hedged() {
+        let hedging = HedgingService::new();
+        let path = "/v1beta/models/hedge-scope:generateContent";
+        assert!(hedging.hedge_delay(&settings(), "POST", path).is_some());
+        assert_eq!(hedging.hedge_delay(&HedgingSettings::default(), "POST", path), None);
+        assert_eq!(hedging.hedge_delay(&settings(), "GET", path), None);
+        assert_eq!(hedging.hedge_delay(&settings(), "POST", "/v1beta/models/hedge-scope:streamGenerateContent"), None);
+    }
+
+    #[test]
+    fn model_and_route_patterns_select_requests() {
+        let hedging = HedgingService::new();
+        let settings = HedgingSettings { models: vec!["hedge-*-flash".to_string()], ..settings() };
+        assert!(hedging.hedge_delay(&settings, "POST", "/v1beta/models/hedge-2.5-flash:generateContent").is_some());
+        assert_eq!(hedging.hedge_delay(&settings, "POST", "/v1beta/models/hedge-2.5-pro:generateContent"), None);
+
+        let settings = HedgingSettings { routes: vec!["/v1/*".to_string()], ..self::settings() };
+        assert!(hedging.hedge_delay(&settings, "POST", "/v1/models/hedge-route:generateContent").is_some());
+        assert_eq!(hedging.hedge_delay(&settings, "POST", "/v1beta/models/hedge-route:generateContent"), None);
+    }
+
+    #[test]
+    fn uses_max_delay_until_enough_samples() {
+        let hedging = HedgingService::new();
+        let path = "/v1beta/models/hedge-few-samples:generateContent";
+        for _ in 0..MIN_SAMPLES - 1 {
+            hedging.record_latency(path, 10);
+        }
+        assert_eq!(hedging.hedge_delay(&settings(), "POST", path), Some(Duration::from_millis(5000)));
+    }
+
+    #[test]
+    fn delay_is_the_clamped_latency_percentile() {
+        let hedging = HedgingService::new();
+        let path = "/v1beta/models/hedge-percentile:generateContent";
+        for latency in 1..=100 {
+            hedging.record_latency(path, latency * 10);
+        }
+        assert_eq!(hedging.hedge_delay(&settings(), "POST", path), Some(Duration::from_millis(950)));
+
+        let low = HedgingSettings { percentile: 10.0, ..settings() };
+        assert_eq!(hedging.hedge_delay(&low, "POST", path), Some(Duration::from_millis(300)));
+
+        let capped = HedgingSettings { max_delay_ms: 800, ..settings() };
+        assert_eq!(hedging.hedge_delay(&capped, "POST", path), Some(Duration::from_millis(800)));
+    }
+
+    #[test]
+    fn keeps_only_the_latest_samples() {
+        let hedging = HedgingService::new();
+        let path = "/v1beta/models/hedge-window:generateContent";
+        for _ in 0..WINDOW_SIZE {
+            hedging.record_latency(path, 4000);
+        }
+        for _ in 0..WINDOW_SIZE {
+            hedging.record_latency(path, 400);
+        }
+        assert_eq!(hedging.hedge_delay(&settings(), "POST", path), Some(Duration::from_millis(400)));
+    }
+}
@@ -0,0 +1,131 @@
+use crate::models::NetworkSettings;
+use crate::utils::ValidationError;
+use anyhow::{Result, anyhow};
+use std::net::IpAddr;
+use std::str::FromStr;
+
+/// 一个 CIDR 网段；不带前缀长度时表示单个地址
+#[derive(Debug, Clone, Copy)]
+pub struct IpNet {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for IpNet {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim();
+        let (addr, prefix) = match value.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (value, None),
+        };
+
+        let addr: IpAddr = addr.parse().map_err(|_| anyhow!("Invalid IP address in '{}'", value))?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(prefix) => prefix
+                .parse::<u8>()
+                .ok()
+                .filter(|&prefix| prefix <= max_prefix)
+                .ok_or_else(|| anyhow!("Invalid prefix length in '{}'", value))?,
+            None => max_prefix,
+        };
+
+        Ok(Self { addr, prefix })
+    }
+}
+
+impl IpNet {
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 映射的 IPv6 地址（::ffff:a.b.c.d）按 IPv4 比较
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// 校验设置中的所有网段，保存前调用
+pub fn validate_cidrs(cidrs: &[String]) -> Result<()> {
+    for cidr in cidrs {
+        cidr.parse::<IpNet>().map_err(|e| ValidationError(e.to_string()))?;
+    }
+    Ok(())
+}
+
+fn matches_any(cidrs: &[String], ip: IpAddr) -> bool {
+    cidrs
+        .iter()
+        .filter_map(|cidr| cidr.parse::<IpNet>().ok())
+        .any(|net| net.contains(ip))
+}
+
+/// 先检查拒绝列表，再检查允许列表（为空时允许所有来源）
+pub fn is_ip_allowed(settings: &NetworkSettings, ip: IpAddr) -> bool {
+    if matches_any(&settings.deny_cidrs, ip) {
+        return false;
+    }
+    settings.allow_cidrs.is_empty() || matches_any(&settings.allow_cidrs, ip)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(value: &str) -> IpNet {
+        value.parse().unwrap()
+    }
+
+    fn ip(value: &str) -> IpAddr {
+        value.parse().unwrap()
+    }
+
+    #[test]
+    fn cidr_matching() {
+        assert!(net("10.0.0.0/8").contains(ip("10.255.1.2")));
+        assert!(!net("10.0.0.0/8").contains(ip("11.0.0.1")));
+        assert!(net("192.168.1.7").contains(ip("192.168.1.7")));
+        assert!(!net("192.168.1.7").contains(ip("192.168.1.8")));
+        assert!(net("0.0.0.0/0").contains(ip("203.0.113.9")));
+        assert!(net("2001:db8::/32").contains(ip("2001:db8:1::1")));
+        assert!(!net("2001:db8::/32").contains(ip("2001:db9::1")));
+        assert!(!net("10.0.0.0/8").contains(ip("2001:db8::1")));
+    }
+
+    #[test]
+    fn ipv4_mapped_addresses_match_ipv4_ranges() {
+        assert!(net("127.0.0.0/8").contains(ip("::ffff:127.0.0.1")));
+    }
+
+    #[test]
+    fn rejects_invalid_cidrs() {
+        for invalid in ["10.0.0.0/33", "::/129", "10.0.0.0/x", "not-an-ip", "10.0.0/8"] {
+            assert!(invalid.parse::<IpNet>().is_err(), "{invalid}");
+        }
+        let error = validate_cidrs(&["10.0.0.0/8".to_string(), "10.0.0.0/40".to_string()]).unwrap_err();
+        assert!(error.is::<ValidationError>());
+    }
+
+    #[test]
+    fn deny_list_wins_over_allow_list() {
+        let settings = NetworkSettings {
+            allow_cidrs: vec!["10.0.0.0/8".to_string()],
+            deny_cidrs: vec!["10.0.0.13".to_string()],
+            ..NetworkSettings::default()
+        };
+        assert!(is_ip_allowed(&settings, ip("10.0.0.12")));
+        assert!(!is_ip_allowed(&settings, ip("10.0.0.13")));
+        assert!(!is_ip_allowed(&settings, ip("192.168.0.1")));
+
+        let open = NetworkSettings::default();
+        assert!(is_ip_allowed(&open, ip("192.168.0.1")));
+    }
+}
@@ -17,10 +17,14 @@ impl KeyRotationService {
         }
     }
 
-    pub async fn get_next_active_key(&self) -> Result<Option<ApiKey>> {
+    /// 按轮换顺序返回第一个满足条件的活跃密钥（例如跳过熔断中的密钥）
+    pub async fn get_next_active_key_where<F>(&self, mut accept: F) -> Result<Option<ApiKey>>
+    where
+        F: FnMut(&ApiKey) -> bool,
+    {
         let keys: Vec<ApiKey> = sqlx::query_as(
             r#"
-            SELECT id, name, key_value, is_active, usage_count, last_used, created_at, updated_at
+            SELECT id, name, key_value, is_active, usage_count, last_used, provider_id, credential_type, vertex_project, vertex_location, created_at, updated_at
             FROM api_keys WHERE is_active = 1 ORDER BY usage_count ASC, last_used ASC NULLS FIRST
             "#,
         )
@@ -32,21 +36,17 @@ impl KeyRotationService {
         }
 
         let mut current_index = self.current_key_index.write().await;
-        let key = keys.get(*current_index).cloned();
-        
-        *current_index = (*current_index + 1) % keys.len();
-        
-        Ok(key)
-    }
-
-    pub async fn get_active_keys_count(&self) -> Result<i64> {
-        let count: (i64,) = sqlx::query_as(
-            "SELECT COUNT(*) FROM api_keys WHERE is_active = 1"
-        )
-        .fetch_one(&self.pool)
-        .await?;
+        let start = *current_index % keys.len();
+
+        for offset in 0..keys.len() {
+            let index = (start + offset) % keys.len();
+            if accept(&keys[index]) {
+                *current_index = (index + 1) % keys.len();
+                return Ok(Some(keys[index].clone()));
+            }
+        }
 
-        Ok(count.0)
+        Ok(None)
     }
 
     pub async fn mark_key_as_failed(&self, key_id: uuid::Uuid) -> Result<()> {
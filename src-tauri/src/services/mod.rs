@@ -1,3 +1,4 @@
+#[cfg(feature = "desktop")]
 pub mod auth;
 pub mod api_key;
 pub mod gemini_proxy;
@@ -5,11 +6,60 @@ pub mod key_rotation;
 pub mod custom_auth;
 pub mod settings;
 pub mod error_logger;
+pub mod circuit_breaker;
+pub mod hedging;
+pub mod provider;
+pub mod vertex_auth;
+pub mod openai_compat;
+pub mod model_alias;
+pub mod policy;
+pub mod request_validator;
+pub mod script_hook;
+pub mod response_cache;
+pub mod single_flight;
+pub mod rate_limiter;
+pub mod request_scheduler;
+pub mod client_token;
+pub mod ip_access;
+pub mod tls;
+#[cfg(feature = "desktop")]
+pub mod admin_session;
+pub mod auth_guard;
+#[cfg(feature = "desktop")]
+pub mod totp;
+pub mod audit;
+pub mod admin_api;
+pub mod request_log;
 
+#[cfg(feature = "desktop")]
 pub use auth::*;
 pub use api_key::*;
 pub use gemini_proxy::*;
 pub use key_rotation::*;
 pub use custom_auth::*;
 pub use settings::*;
-pub use error_logger::*;
\ No newline at end of file
+pub use error_logger::*;
+pub use circuit_breaker::*;
+pub use hedging::*;
+pub use provider::*;
+pub use vertex_auth::*;
+pub use openai_compat::*;
+pub use model_alias::*;
+pub use policy::*;
+pub use request_validator::*;
+pub use script_hook::*;
+pub use response_cache::*;
+pub use single_flight::*;
+pub use rate_limiter::*;
+pub use request_scheduler::*;
+pub use client_token::*;
+pub use ip_access::*;
+pub use tls::*;
+#[cfg(feature = "desktop")]
+pub use admin_session::*;
+pub use auth_guard::*;
+#[cfg(feature = "desktop")]
+pub use totp::*;
+pub use audit::*;
+pub use admin_api::*;
+pub use request_log::*;
\ No newline at end of file
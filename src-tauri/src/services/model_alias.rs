@@ -0,0 +1,206 @@
+use crate::models::ModelAlias;
+#[cfg(feature = "desktop")]
+use crate::models::{CreateModelAliasRequest, UpdateModelAliasRequest};
+use sqlx::SqlitePool;
+use anyhow::Result;
+#[cfg(feature = "desktop")]
+use anyhow::anyhow;
+#[cfg(feature = "desktop")]
+use chrono::{Utc, SecondsFormat};
+use serde_json::{json, Value};
+#[cfg(feature = "desktop")]
+use uuid::Uuid;
+
+#[cfg(feature = "desktop")]
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 别名表每次请求都从数据库读取，修改后立即生效
+pub struct ModelAliasService {
+    pool: SqlitePool,
+}
+
+impl ModelAliasService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn create_alias(&self, request: CreateModelAliasRequest) -> Result<ModelAlias> {
+        let alias = Self::normalize(&request.alias);
+        let target_model = Self::normalize(&request.target_model);
+        Self::validate(&alias, &target_model)?;
+
+        let alias_id = Uuid::new_v4();
+        let now = to_js_compatible_timestamp(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO model_aliases (id, alias, target_model, description, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(alias_id.to_string())
+        .bind(&alias)
+        .bind(&target_model)
+        .bind(&request.description)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_alias_by_id(alias_id).await?
+            .ok_or_else(|| anyhow!("Model alias not found after creation"))
+    }
+
+    pub async fn get_all_aliases(&self) -> Result<Vec<ModelAlias>> {
+        let aliases: Vec<ModelAlias> = sqlx::query_as(
+            r#"
+            SELECT id, alias, target_model, description, created_at, updated_at
+            FROM model_aliases ORDER BY alias ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(aliases)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_alias_by_id(&self, alias_id: Uuid) -> Result<Option<ModelAlias>> {
+        let alias: Option<ModelAlias> = sqlx::query_as(
+            r#"
+            SELECT id, alias, target_model, description, created_at, updated_at
+            FROM model_aliases WHERE id = ?
+            "#,
+        )
+        .bind(alias_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(alias)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn update_alias(&self, alias_id: Uuid, request: UpdateModelAliasRequest) -> Result<Option<ModelAlias>> {
+        let existing = match self.get_alias_by_id(alias_id).await? {
+            Some(existing) => existing,
+            None => return Ok(None),
+        };
+
+        let alias = request.alias.as_deref().map(Self::normalize).unwrap_or(existing.alias);
+        let target_model = request.target_model.as_deref().map(Self::normalize).unwrap_or(existing.target_model);
+        Self::validate(&alias, &target_model)?;
+
+        sqlx::query(
+            r#"
+            UPDATE model_aliases SET alias = ?, target_model = ?, description = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(&alias)
+        .bind(&target_model)
+        .bind(request.description.or(existing.description))
+        .bind(to_js_compatible_timestamp(Utc::now()))
+        .bind(alias_id.to_string())
+        .execute(&self.pool)
+        .await?;
+
+        self.get_alias_by_id(alias_id).await
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn delete_alias(&self, alias_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM model_aliases WHERE id = ?")
+            .bind(alias_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn resolve(&self, model: &str) -> Result<Option<String>> {
+        let target: Option<(String,)> = sqlx::query_as(
+            "SELECT target_model FROM model_aliases WHERE alias = ?"
+        )
+        .bind(Self::normalize(model))
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(target.map(|t| t.0))
+    }
+
+    /// 把 `{model}` 或 `{model}:{method}` 形式的路由参数中的别名替换为真实模型，
+    /// 查询失败时原样转发
+    pub async fn apply_to_path(&self, path: &str) -> String {
+        let (model, method) = match path.split_once(':') {
+            Some((model, method)) => (model, Some(method)),
+            None => (path, None),
+        };
+
+        match self.resolve(model).await {
+            Ok(Some(target)) => {
+                tracing::info!("Model alias {} -> {}", model, target);
+                match method {
+                    Some(method) => format!("{}:{}", target, method),
+                    None => target,
+                }
+            }
+            Ok(None) => path.to_string(),
+            Err(e) => {
+                tracing::warn!("Failed to resolve model alias {}: {}", model, e);
+                path.to_string()
+            }
+        }
+    }
+
+    /// 在模型列表响应中为每个别名追加一条记录，内容复制自目标模型
+    pub async fn append_to_model_list(&self, response: &mut Value) -> Result<()> {
+        let aliases = self.get_all_aliases().await?;
+        let Some(models) = response.get_mut("models").and_then(|m| m.as_array_mut()) else {
+            return Ok(());
+        };
+
+        let entries: Vec<Value> = aliases
+            .iter()
+            .map(|alias| {
+                let target_name = format!("models/{}", alias.target_model);
+                let mut entry = models
+                    .iter()
+                    .find(|m| m.get("name").and_then(|n| n.as_str()) == Some(target_name.as_str()))
+                    .cloned()
+                    .unwrap_or_else(|| json!({}));
+
+                entry["name"] = json!(format!("models/{}", alias.alias));
+                entry["displayName"] = json!(alias.alias);
+                entry["description"] = json!(alias.description.clone()
+                    .unwrap_or_else(|| format!("Alias of {}", alias.target_model)));
+                entry["aliasOf"] = json!(target_name);
+                entry
+            })
+            .collect();
+
+        models.extend(entries);
+        Ok(())
+    }
+
+    fn normalize(model: &str) -> String {
+        model.trim().trim_start_matches("models/").to_string()
+    }
+
+    #[cfg(feature = "desktop")]
+    fn validate(alias: &str, target_model: &str) -> Result<()> {
+        if alias.is_empty() || target_model.is_empty() {
+            return Err(anyhow!("Alias and target model are required"));
+        }
+        if alias.contains([':', '/']) || alias.contains(char::is_whitespace) {
+            return Err(anyhow!("Alias must not contain ':', '/' or whitespace: {}", alias));
+        }
+        if alias == target_model {
+            return Err(anyhow!("Alias must differ from its target model"));
+        }
+        Ok(())
+    }
+}
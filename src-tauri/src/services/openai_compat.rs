@@ -0,0 +1,505 @@
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use serde_json::{json, Map, Value};
+
+/// 流式函数调用序号的上限，上游给出更大的序号时按错误处理，避免按序号分配过大的缓冲
+const MAX_TOOL_CALL_INDEX: usize = 128;
+
+// Gemini 格式的请求与 OpenAI Chat Completions 格式之间的转换
+
+/// 字段同时接受 camelCase 和 snake_case 写法
+fn field<'a>(obj: &'a Map<String, Value>, camel: &str, snake: &str) -> Option<&'a Value> {
+    obj.get(camel).or_else(|| obj.get(snake))
+}
+
+fn parts_of(content: &Value) -> Vec<&Value> {
+    content.get("parts")
+        .and_then(|p| p.as_array())
+        .map(|parts| parts.iter().collect())
+        .unwrap_or_default()
+}
+
+fn text_of(content: &Value) -> String {
+    parts_of(content)
+        .iter()
+        .filter_map(|part| part.get("text").and_then(|t| t.as_str()))
+        .collect::<Vec<_>>()
+        .join("")
+}
+
+/// Gemini 的函数调用没有 ID：按出现顺序为每次调用生成唯一 ID，
+/// 函数结果按位置对应到尚未回应的调用（同名调用依次匹配）
+#[derive(Default)]
+struct ToolCallIds {
+    next: usize,
+    // (调用 ID, 函数名)，等待结果的调用
+    pending: Vec<(String, String)>,
+}
+
+impl ToolCallIds {
+    fn issue(&mut self, name: &str) -> String {
+        let id = format!("call_{}", self.next);
+        self.next += 1;
+        self.pending.push((id.clone(), name.to_string()));
+        id
+    }
+
+    fn resolve(&mut self, name: &str) -> String {
+        match self.pending.iter().position(|(_, pending)| pending == name) {
+            Some(index) => self.pending.remove(index).0,
+            // 找不到对应的调用时保持原来的行为，用函数名作为 ID
+            None => name.to_string(),
+        }
+    }
+}
+
+/// 把一条 Gemini content 转换为一条或多条 chat 消息
+fn content_to_messages(content: &Value, call_ids: &mut ToolCallIds, messages: &mut Vec<Value>) {
+    let role = match content.get("role").and_then(|r| r.as_str()) {
+        Some("model") => "assistant",
+        _ => "user",
+    };
+
+    let mut text_parts = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for part in parts_of(content) {
+        let Some(part) = part.as_object() else { continue };
+
+        if let Some(text) = part.get("text").and_then(|t| t.as_str()) {
+            text_parts.push(json!({ "type": "text", "text": text }));
+        } else if let Some(data) = field(part, "inlineData", "inline_data") {
+            let mime_type = field(data.as_object().unwrap_or(&Map::new()), "mimeType", "mime_type")
+                .and_then(|m| m.as_str())
+                .unwrap_or("application/octet-stream")
+                .to_string();
+            let payload = data.get("data").and_then(|d| d.as_str()).unwrap_or("");
+            text_parts.push(json!({
+                "type": "image_url",
+                "image_url": { "url": format!("data:{};base64,{}", mime_type, payload) }
+            }));
+        } else if let Some(call) = field(part, "functionCall", "function_call") {
+            let name = call.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let args = call.get("args").cloned().unwrap_or_else(|| json!({}));
+            tool_calls.push(json!({
+                "id": call_ids.issue(name),
+                "type": "function",
+                "function": { "name": name, "arguments": args.to_string() }
+            }));
+        } else if let Some(result) = field(part, "functionResponse", "function_response") {
+            let name = result.get("name").and_then(|n| n.as_str()).unwrap_or("");
+            let response = result.get("response").cloned().unwrap_or(Value::Null);
+            messages.push(json!({
+                "role": "tool",
+                "tool_call_id": call_ids.resolve(name),
+                "content": response.to_string()
+            }));
+        }
+    }
+
+    if text_parts.is_empty() && tool_calls.is_empty() {
+        return;
+    }
+
+    // 只有文本时使用字符串形式，兼容不支持多段内容的服务
+    let only_text = text_parts.iter().all(|p| p["type"] == "text");
+    let content_value = if text_parts.is_empty() {
+        Value::Null
+    } else if only_text {
+        Value::String(text_parts.iter().filter_map(|p| p["text"].as_str()).collect::<Vec<_>>().join(""))
+    } else {
+        Value::Array(text_parts)
+    };
+
+    let mut message = json!({ "role": role, "content": content_value });
+    if !tool_calls.is_empty() {
+        message["tool_calls"] = Value::Array(tool_calls);
+    }
+    messages.push(message);
+}
+
+/// 把 Gemini generateContent 请求体转换为 Chat Completions 请求体
+pub fn gemini_to_chat_request(model: &str, body: &Value, stream: bool) -> Result<Value> {
+    let obj = body.as_object()
+        .ok_or_else(|| anyhow!("Request body must be a JSON object"))?;
+
+    let mut messages = Vec::new();
+
+    if let Some(system) = field(obj, "systemInstruction", "system_instruction") {
+        let text = text_of(system);
+        if !text.is_empty() {
+            messages.push(json!({ "role": "system", "content": text }));
+        }
+    }
+
+    let mut call_ids = ToolCallIds::default();
+    for content in obj.get("contents").and_then(|c| c.as_array()).into_iter().flatten() {
+        content_to_messages(content, &mut call_ids, &mut messages);
+    }
+
+    let mut request = json!({
+        "model": model,
+        "messages": messages,
+        "stream": stream,
+    });
+
+    if stream {
+        request["stream_options"] = json!({ "include_usage": true });
+    }
+
+    if let Some(config) = field(obj, "generationConfig", "generation_config").and_then(|c| c.as_object()) {
+        let mappings = [
+            ("temperature", "temperature", "temperature"),
+            ("topP", "top_p", "top_p"),
+            ("maxOutputTokens", "max_output_tokens", "max_tokens"),
+            ("stopSequences", "stop_sequences", "stop"),
+            ("candidateCount", "candidate_count", "n"),
+            ("presencePenalty", "presence_penalty", "presence_penalty"),
+            ("frequencyPenalty", "frequency_penalty", "frequency_penalty"),
+            ("seed", "seed", "seed"),
+        ];
+        for (camel, snake, target) in mappings {
+            if let Some(value) = field(config, camel, snake) {
+                request[target] = value.clone();
+            }
+        }
+
+        if field(config, "responseMimeType", "response_mime_type").and_then(|m| m.as_str()) == Some("application/json") {
+            request["response_format"] = json!({ "type": "json_object" });
+        }
+    }
+
+    let declarations: Vec<Value> = obj.get("tools")
+        .and_then(|t| t.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|tool| tool.as_object().and_then(|t| field(t, "functionDeclarations", "function_declarations")))
+        .filter_map(|d| d.as_array())
+        .flatten()
+        .map(|declaration| json!({
+            "type": "function",
+            "function": {
+                "name": declaration.get("name").cloned().unwrap_or(Value::Null),
+                "description": declaration.get("description").cloned().unwrap_or(Value::Null),
+                "parameters": declaration.get("parameters").cloned().unwrap_or_else(|| json!({ "type": "object" })),
+            }
+        }))
+        .collect();
+
+    if !declarations.is_empty() {
+        request["tools"] = Value::Array(declarations);
+    }
+
+    Ok(request)
+}
+
+fn finish_reason_to_gemini(reason: Option<&str>) -> Option<&'static str> {
+    reason.map(|r| match r {
+        "length" => "MAX_TOKENS",
+        "content_filter" => "SAFETY",
+        _ => "STOP",
+    })
+}
+
+fn usage_to_gemini(usage: &Value) -> Value {
+    json!({
+        "promptTokenCount": usage.get("prompt_tokens").cloned().unwrap_or(json!(0)),
+        "candidatesTokenCount": usage.get("completion_tokens").cloned().unwrap_or(json!(0)),
+        "totalTokenCount": usage.get("total_tokens").cloned().unwrap_or(json!(0)),
+    })
+}
+
+fn tool_call_to_part(call: &Value) -> Option<Value> {
+    let function = call.get("function")?;
+    let name = function.get("name")?.as_str()?;
+    let args = function.get("arguments")
+        .and_then(|a| a.as_str())
+        .and_then(|a| serde_json::from_str::<Value>(a).ok())
+        .unwrap_or_else(|| json!({}));
+    Some(json!({ "functionCall": { "name": name, "args": args } }))
+}
+
+/// 把 Chat Completions 响应转换为 Gemini generateContent 响应
+pub fn chat_response_to_gemini(model: &str, response_text: &str) -> Result<String> {
+    let response: Value = serde_json::from_str(response_text)?;
+
+    let candidates: Vec<Value> = response.get("choices")
+        .and_then(|c| c.as_array())
+        .into_iter()
+        .flatten()
+        .map(|choice| {
+            let message = choice.get("message").cloned().unwrap_or(Value::Null);
+            let mut parts = Vec::new();
+            if let Some(text) = message.get("content").and_then(|c| c.as_str()) {
+                parts.push(json!({ "text": text }));
+            }
+            for call in message.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+                parts.extend(tool_call_to_part(call));
+            }
+
+            let mut candidate = json!({
+                "content": { "role": "model", "parts": parts },
+                "index": choice.get("index").cloned().unwrap_or(json!(0)),
+            });
+            if let Some(reason) = finish_reason_to_gemini(choice.get("finish_reason").and_then(|r| r.as_str())) {
+                candidate["finishReason"] = json!(reason);
+            }
+            candidate
+        })
+        .collect();
+
+    let mut gemini = json!({
+        "candidates": candidates,
+        "modelVersion": response.get("model").and_then(|m| m.as_str()).unwrap_or(model),
+    });
+    if let Some(usage) = response.get("usage") {
+        gemini["usageMetadata"] = usage_to_gemini(usage);
+    }
+
+    Ok(gemini.to_string())
+}
+
+/// 把 Chat Completions 的 SSE 流逐行转换为 Gemini streamGenerateContent 的 SSE 事件，
+/// 上游数据块可能在任意位置断开，未完成的行留到下一块；
+/// 函数调用的参数分片到达，结束时再整体输出
+pub struct ChatStreamTranslator {
+    model: String,
+    // 按字节缓存，多字节字符可能被拆在两个数据块中，只解码完整的行
+    buffer: Vec<u8>,
+    // (函数名, 已收到的参数片段)，按调用序号排列
+    pending_calls: Vec<(String, String)>,
+}
+
+impl ChatStreamTranslator {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            buffer: Vec::new(),
+            pending_calls: Vec::new(),
+        }
+    }
+
+    /// 每个完整事件单独返回，处理器按块读取 `data:` 行
+    pub fn push(&mut self, bytes: &[u8]) -> Result<Vec<Bytes>> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut events = Vec::new();
+        while let Some(pos) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=pos).collect();
+            let line = String::from_utf8_lossy(&line);
+            if let Some(event) = self.translate_line(line.trim())? {
+                events.push(Bytes::from(format!("data: {}\n\n", event)));
+            }
+        }
+        Ok(events)
+    }
+
+    fn translate_line(&mut self, line: &str) -> Result<Option<Value>> {
+        let Some(data) = line.strip_prefix("data:").map(str::trim) else {
+            return Ok(None);
+        };
+        if data.is_empty() || data == "[DONE]" {
+            return Ok(None);
+        }
+
+        let Ok(chunk) = serde_json::from_str::<Value>(data) else {
+            return Ok(None);
+        };
+        let mut event = json!({
+            "modelVersion": chunk.get("model").and_then(|m| m.as_str()).unwrap_or(&self.model),
+        });
+
+        let choice = chunk.get("choices").and_then(|c| c.as_array()).and_then(|c| c.first());
+        if let Some(choice) = choice {
+            let delta = choice.get("delta").cloned().unwrap_or(Value::Null);
+            let mut parts = Vec::new();
+            if let Some(text) = delta.get("content").and_then(|c| c.as_str())
+                && !text.is_empty()
+            {
+                parts.push(json!({ "text": text }));
+            }
+            for call in delta.get("tool_calls").and_then(|t| t.as_array()).into_iter().flatten() {
+                let index = call.get("index").and_then(|i| i.as_u64()).unwrap_or(0);
+                if index > MAX_TOOL_CALL_INDEX as u64 {
+                    return Err(anyhow!("Upstream tool call index {} exceeds the limit of {}", index, MAX_TOOL_CALL_INDEX));
+                }
+                let index = index as usize;
+                if self.pending_calls.len() <= index {
+                    self.pending_calls.resize(index + 1, (String::new(), String::new()));
+                }
+                let function = call.get("function");
+                if let Some(name) = function.and_then(|f| f.get("name")).and_then(|n| n.as_str()) {
+                    self.pending_calls[index].0.push_str(name);
+                }
+                if let Some(args) = function.and_then(|f| f.get("arguments")).and_then(|a| a.as_str()) {
+                    self.pending_calls[index].1.push_str(args);
+                }
+            }
+
+            let reason = finish_reason_to_gemini(choice.get("finish_reason").and_then(|r| r.as_str()));
+            if reason.is_some() {
+                for (name, args) in self.pending_calls.drain(..) {
+                    let args = serde_json::from_str::<Value>(&args).unwrap_or_else(|_| json!({}));
+                    parts.push(json!({ "functionCall": { "name": name, "args": args } }));
+                }
+            }
+            if parts.is_empty() && reason.is_none() {
+                return Ok(None);
+            }
+
+            let mut candidate = json!({
+                "content": { "role": "model", "parts": parts },
+                "index": choice.get("index").cloned().unwrap_or(json!(0)),
+            });
+            if let Some(reason) = reason {
+                candidate["finishReason"] = json!(reason);
+            }
+            event["candidates"] = json!([candidate]);
+        }
+
+        match chunk.get("usage").filter(|u| !u.is_null()) {
+            Some(usage) => event["usageMetadata"] = usage_to_gemini(usage),
+            None if choice.is_none() => return Ok(None),
+            None => {}
+        }
+
+        Ok(Some(event))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn events(translator: &mut ChatStreamTranslator, chunks: &[&[u8]]) -> Vec<Value> {
+        chunks
+            .iter()
+            .flat_map(|chunk| translator.push(chunk).unwrap())
+            .map(|event| {
+                let text = std::str::from_utf8(&event).unwrap();
+                serde_json::from_str(text.strip_prefix("data: ").unwrap().trim()).unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn request_maps_roles_system_and_generation_config() {
+        let body = json!({
+            "system_instruction": { "parts": [{ "text": "be brief" }] },
+            "contents": [
+                { "role": "user", "parts": [{ "text": "hi" }] },
+                { "role": "model", "parts": [{ "text": "hello" }] },
+            ],
+            "generationConfig": { "maxOutputTokens": 64, "stopSequences": ["END"], "responseMimeType": "application/json" },
+        });
+        let request = gemini_to_chat_request("gpt-4o", &body, true).unwrap();
+        assert_eq!(request["messages"], json!([
+            { "role": "system", "content": "be brief" },
+            { "role": "user", "content": "hi" },
+            { "role": "assistant", "content": "hello" },
+        ]));
+        assert_eq!(request["max_tokens"], json!(64));
+        assert_eq!(request["stop"], json!(["END"]));
+        assert_eq!(request["response_format"], json!({ "type": "json_object" }));
+        assert_eq!(request["stream_options"], json!({ "include_usage": true }));
+    }
+
+    #[test]
+    fn repeated_function_calls_get_distinct_ids() {
+        let body = json!({
+            "contents": [
+                { "role": "user", "parts": [{ "text": "weather in Paris and Rome?" }] },
+                { "role": "model", "parts": [
+                    { "functionCall": { "name": "weather", "args": { "city": "Paris" } } },
+                    { "functionCall": { "name": "weather", "args": { "city": "Rome" } } },
+                ] },
+                { "role": "user", "parts": [
+                    { "functionResponse": { "name": "weather", "response": { "temp": 18 } } },
+                    { "functionResponse": { "name": "weather", "response": { "temp": 24 } } },
+                ] },
+            ],
+        });
+        let request = gemini_to_chat_request("gpt-4o", &body, false).unwrap();
+        let messages = request["messages"].as_array().unwrap();
+
+        let call_ids: Vec<&str> = messages[1]["tool_calls"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|call| call["id"].as_str().unwrap())
+            .collect();
+        assert_eq!(call_ids, ["call_0", "call_1"]);
+        assert_eq!(messages[2]["tool_call_id"], "call_0");
+        assert_eq!(messages[2]["content"], json!({ "temp": 18 }).to_string());
+        assert_eq!(messages[3]["tool_call_id"], "call_1");
+        assert_eq!(messages[3]["content"], json!({ "temp": 24 }).to_string());
+    }
+
+    #[test]
+    fn response_converts_text_tool_calls_and_usage() {
+        let response = json!({
+            "model": "gpt-4o-2024",
+            "choices": [{
+                "index": 0,
+                "finish_reason": "length",
+                "message": {
+                    "content": "partial",
+                    "tool_calls": [{ "function": { "name": "lookup", "arguments": "{\"q\":1}" } }],
+                },
+            }],
+            "usage": { "prompt_tokens": 3, "completion_tokens": 5, "total_tokens": 8 },
+        });
+        let gemini: Value = serde_json::from_str(&chat_response_to_gemini("gpt-4o", &response.to_string()).unwrap()).unwrap();
+        assert_eq!(gemini["modelVersion"], "gpt-4o-2024");
+        assert_eq!(gemini["candidates"][0]["finishReason"], "MAX_TOKENS");
+        assert_eq!(gemini["candidates"][0]["content"]["parts"], json!([
+            { "text": "partial" },
+            { "functionCall": { "name": "lookup", "args": { "q": 1 } } },
+        ]));
+        assert_eq!(gemini["usageMetadata"]["totalTokenCount"], 8);
+    }
+
+    #[test]
+    fn stream_keeps_multibyte_characters_split_across_chunks() {
+        let line = format!("data: {}\n", json!({ "choices": [{ "index": 0, "delta": { "content": "你好" } }] }));
+        let bytes = line.as_bytes();
+        // 在“你”的 UTF-8 编码中间断开
+        let split = line.find('你').unwrap() + 1;
+
+        let mut translator = ChatStreamTranslator::new("gpt-4o");
+        let events = events(&mut translator, &[&bytes[..split], &bytes[split..]]);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["candidates"][0]["content"]["parts"], json!([{ "text": "你好" }]));
+    }
+
+    #[test]
+    fn stream_joins_tool_call_arguments_until_finish() {
+        let chunks = [
+            format!("data: {}\n", json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "name": "lookup", "arguments": "{\"q\"" } }] } }] })),
+            format!("data: {}\n", json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 0, "function": { "arguments": ":1}" } }] } }] })),
+            format!("data: {}\n", json!({ "choices": [{ "delta": {}, "finish_reason": "tool_calls" }] })),
+            format!("data: {}\ndata: [DONE]\n", json!({ "choices": [], "usage": { "prompt_tokens": 1, "completion_tokens": 2, "total_tokens": 3 } })),
+        ];
+        let chunks: Vec<&[u8]> = chunks.iter().map(|chunk| chunk.as_bytes()).collect();
+
+        let mut translator = ChatStreamTranslator::new("gpt-4o");
+        let events = events(&mut translator, &chunks);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["candidates"][0]["finishReason"], "STOP");
+        assert_eq!(events[0]["candidates"][0]["content"]["parts"], json!([
+            { "functionCall": { "name": "lookup", "args": { "q": 1 } } },
+        ]));
+        assert_eq!(events[1]["usageMetadata"]["totalTokenCount"], 3);
+    }
+
+    #[test]
+    fn stream_rejects_huge_tool_call_index() {
+        let line = format!("data: {}\n", json!({ "choices": [{ "delta": { "tool_calls": [{ "index": 1u64 << 40, "function": { "name": "x" } }] } }] }));
+        let mut translator = ChatStreamTranslator::new("gpt-4o");
+        assert!(translator.push(line.as_bytes()).is_err());
+        assert!(translator.pending_calls.is_empty());
+
+        let line = format!("data: {}\n", json!({ "choices": [{ "delta": { "tool_calls": [{ "index": MAX_TOOL_CALL_INDEX, "function": { "name": "x" } }] } }] }));
+        assert!(translator.push(line.as_bytes()).is_ok());
+    }
+}
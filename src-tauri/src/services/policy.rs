@@ -0,0 +1,346 @@
+use crate::models::{PolicyAction, PolicyRule};
+#[cfg(feature = "desktop")]
+use crate::models::{CreatePolicyRuleRequest, UpdatePolicyRuleRequest};
+use crate::utils::glob_match;
+use sqlx::SqlitePool;
+use anyhow::Result;
+#[cfg(feature = "desktop")]
+use anyhow::anyhow;
+#[cfg(feature = "desktop")]
+use chrono::{Utc, SecondsFormat};
+use serde_json::{json, Map, Value};
+#[cfg(feature = "desktop")]
+use uuid::Uuid;
+
+#[cfg(feature = "desktop")]
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+fn to_snake_case(key: &str) -> String {
+    let mut snake = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// 找到对象中实际使用的键名：优先原样，其次 snake_case 写法
+fn existing_key(obj: &Map<String, Value>, key: &str) -> Option<String> {
+    if obj.contains_key(key) {
+        return Some(key.to_string());
+    }
+    let snake = to_snake_case(key);
+    obj.contains_key(&snake).then_some(snake)
+}
+
+/// 按路径取得字段的可变引用，`create` 为真时补齐缺失的中间对象
+fn lookup<'a>(body: &'a mut Value, path: &str, create: bool) -> Option<(&'a mut Map<String, Value>, String)> {
+    let segments: Vec<&str> = path.split('.').filter(|s| !s.is_empty()).collect();
+    let (last, parents) = segments.split_last()?;
+
+    let mut current = body.as_object_mut()?;
+    for segment in parents {
+        let key = match existing_key(current, segment) {
+            Some(key) => key,
+            None if create => {
+                current.insert(segment.to_string(), json!({}));
+                segment.to_string()
+            }
+            None => return None,
+        };
+        current = current.get_mut(&key)?.as_object_mut()?;
+    }
+
+    let key = existing_key(current, last).unwrap_or_else(|| last.to_string());
+    Some((current, key))
+}
+
+fn apply_action(body: &mut Value, action: &PolicyAction) {
+    match action {
+        PolicyAction::SetDefault { path, value } => {
+            if let Some((obj, key)) = lookup(body, path, true) {
+                obj.entry(key).or_insert_with(|| value.clone());
+            }
+        }
+        PolicyAction::Force { path, value } => {
+            if let Some((obj, key)) = lookup(body, path, true) {
+                obj.insert(key, value.clone());
+            }
+        }
+        PolicyAction::Clamp { path, min, max } => {
+            let Some((obj, key)) = lookup(body, path, false) else { return };
+            let Some(current) = obj.get(&key) else { return };
+            let Some(number) = current.as_f64() else { return };
+
+            let mut clamped = number;
+            if let Some(min) = min {
+                clamped = clamped.max(*min);
+            }
+            if let Some(max) = max {
+                clamped = clamped.min(*max);
+            }
+
+            // 整数字段（例如 maxOutputTokens）保持整数
+            let value = if current.is_i64() || current.is_u64() {
+                json!(clamped.round() as i64)
+            } else {
+                json!(clamped)
+            };
+            obj.insert(key, value);
+        }
+        PolicyAction::Strip { path } => {
+            if let Some((obj, key)) = lookup(body, path, false) {
+                obj.remove(&key);
+            }
+        }
+        PolicyAction::PrependSystemInstruction { text } => {
+            let Some(obj) = body.as_object_mut() else { return };
+            let key = existing_key(obj, "systemInstruction").unwrap_or_else(|| "systemInstruction".to_string());
+            let instruction = obj.entry(key).or_insert_with(|| json!({ "parts": [] }));
+
+            // 客户端传来的可能不是对象（字符串、数组等），策略在请求校验之前执行，不能直接索引
+            let Some(instruction) = instruction.as_object_mut() else {
+                *instruction = json!({ "parts": [{ "text": text }] });
+                return;
+            };
+            let parts = instruction.entry("parts").or_insert_with(|| json!([]));
+            if !parts.is_array() {
+                *parts = json!([]);
+            }
+            if let Some(parts) = parts.as_array_mut() {
+                parts.insert(0, json!({ "text": text }));
+            }
+        }
+    }
+}
+
+pub struct PolicyService {
+    pool: SqlitePool,
+}
+
+impl PolicyService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn create_rule(&self, request: CreatePolicyRuleRequest) -> Result<PolicyRule> {
+        if request.name.trim().is_empty() {
+            return Err(anyhow!("Policy rule name is required"));
+        }
+
+        let rule_id = Uuid::new_v4();
+        let now = to_js_compatible_timestamp(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO policy_rules (id, name, enabled, priority, model_patterns, client_tokens, actions, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(rule_id.to_string())
+        .bind(request.name.trim())
+        .bind(request.enabled.unwrap_or(true) as i32)
+        .bind(request.priority.unwrap_or(0))
+        .bind(serde_json::to_string(&request.model_patterns)?)
+        .bind(serde_json::to_string(&request.client_tokens)?)
+        .bind(serde_json::to_string(&request.actions)?)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_rule_by_id(rule_id).await?
+            .ok_or_else(|| anyhow!("Policy rule not found after creation"))
+    }
+
+    pub async fn get_all_rules(&self) -> Result<Vec<PolicyRule>> {
+        let rules: Vec<PolicyRule> = sqlx::query_as(
+            r#"
+            SELECT id, name, enabled, priority, model_patterns, client_tokens, actions, created_at, updated_at
+            FROM policy_rules ORDER BY priority ASC, created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(rules)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_rule_by_id(&self, rule_id: Uuid) -> Result<Option<PolicyRule>> {
+        let rule: Option<PolicyRule> = sqlx::query_as(
+            r#"
+            SELECT id, name, enabled, priority, model_patterns, client_tokens, actions, created_at, updated_at
+            FROM policy_rules WHERE id = ?
+            "#,
+        )
+        .bind(rule_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(rule)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn update_rule(&self, rule_id: Uuid, request: UpdatePolicyRuleRequest) -> Result<Option<PolicyRule>> {
+        let mut query_parts = Vec::new();
+        let mut bind_values = Vec::new();
+
+        if let Some(name) = &request.name {
+            query_parts.push("name = ?");
+            bind_values.push(name.trim().to_string());
+        }
+
+        if let Some(enabled) = request.enabled {
+            query_parts.push("enabled = ?");
+            bind_values.push((enabled as i32).to_string());
+        }
+
+        if let Some(priority) = request.priority {
+            query_parts.push("priority = ?");
+            bind_values.push(priority.to_string());
+        }
+
+        if let Some(model_patterns) = &request.model_patterns {
+            query_parts.push("model_patterns = ?");
+            bind_values.push(serde_json::to_string(model_patterns)?);
+        }
+
+        if let Some(client_tokens) = &request.client_tokens {
+            query_parts.push("client_tokens = ?");
+            bind_values.push(serde_json::to_string(client_tokens)?);
+        }
+
+        if let Some(actions) = &request.actions {
+            query_parts.push("actions = ?");
+            bind_values.push(serde_json::to_string(actions)?);
+        }
+
+        if query_parts.is_empty() {
+            return self.get_rule_by_id(rule_id).await;
+        }
+
+        query_parts.push("updated_at = ?");
+        bind_values.push(to_js_compatible_timestamp(Utc::now()));
+
+        let query = format!(
+            "UPDATE policy_rules SET {} WHERE id = ?",
+            query_parts.join(", ")
+        );
+
+        let mut query_builder = sqlx::query(&query);
+        for value in bind_values {
+            query_builder = query_builder.bind(value);
+        }
+        query_builder = query_builder.bind(rule_id.to_string());
+
+        query_builder.execute(&self.pool).await?;
+
+        self.get_rule_by_id(rule_id).await
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn delete_rule(&self, rule_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM policy_rules WHERE id = ?")
+            .bind(rule_id.to_string())
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 按优先级对请求体应用所有匹配的规则，返回已应用的规则名称
+    pub async fn apply(&self, model: &str, client_token: &str, body: &mut Value) -> Result<Vec<String>> {
+        let rules = self.get_all_rules().await?;
+        let mut applied = Vec::new();
+
+        for rule in rules.iter().filter(|r| r.enabled) {
+            let model_matches = rule.model_patterns.is_empty()
+                || rule.model_patterns.iter().any(|p| glob_match(p, model));
+            let client_matches = rule.client_tokens.is_empty()
+                || rule.client_tokens.iter().any(|p| glob_match(p, client_token));
+            if !model_matches || !client_matches {
+                continue;
+            }
+
+            for action in &rule.actions {
+                apply_action(body, action);
+            }
+            applied.push(rule.name.clone());
+        }
+
+        Ok(applied)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prepend(text: &str) -> PolicyAction {
+        PolicyAction::PrependSystemInstruction { text: text.to_string() }
+    }
+
+    #[test]
+    fn set_default_only_fills_missing_fields() {
+        let mut body = json!({ "generationConfig": { "temperature": 0.2 } });
+        apply_action(&mut body, &PolicyAction::SetDefault { path: "generationConfig.temperature".into(), value: json!(1.0) });
+        apply_action(&mut body, &PolicyAction::SetDefault { path: "generationConfig.topK".into(), value: json!(40) });
+        assert_eq!(body, json!({ "generationConfig": { "temperature": 0.2, "topK": 40 } }));
+    }
+
+    #[test]
+    fn force_creates_missing_parents() {
+        let mut body = json!({});
+        apply_action(&mut body, &PolicyAction::Force { path: "generationConfig.candidateCount".into(), value: json!(1) });
+        assert_eq!(body, json!({ "generationConfig": { "candidateCount": 1 } }));
+    }
+
+    #[test]
+    fn clamp_keeps_integers_and_matches_snake_case() {
+        let mut body = json!({ "generation_config": { "max_output_tokens": 9000, "temperature": 3.5 } });
+        apply_action(&mut body, &PolicyAction::Clamp { path: "generationConfig.maxOutputTokens".into(), min: None, max: Some(2048.0) });
+        apply_action(&mut body, &PolicyAction::Clamp { path: "generationConfig.temperature".into(), min: Some(0.0), max: Some(2.0) });
+        assert_eq!(body, json!({ "generation_config": { "max_output_tokens": 2048, "temperature": 2.0 } }));
+    }
+
+    #[test]
+    fn strip_ignores_missing_paths() {
+        let mut body = json!({ "safetySettings": [], "contents": [] });
+        apply_action(&mut body, &PolicyAction::Strip { path: "safetySettings".into() });
+        apply_action(&mut body, &PolicyAction::Strip { path: "generationConfig.topP".into() });
+        assert_eq!(body, json!({ "contents": [] }));
+    }
+
+    #[test]
+    fn prepend_system_instruction_inserts_first_part() {
+        let mut body = json!({ "systemInstruction": { "parts": [{ "text": "client" }] } });
+        apply_action(&mut body, &prepend("policy"));
+        assert_eq!(body["systemInstruction"]["parts"], json!([{ "text": "policy" }, { "text": "client" }]));
+
+        let mut body = json!({ "system_instruction": { "role": "system" } });
+        apply_action(&mut body, &prepend("policy"));
+        assert_eq!(body, json!({ "system_instruction": { "role": "system", "parts": [{ "text": "policy" }] } }));
+    }
+
+    #[test]
+    fn prepend_system_instruction_replaces_non_object_values() {
+        for malformed in [json!("be nice"), json!(["a", "b"]), json!(42), json!(null)] {
+            let mut body = json!({ "systemInstruction": malformed });
+            apply_action(&mut body, &prepend("policy"));
+            assert_eq!(body["systemInstruction"], json!({ "parts": [{ "text": "policy" }] }));
+        }
+
+        let mut body = json!({ "systemInstruction": { "parts": "oops" } });
+        apply_action(&mut body, &prepend("policy"));
+        assert_eq!(body["systemInstruction"]["parts"], json!([{ "text": "policy" }]));
+    }
+}
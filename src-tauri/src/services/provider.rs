@@ -0,0 +1,375 @@
+use crate::models::{ApiKey, CredentialType, Provider, ProviderType};
+#[cfg(feature = "desktop")]
+use crate::models::{CreateProviderRequest, UpdateProviderRequest};
+use sqlx::SqlitePool;
+use anyhow::Result;
+use chrono::Utc;
+#[cfg(feature = "desktop")]
+use anyhow::anyhow;
+#[cfg(feature = "desktop")]
+use chrono::SecondsFormat;
+use std::borrow::Cow;
+use crate::utils::model_from_path;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+pub const DEFAULT_PROVIDER_BASE_URL: &str = "https://generativelanguage.googleapis.com";
+pub const DEFAULT_PROVIDER_TIMEOUT_SECS: u64 = 60;
+pub const DEFAULT_VERTEX_LOCATION: &str = "us-central1";
+
+/// Vertex AI 区域会拼进端点的主机名，只允许小写字母、数字和连字符
+pub fn validate_vertex_location(location: &str) -> Result<()> {
+    let valid = !location.is_empty()
+        && location.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-');
+    if !valid {
+        anyhow::bail!("Invalid Vertex AI location '{}'", location);
+    }
+    Ok(())
+}
+
+#[cfg(feature = "desktop")]
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// 一次请求期间使用的供应商快照，避免每次重试都查询数据库
+pub struct ProviderDirectory {
+    providers: HashMap<Uuid, Provider>,
+    default_provider: Provider,
+}
+
+impl ProviderDirectory {
+    /// 密钥未绑定供应商、绑定的供应商已被删除或类型与凭据不符时使用默认供应商；
+    /// Vertex AI 服务账号没有绑定 Vertex 供应商时按所在区域生成官方端点
+    pub fn for_key(&self, api_key: &ApiKey) -> Cow<'_, Provider> {
+        let assigned = api_key.provider_id.and_then(|id| self.providers.get(&id));
+
+        match api_key.credential_type {
+            CredentialType::ApiKey => Cow::Borrowed(
+                assigned
+                    .filter(|p| p.provider_type != ProviderType::Vertex)
+                    .unwrap_or(&self.default_provider),
+            ),
+            CredentialType::VertexServiceAccount => match assigned.filter(|p| p.provider_type == ProviderType::Vertex) {
+                Some(provider) => Cow::Borrowed(provider),
+                None => Cow::Owned(ProviderService::builtin_vertex(
+                    api_key.vertex_location.as_deref().unwrap_or(DEFAULT_VERTEX_LOCATION),
+                )),
+            },
+        }
+    }
+
+    /// 按模型名前缀选出处理该路径的 OpenAI 兼容供应商，不匹配时由 Gemini 上游处理
+    pub fn openai_for_path(&self, path: &str) -> Option<&Provider> {
+        let model = model_from_path(path)?;
+        self.providers
+            .values()
+            .filter(|p| p.provider_type == ProviderType::OpenAi)
+            .find(|p| p.serves_model(model))
+    }
+
+    /// 处理该路径的上游供应商：按模型前缀路由到的 OpenAI 兼容供应商，否则为默认供应商
+    pub fn route_for_path(&self, path: &str) -> &Provider {
+        self.openai_for_path(path).unwrap_or(&self.default_provider)
+    }
+
+    /// 判断密钥能否处理该路径：按模型前缀路由到 OpenAI 兼容供应商时只用其下的密钥，
+    /// 否则排除 OpenAI 兼容密钥；Vertex AI 只支持针对单个模型的调用
+    pub fn accepts(&self, api_key: &ApiKey, path: &str) -> bool {
+        let provider = self.for_key(api_key);
+
+        if let Some(target) = self.openai_for_path(path) {
+            return provider.id == target.id;
+        }
+
+        match provider.provider_type {
+            ProviderType::OpenAi => false,
+            ProviderType::Vertex => provider.vertex_url(path, "", "").is_some(),
+            ProviderType::Gemini => true,
+        }
+    }
+}
+
+pub struct ProviderService {
+    pool: SqlitePool,
+}
+
+impl ProviderService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn create_provider(&self, request: CreateProviderRequest) -> Result<Provider> {
+        Self::validate_base_url(&request.base_url)?;
+        if let Some(token_url) = &request.token_url {
+            Self::validate_base_url(token_url)?;
+        }
+
+        let provider_id = Uuid::new_v4();
+        let now = to_js_compatible_timestamp(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO providers (id, name, provider_type, base_url, api_version_map, extra_headers, timeout_secs, token_url, model_prefixes, is_default, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?, ?)
+            "#,
+        )
+        .bind(provider_id.to_string())
+        .bind(&request.name)
+        .bind(request.provider_type.as_str())
+        .bind(request.base_url.trim_end_matches('/'))
+        .bind(serde_json::to_string(&request.api_version_map)?)
+        .bind(serde_json::to_string(&request.extra_headers)?)
+        .bind(request.timeout_secs.unwrap_or(DEFAULT_PROVIDER_TIMEOUT_SECS).max(1) as i64)
+        .bind(&request.token_url)
+        .bind(serde_json::to_string(&request.model_prefixes)?)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_provider_by_id(provider_id).await?
+            .ok_or_else(|| anyhow!("Provider not found after creation"))
+    }
+
+    pub async fn get_all_providers(&self) -> Result<Vec<Provider>> {
+        let providers: Vec<Provider> = sqlx::query_as(
+            r#"
+            SELECT id, name, provider_type, base_url, api_version_map, extra_headers, timeout_secs, token_url, model_prefixes, is_default, created_at, updated_at
+            FROM providers ORDER BY is_default DESC, created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(providers)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_provider_by_id(&self, provider_id: Uuid) -> Result<Option<Provider>> {
+        let provider: Option<Provider> = sqlx::query_as(
+            r#"
+            SELECT id, name, provider_type, base_url, api_version_map, extra_headers, timeout_secs, token_url, model_prefixes, is_default, created_at, updated_at
+            FROM providers WHERE id = ?
+            "#,
+        )
+        .bind(provider_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(provider)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn update_provider(&self, provider_id: Uuid, request: UpdateProviderRequest) -> Result<Option<Provider>> {
+        let mut query_parts = Vec::new();
+        let mut bind_values = Vec::new();
+
+        if let Some(name) = &request.name {
+            query_parts.push("name = ?");
+            bind_values.push(name.clone());
+        }
+
+        if let Some(base_url) = &request.base_url {
+            Self::validate_base_url(base_url)?;
+            query_parts.push("base_url = ?");
+            bind_values.push(base_url.trim_end_matches('/').to_string());
+        }
+
+        if let Some(api_version_map) = &request.api_version_map {
+            query_parts.push("api_version_map = ?");
+            bind_values.push(serde_json::to_string(api_version_map)?);
+        }
+
+        if let Some(extra_headers) = &request.extra_headers {
+            query_parts.push("extra_headers = ?");
+            bind_values.push(serde_json::to_string(extra_headers)?);
+        }
+
+        if let Some(timeout_secs) = request.timeout_secs {
+            query_parts.push("timeout_secs = ?");
+            bind_values.push(timeout_secs.max(1).to_string());
+        }
+
+        if let Some(token_url) = &request.token_url {
+            Self::validate_base_url(token_url)?;
+            query_parts.push("token_url = ?");
+            bind_values.push(token_url.clone());
+        }
+
+        if let Some(model_prefixes) = &request.model_prefixes {
+            query_parts.push("model_prefixes = ?");
+            bind_values.push(serde_json::to_string(model_prefixes)?);
+        }
+
+        if query_parts.is_empty() {
+            return self.get_provider_by_id(provider_id).await;
+        }
+
+        query_parts.push("updated_at = ?");
+        bind_values.push(to_js_compatible_timestamp(Utc::now()));
+
+        let query = format!(
+            "UPDATE providers SET {} WHERE id = ?",
+            query_parts.join(", ")
+        );
+
+        let mut query_builder = sqlx::query(&query);
+        for value in bind_values {
+            query_builder = query_builder.bind(value);
+        }
+        query_builder = query_builder.bind(provider_id.to_string());
+
+        query_builder.execute(&self.pool).await?;
+
+        self.get_provider_by_id(provider_id).await
+    }
+
+    /// 删除供应商，绑定到它的密钥回退到默认供应商；默认供应商不可删除
+    #[cfg(feature = "desktop")]
+    pub async fn delete_provider(&self, provider_id: Uuid) -> Result<bool> {
+        let provider = match self.get_provider_by_id(provider_id).await? {
+            Some(provider) => provider,
+            None => return Ok(false),
+        };
+
+        if provider.is_default {
+            return Err(anyhow!("The default provider cannot be deleted"));
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE api_keys SET provider_id = NULL WHERE provider_id = ?")
+            .bind(provider_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        let result = sqlx::query("DELETE FROM providers WHERE id = ?")
+            .bind(provider_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 设置 API 密钥默认使用的供应商，只能是 Gemini 类型
+    #[cfg(feature = "desktop")]
+    pub async fn set_default_provider(&self, provider_id: Uuid) -> Result<bool> {
+        match self.get_provider_by_id(provider_id).await? {
+            Some(provider) if provider.provider_type != ProviderType::Gemini => {
+                return Err(anyhow!("Only Gemini providers can be the default provider"));
+            }
+            Some(_) => {}
+            None => return Ok(false),
+        }
+
+        let mut tx = self.pool.begin().await?;
+
+        sqlx::query("UPDATE providers SET is_default = 0 WHERE is_default = 1")
+            .execute(&mut *tx)
+            .await?;
+
+        sqlx::query("UPDATE providers SET is_default = 1, updated_at = ? WHERE id = ?")
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .bind(provider_id.to_string())
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+
+        Ok(true)
+    }
+
+    pub async fn load_directory(&self) -> Result<ProviderDirectory> {
+        let providers = self.get_all_providers().await?;
+        let default_provider = providers.iter()
+            .find(|p| p.is_default && p.provider_type == ProviderType::Gemini)
+            .cloned()
+            .unwrap_or_else(Self::builtin_default);
+
+        Ok(ProviderDirectory {
+            providers: providers.into_iter().map(|p| (p.id, p)).collect(),
+            default_provider,
+        })
+    }
+
+    /// 数据库中没有默认供应商时（例如被手动清空）使用的内置 AI Studio 配置
+    fn builtin_default() -> Provider {
+        let now = Utc::now();
+        Provider {
+            id: Uuid::nil(),
+            name: "Google AI Studio".to_string(),
+            provider_type: ProviderType::Gemini,
+            base_url: DEFAULT_PROVIDER_BASE_URL.to_string(),
+            api_version_map: HashMap::from([("v1".to_string(), "v1beta".to_string())]),
+            extra_headers: HashMap::new(),
+            timeout_secs: DEFAULT_PROVIDER_TIMEOUT_SECS,
+            token_url: None,
+            model_prefixes: Vec::new(),
+            is_default: true,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    /// 指定区域的 Vertex AI 官方端点，`global` 区域没有区域前缀；
+    /// 区域无效时不拼接主机名，构造请求时会拒绝该区域
+    fn builtin_vertex(location: &str) -> Provider {
+        let now = Utc::now();
+        let base_url = if location != "global" && validate_vertex_location(location).is_ok() {
+            format!("https://{}-aiplatform.googleapis.com", location)
+        } else {
+            "https://aiplatform.googleapis.com".to_string()
+        };
+
+        Provider {
+            id: Uuid::nil(),
+            name: format!("Vertex AI ({})", location),
+            provider_type: ProviderType::Vertex,
+            base_url,
+            api_version_map: HashMap::new(),
+            extra_headers: HashMap::new(),
+            timeout_secs: DEFAULT_PROVIDER_TIMEOUT_SECS,
+            token_url: None,
+            model_prefixes: Vec::new(),
+            is_default: false,
+            created_at: now,
+            updated_at: now,
+        }
+    }
+
+    #[cfg(feature = "desktop")]
+    fn validate_base_url(base_url: &str) -> Result<()> {
+        let url = url::Url::parse(base_url)
+            .map_err(|e| anyhow!("Invalid base URL '{}': {}", base_url, e))?;
+        if url.scheme() != "http" && url.scheme() != "https" {
+            return Err(anyhow!("Base URL must use http or https: {}", base_url));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vertex_location_only_allows_region_names() {
+        assert!(validate_vertex_location("us-central1").is_ok());
+        assert!(validate_vertex_location("global").is_ok());
+        for location in ["", "US-CENTRAL1", "evil.com/", "a.b", "x@y", "us central1"] {
+            assert!(validate_vertex_location(location).is_err(), "{location:?}");
+        }
+    }
+
+    #[test]
+    fn invalid_location_never_reaches_the_host_name() {
+        assert_eq!(ProviderService::builtin_vertex("europe-west4").base_url, "https://europe-west4-aiplatform.googleapis.com");
+        assert_eq!(ProviderService::builtin_vertex("global").base_url, "https://aiplatform.googleapis.com");
+        assert_eq!(ProviderService::builtin_vertex("evil.com/x").base_url, "https://aiplatform.googleapis.com");
+    }
+}
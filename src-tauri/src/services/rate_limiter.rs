@@ -0,0 +1,235 @@
+use crate::models::{RateLimitRule, RateLimitSettings};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+// 并发名额被占满时给客户端的重试建议
+const CONCURRENCY_RETRY_AFTER: Duration = Duration::from_secs(1);
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Default)]
+struct LimiterState {
+    buckets: HashMap<String, Bucket>,
+    in_flight: HashMap<String, u32>,
+    queued: u32,
+}
+
+// 限流状态在进程内所有请求之间共享
+static STATE: LazyLock<Mutex<LimiterState>> = LazyLock::new(|| Mutex::new(LimiterState::default()));
+// 有请求结束、释放并发名额时唤醒排队的请求
+static RELEASED: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// 超出限流时的错误，中间件据此返回 429 和 Retry-After
+#[derive(Debug, thiserror::Error)]
+#[error("Rate limit exceeded for {scope}, retry after {}s", retry_after.as_secs().max(1))]
+pub struct RateLimitedError {
+    pub scope: String,
+    pub retry_after: Duration,
+}
+
+/// 通过限流的请求持有的并发名额，释放时归还
+pub struct RateLimitPermit {
+    scopes: Vec<String>,
+}
+
+impl Drop for RateLimitPermit {
+    fn drop(&mut self) {
+        let mut state = STATE.lock().unwrap();
+        for scope in &self.scopes {
+            if let Some(count) = state.in_flight.get_mut(scope) {
+                *count = count.saturating_sub(1);
+            }
+        }
+        drop(state);
+        RELEASED.notify_waiters();
+    }
+}
+
+struct QueueSlot;
+
+impl Drop for QueueSlot {
+    fn drop(&mut self) {
+        let mut state = STATE.lock().unwrap();
+        state.queued = state.queued.saturating_sub(1);
+    }
+}
+
+/// 按令牌桶补充后检查是否还有令牌和并发名额，不满足时返回建议的等待时间
+fn check_scope(state: &mut LimiterState, scope: &str, rule: &RateLimitRule, now: Instant) -> Option<Duration> {
+    if rule.requests_per_minute > 0 {
+        let rate_per_sec = rule.requests_per_minute as f64 / 60.0;
+        let capacity = rule.capacity().max(1.0);
+        let bucket = state.buckets.entry(scope.to_string()).or_insert(Bucket { tokens: capacity, last_refill: now });
+
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate_per_sec).min(capacity);
+        bucket.last_refill = now;
+
+        if bucket.tokens < 1.0 {
+            return Some(Duration::from_secs_f64((1.0 - bucket.tokens) / rate_per_sec));
+        }
+    }
+
+    if rule.max_concurrent > 0 && state.in_flight.get(scope).copied().unwrap_or(0) >= rule.max_concurrent {
+        return Some(CONCURRENCY_RETRY_AFTER);
+    }
+
+    None
+}
+
+/// 入站请求的令牌桶限流和并发上限，分为全局和每个客户端两级
+pub struct RateLimiterService;
+
+impl Default for RateLimiterService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimiterService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    fn try_acquire(&self, client: &str, settings: &RateLimitSettings) -> Result<RateLimitPermit, RateLimitedError> {
+        let scopes = [
+            ("global".to_string(), &settings.global),
+            (format!("client:{}", client), settings.rule_for(client)),
+        ];
+
+        let now = Instant::now();
+        let mut state = STATE.lock().unwrap();
+
+        // 两级都满足才放行，避免一级通过、另一级拒绝时白白消耗令牌
+        for (scope, rule) in &scopes {
+            if let Some(retry_after) = check_scope(&mut state, scope, rule, now) {
+                return Err(RateLimitedError { scope: scope.clone(), retry_after });
+            }
+        }
+
+        for (scope, rule) in &scopes {
+            if rule.requests_per_minute > 0
+                && let Some(bucket) = state.buckets.get_mut(scope)
+            {
+                bucket.tokens -= 1.0;
+            }
+            *state.in_flight.entry(scope.clone()).or_insert(0) += 1;
+        }
+
+        Ok(RateLimitPermit { scopes: scopes.into_iter().map(|(scope, _)| scope).collect() })
+    }
+
+    /// 取得放行许可；配置了排队时在限定时间内等待令牌补充或并发名额释放
+    pub async fn acquire(&self, client: &str, settings: &RateLimitSettings) -> Result<RateLimitPermit, RateLimitedError> {
+        let deadline = Instant::now() + Duration::from_millis(settings.queue_timeout_ms);
+        let mut queue_slot = None;
+
+        loop {
+            let notified = RELEASED.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let limited = match self.try_acquire(client, settings) {
+                Ok(permit) => return Ok(permit),
+                Err(limited) => limited,
+            };
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(limited);
+            }
+
+            if queue_slot.is_none() {
+                let mut state = STATE.lock().unwrap();
+                if state.queued >= settings.max_queued {
+                    return Err(limited);
+                }
+                state.queued += 1;
+                queue_slot = Some(QueueSlot);
+            }
+
+            tokio::select! {
+                _ = tokio::time::sleep(limited.retry_after.min(remaining)) => {}
+                _ = &mut notified => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 状态在所有测试之间共享，每个测试使用自己的客户端名称，且不设全局限制
+    fn settings(client: &str, rule: RateLimitRule) -> RateLimitSettings {
+        RateLimitSettings {
+            enabled: true,
+            client_overrides: HashMap::from([(client.to_string(), rule)]),
+            ..RateLimitSettings::default()
+        }
+    }
+
+    #[test]
+    fn bucket_allows_burst_then_rejects() {
+        let settings = settings("bucket-burst", RateLimitRule { requests_per_minute: 60, burst: 2, max_concurrent: 0 });
+        let limiter = RateLimiterService::new();
+        drop(limiter.try_acquire("bucket-burst", &settings).unwrap());
+        drop(limiter.try_acquire("bucket-burst", &settings).unwrap());
+
+        let limited = limiter.try_acquire("bucket-burst", &settings).err().unwrap();
+        assert_eq!(limited.scope, "client:bucket-burst");
+        assert!(limited.retry_after > Duration::ZERO && limited.retry_after <= Duration::from_secs(1));
+    }
+
+    #[test]
+    fn bucket_refills_at_the_configured_rate() {
+        let rule = RateLimitRule { requests_per_minute: 60, burst: 1, max_concurrent: 0 };
+        let start = Instant::now();
+        let mut state = LimiterState::default();
+        state.buckets.insert("refill".to_string(), Bucket { tokens: 0.0, last_refill: start });
+
+        let retry_after = check_scope(&mut state, "refill", &rule, start + Duration::from_millis(400)).unwrap();
+        assert!((retry_after.as_secs_f64() - 0.6).abs() < 1e-6);
+        assert_eq!(check_scope(&mut state, "refill", &rule, start + Duration::from_secs(1)), None);
+        // 补充不会超过桶容量
+        assert_eq!(check_scope(&mut state, "refill", &rule, start + Duration::from_secs(60)), None);
+        assert_eq!(state.buckets["refill"].tokens, 1.0);
+    }
+
+    #[test]
+    fn concurrency_cap_is_released_with_the_permit() {
+        let settings = settings("concurrency-cap", RateLimitRule { requests_per_minute: 0, burst: 0, max_concurrent: 1 });
+        let limiter = RateLimiterService::new();
+        let permit = limiter.try_acquire("concurrency-cap", &settings).unwrap();
+
+        let limited = limiter.try_acquire("concurrency-cap", &settings).err().unwrap();
+        assert_eq!(limited.retry_after, CONCURRENCY_RETRY_AFTER);
+
+        drop(permit);
+        assert!(limiter.try_acquire("concurrency-cap", &settings).is_ok());
+    }
+
+    #[tokio::test]
+    async fn queued_request_gets_the_released_slot() {
+        let settings = RateLimitSettings {
+            queue_timeout_ms: 5_000,
+            max_queued: 1,
+            ..settings("concurrency-queue", RateLimitRule { requests_per_minute: 0, burst: 0, max_concurrent: 1 })
+        };
+        let limiter = RateLimiterService::new();
+        let permit = limiter.try_acquire("concurrency-queue", &settings).unwrap();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            drop(permit);
+        });
+
+        let started = Instant::now();
+        assert!(limiter.acquire("concurrency-queue", &settings).await.is_ok());
+        assert!(started.elapsed() < CONCURRENCY_RETRY_AFTER);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::models::RequestLogResponse;
+use crate::services::RequestSchedulerService;
+use sqlx::SqlitePool;
+use anyhow::Result;
+use chrono::{Timelike, TimeZone};
+
+const LOG_COLUMNS: &str = r#"
+    SELECT
+        rl.id,
+        ak.name as api_key_name,
+        rl.method,
+        rl.path,
+        rl.status_code,
+        rl.response_time_ms,
+        rl.request_body,
+        rl.response_body,
+        rl.fallback_from,
+        rl.applied_policies,
+        rl.cache_hit,
+        rl.coalesced,
+        ct.name as client_token_name,
+        rl.total_tokens,
+        rl.created_at
+    FROM request_logs rl
+    JOIN api_keys ak ON rl.api_key_id = ak.id
+    LEFT JOIN client_tokens ct ON rl.client_token_id = ct.id
+    ORDER BY rl.created_at DESC
+"#;
+
+/// 今日统计的起点：北京时间下午 3 点（UTC 7 点）重置
+fn today_reset_time() -> String {
+    let now = chrono::Utc::now();
+    let china_tz = chrono::FixedOffset::east_opt(8 * 3600).unwrap(); // UTC+8
+    let now_china = now.with_timezone(&china_tz);
+
+    // Get today's reset time (3 PM China time = 7 AM UTC)
+    let today_reset = if now_china.hour() >= 15 {
+        // If it's after 3 PM, today's reset already happened
+        now_china.date_naive().and_hms_opt(15, 0, 0).unwrap()
+    } else {
+        // If it's before 3 PM, use yesterday's reset
+        (now_china.date_naive() - chrono::Duration::days(1)).and_hms_opt(15, 0, 0).unwrap()
+    };
+
+    let reset_time_utc = china_tz.from_local_datetime(&today_reset).unwrap().with_timezone(&chrono::Utc);
+    reset_time_utc.to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+}
+
+/// 请求日志和使用统计的查询，管理界面和管理 API 共用
+pub struct RequestLogService {
+    pool: SqlitePool,
+}
+
+impl RequestLogService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 最近的请求日志
+    #[cfg(feature = "desktop")]
+    pub async fn recent(&self, limit: i32) -> Result<Vec<RequestLogResponse>> {
+        let logs = sqlx::query_as(&format!("{} LIMIT ?", LOG_COLUMNS))
+            .bind(limit)
+            .fetch_all(&self.pool)
+            .await?;
+        Ok(logs)
+    }
+
+    /// 按时间倒序分页，返回当前页和日志总数
+    pub async fn paginated(&self, page: u32, per_page: u32) -> Result<(Vec<RequestLogResponse>, u32)> {
+        let offset = page.saturating_sub(1) * per_page;
+
+        // 获取总数
+        let (total_count,): (i64,) = sqlx::query_as("SELECT COUNT(*) FROM request_logs")
+            .fetch_one(&self.pool)
+            .await?;
+
+        // 获取分页数据
+        let logs = sqlx::query_as(&format!("{} LIMIT ? OFFSET ?", LOG_COLUMNS))
+            .bind(per_page)
+            .bind(offset)
+            .fetch_all(&self.pool)
+            .await?;
+
+        Ok((logs, total_count as u32))
+    }
+
+    /// 密钥自今日重置以来实际发往上游的请求数（不含缓存命中和合并的请求）
+    pub async fn api_key_today_requests(&self, api_key_id: &str) -> Result<i64> {
+        let (count,): (i64,) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*) as today_requests
+            FROM request_logs rl
+            WHERE rl.api_key_id = ? AND rl.created_at >= ? AND rl.cache_hit = 0 AND rl.coalesced = 0
+            "#
+        )
+        .bind(api_key_id)
+        .bind(today_reset_time())
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(count)
+    }
+
+    /// 总体和今日的使用统计
+    pub async fn usage_stats(&self) -> Result<serde_json::Value> {
+        let reset_time_str = today_reset_time();
+
+        // Get total stats
+        let (total_requests, total_usage, avg_response_time): (i64, i64, Option<f64>) = sqlx::query_as(
+            r#"
+            SELECT
+                COUNT(*) as total_requests,
+                SUM(ak.usage_count) as total_usage,
+                AVG(rl.response_time_ms) as avg_response_time
+            FROM request_logs rl
+            JOIN api_keys ak ON rl.api_key_id = ak.id
+            "#
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Get today's stats (since the last 3 PM reset)
+        let (today_requests, today_avg_response_time): (i64, Option<f64>) = sqlx::query_as(
+            r#"
+            SELECT
+                COUNT(*) as today_requests,
+                AVG(rl.response_time_ms) as today_avg_response_time
+            FROM request_logs rl
+            WHERE rl.created_at >= ?
+            "#
+        )
+        .bind(&reset_time_str)
+        .fetch_one(&self.pool)
+        .await?;
+
+        // Get today's requests per API key
+        let api_key_today_requests: Result<Vec<(String, i64)>, sqlx::Error> = sqlx::query_as(
+            r#"
+            SELECT rl.api_key_id, COUNT(*) as today_requests
+            FROM request_logs rl
+            WHERE rl.created_at >= ?
+            GROUP BY rl.api_key_id
+            "#
+        )
+        .bind(&reset_time_str)
+        .fetch_all(&self.pool)
+        .await;
+
+        let api_key_today_map: std::collections::HashMap<String, i64> = match api_key_today_requests {
+            Ok(results) => results.into_iter().collect(),
+            Err(_) => std::collections::HashMap::new(),
+        };
+
+        Ok(serde_json::json!({
+            "totalRequests": total_requests,
+            "totalUsage": total_usage,
+            "avgResponseTime": avg_response_time.unwrap_or(0.0),
+            "todayRequests": today_requests,
+            "todayAvgResponseTime": today_avg_response_time.unwrap_or(0.0),
+            "resetTime": reset_time_str,
+            "apiKeyTodayRequests": api_key_today_map,
+            "keyQueue": RequestSchedulerService::global_stats()
+        }))
+    }
+}
@@ -0,0 +1,282 @@
+use crate::models::{PriorityQueueStats, RequestPriority, SchedulerStats};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::futures::Notified;
+use tokio::sync::Notify;
+
+/// 客户端用该请求头声明优先级：`interactive`、`normal` 或 `batch`
+pub const PRIORITY_HEADER: &str = "x-request-priority";
+
+/// 各优先级轮流取得密钥的权重，低优先级也会按比例得到密钥，不会一直等到超时
+fn weight(priority: RequestPriority) -> i64 {
+    match priority {
+        RequestPriority::Interactive => 4,
+        RequestPriority::Normal => 2,
+        RequestPriority::Batch => 1,
+    }
+}
+
+#[derive(Default)]
+struct WaitStats {
+    served: u64,
+    timed_out: u64,
+    total_wait: Duration,
+    max_wait: Duration,
+}
+
+struct Waiter {
+    seq: u64,
+    /// 上次尝试时没有可用密钥，在此之前不会再尝试，排在后面的请求可以先取
+    blocked_until: Option<Instant>,
+}
+
+impl Waiter {
+    fn is_blocked(&self, now: Instant) -> bool {
+        self.blocked_until.is_some_and(|until| until > now)
+    }
+}
+
+#[derive(Default)]
+struct SchedulerState {
+    /// 每个优先级一个先到先得的队列
+    queues: HashMap<RequestPriority, VecDeque<Waiter>>,
+    /// 平滑加权轮询的当前值，只在请求取得密钥时推进
+    credits: HashMap<RequestPriority, i64>,
+    next_seq: u64,
+    stats: HashMap<RequestPriority, WaitStats>,
+}
+
+impl SchedulerState {
+    fn depth(&self, priority: RequestPriority) -> usize {
+        self.queues.get(&priority).map_or(0, VecDeque::len)
+    }
+
+    /// 平滑加权轮询选出下一个优先级：各非空队列加上权重，取最大者并减去权重之和；
+    /// `served` 指定时改为推进该优先级（排在前面的请求被跳过时）
+    fn advance(credits: &mut HashMap<RequestPriority, i64>, pending: &[RequestPriority], served: Option<RequestPriority>) -> Option<RequestPriority> {
+        let total: i64 = pending.iter().map(|&p| weight(p)).sum();
+        let mut chosen = None;
+        for &priority in pending {
+            let credit = credits.entry(priority).or_default();
+            *credit += weight(priority);
+            if chosen.is_none_or(|(_, best)| *credit > best) {
+                chosen = Some((priority, *credit));
+            }
+        }
+        let chosen = served.or(chosen.map(|(priority, _)| priority))?;
+        *credits.entry(chosen).or_default() -= total;
+        Some(chosen)
+    }
+
+    /// 按轮询顺序排列的全部排队请求
+    fn order(&self) -> Vec<(RequestPriority, &Waiter)> {
+        let mut credits = self.credits.clone();
+        let mut cursors: HashMap<RequestPriority, usize> = HashMap::new();
+        let mut order = Vec::new();
+        loop {
+            let pending: Vec<RequestPriority> = RequestPriority::ALL
+                .into_iter()
+                .filter(|p| cursors.get(p).copied().unwrap_or(0) < self.depth(*p))
+                .collect();
+            let Some(priority) = Self::advance(&mut credits, &pending, None) else {
+                return order;
+            };
+            let cursor = cursors.entry(priority).or_default();
+            order.push((priority, &self.queues[&priority][*cursor]));
+            *cursor += 1;
+        }
+    }
+
+    fn waiter_mut(&mut self, entry: (RequestPriority, u64)) -> Option<&mut Waiter> {
+        self.queues.get_mut(&entry.0)?.iter_mut().find(|w| w.seq == entry.1)
+    }
+}
+
+// 排队状态在进程内所有请求之间共享
+static STATE: LazyLock<Mutex<SchedulerState>> = LazyLock::new(|| Mutex::new(SchedulerState::default()));
+// 有请求离开队列或暂时取不到密钥时唤醒排队的请求
+static WAKE: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+/// 排队中的请求持有的位置，释放时离开队列并唤醒其余请求
+pub struct QueueTicket {
+    entry: (RequestPriority, u64),
+    enqueued_at: Instant,
+}
+
+impl QueueTicket {
+    /// 轮到该请求尝试取密钥：排在前面的请求都暂时取不到密钥，且自己没有在等待冷却
+    pub fn may_acquire(&self) -> bool {
+        let state = STATE.lock().unwrap();
+        let now = Instant::now();
+        for (priority, waiter) in state.order() {
+            if (priority, waiter.seq) == self.entry {
+                return !waiter.is_blocked(now);
+            }
+            if !waiter.is_blocked(now) {
+                return false;
+            }
+        }
+        false
+    }
+
+    /// 暂时取不到密钥，`retry_after` 内不再尝试，让排在后面、可能使用其他密钥的请求先取
+    pub fn block_for(&self, retry_after: Duration) {
+        if let Some(waiter) = STATE.lock().unwrap().waiter_mut(self.entry) {
+            waiter.blocked_until = Some(Instant::now() + retry_after);
+        }
+        WAKE.notify_waiters();
+    }
+
+    /// 距离可以再次尝试的剩余时间
+    pub fn blocked_remaining(&self) -> Option<Duration> {
+        let mut state = STATE.lock().unwrap();
+        let until = state.waiter_mut(self.entry)?.blocked_until?;
+        Some(until.saturating_duration_since(Instant::now())).filter(|d| !d.is_zero())
+    }
+
+    pub fn waited(&self) -> Duration {
+        self.enqueued_at.elapsed()
+    }
+
+    /// 记录本次等待的结果并离开队列；取得密钥时推进轮询
+    pub fn finish(self, timed_out: bool) {
+        let waited = self.waited();
+        let mut state = STATE.lock().unwrap();
+        if !timed_out {
+            let pending: Vec<RequestPriority> = RequestPriority::ALL
+                .into_iter()
+                .filter(|p| state.depth(*p) > 0)
+                .collect();
+            SchedulerState::advance(&mut state.credits, &pending, Some(self.entry.0));
+        }
+        let stats = state.stats.entry(self.entry.0).or_default();
+        if timed_out {
+            stats.timed_out += 1;
+        } else {
+            stats.served += 1;
+        }
+        stats.total_wait += waited;
+        stats.max_wait = stats.max_wait.max(waited);
+    }
+}
+
+impl Drop for QueueTicket {
+    fn drop(&mut self) {
+        let mut state = STATE.lock().unwrap();
+        if let Some(queue) = state.queues.get_mut(&self.entry.0) {
+            queue.retain(|w| w.seq != self.entry.1);
+        }
+        // 队列清空后从头轮询，不保留之前积累的差额
+        if RequestPriority::ALL.iter().all(|p| state.depth(*p) == 0) {
+            state.credits.clear();
+        }
+        drop(state);
+        WAKE.notify_waiters();
+    }
+}
+
+/// 所有密钥都在冷却时，请求排队等待空闲密钥；各优先级按权重轮流取得密钥，同一优先级先到先得
+pub struct RequestSchedulerService;
+
+impl Default for RequestSchedulerService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestSchedulerService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// 没有请求在排队，新请求可以直接取密钥
+    pub fn is_idle(&self) -> bool {
+        let state = STATE.lock().unwrap();
+        RequestPriority::ALL.iter().all(|p| state.depth(*p) == 0)
+    }
+
+    pub fn enqueue(&self, priority: RequestPriority) -> QueueTicket {
+        let mut state = STATE.lock().unwrap();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.queues.entry(priority).or_default().push_back(Waiter { seq, blocked_until: None });
+        QueueTicket { entry: (priority, seq), enqueued_at: Instant::now() }
+    }
+
+    /// 排队状态变化的通知；应在检查 `may_acquire` 之前登记，避免错过通知
+    pub fn notified(&self) -> Notified<'static> {
+        WAKE.notified()
+    }
+
+    /// 进程内的排队统计，所有代理请求共享
+    pub fn global_stats() -> SchedulerStats {
+        let state = STATE.lock().unwrap();
+        let priorities: Vec<PriorityQueueStats> = RequestPriority::ALL
+            .iter()
+            .map(|&priority| {
+                let depth = state.depth(priority);
+                let (served, timed_out, average_wait_ms, max_wait_ms) = match state.stats.get(&priority) {
+                    Some(stats) => {
+                        let count = stats.served + stats.timed_out;
+                        let average = if count > 0 { stats.total_wait.as_secs_f64() * 1000.0 / count as f64 } else { 0.0 };
+                        (stats.served, stats.timed_out, average, stats.max_wait.as_millis() as u64)
+                    }
+                    None => (0, 0, 0.0, 0),
+                };
+                PriorityQueueStats { priority, depth, served, timed_out, average_wait_ms, max_wait_ms }
+            })
+            .collect();
+
+        SchedulerStats {
+            depth: priorities.iter().map(|p| p.depth).sum(),
+            priorities,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state_with(depths: [usize; 3]) -> SchedulerState {
+        let mut state = SchedulerState::default();
+        for (priority, depth) in RequestPriority::ALL.into_iter().zip(depths) {
+            for _ in 0..depth {
+                let seq = state.next_seq;
+                state.next_seq += 1;
+                state.queues.entry(priority).or_default().push_back(Waiter { seq, blocked_until: None });
+            }
+        }
+        state
+    }
+
+    #[test]
+    fn classes_take_turns_by_weight() {
+        let state = state_with([8, 8, 8]);
+        let first_seven: Vec<RequestPriority> = state.order().into_iter().take(7).map(|(p, _)| p).collect();
+        let count = |priority| first_seven.iter().filter(|p| **p == priority).count();
+        assert_eq!(count(RequestPriority::Interactive), 4);
+        assert_eq!(count(RequestPriority::Normal), 2);
+        assert_eq!(count(RequestPriority::Batch), 1);
+        assert_eq!(first_seven[0], RequestPriority::Interactive);
+    }
+
+    #[test]
+    fn same_class_is_first_come_first_served() {
+        let state = state_with([0, 3, 0]);
+        let seqs: Vec<u64> = state.order().into_iter().map(|(_, w)| w.seq).collect();
+        assert_eq!(seqs, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn serving_advances_the_rotation() {
+        let mut state = state_with([3, 0, 3]);
+        let pending = [RequestPriority::Interactive, RequestPriority::Batch];
+        // 连续服务 4 个 interactive 后应轮到 batch
+        for _ in 0..4 {
+            SchedulerState::advance(&mut state.credits, &pending, Some(RequestPriority::Interactive));
+        }
+        assert_eq!(state.order()[0].0, RequestPriority::Batch);
+    }
+}
@@ -0,0 +1,434 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// GenerateContentRequest 的结构描述，字段名使用 camelCase，校验时同时接受 snake_case 写法。
+// 未列出的字段不视为错误，以免上游新增字段后被代理拒绝。
+
+enum Schema {
+    Any,
+    String,
+    Bool,
+    Integer { min: Option<i64> },
+    Number { min: Option<f64>, max: Option<f64> },
+    Enum(&'static [&'static str]),
+    /// 以指定前缀开头的字符串，例如安全类别
+    Prefixed(&'static str),
+    Array { item: &'static Schema, min_items: usize, max_items: Option<usize> },
+    /// `one_of` 中的字段必须恰好出现一个
+    Object { fields: &'static [Field], one_of: Option<&'static [&'static str]> },
+}
+
+struct Field {
+    name: &'static str,
+    schema: &'static Schema,
+    required: bool,
+}
+
+const fn field(name: &'static str, schema: &'static Schema) -> Field {
+    Field { name, schema, required: false }
+}
+
+const fn required(name: &'static str, schema: &'static Schema) -> Field {
+    Field { name, schema, required: true }
+}
+
+static ANY: Schema = Schema::Any;
+static STRING: Schema = Schema::String;
+static BOOL: Schema = Schema::Bool;
+static INTEGER: Schema = Schema::Integer { min: None };
+static POSITIVE_INTEGER: Schema = Schema::Integer { min: Some(1) };
+static NUMBER: Schema = Schema::Number { min: None, max: None };
+static OBJECT: Schema = Schema::Object { fields: &[], one_of: None };
+static STRING_ARRAY: Schema = Schema::Array { item: &STRING, min_items: 0, max_items: None };
+
+static INLINE_DATA: Schema = Schema::Object {
+    fields: &[required("mimeType", &STRING), required("data", &STRING)],
+    one_of: None,
+};
+
+static FILE_DATA: Schema = Schema::Object {
+    fields: &[field("mimeType", &STRING), required("fileUri", &STRING)],
+    one_of: None,
+};
+
+static FUNCTION_CALL: Schema = Schema::Object {
+    fields: &[required("name", &STRING), field("args", &OBJECT), field("id", &STRING)],
+    one_of: None,
+};
+
+static FUNCTION_RESPONSE: Schema = Schema::Object {
+    fields: &[required("name", &STRING), required("response", &OBJECT), field("id", &STRING)],
+    one_of: None,
+};
+
+static EXECUTABLE_CODE: Schema = Schema::Object {
+    fields: &[required("language", &STRING), required("code", &STRING)],
+    one_of: None,
+};
+
+static CODE_EXECUTION_RESULT: Schema = Schema::Object {
+    fields: &[required("outcome", &STRING), field("output", &STRING)],
+    one_of: None,
+};
+
+const PART_DATA_FIELDS: &[&str] = &[
+    "text",
+    "inlineData",
+    "fileData",
+    "functionCall",
+    "functionResponse",
+    "executableCode",
+    "codeExecutionResult",
+];
+
+static PART: Schema = Schema::Object {
+    fields: &[
+        field("text", &STRING),
+        field("inlineData", &INLINE_DATA),
+        field("fileData", &FILE_DATA),
+        field("functionCall", &FUNCTION_CALL),
+        field("functionResponse", &FUNCTION_RESPONSE),
+        field("executableCode", &EXECUTABLE_CODE),
+        field("codeExecutionResult", &CODE_EXECUTION_RESULT),
+        field("thought", &BOOL),
+        field("thoughtSignature", &STRING),
+        field("videoMetadata", &OBJECT),
+    ],
+    one_of: Some(PART_DATA_FIELDS),
+};
+
+static PARTS: Schema = Schema::Array { item: &PART, min_items: 1, max_items: None };
+
+static ROLE: Schema = Schema::Enum(&["user", "model", "function"]);
+
+static CONTENT: Schema = Schema::Object {
+    fields: &[field("role", &ROLE), required("parts", &PARTS)],
+    one_of: None,
+};
+
+static CONTENTS: Schema = Schema::Array { item: &CONTENT, min_items: 1, max_items: None };
+
+// systemInstruction 只支持文本
+static TEXT_PART: Schema = Schema::Object {
+    fields: &[required("text", &STRING)],
+    one_of: None,
+};
+
+static SYSTEM_INSTRUCTION: Schema = Schema::Object {
+    fields: &[
+        field("role", &STRING),
+        required("parts", &Schema::Array { item: &TEXT_PART, min_items: 1, max_items: None }),
+    ],
+    one_of: None,
+};
+
+static FUNCTION_DECLARATION: Schema = Schema::Object {
+    fields: &[
+        required("name", &STRING),
+        field("description", &STRING),
+        field("parameters", &OBJECT),
+        field("parametersJsonSchema", &ANY),
+        field("response", &OBJECT),
+        field("responseJsonSchema", &ANY),
+    ],
+    one_of: None,
+};
+
+static TOOL: Schema = Schema::Object {
+    fields: &[
+        field("functionDeclarations", &Schema::Array { item: &FUNCTION_DECLARATION, min_items: 0, max_items: None }),
+        field("googleSearch", &OBJECT),
+        field("googleSearchRetrieval", &OBJECT),
+        field("codeExecution", &OBJECT),
+        field("urlContext", &OBJECT),
+    ],
+    one_of: None,
+};
+
+static THINKING_CONFIG: Schema = Schema::Object {
+    fields: &[
+        field("includeThoughts", &BOOL),
+        field("thinkingBudget", &INTEGER),
+        field("thinkingLevel", &STRING),
+    ],
+    one_of: None,
+};
+
+static GENERATION_CONFIG: Schema = Schema::Object {
+    fields: &[
+        field("stopSequences", &Schema::Array { item: &STRING, min_items: 0, max_items: Some(5) }),
+        field("responseMimeType", &STRING),
+        field("responseSchema", &OBJECT),
+        field("responseJsonSchema", &ANY),
+        field("responseModalities", &STRING_ARRAY),
+        field("candidateCount", &POSITIVE_INTEGER),
+        field("maxOutputTokens", &POSITIVE_INTEGER),
+        field("temperature", &Schema::Number { min: Some(0.0), max: Some(2.0) }),
+        field("topP", &Schema::Number { min: Some(0.0), max: Some(1.0) }),
+        field("topK", &POSITIVE_INTEGER),
+        field("seed", &INTEGER),
+        field("presencePenalty", &NUMBER),
+        field("frequencyPenalty", &NUMBER),
+        field("responseLogprobs", &BOOL),
+        field("logprobs", &INTEGER),
+        field("enableEnhancedCivicAnswers", &BOOL),
+        field("speechConfig", &OBJECT),
+        field("thinkingConfig", &THINKING_CONFIG),
+        field("mediaResolution", &STRING),
+    ],
+    one_of: None,
+};
+
+static SAFETY_SETTING: Schema = Schema::Object {
+    fields: &[
+        required("category", &Schema::Prefixed("HARM_CATEGORY_")),
+        required("threshold", &Schema::Enum(&[
+            "HARM_BLOCK_THRESHOLD_UNSPECIFIED",
+            "BLOCK_LOW_AND_ABOVE",
+            "BLOCK_MEDIUM_AND_ABOVE",
+            "BLOCK_ONLY_HIGH",
+            "BLOCK_NONE",
+            "OFF",
+        ])),
+    ],
+    one_of: None,
+};
+
+static GENERATE_CONTENT_REQUEST: Schema = Schema::Object {
+    fields: &[
+        required("contents", &CONTENTS),
+        field("systemInstruction", &SYSTEM_INSTRUCTION),
+        field("tools", &Schema::Array { item: &TOOL, min_items: 0, max_items: None }),
+        field("toolConfig", &OBJECT),
+        field("generationConfig", &GENERATION_CONFIG),
+        field("safetySettings", &Schema::Array { item: &SAFETY_SETTING, min_items: 0, max_items: None }),
+        field("cachedContent", &STRING),
+    ],
+    one_of: None,
+};
+
+// 单次请求最多报告的错误数
+const MAX_VIOLATIONS: usize = 20;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldViolation {
+    pub field: String,
+    pub description: String,
+}
+
+/// 请求体不符合 GenerateContentRequest 结构，处理器据此返回 400 INVALID_ARGUMENT 和字段路径
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid request format: {}", .violations.iter().map(|v| format!("{}: {}", v.field, v.description)).collect::<Vec<_>>().join("; "))]
+pub struct RequestValidationError {
+    pub violations: Vec<FieldViolation>,
+}
+
+fn snake_case(name: &str) -> String {
+    let mut snake = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake.push('_');
+            snake.push(c.to_ascii_lowercase());
+        } else {
+            snake.push(c);
+        }
+    }
+    snake
+}
+
+/// 按 camelCase 或 snake_case 取字段，返回实际使用的键名和值
+fn get_field<'a>(obj: &'a Map<String, Value>, name: &str) -> Option<(String, &'a Value)> {
+    if let Some(value) = obj.get(name) {
+        return Some((name.to_string(), value));
+    }
+    let snake = snake_case(name);
+    obj.get(&snake).map(|value| (snake, value))
+}
+
+fn join_path(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+struct Validator {
+    violations: Vec<FieldViolation>,
+}
+
+impl Validator {
+    fn report(&mut self, field: &str, description: String) {
+        if self.violations.len() < MAX_VIOLATIONS {
+            self.violations.push(FieldViolation {
+                field: if field.is_empty() { "(root)".to_string() } else { field.to_string() },
+                description,
+            });
+        }
+    }
+
+    fn check(&mut self, path: &str, schema: &Schema, value: &Value) {
+        match schema {
+            Schema::Any => {}
+            Schema::String => {
+                if !value.is_string() {
+                    self.report(path, "must be a string".to_string());
+                }
+            }
+            Schema::Bool => {
+                if !value.is_boolean() {
+                    self.report(path, "must be a boolean".to_string());
+                }
+            }
+            Schema::Integer { min } => match value.as_i64() {
+                None => self.report(path, "must be an integer".to_string()),
+                Some(n) => {
+                    if let Some(min) = min
+                        && n < *min
+                    {
+                        self.report(path, format!("must be at least {}", min));
+                    }
+                }
+            },
+            Schema::Number { min, max } => match value.as_f64() {
+                None => self.report(path, "must be a number".to_string()),
+                Some(n) => {
+                    if min.is_some_and(|min| n < min) || max.is_some_and(|max| n > max) {
+                        self.report(path, format!(
+                            "must be between {} and {}",
+                            min.map_or("-inf".to_string(), |m| m.to_string()),
+                            max.map_or("inf".to_string(), |m| m.to_string()),
+                        ));
+                    }
+                }
+            },
+            Schema::Enum(allowed) => match value.as_str() {
+                Some(s) if allowed.contains(&s) => {}
+                _ => self.report(path, format!("must be one of {}", allowed.join(", "))),
+            },
+            Schema::Prefixed(prefix) => match value.as_str() {
+                Some(s) if s.starts_with(prefix) => {}
+                _ => self.report(path, format!("must be a string starting with {}", prefix)),
+            },
+            Schema::Array { item, min_items, max_items } => {
+                let Some(items) = value.as_array() else {
+                    self.report(path, "must be an array".to_string());
+                    return;
+                };
+                if items.len() < *min_items {
+                    self.report(path, "must not be empty".to_string());
+                }
+                if let Some(max) = max_items
+                    && items.len() > *max
+                {
+                    self.report(path, format!("must contain at most {} items", max));
+                }
+                for (index, item_value) in items.iter().enumerate() {
+                    self.check(&format!("{}[{}]", path, index), item, item_value);
+                }
+            }
+            Schema::Object { fields, one_of } => {
+                let Some(obj) = value.as_object() else {
+                    self.report(path, "must be an object".to_string());
+                    return;
+                };
+
+                for field in fields.iter() {
+                    match get_field(obj, field.name) {
+                        Some((key, field_value)) => self.check(&join_path(path, &key), field.schema, field_value),
+                        None if field.required => self.report(&join_path(path, field.name), "is required".to_string()),
+                        None => {}
+                    }
+                }
+
+                if let Some(one_of) = one_of {
+                    let present = one_of.iter().filter(|name| get_field(obj, name).is_some()).count();
+                    if present != 1 {
+                        self.report(path, format!("must contain exactly one of {}", one_of.join(", ")));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 按 Gemini Content 结构校验 generateContent / streamGenerateContent 请求体
+pub fn validate_generate_content_request(body: &Value) -> Result<(), RequestValidationError> {
+    let mut validator = Validator { violations: Vec::new() };
+    validator.check("", &GENERATE_CONTENT_REQUEST, body);
+
+    if validator.violations.is_empty() {
+        Ok(())
+    } else {
+        Err(RequestValidationError { violations: validator.violations })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn violations(body: Value) -> Vec<(String, String)> {
+        match validate_generate_content_request(&body) {
+            Ok(()) => Vec::new(),
+            Err(e) => e.violations.into_iter().map(|v| (v.field, v.description)).collect(),
+        }
+    }
+
+    #[test]
+    fn accepts_camel_and_snake_case_fields() {
+        let camel = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": "hi" }, { "inlineData": { "mimeType": "image/png", "data": "AA==" } }] }],
+            "systemInstruction": { "parts": [{ "text": "be brief" }] },
+            "generationConfig": { "maxOutputTokens": 64, "temperature": 0.5, "stopSequences": ["END"] },
+            "safetySettings": [{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE" }]
+        });
+        let snake = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": "hi" }, { "inline_data": { "mime_type": "image/png", "data": "AA==" } }] }],
+            "system_instruction": { "parts": [{ "text": "be brief" }] },
+            "generation_config": { "max_output_tokens": 64, "temperature": 0.5, "stop_sequences": ["END"] },
+            "safety_settings": [{ "category": "HARM_CATEGORY_HARASSMENT", "threshold": "BLOCK_NONE" }]
+        });
+        assert_eq!(violations(camel), Vec::new());
+        assert_eq!(violations(snake), Vec::new());
+    }
+
+    #[test]
+    fn unknown_fields_are_ignored() {
+        let body = json!({
+            "contents": [{ "parts": [{ "text": "hi", "futurePartField": 1 }] }],
+            "someNewTopLevelField": { "anything": true }
+        });
+        assert_eq!(violations(body), Vec::new());
+    }
+
+    #[test]
+    fn reports_field_paths() {
+        let body = json!({
+            "contents": [
+                { "role": "user", "parts": [{ "text": "hi" }] },
+                { "role": "bot", "parts": [{ "text": "a", "inline_data": { "data": "AA==" } }] }
+            ],
+            "generation_config": { "temperature": 3, "max_output_tokens": 0 }
+        });
+        let fields: Vec<String> = violations(body).into_iter().map(|(field, _)| field).collect();
+        assert_eq!(fields, [
+            "contents[1].role",
+            "contents[1].parts[0].inline_data.mimeType",
+            "contents[1].parts[0]",
+            "generation_config.max_output_tokens",
+            "generation_config.temperature",
+        ]);
+    }
+
+    #[test]
+    fn missing_contents_and_wrong_root_type() {
+        assert_eq!(violations(json!({})), vec![("contents".to_string(), "is required".to_string())]);
+        assert_eq!(violations(json!([])), vec![("(root)".to_string(), "must be an object".to_string())]);
+        assert_eq!(
+            violations(json!({ "contents": [] })),
+            vec![("contents".to_string(), "must not be empty".to_string())]
+        );
+    }
+}
@@ -0,0 +1,250 @@
+use crate::models::ResponseCacheSettings;
+#[cfg(feature = "desktop")]
+use crate::models::ResponseCacheStats;
+use sqlx::SqlitePool;
+use anyhow::Result;
+use chrono::{Utc, SecondsFormat};
+#[cfg(feature = "desktop")]
+use chrono::DateTime;
+use serde_json::Value;
+use uuid::Uuid;
+
+/// 客户端用该请求头（值为 `1`、`true` 或 `on`）在温度不为 0 时也启用响应缓存
+pub const CACHE_OPT_IN_HEADER: &str = "x-proxy-cache";
+
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+#[cfg(feature = "desktop")]
+fn parse_timestamp(value: Option<String>) -> Option<DateTime<Utc>> {
+    value
+        .and_then(|s| DateTime::parse_from_rfc3339(&s).ok())
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+/// 请求头是否表示启用缓存
+pub fn cache_opt_in(value: Option<&str>) -> bool {
+    matches!(value.map(|v| v.trim().to_ascii_lowercase()).as_deref(), Some("1" | "true" | "on"))
+}
+
+/// 温度为 0 的请求结果是确定的，可以直接缓存
+pub fn is_deterministic(body: &Value) -> bool {
+    body.get("generationConfig")
+        .or_else(|| body.get("generation_config"))
+        .and_then(|config| config.get("temperature"))
+        .and_then(Value::as_f64)
+        .is_some_and(|temperature| temperature == 0.0)
+}
+
+/// 命中的缓存条目
+pub struct CachedResponse {
+    pub body: Value,
+    pub served_model: Option<String>,
+    /// 最初处理该请求的密钥，命中时的日志记在它名下
+    pub api_key_id: Uuid,
+}
+
+pub struct ResponseCacheService {
+    pool: SqlitePool,
+}
+
+impl ResponseCacheService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 查找未过期的缓存条目并累计命中次数
+    pub async fn get(&self, cache_key: &str) -> Result<Option<CachedResponse>> {
+        let now = to_js_compatible_timestamp(Utc::now());
+        let row: Option<(String, Option<String>, String)> = sqlx::query_as(
+            "SELECT response_body, served_model, api_key_id FROM response_cache WHERE cache_key = ? AND expires_at > ?"
+        )
+        .bind(cache_key)
+        .bind(&now)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        let Some((response_body, served_model, api_key_id)) = row else {
+            return Ok(None);
+        };
+
+        sqlx::query("UPDATE response_cache SET hit_count = hit_count + 1, last_hit_at = ? WHERE cache_key = ?")
+            .bind(&now)
+            .bind(cache_key)
+            .execute(&self.pool)
+            .await?;
+
+        Ok(Some(CachedResponse {
+            body: serde_json::from_str(&response_body)?,
+            served_model,
+            api_key_id: Uuid::parse_str(&api_key_id)?,
+        }))
+    }
+
+    /// 写入缓存，随后清理过期条目并把总大小控制在上限内
+    pub async fn put(
+        &self,
+        cache_key: &str,
+        body: &Value,
+        served_model: Option<&str>,
+        api_key_id: Uuid,
+        settings: &ResponseCacheSettings,
+    ) -> Result<()> {
+        let now = Utc::now();
+        let expires_at = now + chrono::Duration::seconds(settings.ttl_secs.max(1) as i64);
+        let response_body = body.to_string();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO response_cache (cache_key, response_body, served_model, api_key_id, size_bytes, hit_count, created_at, expires_at)
+            VALUES (?, ?, ?, ?, ?, 0, ?, ?)
+            "#,
+        )
+        .bind(cache_key)
+        .bind(&response_body)
+        .bind(served_model)
+        .bind(api_key_id.to_string())
+        .bind(response_body.len() as i64)
+        .bind(to_js_compatible_timestamp(now))
+        .bind(to_js_compatible_timestamp(expires_at))
+        .execute(&self.pool)
+        .await?;
+
+        self.purge_expired().await?;
+        self.enforce_size_cap(settings.max_size_mb.saturating_mul(1024 * 1024)).await?;
+
+        Ok(())
+    }
+
+    pub async fn purge_expired(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM response_cache WHERE expires_at <= ?")
+            .bind(to_js_compatible_timestamp(Utc::now()))
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// 从最新的条目开始累计大小，删除超出上限的旧条目
+    async fn enforce_size_cap(&self, max_bytes: u64) -> Result<()> {
+        sqlx::query(
+            r#"
+            DELETE FROM response_cache WHERE cache_key IN (
+                SELECT cache_key FROM (
+                    SELECT cache_key, SUM(size_bytes) OVER (ORDER BY created_at DESC, cache_key) AS running_size
+                    FROM response_cache
+                ) WHERE running_size > ?
+            )
+            "#,
+        )
+        .bind(max_bytes as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn purge(&self) -> Result<u64> {
+        let result = sqlx::query("DELETE FROM response_cache")
+            .execute(&self.pool)
+            .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn stats(&self) -> Result<ResponseCacheStats> {
+        let now = to_js_compatible_timestamp(Utc::now());
+        let (entries, expired_entries, size_bytes, entry_hits, oldest, newest): (i64, i64, i64, i64, Option<String>, Option<String>) = sqlx::query_as(
+            r#"
+            SELECT
+                COUNT(*),
+                COALESCE(SUM(CASE WHEN expires_at <= ? THEN 1 ELSE 0 END), 0),
+                COALESCE(SUM(size_bytes), 0),
+                COALESCE(SUM(hit_count), 0),
+                MIN(created_at),
+                MAX(created_at)
+            FROM response_cache
+            "#,
+        )
+        .bind(&now)
+        .fetch_one(&self.pool)
+        .await?;
+
+        let (logged_hits,): (i64,) = sqlx::query_as(
+            "SELECT COUNT(*) FROM request_logs WHERE cache_hit = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(ResponseCacheStats {
+            entries,
+            expired_entries,
+            size_bytes,
+            entry_hits,
+            logged_hits,
+            oldest_entry_at: parse_timestamp(oldest),
+            newest_entry_at: parse_timestamp(newest),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_pool;
+    use serde_json::json;
+
+    #[test]
+    fn only_deterministic_or_opted_in_requests_are_cached() {
+        assert!(is_deterministic(&json!({ "generationConfig": { "temperature": 0 } })));
+        assert!(is_deterministic(&json!({ "generation_config": { "temperature": 0.0 } })));
+        assert!(!is_deterministic(&json!({ "generationConfig": { "temperature": 0.7 } })));
+        assert!(!is_deterministic(&json!({ "contents": [] })));
+
+        for value in ["1", "true", " ON "] {
+            assert!(cache_opt_in(Some(value)), "{value}");
+        }
+        assert!(!cache_opt_in(Some("0")));
+        assert!(!cache_opt_in(None));
+    }
+
+    #[tokio::test]
+    async fn expired_entries_are_not_served() {
+        let cache = ResponseCacheService::new(test_pool().await);
+        let api_key_id = Uuid::new_v4();
+        cache.put("fresh", &json!({ "ok": 1 }), Some("gemini-2.5-flash"), api_key_id, &ResponseCacheSettings::default()).await.unwrap();
+        cache.put("stale", &json!({ "ok": 2 }), None, api_key_id, &ResponseCacheSettings::default()).await.unwrap();
+        sqlx::query("UPDATE response_cache SET expires_at = ? WHERE cache_key = 'stale'")
+            .bind(to_js_compatible_timestamp(Utc::now() - chrono::Duration::seconds(1)))
+            .execute(&cache.pool)
+            .await
+            .unwrap();
+
+        let hit = cache.get("fresh").await.unwrap().unwrap();
+        assert_eq!(hit.body, json!({ "ok": 1 }));
+        assert_eq!(hit.served_model.as_deref(), Some("gemini-2.5-flash"));
+        assert_eq!(hit.api_key_id, api_key_id);
+        assert!(cache.get("stale").await.unwrap().is_none());
+        assert_eq!(cache.purge_expired().await.unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn size_cap_evicts_the_oldest_entries() {
+        let cache = ResponseCacheService::new(test_pool().await);
+        let settings = ResponseCacheSettings { enabled: true, ttl_secs: 60, max_size_mb: 1 };
+        // 每条约 400 KB，上限 1 MB 时只能保留最新的两条
+        let body = json!({ "text": "x".repeat(400 * 1024) });
+        for key in ["first", "second", "third"] {
+            cache.put(key, &body, None, Uuid::new_v4(), &settings).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        }
+
+        assert!(cache.get("first").await.unwrap().is_none());
+        assert!(cache.get("second").await.unwrap().is_some());
+        assert!(cache.get("third").await.unwrap().is_some());
+    }
+}
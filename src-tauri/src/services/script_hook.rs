@@ -0,0 +1,484 @@
+use crate::models::{HookMetadata, HookStage, ScriptHook};
+#[cfg(feature = "desktop")]
+use crate::models::{
+    CreateScriptHookRequest, UpdateScriptHookRequest, DEFAULT_HOOK_TIME_BUDGET_MS, DEFAULT_HOOK_MEMORY_BUDGET_KB,
+};
+use sqlx::SqlitePool;
+use anyhow::{Result, anyhow};
+#[cfg(feature = "desktop")]
+use chrono::SecondsFormat;
+use chrono::{DateTime, Utc};
+use rhai::{Dynamic, Engine, Scope, AST};
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+const MAX_TIME_BUDGET_MS: i64 = 5000;
+const MIN_MEMORY_BUDGET_KB: i64 = 64;
+const MAX_MEMORY_BUDGET_KB: i64 = 65536;
+
+#[cfg(feature = "desktop")]
+fn to_js_compatible_timestamp(dt: chrono::DateTime<Utc>) -> String {
+    // 生成 JavaScript 兼容的时间戳（毫秒精度）
+    dt.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+thread_local! {
+    // 当前线程上正在执行的钩子的截止时间；引擎随编译结果缓存，时间预算按每次执行计算
+    static DEADLINE: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+// 按钩子 ID 缓存编译结果，钩子修改后 updated_at 变化时重新编译
+static COMPILED_HOOKS: LazyLock<Mutex<HashMap<Uuid, Arc<CompiledHook>>>> = LazyLock::new(Default::default);
+
+/// 创建受限的脚本引擎：没有文件、网络和 `eval`，超过时间或大小预算时中止执行
+fn sandboxed_engine(name: &str, memory_budget_bytes: usize) -> Engine {
+    let mut engine = Engine::new();
+    engine.disable_symbol("eval");
+    engine.set_max_call_levels(32);
+    engine.set_max_expr_depths(64, 32);
+
+    // Rhai 不统计总内存，大小预算只限制单个字符串的字节数，数组和对象按每个元素约 32 字节折算
+    engine.set_max_string_size(memory_budget_bytes);
+    engine.set_max_array_size((memory_budget_bytes / 32).max(1));
+    engine.set_max_map_size((memory_budget_bytes / 32).max(1));
+
+    engine.on_progress(|_| {
+        DEADLINE
+            .get()
+            .is_some_and(|deadline| Instant::now() > deadline)
+            .then(|| Dynamic::from("time budget exceeded"))
+    });
+
+    let print_name = name.to_string();
+    engine.on_print(move |s| tracing::info!("[hook {}] {}", print_name, s));
+    let debug_name = name.to_string();
+    engine.on_debug(move |s, _, _| tracing::debug!("[hook {}] {}", debug_name, s));
+
+    engine
+}
+
+fn compile(source: &str) -> Result<AST> {
+    // `eval` 在解析阶段被拒绝，因此编译也要使用同样的限制
+    let mut engine = Engine::new();
+    engine.disable_symbol("eval");
+    engine
+        .compile(source)
+        .map_err(|e| anyhow!("Script compile error: {}", e))
+}
+
+fn clamp_budgets(time_budget_ms: i64, memory_budget_kb: i64) -> (i64, i64) {
+    (
+        time_budget_ms.clamp(1, MAX_TIME_BUDGET_MS),
+        memory_budget_kb.clamp(MIN_MEMORY_BUDGET_KB, MAX_MEMORY_BUDGET_KB),
+    )
+}
+
+struct CompiledHook {
+    name: String,
+    updated_at: DateTime<Utc>,
+    engine: Engine,
+    ast: AST,
+    time_budget: Duration,
+    fail_closed: bool,
+}
+
+impl CompiledHook {
+    fn new(hook: ScriptHook) -> Result<Self> {
+        let ast = compile(&hook.source)?;
+        let (time_budget_ms, memory_budget_kb) = clamp_budgets(hook.time_budget_ms, hook.memory_budget_kb);
+        Ok(Self {
+            engine: sandboxed_engine(&hook.name, memory_budget_kb as usize * 1024),
+            name: hook.name,
+            updated_at: hook.updated_at,
+            ast,
+            time_budget: Duration::from_millis(time_budget_ms as u64),
+            fail_closed: hook.fail_closed,
+        })
+    }
+
+    /// 执行脚本，成功时返回脚本修改后的 `body`；同步执行，调用方需要在阻塞线程中调用
+    fn execute(&self, body: &Value, meta: &HookMetadata) -> Result<Value> {
+        let mut scope = Scope::new();
+        scope.push("body", rhai::serde::to_dynamic(body).map_err(|e| anyhow!("{}", e))?);
+        scope.push_constant("meta", rhai::serde::to_dynamic(meta).map_err(|e| anyhow!("{}", e))?);
+
+        DEADLINE.set(Some(Instant::now() + self.time_budget));
+        let result = self.engine.run_ast_with_scope(&mut scope, &self.ast);
+        DEADLINE.set(None);
+        result.map_err(|e| anyhow!("{}", e))?;
+
+        let result = scope
+            .get_value::<Dynamic>("body")
+            .ok_or_else(|| anyhow!("script removed `body`"))?;
+        rhai::serde::from_dynamic(&result).map_err(|e| anyhow!("{}", e))
+    }
+}
+
+/// 设置为出错时拒绝请求的钩子执行失败
+#[derive(Debug, thiserror::Error)]
+#[error("Script hook '{hook}' failed: {reason}")]
+pub struct HookFailedError {
+    pub hook: String,
+    pub reason: String,
+}
+
+/// 某个阶段所有启用的钩子，按优先级依次执行；
+/// 脚本是同步执行的，不能直接在异步运行时的工作线程上运行，以免慢脚本阻塞其它连接
+#[derive(Clone, Default)]
+pub struct HookChain {
+    hooks: Vec<Arc<CompiledHook>>,
+}
+
+impl HookChain {
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// 依次执行钩子；钩子出错或超出预算时记录警告并跳过，设置了 `fail_closed` 的钩子则返回错误
+    fn run_blocking(&self, body: &mut Value, meta: &HookMetadata) -> Result<(), HookFailedError> {
+        for hook in &self.hooks {
+            match hook.execute(body, meta) {
+                Ok(updated) => *body = updated,
+                Err(e) if hook.fail_closed => {
+                    return Err(HookFailedError { hook: hook.name.clone(), reason: e.to_string() });
+                }
+                Err(e) => tracing::warn!("Script hook '{}' failed, skipped: {}", hook.name, e),
+            }
+        }
+        Ok(())
+    }
+
+    /// 在阻塞线程池中执行钩子，返回错误时调用方应拒绝该请求
+    pub async fn run(&self, body: &mut Value, meta: &HookMetadata) -> Result<(), HookFailedError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let chain = self.clone();
+        let meta = meta.clone();
+        let mut input = body.clone();
+        match tokio::task::spawn_blocking(move || {
+            chain.run_blocking(&mut input, &meta).map(|_| input)
+        })
+        .await
+        {
+            Ok(result) => {
+                *body = result?;
+                Ok(())
+            }
+            Err(e) => match self.hooks.iter().find(|hook| hook.fail_closed) {
+                Some(hook) => Err(HookFailedError { hook: hook.name.clone(), reason: e.to_string() }),
+                None => {
+                    tracing::warn!("Script hooks aborted, request left unchanged: {}", e);
+                    Ok(())
+                }
+            },
+        }
+    }
+
+    /// 对一个流式数据块执行钩子，数据块不是 JSON 时原样返回
+    pub async fn run_on_chunk(&self, data: &str, meta: &HookMetadata) -> Result<String, HookFailedError> {
+        if self.is_empty() {
+            return Ok(data.to_string());
+        }
+
+        match serde_json::from_str::<Value>(data) {
+            Ok(mut chunk) => {
+                self.run(&mut chunk, meta).await?;
+                Ok(chunk.to_string())
+            }
+            Err(_) => Ok(data.to_string()),
+        }
+    }
+}
+
+pub struct ScriptHookService {
+    pool: SqlitePool,
+}
+
+impl ScriptHookService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn create_hook(&self, request: CreateScriptHookRequest) -> Result<ScriptHook> {
+        if request.name.trim().is_empty() {
+            return Err(anyhow!("Script hook name is required"));
+        }
+        compile(&request.source)?;
+
+        let (time_budget_ms, memory_budget_kb) = clamp_budgets(
+            request.time_budget_ms.unwrap_or(DEFAULT_HOOK_TIME_BUDGET_MS),
+            request.memory_budget_kb.unwrap_or(DEFAULT_HOOK_MEMORY_BUDGET_KB),
+        );
+
+        let hook_id = Uuid::new_v4();
+        let now = to_js_compatible_timestamp(Utc::now());
+
+        sqlx::query(
+            r#"
+            INSERT INTO script_hooks (id, name, stage, enabled, priority, source, time_budget_ms, memory_budget_kb, fail_closed, created_at, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(hook_id.to_string())
+        .bind(request.name.trim())
+        .bind(request.stage.as_str())
+        .bind(request.enabled.unwrap_or(true) as i32)
+        .bind(request.priority.unwrap_or(0))
+        .bind(&request.source)
+        .bind(time_budget_ms)
+        .bind(memory_budget_kb)
+        .bind(request.fail_closed.unwrap_or(false) as i32)
+        .bind(&now)
+        .bind(&now)
+        .execute(&self.pool)
+        .await?;
+
+        self.get_hook_by_id(hook_id).await?
+            .ok_or_else(|| anyhow!("Script hook not found after creation"))
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_all_hooks(&self) -> Result<Vec<ScriptHook>> {
+        let hooks: Vec<ScriptHook> = sqlx::query_as(
+            r#"
+            SELECT id, name, stage, enabled, priority, source, time_budget_ms, memory_budget_kb, fail_closed, created_at, updated_at
+            FROM script_hooks ORDER BY stage ASC, priority ASC, created_at ASC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        Ok(hooks)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn get_hook_by_id(&self, hook_id: Uuid) -> Result<Option<ScriptHook>> {
+        let hook: Option<ScriptHook> = sqlx::query_as(
+            r#"
+            SELECT id, name, stage, enabled, priority, source, time_budget_ms, memory_budget_kb, fail_closed, created_at, updated_at
+            FROM script_hooks WHERE id = ?
+            "#,
+        )
+        .bind(hook_id.to_string())
+        .fetch_optional(&self.pool)
+        .await?;
+
+        Ok(hook)
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn update_hook(&self, hook_id: Uuid, request: UpdateScriptHookRequest) -> Result<Option<ScriptHook>> {
+        let Some(existing) = self.get_hook_by_id(hook_id).await? else {
+            return Ok(None);
+        };
+
+        let mut query_parts = Vec::new();
+        let mut bind_values = Vec::new();
+
+        if let Some(name) = &request.name {
+            query_parts.push("name = ?");
+            bind_values.push(name.trim().to_string());
+        }
+
+        if let Some(stage) = request.stage {
+            query_parts.push("stage = ?");
+            bind_values.push(stage.as_str().to_string());
+        }
+
+        if let Some(enabled) = request.enabled {
+            query_parts.push("enabled = ?");
+            bind_values.push((enabled as i32).to_string());
+        }
+
+        if let Some(priority) = request.priority {
+            query_parts.push("priority = ?");
+            bind_values.push(priority.to_string());
+        }
+
+        if let Some(source) = &request.source {
+            compile(source)?;
+            query_parts.push("source = ?");
+            bind_values.push(source.clone());
+        }
+
+        if let Some(fail_closed) = request.fail_closed {
+            query_parts.push("fail_closed = ?");
+            bind_values.push((fail_closed as i32).to_string());
+        }
+
+        if request.time_budget_ms.is_some() || request.memory_budget_kb.is_some() {
+            let (time_budget_ms, memory_budget_kb) = clamp_budgets(
+                request.time_budget_ms.unwrap_or(existing.time_budget_ms),
+                request.memory_budget_kb.unwrap_or(existing.memory_budget_kb),
+            );
+            query_parts.push("time_budget_ms = ?");
+            bind_values.push(time_budget_ms.to_string());
+            query_parts.push("memory_budget_kb = ?");
+            bind_values.push(memory_budget_kb.to_string());
+        }
+
+        if query_parts.is_empty() {
+            return Ok(Some(existing));
+        }
+
+        query_parts.push("updated_at = ?");
+        bind_values.push(to_js_compatible_timestamp(Utc::now()));
+
+        let query = format!(
+            "UPDATE script_hooks SET {} WHERE id = ?",
+            query_parts.join(", ")
+        );
+
+        let mut query_builder = sqlx::query(&query);
+        for value in bind_values {
+            query_builder = query_builder.bind(value);
+        }
+        query_builder = query_builder.bind(hook_id.to_string());
+
+        query_builder.execute(&self.pool).await?;
+
+        self.get_hook_by_id(hook_id).await
+    }
+
+    #[cfg(feature = "desktop")]
+    pub async fn delete_hook(&self, hook_id: Uuid) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM script_hooks WHERE id = ?")
+            .bind(hook_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        COMPILED_HOOKS.lock().unwrap().remove(&hook_id);
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 编译某个阶段所有启用的钩子；编译失败的钩子记录警告后跳过
+    pub async fn load_chain(&self, stage: HookStage) -> Result<HookChain> {
+        let hooks: Vec<ScriptHook> = sqlx::query_as(
+            r#"
+            SELECT id, name, stage, enabled, priority, source, time_budget_ms, memory_budget_kb, fail_closed, created_at, updated_at
+            FROM script_hooks WHERE stage = ? AND enabled = 1 ORDER BY priority ASC, created_at ASC
+            "#,
+        )
+        .bind(stage.as_str())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut compiled = COMPILED_HOOKS.lock().unwrap();
+        let hooks = hooks
+            .into_iter()
+            .filter_map(|hook| {
+                if let Some(cached) = compiled.get(&hook.id).filter(|cached| cached.updated_at == hook.updated_at) {
+                    return Some(cached.clone());
+                }
+
+                let hook_id = hook.id;
+                let name = hook.name.clone();
+                match CompiledHook::new(hook) {
+                    Ok(hook) => {
+                        let hook = Arc::new(hook);
+                        compiled.insert(hook_id, hook.clone());
+                        Some(hook)
+                    }
+                    Err(e) => {
+                        tracing::warn!("Script hook '{}' skipped: {}", name, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Ok(HookChain { hooks })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn chain(sources: &[(&str, i64)]) -> HookChain {
+        chain_with(sources, false)
+    }
+
+    fn chain_with(sources: &[(&str, i64)], fail_closed: bool) -> HookChain {
+        let hooks = sources
+            .iter()
+            .map(|(source, time_budget_ms)| {
+                let hook = ScriptHook {
+                    id: Uuid::new_v4(),
+                    name: "test".to_string(),
+                    stage: HookStage::PreRequest,
+                    enabled: true,
+                    priority: 0,
+                    source: source.to_string(),
+                    time_budget_ms: *time_budget_ms,
+                    memory_budget_kb: 256,
+                    fail_closed,
+                    created_at: Utc::now(),
+                    updated_at: Utc::now(),
+                };
+                Arc::new(CompiledHook::new(hook).unwrap())
+            })
+            .collect();
+        HookChain { hooks }
+    }
+
+    fn meta() -> HookMetadata {
+        HookMetadata {
+            model: "gemini-2.0-flash".to_string(),
+            path: "/v1beta/models/gemini-2.0-flash:generateContent".to_string(),
+            method: "POST".to_string(),
+            client: "default".to_string(),
+            stream: false,
+            status_code: None,
+        }
+    }
+
+    #[test]
+    fn compile_rejects_eval() {
+        assert!(compile(r#"eval("1")"#).is_err());
+        assert!(compile("body.x = 1;").is_ok());
+    }
+
+    #[tokio::test]
+    async fn hooks_run_in_order_and_see_metadata() {
+        let chain = chain(&[("body.model = meta.model;", 100), ("body.steps = [body.model];", 100)]);
+        let mut body = json!({});
+        chain.run(&mut body, &meta()).await.unwrap();
+        assert_eq!(body, json!({ "model": "gemini-2.0-flash", "steps": ["gemini-2.0-flash"] }));
+    }
+
+    #[tokio::test]
+    async fn failing_hook_is_skipped_and_budget_applies_per_run() {
+        let chain = chain(&[("loop { }", 20), ("body.after = true;", 100)]);
+        for _ in 0..2 {
+            let mut body = json!({ "before": true });
+            chain.run(&mut body, &meta()).await.unwrap();
+            assert_eq!(body, json!({ "before": true, "after": true }));
+        }
+    }
+
+    #[tokio::test]
+    async fn fail_closed_hook_rejects_and_leaves_body_unchanged() {
+        let chain = chain_with(&[("body.touched = true; throw \"denied\";", 100)], true);
+        let mut body = json!({ "before": true });
+        let error = chain.run(&mut body, &meta()).await.unwrap_err();
+        assert!(error.reason.contains("denied"));
+        assert_eq!(body, json!({ "before": true }));
+    }
+
+    #[tokio::test]
+    async fn chunk_hooks_leave_non_json_data_alone() {
+        // 默认的 current_thread 运行时上也能执行
+        let chain = chain(&[("body.seen = true;", 100)]);
+        assert_eq!(chain.run_on_chunk("not json", &meta()).await.unwrap(), "not json");
+        assert_eq!(chain.run_on_chunk("{}", &meta()).await.unwrap(), r#"{"seen":true}"#);
+    }
+}
@@ -1,3 +1,6 @@
+use crate::models::{CircuitBreakerSettings, HedgingSettings, ModelFallbackSettings, ResponseCacheSettings, CoalescingSettings, RateLimitSettings, SchedulerSettings, NetworkSettings, TlsSettings, SessionSettings, BruteForceSettings, ValidationMode, SettingValue, SettingValueError};
+use crate::services::validate_cidrs;
+use crate::utils::ValidationError;
 use sqlx::SqlitePool;
 use anyhow::Result;
 
@@ -34,4 +37,369 @@ impl SettingsService {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    pub async fn get_circuit_breaker_settings(&self) -> Result<CircuitBreakerSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT circuit_breaker_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(CircuitBreakerSettings::default()),
+        }
+    }
+
+    pub async fn set_circuit_breaker_settings(&self, settings: CircuitBreakerSettings) -> Result<()> {
+        // 所有阈值至少为 1，避免熔断器永远打开或无法试探恢复
+        let settings = CircuitBreakerSettings {
+            failure_threshold: settings.failure_threshold.max(1),
+            open_duration_secs: settings.open_duration_secs.max(1),
+            half_open_max_requests: settings.half_open_max_requests.max(1),
+        };
+        sqlx::query(
+            "UPDATE app_settings SET circuit_breaker_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_hedging_settings(&self) -> Result<HedgingSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT hedging_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(HedgingSettings::default()),
+        }
+    }
+
+    pub async fn set_hedging_settings(&self, settings: HedgingSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET hedging_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_model_fallback_settings(&self) -> Result<ModelFallbackSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT model_fallback_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(ModelFallbackSettings::default()),
+        }
+    }
+
+    pub async fn set_model_fallback_settings(&self, settings: ModelFallbackSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET model_fallback_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_validation_mode(&self) -> Result<ValidationMode> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT validation_mode FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(result.0.map(|mode| ValidationMode::parse(&mode)).unwrap_or_default())
+    }
+
+    pub async fn set_validation_mode(&self, mode: ValidationMode) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET validation_mode = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(mode.as_str())
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_response_cache_settings(&self) -> Result<ResponseCacheSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT response_cache_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(ResponseCacheSettings::default()),
+        }
+    }
+
+    pub async fn set_response_cache_settings(&self, settings: ResponseCacheSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET response_cache_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_coalescing_settings(&self) -> Result<CoalescingSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT coalescing_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(CoalescingSettings::default()),
+        }
+    }
+
+    pub async fn set_coalescing_settings(&self, settings: CoalescingSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET coalescing_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_rate_limit_settings(&self) -> Result<RateLimitSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT rate_limit_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(RateLimitSettings::default()),
+        }
+    }
+
+    pub async fn set_rate_limit_settings(&self, settings: RateLimitSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET rate_limit_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_scheduler_settings(&self) -> Result<SchedulerSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT scheduler_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(SchedulerSettings::default()),
+        }
+    }
+
+    pub async fn set_scheduler_settings(&self, settings: SchedulerSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET scheduler_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_network_settings(&self) -> Result<NetworkSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT network_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(NetworkSettings::default()),
+        }
+    }
+
+    pub async fn set_network_settings(&self, settings: NetworkSettings) -> Result<()> {
+        settings.host.parse::<std::net::IpAddr>()
+            .map_err(|_| ValidationError(format!("Invalid listen host '{}', expected an IP address such as 0.0.0.0 or 127.0.0.1", settings.host)))?;
+        if settings.port == 0 {
+            return Err(ValidationError("Listen port must be between 1 and 65535".to_string()).into());
+        }
+        validate_cidrs(&settings.allow_cidrs)?;
+        validate_cidrs(&settings.deny_cidrs)?;
+
+        sqlx::query(
+            "UPDATE app_settings SET network_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_tls_settings(&self) -> Result<TlsSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT tls_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(TlsSettings::default()),
+        }
+    }
+
+    pub async fn set_tls_settings(&self, settings: TlsSettings) -> Result<()> {
+        let has_cert = settings.cert_path.as_deref().is_some_and(|p| !p.trim().is_empty());
+        let has_key = settings.key_path.as_deref().is_some_and(|p| !p.trim().is_empty());
+        if has_cert != has_key {
+            return Err(ValidationError("Certificate and private key paths must be configured together".to_string()).into());
+        }
+        if !has_cert && settings.subject_alt_names.iter().all(|san| san.trim().is_empty()) {
+            return Err(ValidationError("At least one subject alternative name is required for the self-signed certificate".to_string()).into());
+        }
+
+        sqlx::query(
+            "UPDATE app_settings SET tls_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_session_settings(&self) -> Result<SessionSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT session_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(SessionSettings::default()),
+        }
+    }
+
+    pub async fn set_session_settings(&self, settings: SessionSettings) -> Result<()> {
+        // 至少 1 分钟，避免刚登录就失效
+        let settings = SessionSettings {
+            ttl_minutes: settings.ttl_minutes.max(1),
+            idle_timeout_minutes: settings.idle_timeout_minutes.max(1),
+        };
+
+        sqlx::query(
+            "UPDATE app_settings SET session_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_brute_force_settings(&self) -> Result<BruteForceSettings> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT brute_force_config FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        match result.0 {
+            Some(config) => Ok(serde_json::from_str(&config)?),
+            None => Ok(BruteForceSettings::default()),
+        }
+    }
+
+    pub async fn set_brute_force_settings(&self, settings: BruteForceSettings) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET brute_force_config = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(serde_json::to_string(&settings)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+
+    /// 按名称读取设置，名称见 `SETTING_NAMES`
+    pub async fn get_named(&self, name: &str) -> Result<SettingValue> {
+        Ok(match name {
+            "retry-count" => SettingValue::RetryCount(self.get_retry_count().await?),
+            "circuit-breaker" => SettingValue::CircuitBreaker(self.get_circuit_breaker_settings().await?),
+            "hedging" => SettingValue::Hedging(self.get_hedging_settings().await?),
+            "model-fallback" => SettingValue::ModelFallback(self.get_model_fallback_settings().await?),
+            "validation-mode" => SettingValue::ValidationMode(self.get_validation_mode().await?),
+            "response-cache" => SettingValue::ResponseCache(self.get_response_cache_settings().await?),
+            "coalescing" => SettingValue::Coalescing(self.get_coalescing_settings().await?),
+            "rate-limit" => SettingValue::RateLimit(self.get_rate_limit_settings().await?),
+            "scheduler" => SettingValue::Scheduler(self.get_scheduler_settings().await?),
+            "network" => SettingValue::Network(self.get_network_settings().await?),
+            "tls" => SettingValue::Tls(self.get_tls_settings().await?),
+            "session" => SettingValue::Session(self.get_session_settings().await?),
+            "brute-force" => SettingValue::BruteForce(self.get_brute_force_settings().await?),
+            _ => return Err(SettingValueError::Unknown(name.to_string()).into()),
+        })
+    }
+
+    /// 保存按名称解析的设置；监听地址和 HTTPS 的变化需要调用方另行应用
+    pub async fn set_named(&self, value: SettingValue) -> Result<()> {
+        match value {
+            SettingValue::RetryCount(retry_count) => self.set_retry_count(retry_count).await,
+            SettingValue::CircuitBreaker(settings) => self.set_circuit_breaker_settings(settings).await,
+            SettingValue::Hedging(settings) => self.set_hedging_settings(settings).await,
+            SettingValue::ModelFallback(settings) => self.set_model_fallback_settings(settings).await,
+            SettingValue::ValidationMode(mode) => self.set_validation_mode(mode).await,
+            SettingValue::ResponseCache(settings) => self.set_response_cache_settings(settings).await,
+            SettingValue::Coalescing(settings) => self.set_coalescing_settings(settings).await,
+            SettingValue::RateLimit(settings) => self.set_rate_limit_settings(settings).await,
+            SettingValue::Scheduler(settings) => self.set_scheduler_settings(settings).await,
+            SettingValue::Network(settings) => self.set_network_settings(settings).await,
+            SettingValue::Tls(settings) => self.set_tls_settings(settings).await,
+            SettingValue::Session(settings) => self.set_session_settings(settings).await,
+            SettingValue::BruteForce(settings) => self.set_brute_force_settings(settings).await,
+        }
+    }
+}
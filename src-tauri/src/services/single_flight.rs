@@ -0,0 +1,330 @@
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use futures::stream::BoxStream;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Arc, LazyLock, Mutex};
+use tokio::sync::{broadcast, Notify};
+use tokio_stream::StreamExt;
+use uuid::Uuid;
+
+/// 领头请求的非流式结果，所有合并的请求共享同一份
+#[derive(Debug, Clone)]
+pub struct FlightResponse {
+    pub body: Value,
+    pub served_model: Option<String>,
+    /// 实际处理请求的密钥，合并的请求在日志中记在它名下
+    pub api_key_id: Uuid,
+}
+
+pub type SharedResult = Arc<Result<FlightResponse>>;
+
+// 按请求哈希登记正在进行的上游调用，进程内所有 GeminiProxyService 共享
+static RESPONSE_FLIGHTS: LazyLock<Mutex<HashMap<String, broadcast::Sender<SharedResult>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+static STREAM_FLIGHTS: LazyLock<Mutex<HashMap<String, Arc<StreamFlight>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+pub enum Flight {
+    /// 没有相同的请求在进行，由本请求调用上游并分发结果
+    Leader(FlightLeader),
+    /// 等待领头请求的结果；通道关闭表示领头请求被取消
+    Follower(broadcast::Receiver<SharedResult>),
+}
+
+pub struct FlightLeader {
+    key: String,
+    sender: broadcast::Sender<SharedResult>,
+}
+
+impl FlightLeader {
+    /// 注销并把结果发给所有等待的请求
+    pub fn finish(self, result: SharedResult) {
+        {
+            let mut flights = RESPONSE_FLIGHTS.lock().unwrap();
+            flights.remove(&self.key);
+        }
+        // 没有等待者时发送失败，忽略即可
+        let _ = self.sender.send(result);
+    }
+}
+
+impl Drop for FlightLeader {
+    fn drop(&mut self) {
+        // 领头请求被取消时注销，等待者收到通道关闭后自行请求上游
+        let mut flights = RESPONSE_FLIGHTS.lock().unwrap();
+        if flights.get(&self.key).is_some_and(|sender| sender.same_channel(&self.sender)) {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+enum StreamStart {
+    Pending,
+    Started(Uuid),
+    Failed(Arc<anyhow::Error>),
+    /// 领头请求在连上上游之前被取消
+    Abandoned,
+}
+
+struct StreamState {
+    start: StreamStart,
+    chunks: Vec<Bytes>,
+    /// 上游数据流结束；`Some(Some(..))` 表示以错误结束
+    finished: Option<Option<String>>,
+}
+
+/// 一次共享的流式上游调用，数据块全部缓存，晚加入的订阅者从头重放
+pub struct StreamFlight {
+    state: Mutex<StreamState>,
+    notify: Notify,
+}
+
+impl StreamFlight {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(StreamState {
+                start: StreamStart::Pending,
+                chunks: Vec::new(),
+                finished: None,
+            }),
+            notify: Notify::new(),
+        }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut StreamState)) {
+        f(&mut self.state.lock().unwrap());
+        self.notify.notify_waiters();
+    }
+
+    /// 等待领头请求连上上游；返回 None 表示领头请求已被取消
+    pub async fn wait_started(&self) -> Option<Result<Uuid, Arc<anyhow::Error>>> {
+        loop {
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            match &self.state.lock().unwrap().start {
+                StreamStart::Pending => {}
+                StreamStart::Started(api_key_id) => return Some(Ok(*api_key_id)),
+                StreamStart::Failed(e) => return Some(Err(e.clone())),
+                StreamStart::Abandoned => return None,
+            }
+
+            notified.await;
+        }
+    }
+
+    /// 从头读取共享数据流
+    pub fn subscribe(self: Arc<Self>) -> BoxStream<'static, Result<Bytes>> {
+        Box::pin(futures::stream::unfold((self, 0usize), |(flight, index)| async move {
+            loop {
+                // 先登记通知再检查状态，避免错过两者之间到达的数据块
+                let waiter = flight.clone();
+                let notified = waiter.notify.notified();
+                tokio::pin!(notified);
+                notified.as_mut().enable();
+
+                let next = {
+                    let state = flight.state.lock().unwrap();
+                    if let Some(chunk) = state.chunks.get(index) {
+                        Some(Some(Ok(chunk.clone())))
+                    } else {
+                        match &state.finished {
+                            Some(None) => Some(None),
+                            Some(Some(error)) if index == state.chunks.len() => Some(Some(Err(anyhow!("{}", error)))),
+                            Some(Some(_)) => Some(None),
+                            None => None,
+                        }
+                    }
+                };
+
+                match next {
+                    Some(Some(item)) => return Some((item, (flight, index + 1))),
+                    Some(None) => return None,
+                    None => notified.await,
+                }
+            }
+        }))
+    }
+}
+
+pub enum StreamRole {
+    Leader(StreamLeader),
+    Follower(Arc<StreamFlight>),
+}
+
+pub struct StreamLeader {
+    key: String,
+    flight: Arc<StreamFlight>,
+    resolved: bool,
+}
+
+impl StreamLeader {
+    fn unregister(&self) {
+        let mut flights = STREAM_FLIGHTS.lock().unwrap();
+        if flights.get(&self.key).is_some_and(|flight| Arc::ptr_eq(flight, &self.flight)) {
+            flights.remove(&self.key);
+        }
+    }
+
+    /// 连接上游失败，等待者收到同样的错误
+    pub fn fail(mut self, error: anyhow::Error) {
+        self.resolved = true;
+        self.unregister();
+        self.flight.update(|state| state.start = StreamStart::Failed(Arc::new(error)));
+    }
+
+    /// 在后台任务中读完上游数据流并分发给所有订阅者（包括领头请求自己），
+    /// 这样领头的客户端断开也不影响其他订阅者
+    pub fn start(mut self, api_key_id: Uuid, mut upstream: BoxStream<'static, Result<Bytes>>) -> BoxStream<'static, Result<Bytes>> {
+        self.resolved = true;
+        self.flight.update(|state| state.start = StreamStart::Started(api_key_id));
+
+        let flight = self.flight.clone();
+        let key = self.key.clone();
+        tokio::spawn(async move {
+            let mut error = None;
+            while let Some(item) = upstream.next().await {
+                match item {
+                    Ok(bytes) => flight.update(|state| state.chunks.push(bytes)),
+                    Err(e) => {
+                        error = Some(e.to_string());
+                        break;
+                    }
+                }
+            }
+
+            {
+                let mut flights = STREAM_FLIGHTS.lock().unwrap();
+                if flights.get(&key).is_some_and(|f| Arc::ptr_eq(f, &flight)) {
+                    flights.remove(&key);
+                }
+            }
+            flight.update(|state| state.finished = Some(error));
+        });
+
+        self.flight.clone().subscribe()
+    }
+}
+
+impl Drop for StreamLeader {
+    fn drop(&mut self) {
+        if !self.resolved {
+            self.unregister();
+            self.flight.update(|state| state.start = StreamStart::Abandoned);
+        }
+    }
+}
+
+/// 合并相同的并发请求（single-flight）：相同哈希的请求只向上游发送一次
+pub struct SingleFlightService;
+
+impl Default for SingleFlightService {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SingleFlightService {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub fn join(&self, key: &str) -> Flight {
+        let mut flights = RESPONSE_FLIGHTS.lock().unwrap();
+        if let Some(sender) = flights.get(key) {
+            return Flight::Follower(sender.subscribe());
+        }
+
+        let (sender, _) = broadcast::channel(1);
+        flights.insert(key.to_string(), sender.clone());
+        Flight::Leader(FlightLeader { key: key.to_string(), sender })
+    }
+
+    pub fn join_stream(&self, key: &str) -> StreamRole {
+        let mut flights = STREAM_FLIGHTS.lock().unwrap();
+        if let Some(flight) = flights.get(key) {
+            return StreamRole::Follower(flight.clone());
+        }
+
+        let flight = Arc::new(StreamFlight::new());
+        flights.insert(key.to_string(), flight.clone());
+        StreamRole::Leader(StreamLeader { key: key.to_string(), flight, resolved: false })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(api_key_id: Uuid) -> SharedResult {
+        Arc::new(Ok(FlightResponse { body: serde_json::json!({ "ok": true }), served_model: None, api_key_id }))
+    }
+
+    #[tokio::test]
+    async fn followers_receive_the_leader_result() {
+        let service = SingleFlightService::new();
+        let Flight::Leader(leader) = service.join("fan-out") else { panic!("first request should lead") };
+        let mut followers: Vec<_> = (0..3)
+            .map(|_| match service.join("fan-out") {
+                Flight::Follower(receiver) => receiver,
+                Flight::Leader(_) => panic!("identical request should follow"),
+            })
+            .collect();
+
+        let api_key_id = Uuid::new_v4();
+        leader.finish(response(api_key_id));
+        for follower in &mut followers {
+            let result = follower.recv().await.unwrap();
+            assert_eq!(result.as_ref().as_ref().unwrap().api_key_id, api_key_id);
+        }
+        // 结果发出后不再合并，新请求重新领头
+        assert!(matches!(service.join("fan-out"), Flight::Leader(_)));
+    }
+
+    #[tokio::test]
+    async fn cancelled_leader_closes_the_channel() {
+        let service = SingleFlightService::new();
+        let leader = service.join("cancelled");
+        let Flight::Follower(mut follower) = service.join("cancelled") else { panic!("identical request should follow") };
+        drop(leader);
+
+        assert!(matches!(follower.recv().await, Err(broadcast::error::RecvError::Closed)));
+        assert!(matches!(service.join("cancelled"), Flight::Leader(_)));
+    }
+
+    #[tokio::test]
+    async fn stream_followers_replay_every_chunk() {
+        let service = SingleFlightService::new();
+        let StreamRole::Leader(leader) = service.join_stream("stream") else { panic!("first request should lead") };
+        let StreamRole::Follower(early) = service.join_stream("stream") else { panic!("identical request should follow") };
+
+        let api_key_id = Uuid::new_v4();
+        let chunks = vec![Ok(Bytes::from("a")), Ok(Bytes::from("b"))];
+        let leader_stream: Vec<Bytes> = leader
+            .start(api_key_id, Box::pin(futures::stream::iter(chunks)))
+            .map(|chunk| chunk.unwrap())
+            .collect()
+            .await;
+        assert_eq!(leader_stream, ["a", "b"]);
+
+        assert_eq!(early.wait_started().await.unwrap().unwrap(), api_key_id);
+        let replayed: Vec<Bytes> = early.subscribe().map(|chunk| chunk.unwrap()).collect().await;
+        assert_eq!(replayed, ["a", "b"]);
+    }
+
+    #[tokio::test]
+    async fn stream_errors_reach_followers() {
+        let service = SingleFlightService::new();
+        let StreamRole::Leader(leader) = service.join_stream("stream-failed") else { panic!("first request should lead") };
+        let StreamRole::Follower(follower) = service.join_stream("stream-failed") else { panic!("identical request should follow") };
+        leader.fail(anyhow!("upstream unavailable"));
+        assert_eq!(follower.wait_started().await.unwrap().unwrap_err().to_string(), "upstream unavailable");
+
+        let StreamRole::Leader(leader) = service.join_stream("stream-abandoned") else { panic!("first request should lead") };
+        let StreamRole::Follower(follower) = service.join_stream("stream-abandoned") else { panic!("identical request should follow") };
+        drop(leader);
+        assert!(follower.wait_started().await.is_none());
+    }
+}
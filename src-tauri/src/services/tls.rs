@@ -0,0 +1,207 @@
+use crate::models::{CertificateSource, TlsCertificateInfo, TlsSettings};
+use sqlx::SqlitePool;
+use anyhow::{Result, anyhow};
+use chrono::{DateTime, Datelike, SecondsFormat, Utc};
+use sha2::{Sha256, Digest};
+use std::time::Duration;
+
+// 自签名证书的有效期
+const SELF_SIGNED_VALIDITY: Duration = Duration::from_secs(10 * 365 * 24 * 3600);
+const SELF_SIGNED_COMMON_NAME: &str = "Gemini API Proxy";
+
+/// 一对可直接交给 rustls 的证书链和私钥
+pub struct CertificatePem {
+    pub cert: Vec<u8>,
+    pub key: Vec<u8>,
+    pub info: TlsCertificateInfo,
+}
+
+pub struct TlsService {
+    pool: SqlitePool,
+}
+
+impl TlsService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// 配置了证书文件时从磁盘读取，否则使用保存的自签名证书（首次使用或 SAN 变化时生成）
+    pub async fn load_certificate(&self, settings: &TlsSettings) -> Result<CertificatePem> {
+        match settings.file_paths() {
+            Some((cert_path, key_path)) => {
+                let cert = tokio::fs::read(cert_path).await
+                    .map_err(|e| anyhow!("Failed to read certificate file '{}': {}", cert_path, e))?;
+                let key = tokio::fs::read(key_path).await
+                    .map_err(|e| anyhow!("Failed to read private key file '{}': {}", key_path, e))?;
+                let fingerprint_sha256 = inspect_pem(&cert, &key)?;
+
+                Ok(CertificatePem {
+                    cert,
+                    key,
+                    info: TlsCertificateInfo {
+                        source: CertificateSource::File,
+                        fingerprint_sha256,
+                        subject_alt_names: Vec::new(),
+                        generated_at: None,
+                        active: false,
+                    },
+                })
+            }
+            None => self.self_signed(&settings.subject_alt_names, false).await,
+        }
+    }
+
+    /// 丢弃保存的自签名证书并按给定 SAN 重新生成
+    #[cfg(feature = "desktop")]
+    pub async fn regenerate_self_signed(&self, subject_alt_names: &[String]) -> Result<CertificatePem> {
+        self.self_signed(subject_alt_names, true).await
+    }
+
+    async fn self_signed(&self, subject_alt_names: &[String], force: bool) -> Result<CertificatePem> {
+        let sans = normalize_sans(subject_alt_names)?;
+
+        let stored: (Option<String>, Option<String>, Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT tls_generated_cert, tls_generated_key, tls_generated_sans, tls_generated_at FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        if !force
+            && let (Some(cert), Some(key), Some(stored_sans)) = (&stored.0, &stored.1, &stored.2)
+            && serde_json::from_str::<Vec<String>>(stored_sans).ok().as_ref() == Some(&sans)
+        {
+            let fingerprint_sha256 = inspect_pem(cert.as_bytes(), key.as_bytes())?;
+            let generated_at = stored.3
+                .as_deref()
+                .and_then(|s| DateTime::parse_from_rfc3339(s).ok())
+                .map(|dt| dt.with_timezone(&Utc));
+
+            return Ok(CertificatePem {
+                cert: cert.clone().into_bytes(),
+                key: key.clone().into_bytes(),
+                info: TlsCertificateInfo {
+                    source: CertificateSource::SelfSigned,
+                    fingerprint_sha256,
+                    subject_alt_names: sans,
+                    generated_at,
+                    active: false,
+                },
+            });
+        }
+
+        let (cert, key) = generate_self_signed(&sans)?;
+        let fingerprint_sha256 = inspect_pem(cert.as_bytes(), key.as_bytes())?;
+        let now = Utc::now();
+
+        sqlx::query(
+            "UPDATE app_settings SET tls_generated_cert = ?, tls_generated_key = ?, tls_generated_sans = ?, tls_generated_at = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(&cert)
+        .bind(&key)
+        .bind(serde_json::to_string(&sans)?)
+        .bind(now.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .bind(now.to_rfc3339_opts(SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        tracing::info!("Generated self-signed TLS certificate for {} ({})", sans.join(", "), fingerprint_sha256);
+
+        Ok(CertificatePem {
+            cert: cert.into_bytes(),
+            key: key.into_bytes(),
+            info: TlsCertificateInfo {
+                source: CertificateSource::SelfSigned,
+                fingerprint_sha256,
+                subject_alt_names: sans,
+                generated_at: Some(now),
+                active: false,
+            },
+        })
+    }
+}
+
+/// 去掉空白和重复项，保持原有顺序
+fn normalize_sans(subject_alt_names: &[String]) -> Result<Vec<String>> {
+    let mut sans: Vec<String> = Vec::new();
+    for san in subject_alt_names {
+        let san = san.trim();
+        if !san.is_empty() && !sans.iter().any(|s| s.eq_ignore_ascii_case(san)) {
+            sans.push(san.to_string());
+        }
+    }
+
+    if sans.is_empty() {
+        return Err(anyhow!("At least one subject alternative name is required for the self-signed certificate"));
+    }
+    Ok(sans)
+}
+
+/// 生成自签名证书，返回 PEM 格式的证书和私钥
+fn generate_self_signed(sans: &[String]) -> Result<(String, String)> {
+    let mut params = rcgen::CertificateParams::new(sans.to_vec())?;
+    params.distinguished_name.push(rcgen::DnType::CommonName, SELF_SIGNED_COMMON_NAME);
+
+    // 从前一天开始生效，避免客户端时钟稍慢时证书尚未生效
+    let today = Utc::now().date_naive();
+    let not_before = rcgen::date_time_ymd(today.year(), today.month() as u8, today.day() as u8)
+        - Duration::from_secs(24 * 3600);
+    params.not_before = not_before;
+    params.not_after = not_before + SELF_SIGNED_VALIDITY;
+
+    let key_pair = rcgen::KeyPair::generate()?;
+    let cert = params.self_signed(&key_pair)?;
+    Ok((cert.pem(), key_pair.serialize_pem()))
+}
+
+/// 校验 PEM 中包含证书和私钥，返回叶子证书的 SHA-256 指纹
+fn inspect_pem(cert_pem: &[u8], key_pem: &[u8]) -> Result<String> {
+    let leaf = rustls_pemfile::certs(&mut &cert_pem[..])
+        .next()
+        .ok_or_else(|| anyhow!("No certificate found in PEM data"))?
+        .map_err(|e| anyhow!("Invalid certificate PEM: {}", e))?;
+
+    rustls_pemfile::private_key(&mut &key_pem[..])
+        .map_err(|e| anyhow!("Invalid private key PEM: {}", e))?
+        .ok_or_else(|| anyhow!("No private key found in PEM data"))?;
+
+    let digest = Sha256::digest(leaf.as_ref());
+    Ok(digest.iter().map(|b| format!("{:02X}", b)).collect::<Vec<_>>().join(":"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::test_pool;
+
+    #[test]
+    fn sans_are_trimmed_and_deduplicated() {
+        let sans = normalize_sans(&[" localhost ".to_string(), "LOCALHOST".to_string(), "".to_string(), "127.0.0.1".to_string()]).unwrap();
+        assert_eq!(sans, ["localhost", "127.0.0.1"]);
+        assert!(normalize_sans(&[" ".to_string()]).is_err());
+    }
+
+    #[test]
+    fn generated_certificate_passes_inspection() {
+        let (cert, key) = generate_self_signed(&["localhost".to_string()]).unwrap();
+        let fingerprint = inspect_pem(cert.as_bytes(), key.as_bytes()).unwrap();
+        assert_eq!(fingerprint.split(':').count(), 32);
+
+        assert!(inspect_pem(cert.as_bytes(), b"").is_err());
+        assert!(inspect_pem(key.as_bytes(), key.as_bytes()).is_err());
+    }
+
+    #[tokio::test]
+    async fn self_signed_certificate_is_reused_until_sans_change() {
+        let service = TlsService::new(test_pool().await);
+        let settings = TlsSettings { subject_alt_names: vec!["localhost".to_string()], ..TlsSettings::default() };
+        let first = service.load_certificate(&settings).await.unwrap();
+        let again = service.load_certificate(&settings).await.unwrap();
+        assert_eq!(first.info.fingerprint_sha256, again.info.fingerprint_sha256);
+        assert!(matches!(again.info.source, CertificateSource::SelfSigned));
+
+        let changed = TlsSettings { subject_alt_names: vec!["proxy.lan".to_string()], ..settings };
+        let regenerated = service.load_certificate(&changed).await.unwrap();
+        assert_ne!(first.info.fingerprint_sha256, regenerated.info.fingerprint_sha256);
+        assert_eq!(regenerated.info.subject_alt_names, ["proxy.lan"]);
+    }
+}
@@ -0,0 +1,308 @@
+use crate::models::{TotpEnrollment, TotpStatus};
+use crate::utils::constant_time_eq;
+use sqlx::SqlitePool;
+use anyhow::{Result, anyhow};
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+use sha2::{Sha256, Digest};
+use uuid::Uuid;
+
+// RFC 6238 的默认参数，主流验证器应用都支持
+const TOTP_STEP_SECS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+// 允许前后各一个时间步（±30 秒）的时钟偏差
+const ALLOWED_SKEW_STEPS: i64 = 1;
+const SECRET_LEN: usize = 20;
+const RECOVERY_CODE_COUNT: usize = 10;
+const ISSUER: &str = "Tjimi";
+const ACCOUNT: &str = "admin";
+
+fn current_step() -> i64 {
+    chrono::Utc::now().timestamp().div_euclid(TOTP_STEP_SECS)
+}
+
+/// 160 位随机密钥，取自两个 v4 UUID 的随机字节
+fn generate_secret() -> String {
+    let mut bytes = Vec::with_capacity(32);
+    bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    bytes.extend_from_slice(Uuid::new_v4().as_bytes());
+    BASE32_NOPAD.encode(&bytes[..SECRET_LEN])
+}
+
+/// RFC 4226 HOTP，动态截断后取 6 位
+fn hotp(secret: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]]) & 0x7fff_ffff;
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+/// 在允许的时钟偏差内查找与验证码匹配的时间步，只接受晚于 last_step 的时间步
+fn match_step(secret: &str, code: &str, last_step: Option<i64>) -> Option<i64> {
+    match_step_at(secret, code, last_step, current_step())
+}
+
+fn match_step_at(secret: &str, code: &str, last_step: Option<i64>, now: i64) -> Option<i64> {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).ok()?;
+    if code.len() != TOTP_DIGITS as usize || !code.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    (now - ALLOWED_SKEW_STEPS..=now + ALLOWED_SKEW_STEPS)
+        .filter(|step| *step >= 0 && last_step.is_none_or(|last| *step > last))
+        .find(|step| {
+            let expected = format!("{:0width$}", hotp(&secret, *step as u64), width = TOTP_DIGITS as usize);
+            constant_time_eq(expected.as_bytes(), code.as_bytes())
+        })
+}
+
+fn generate_recovery_code() -> String {
+    let hex = Uuid::new_v4().simple().to_string();
+    format!("{}-{}", &hex[..5], &hex[5..10])
+}
+
+/// 忽略空格、连字符和大小写
+fn normalize_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_ascii_lowercase()
+}
+
+fn hash_recovery_code(code: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(normalize_code(code).as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct TotpService {
+    pool: SqlitePool,
+}
+
+impl TotpService {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn status(&self) -> Result<TotpStatus> {
+        let result: (Option<i32>, Option<String>, Option<String>) = sqlx::query_as(
+            "SELECT totp_enabled, totp_pending_secret, totp_recovery_codes FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        Ok(TotpStatus {
+            enabled: result.0.unwrap_or(0) != 0,
+            pending: result.1.is_some(),
+            recovery_codes_remaining: Self::parse_codes(result.2.as_deref()).len(),
+        })
+    }
+
+    pub async fn is_enabled(&self) -> Result<bool> {
+        Ok(self.status().await?.enabled)
+    }
+
+    /// 生成新的待确认密钥，已启用时需要先停用
+    pub async fn begin_enrollment(&self) -> Result<TotpEnrollment> {
+        if self.is_enabled().await? {
+            return Err(anyhow!("两步验证已启用，请先停用后再重新绑定"));
+        }
+
+        let secret = generate_secret();
+        sqlx::query("UPDATE app_settings SET totp_pending_secret = ?, updated_at = ? WHERE id = 1")
+            .bind(&secret)
+            .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .execute(&self.pool)
+            .await?;
+
+        let otpauth_uri = format!(
+            "otpauth://totp/{issuer}:{account}?secret={secret}&issuer={issuer}&algorithm=SHA1&digits={digits}&period={period}",
+            issuer = ISSUER,
+            account = ACCOUNT,
+            secret = secret,
+            digits = TOTP_DIGITS,
+            period = TOTP_STEP_SECS,
+        );
+        Ok(TotpEnrollment { secret, otpauth_uri })
+    }
+
+    /// 用验证器应用生成的验证码确认绑定，启用两步验证并返回恢复码明文（只显示这一次）
+    pub async fn confirm_enrollment(&self, code: &str) -> Result<Vec<String>> {
+        let result: (Option<String>,) = sqlx::query_as(
+            "SELECT totp_pending_secret FROM app_settings WHERE id = 1"
+        )
+        .fetch_one(&self.pool)
+        .await?;
+
+        let secret = result.0.ok_or_else(|| anyhow!("没有待确认的两步验证绑定，请重新开始"))?;
+        let step = match_step(&secret, code.trim(), None)
+            .ok_or_else(|| anyhow!("验证码错误，请确认设备时间准确后重试"))?;
+
+        let (codes, hashes) = Self::new_recovery_codes();
+        sqlx::query(
+            "UPDATE app_settings SET totp_enabled = 1, totp_secret = ?, totp_pending_secret = NULL, totp_last_step = ?, totp_recovery_codes = ?, updated_at = ? WHERE id = 1"
+        )
+        .bind(&secret)
+        .bind(step)
+        .bind(serde_json::to_string(&hashes)?)
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+
+        Ok(codes)
+    }
+
+    pub async fn disable(&self) -> Result<()> {
+        sqlx::query(
+            "UPDATE app_settings SET totp_enabled = 0, totp_secret = NULL, totp_pending_secret = NULL, totp_last_step = NULL, totp_recovery_codes = NULL, updated_at = ? WHERE id = 1"
+        )
+        .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// 作废旧的恢复码并生成一组新的
+    pub async fn regenerate_recovery_codes(&self) -> Result<Vec<String>> {
+        let (codes, hashes) = Self::new_recovery_codes();
+        sqlx::query("UPDATE app_settings SET totp_recovery_codes = ?, updated_at = ? WHERE id = 1 AND totp_enabled = 1")
+            .bind(serde_json::to_string(&hashes)?)
+            .bind(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true))
+            .execute(&self.pool)
+            .await?;
+        Ok(codes)
+    }
+
+    /// 校验动态验证码或恢复码；验证码用过之后同一时间步及更早的验证码都不再接受，恢复码只能使用一次
+    pub async fn verify(&self, code: &str) -> Result<bool> {
+        let result: (Option<String>, Option<i64>, Option<String>) = sqlx::query_as(
+            "SELECT totp_secret, totp_last_step, totp_recovery_codes FROM app_settings WHERE id = 1 AND totp_enabled = 1"
+        )
+        .fetch_optional(&self.pool)
+        .await?
+        .unwrap_or((None, None, None));
+
+        let Some(secret) = result.0 else {
+            return Ok(false);
+        };
+
+        let code = code.trim();
+        if let Some(step) = match_step(&secret, code, result.1) {
+            // 条件更新，两个请求同时使用同一个验证码时只有一个成功
+            let updated = sqlx::query(
+                "UPDATE app_settings SET totp_last_step = ? WHERE id = 1 AND (totp_last_step IS NULL OR totp_last_step < ?)"
+            )
+            .bind(step)
+            .bind(step)
+            .execute(&self.pool)
+            .await?;
+            return Ok(updated.rows_affected() == 1);
+        }
+
+        let stored_codes = result.2;
+        let mut hashes = Self::parse_codes(stored_codes.as_deref());
+        let candidate = hash_recovery_code(code);
+        let Some(index) = hashes.iter().position(|hash| constant_time_eq(hash.as_bytes(), candidate.as_bytes())) else {
+            return Ok(false);
+        };
+        hashes.remove(index);
+
+        let updated = sqlx::query(
+            "UPDATE app_settings SET totp_recovery_codes = ? WHERE id = 1 AND totp_recovery_codes = ?"
+        )
+        .bind(serde_json::to_string(&hashes)?)
+        .bind(stored_codes)
+        .execute(&self.pool)
+        .await?;
+
+        if updated.rows_affected() == 1 {
+            tracing::warn!("Admin login used a TOTP recovery code, {} remaining", hashes.len());
+        }
+        Ok(updated.rows_affected() == 1)
+    }
+
+    fn new_recovery_codes() -> (Vec<String>, Vec<String>) {
+        let codes: Vec<String> = (0..RECOVERY_CODE_COUNT).map(|_| generate_recovery_code()).collect();
+        let hashes = codes.iter().map(|code| hash_recovery_code(code)).collect();
+        (codes, hashes)
+    }
+
+    fn parse_codes(value: Option<&str>) -> Vec<String> {
+        value
+            .and_then(|codes| serde_json::from_str(codes).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 4226 附录 D 的测试密钥
+    const RFC_SECRET: &[u8] = b"12345678901234567890";
+
+    fn code_at(secret: &str, step: i64) -> String {
+        let secret = BASE32_NOPAD.decode(secret.as_bytes()).unwrap();
+        format!("{:06}", hotp(&secret, step as u64))
+    }
+
+    #[test]
+    fn hotp_matches_rfc_4226_vectors() {
+        let expected = [755224, 287082, 359152, 969429, 338314, 254676, 287922, 162583, 399871, 520489];
+        for (counter, code) in expected.into_iter().enumerate() {
+            assert_eq!(hotp(RFC_SECRET, counter as u64), code, "counter {counter}");
+        }
+    }
+
+    #[test]
+    fn codes_within_one_step_of_skew_are_accepted() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let now = 1_000;
+        for step in [now - 1, now, now + 1] {
+            assert_eq!(match_step_at(&secret, &code_at(&secret, step), None, now), Some(step));
+        }
+        for step in [now - 2, now + 2] {
+            let code = code_at(&secret, step);
+            // 相邻时间步的验证码偶尔相同，只检查不会匹配到超出偏差的时间步
+            assert_ne!(match_step_at(&secret, &code, None, now), Some(step));
+        }
+    }
+
+    #[test]
+    fn used_steps_cannot_be_replayed() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let now = 1_000;
+        let code = code_at(&secret, now);
+        assert_eq!(match_step_at(&secret, &code, Some(now - 1), now), Some(now));
+        assert_eq!(match_step_at(&secret, &code, Some(now), now), None);
+    }
+
+    #[test]
+    fn malformed_codes_and_secrets_are_rejected() {
+        let secret = BASE32_NOPAD.encode(RFC_SECRET);
+        let code = code_at(&secret, 1_000);
+        assert_eq!(match_step_at(&secret, &code[..5], None, 1_000), None);
+        assert_eq!(match_step_at(&secret, &format!("{code}0"), None, 1_000), None);
+        assert_eq!(match_step_at(&secret, "12345a", None, 1_000), None);
+        assert_eq!(match_step_at("not base32!", &code, None, 1_000), None);
+    }
+
+    #[test]
+    fn generated_secrets_decode_to_160_bits() {
+        let secret = generate_secret();
+        assert_eq!(BASE32_NOPAD.decode(secret.as_bytes()).unwrap().len(), SECRET_LEN);
+        assert_ne!(secret, generate_secret());
+    }
+
+    #[test]
+    fn recovery_codes_ignore_formatting() {
+        let code = generate_recovery_code();
+        assert_eq!(code.len(), 11);
+        assert_eq!(hash_recovery_code(&code), hash_recovery_code(&code.replace('-', " ").to_uppercase()));
+        assert_ne!(hash_recovery_code(&code), hash_recovery_code(&generate_recovery_code()));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::models::{ApiKey, Provider};
+use anyhow::{Result, anyhow};
+use jsonwebtoken::{Algorithm, EncodingKey, Header};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const CLOUD_PLATFORM_SCOPE: &str = "https://www.googleapis.com/auth/cloud-platform";
+const ASSERTION_LIFETIME_SECS: i64 = 3600;
+// 距离过期不足该时间时提前换取新令牌，有效期很短的令牌最多提前一半有效期
+const REFRESH_MARGIN: Duration = Duration::from_secs(300);
+
+// 按服务账号和令牌地址缓存的访问令牌，所有请求共享
+static TOKEN_CACHE: LazyLock<Mutex<HashMap<String, CachedToken>>> = LazyLock::new(|| Mutex::new(HashMap::new()));
+
+struct CachedToken {
+    access_token: String,
+    refresh_at: Instant,
+}
+
+/// 令牌在签发后多久需要换新
+fn refresh_after(lifetime: Duration) -> Duration {
+    lifetime - REFRESH_MARGIN.min(lifetime / 2)
+}
+
+/// 服务账号 JSON 中换取令牌需要的字段
+#[derive(Debug, Clone, Deserialize)]
+pub struct ServiceAccountKey {
+    pub client_email: String,
+    pub private_key: String,
+    pub project_id: Option<String>,
+    pub token_uri: Option<String>,
+}
+
+impl ServiceAccountKey {
+    pub fn parse(json: &str) -> Result<Self> {
+        let account: ServiceAccountKey = serde_json::from_str(json)
+            .map_err(|e| anyhow!("Invalid service account JSON: {}", e))?;
+
+        if account.client_email.trim().is_empty() {
+            return Err(anyhow!("Service account JSON is missing client_email"));
+        }
+        EncodingKey::from_rsa_pem(account.private_key.as_bytes())
+            .map_err(|e| anyhow!("Service account private_key is not a valid RSA PEM key: {}", e))?;
+
+        Ok(account)
+    }
+}
+
+#[derive(Serialize)]
+struct AssertionClaims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    expires_in: Option<u64>,
+}
+
+pub struct VertexAuthService {
+    client: Client,
+}
+
+impl VertexAuthService {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// 返回服务账号的 OAuth2 访问令牌，优先使用缓存
+    pub async fn access_token(&self, api_key: &ApiKey, provider: &Provider) -> Result<String> {
+        let account = ServiceAccountKey::parse(&api_key.key_value)?;
+        // 令牌地址优先级：供应商配置 > 服务账号 JSON 的 token_uri > Google 默认地址
+        let token_url = provider.token_url.clone()
+            .or_else(|| account.token_uri.clone())
+            .unwrap_or_else(|| DEFAULT_TOKEN_URL.to_string());
+        let cache_key = format!("{}|{}", account.client_email, token_url);
+
+        {
+            let cache = TOKEN_CACHE.lock().unwrap();
+            if let Some(cached) = cache.get(&cache_key)
+                && cached.refresh_at > Instant::now()
+            {
+                return Ok(cached.access_token.clone());
+            }
+        }
+
+        let token = self.mint_token(&account, &token_url).await?;
+        let lifetime = Duration::from_secs(token.expires_in.unwrap_or(ASSERTION_LIFETIME_SECS as u64));
+
+        TOKEN_CACHE.lock().unwrap().insert(cache_key, CachedToken {
+            access_token: token.access_token.clone(),
+            refresh_at: Instant::now() + refresh_after(lifetime),
+        });
+
+        Ok(token.access_token)
+    }
+
+    /// 用 RS256 签名的 JWT 断言换取访问令牌（RFC 7523 jwt-bearer 授权）
+    async fn mint_token(&self, account: &ServiceAccountKey, token_url: &str) -> Result<TokenResponse> {
+        let now = chrono::Utc::now().timestamp();
+        let claims = AssertionClaims {
+            iss: &account.client_email,
+            scope: CLOUD_PLATFORM_SCOPE,
+            aud: token_url,
+            iat: now,
+            exp: now + ASSERTION_LIFETIME_SECS,
+        };
+
+        let key = EncodingKey::from_rsa_pem(account.private_key.as_bytes())?;
+        let assertion = jsonwebtoken::encode(&Header::new(Algorithm::RS256), &claims, &key)?;
+
+        tracing::info!("Minting Vertex AI access token for {}", account.client_email);
+
+        let response = self.client
+            .post(token_url)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(anyhow!("Token exchange failed for {} ({}): {}", account.client_email, status.as_u16(), error_text));
+        }
+
+        Ok(response.json().await?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn short_lived_tokens_are_still_cached() {
+        assert_eq!(refresh_after(Duration::from_secs(3600)), Duration::from_secs(3300));
+        assert_eq!(refresh_after(Duration::from_secs(600)), Duration::from_secs(300));
+        assert_eq!(refresh_after(Duration::from_secs(120)), Duration::from_secs(60));
+        assert_eq!(refresh_after(Duration::ZERO), Duration::ZERO);
+    }
+}
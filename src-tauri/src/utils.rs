@@ -0,0 +1,218 @@
+use serde_json::Value;
+use sha2::{Sha256, Digest};
+
+/// 调用方提供的参数不合法，管理 API 据此返回 400；其余错误都按内部错误处理
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct ValidationError(pub String);
+
+/// 从 `/v1beta/models/{model}:{method}` 形式的路径中取出模型名
+pub fn model_from_path(path: &str) -> Option<&str> {
+    let rest = path.split("/models/").nth(1)?;
+    let model = rest.split(':').next().unwrap_or(rest);
+    if model.is_empty() { None } else { Some(model) }
+}
+
+/// 把路径中的模型名替换为另一个模型，保留 `:{method}` 后缀
+pub fn replace_model_in_path(path: &str, model: &str) -> String {
+    let Some(start) = path.find("/models/").map(|i| i + "/models/".len()) else {
+        return path.to_string();
+    };
+    let end = path[start..].find(':').map(|i| start + i).unwrap_or(path.len());
+    format!("{}{}{}", &path[..start], model, &path[end..])
+}
+
+/// 简单的通配符匹配，`*` 匹配任意长度的字符
+pub fn glob_match(pattern: &str, value: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == value;
+    }
+
+    let mut remaining = value;
+    for (index, part) in parts.iter().enumerate() {
+        if index == 0 {
+            match remaining.strip_prefix(part) {
+                Some(rest) => remaining = rest,
+                None => return false,
+            }
+        } else if index == parts.len() - 1 {
+            return remaining.ends_with(part);
+        } else {
+            match remaining.find(part) {
+                Some(pos) => remaining = &remaining[pos + part.len()..],
+                None => return false,
+            }
+        }
+    }
+
+    true
+}
+
+/// 键按字典序排列的紧凑 JSON，字段顺序不同的相同请求得到相同的结果
+fn canonical_json(value: &Value, out: &mut String) {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (index, key) in keys.into_iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::String(key.clone()).to_string());
+                out.push(':');
+                canonical_json(&map[key], out);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                canonical_json(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+// 参与请求哈希的字段（camelCase 与 snake_case 视为同一字段）
+const HASHED_REQUEST_FIELDS: &[(&str, &str)] = &[
+    ("contents", "contents"),
+    ("systemInstruction", "system_instruction"),
+    ("generationConfig", "generation_config"),
+    ("safetySettings", "safety_settings"),
+    ("tools", "tools"),
+    ("toolConfig", "tool_config"),
+    ("cachedContent", "cached_content"),
+];
+
+/// 由处理请求的供应商、模型、内容、生成配置和工具计算的请求哈希，用于响应缓存和合并相同的并发请求
+pub fn canonical_request_hash(provider: &str, model: &str, body: &Value) -> String {
+    let mut fields = serde_json::Map::new();
+    fields.insert("provider".to_string(), Value::String(provider.to_string()));
+    fields.insert("model".to_string(), Value::String(model.to_string()));
+    for (camel, snake) in HASHED_REQUEST_FIELDS {
+        if let Some(value) = body.get(*camel).or_else(|| body.get(*snake)) {
+            fields.insert(camel.to_string(), value.clone());
+        }
+    }
+
+    let mut canonical = String::new();
+    canonical_json(&Value::Object(fields), &mut canonical);
+
+    let mut hasher = Sha256::new();
+    hasher.update(canonical.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+/// 常数时间比较，避免通过响应时间逐字节猜出密钥或哈希
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    use subtle::ConstantTimeEq;
+    a.ct_eq(b).into()
+}
+
+/// 隐藏 API 密钥的中间部分，规则与管理界面的 `maskApiKey` 相同
+pub fn mask_key_value(key: &str) -> String {
+    let chars: Vec<char> = key.chars().collect();
+    if chars.len() < 8 {
+        return "****".to_string();
+    }
+    let head = if key.starts_with("AIza") { 6 } else { 4 };
+    let tail: String = chars[chars.len() - 4..].iter().collect();
+    format!("{}****{}", chars[..head].iter().collect::<String>(), tail)
+}
+
+/// 按 RFC 4180 转义一个 CSV 字段
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn glob_without_wildcard_is_exact() {
+        assert!(glob_match("gemini-2.0-flash", "gemini-2.0-flash"));
+        assert!(!glob_match("gemini-2.0-flash", "gemini-2.0-flash-lite"));
+    }
+
+    #[test]
+    fn glob_wildcards_match_any_run_of_characters() {
+        assert!(glob_match("*", ""));
+        assert!(glob_match("gemini-*", "gemini-2.5-pro"));
+        assert!(glob_match("*-flash", "gemini-2.0-flash"));
+        assert!(glob_match("gemini-*-flash*", "gemini-2.0-flash-lite"));
+        assert!(glob_match("gemini-*-flash*", "gemini-2.0-flash"));
+        assert!(!glob_match("gemini-*-flash*", "gemini-2.5-pro"));
+        assert!(!glob_match("gemini-*", "models/gemini-2.5-pro"));
+    }
+
+    #[test]
+    fn glob_prefix_and_suffix_do_not_overlap() {
+        assert!(!glob_match("abc*c", "abc"));
+        assert!(glob_match("abc*c", "abcc"));
+        assert!(!glob_match("a*b*c", "acb"));
+    }
+
+    #[test]
+    fn request_hash_ignores_field_order_and_naming() {
+        let a = json!({
+            "contents": [{ "role": "user", "parts": [{ "text": "hi" }] }],
+            "generationConfig": { "temperature": 0, "topK": 1 }
+        });
+        let b = json!({
+            "generation_config": { "topK": 1, "temperature": 0 },
+            "contents": [{ "parts": [{ "text": "hi" }], "role": "user" }]
+        });
+        assert_eq!(canonical_request_hash("p", "gemini-2.0-flash", &a), canonical_request_hash("p", "gemini-2.0-flash", &b));
+    }
+
+    #[test]
+    fn request_hash_depends_on_provider_model_and_hashed_fields_only() {
+        let body = json!({ "contents": [{ "parts": [{ "text": "hi" }] }] });
+        let hash = canonical_request_hash("p", "gemini-2.0-flash", &body);
+        assert_ne!(hash, canonical_request_hash("p", "gemini-2.5-pro", &body));
+        assert_ne!(hash, canonical_request_hash("q", "gemini-2.0-flash", &body));
+
+        let mut changed = body.clone();
+        changed["contents"][0]["parts"][0]["text"] = json!("hello");
+        assert_ne!(hash, canonical_request_hash("p", "gemini-2.0-flash", &changed));
+
+        // 不参与哈希的字段不影响结果
+        let mut extra = body.clone();
+        extra["labels"] = json!({ "team": "a" });
+        assert_eq!(hash, canonical_request_hash("p", "gemini-2.0-flash", &extra));
+
+        // 数组顺序有意义
+        let ordered = json!({ "contents": [{ "parts": [{ "text": "a" }, { "text": "b" }] }] });
+        let reversed = json!({ "contents": [{ "parts": [{ "text": "b" }, { "text": "a" }] }] });
+        assert_ne!(canonical_request_hash("p", "m", &ordered), canonical_request_hash("p", "m", &reversed));
+    }
+
+    #[test]
+    fn constant_time_eq_compares_whole_input() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"secret", b""));
+    }
+
+    #[test]
+    fn masked_key_keeps_only_the_ends() {
+        assert_eq!(mask_key_value("AIzaSyA1234567890abcd"), "AIzaSy****abcd");
+        assert_eq!(mask_key_value("sk-proj-1234567890"), "sk-p****7890");
+        assert_eq!(mask_key_value("short"), "****");
+    }
+}
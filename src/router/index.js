@@ -29,6 +29,12 @@ const routes = [
     component: () => import('../views/Logs.vue'),
     meta: { requiresAuth: true }
   },
+  {
+    path: '/audit',
+    name: 'audit',
+    component: () => import('../views/AuditLog.vue'),
+    meta: { requiresAuth: true }
+  },
   {
     path: '/settings',
     name: 'settings',
@@ -47,6 +53,9 @@ router.beforeEach((to, from, next) => {
   
   if (to.meta.requiresAuth && !authStore.isAuthenticated) {
     next('/login')
+  } else if (to.meta.requiresAuth && authStore.isDefaultPassword && to.path !== '/settings') {
+    // 仍在使用默认密码时只能访问设置页修改密码
+    next('/settings')
   } else if (to.path === '/login' && authStore.isAuthenticated) {
     next('/api-keys')
   } else {
@@ -1,5 +1,5 @@
 import { defineStore } from 'pinia'
-import { invoke } from '@tauri-apps/api/core'
+import { adminInvoke } from '../utils/adminInvoke'
 
 export const useApiKeysStore = defineStore('apiKeys', {
   state: () => ({
@@ -21,7 +21,7 @@ export const useApiKeysStore = defineStore('apiKeys', {
       this.error = null
 
       try {
-        const result = await invoke('get_all_api_keys')
+        const result = await adminInvoke('get_all_api_keys')
 
         if (result.success) {
           this.keys = result.data || []
@@ -40,7 +40,7 @@ export const useApiKeysStore = defineStore('apiKeys', {
       this.error = null
 
       try {
-        const result = await invoke('create_api_key', {
+        const result = await adminInvoke('create_api_key', {
           request: {
             name: apiKeyData.name || `密钥 ${Date.now()}`,
             keyValue: apiKeyData.key_value
@@ -69,7 +69,7 @@ export const useApiKeysStore = defineStore('apiKeys', {
       this.error = null
 
       try {
-        const result = await invoke('update_api_key', {
+        const result = await adminInvoke('update_api_key', {
           keyId: apiKeyData.id,
           request: {
             name: apiKeyData.name,
@@ -100,7 +100,7 @@ export const useApiKeysStore = defineStore('apiKeys', {
       this.error = null
 
       try {
-        const result = await invoke('delete_api_key', {
+        const result = await adminInvoke('delete_api_key', {
           keyId: keyId
         })
 
@@ -124,7 +124,7 @@ export const useApiKeysStore = defineStore('apiKeys', {
       this.error = null
 
       try {
-        const result = await invoke('get_api_keys_paginated', {
+        const result = await adminInvoke('get_api_keys_paginated', {
           page,
           perPage
         })
@@ -0,0 +1,88 @@
+import { defineStore } from 'pinia'
+import { adminInvoke } from '../utils/adminInvoke'
+import { useAuthStore } from './auth'
+
+export const useAuditStore = defineStore('audit', {
+  state: () => ({
+    events: [],
+    loading: false,
+    error: null,
+    // 查询条件，为空表示不限制
+    filter: {
+      action: '',
+      target: '',
+      channel: '',
+      since: '',
+      until: ''
+    },
+    pagination: {
+      currentPage: 1,
+      perPage: 50,
+      totalCount: 0,
+      totalPages: 0
+    }
+  }),
+
+  actions: {
+    buildFilter() {
+      const toIso = (value) => (value ? new Date(value).toISOString() : null)
+      return {
+        action: this.filter.action || null,
+        target: this.filter.target || null,
+        channel: this.filter.channel || null,
+        since: toIso(this.filter.since),
+        until: toIso(this.filter.until)
+      }
+    },
+
+    async fetchEvents(page = 1, perPage = 50) {
+      const authStore = useAuthStore()
+      if (!authStore.isAuthenticated) return
+
+      this.loading = true
+      this.error = null
+
+      try {
+        const result = await adminInvoke('get_audit_events', {
+          filter: this.buildFilter(),
+          page,
+          perPage
+        })
+
+        this.events = result.events || []
+        this.pagination = {
+          currentPage: result.page,
+          perPage: result.perPage,
+          totalCount: result.totalCount,
+          totalPages: result.totalPages
+        }
+      } catch (error) {
+        this.error = error.message || String(error)
+      } finally {
+        this.loading = false
+      }
+    },
+
+    // 导出当前筛选条件下的全部记录并保存为文件
+    async exportEvents(format = 'json') {
+      this.error = null
+
+      try {
+        const content = await adminInvoke('export_audit_events', {
+          filter: this.buildFilter(),
+          format
+        })
+
+        const type = format === 'csv' ? 'text/csv' : 'application/json'
+        const url = URL.createObjectURL(new Blob([content], { type }))
+        const link = document.createElement('a')
+        link.href = url
+        link.download = `audit-events-${new Date().toISOString().slice(0, 10)}.${format}`
+        link.click()
+        URL.revokeObjectURL(url)
+      } catch (error) {
+        this.error = error.message || String(error)
+      }
+    }
+  }
+})
@@ -1,5 +1,6 @@
 import { defineStore } from 'pinia'
 import { invoke } from '@tauri-apps/api/core'
+import { adminInvoke } from '../utils/adminInvoke'
 
 export const useAuthStore = defineStore('auth', {
   state: () => ({
@@ -7,28 +8,35 @@ export const useAuthStore = defineStore('auth', {
     sessionToken: null,
     loading: false,
     error: null,
-    isDefaultPassword: false
+    isDefaultPassword: false,
+    totpRequired: false
   }),
 
   actions: {
-    async login(password) {
+    async login(password, totpCode = null) {
       this.loading = true
       this.error = null
       
       try {
         const result = await invoke('login', {
-          request: { password }
+          request: { password, totpCode: totpCode || null }
         })
         
         if (result.success) {
+          this.totpRequired = false
           this.isAuthenticated = true
           this.sessionToken = result.data.sessionToken
           localStorage.setItem('sessionToken', result.data.sessionToken)
           
-          // Check if still using default password
-          await this.checkDefaultPassword()
+          // 仍在使用默认密码时，修改密码之前不能进行其他管理操作
+          this.isDefaultPassword = result.data.passwordChangeRequired
           
           return true
+        } else if (result.error?.startsWith('TOTP_REQUIRED')) {
+          // 已启用两步验证，密码正确后还需要动态验证码或恢复码
+          this.totpRequired = true
+          this.error = totpCode ? result.error.replace(/^TOTP_REQUIRED:\s*/, '') : null
+          return false
         } else {
           this.error = result.error
           return false
@@ -46,7 +54,7 @@ export const useAuthStore = defineStore('auth', {
       this.error = null
       
       try {
-        const result = await invoke('change_password', {
+        const result = await adminInvoke('change_password', {
           request: { 
             currentPassword, 
             newPassword 
@@ -70,7 +78,7 @@ export const useAuthStore = defineStore('auth', {
 
     async checkDefaultPassword() {
       try {
-        const result = await invoke('check_default_password')
+        const result = await adminInvoke('check_default_password')
         if (result.success) {
           this.isDefaultPassword = result.data
         }
@@ -84,15 +92,36 @@ export const useAuthStore = defineStore('auth', {
       if (token) {
         this.sessionToken = token
         this.isAuthenticated = true
-        await this.checkDefaultPassword()
+
+        // 确认保存的会话在服务端仍然有效，过期或空闲超时后会被退出登录
+        try {
+          const result = await adminInvoke('get_current_session')
+          if (result.success) {
+            this.isDefaultPassword = result.data.passwordChangeRequired
+          }
+        } catch (error) {
+          console.error('Session is no longer valid:', error)
+        }
+      }
+    },
+
+    async logout() {
+      if (this.sessionToken) {
+        try {
+          await invoke('logout', { sessionToken: this.sessionToken })
+        } catch (error) {
+          console.error('Failed to revoke session:', error)
+        }
       }
+      this.clearSession()
     },
 
-    logout() {
+    clearSession() {
       this.isAuthenticated = false
       this.sessionToken = null
       this.error = null
       this.isDefaultPassword = false
+      this.totpRequired = false
       localStorage.removeItem('sessionToken')
     }
   }
@@ -1,5 +1,5 @@
 import { defineStore } from 'pinia'
-import { invoke } from '@tauri-apps/api/core'
+import { adminInvoke } from '../utils/adminInvoke'
 import { useAuthStore } from './auth'
 
 export const useLogsStore = defineStore('logs', {
@@ -30,7 +30,7 @@ export const useLogsStore = defineStore('logs', {
 
       try {
         console.log('Fetching logs with limit:', limit)
-        const result = await invoke('get_request_logs', {
+        const result = await adminInvoke('get_request_logs', {
           limit
         })
 
@@ -59,7 +59,7 @@ export const useLogsStore = defineStore('logs', {
       this.error = null
 
       try {
-        const result = await invoke('get_usage_stats')
+        const result = await adminInvoke('get_usage_stats')
 
         if (result.success) {
           this.stats = result.data
@@ -85,7 +85,7 @@ export const useLogsStore = defineStore('logs', {
 
       try {
         console.log('Fetching logs paginated:', { page, perPage })
-        const result = await invoke('get_request_logs_paginated', {
+        const result = await adminInvoke('get_request_logs_paginated', {
           page,
           perPage
         })
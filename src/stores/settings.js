@@ -1,6 +1,6 @@
 import { defineStore } from 'pinia'
 import { ref } from 'vue'
-import { invoke } from '@tauri-apps/api/core'
+import { adminInvoke } from '../utils/adminInvoke'
 
 export const useSettingsStore = defineStore('settings', () => {
   const retryCount = ref(3)
@@ -12,7 +12,7 @@ export const useSettingsStore = defineStore('settings', () => {
     error.value = null
     
     try {
-      const count = await invoke('get_retry_count')
+      const count = await adminInvoke('get_retry_count')
       retryCount.value = count
       return count
     } catch (err) {
@@ -29,7 +29,7 @@ export const useSettingsStore = defineStore('settings', () => {
     error.value = null
     
     try {
-      await invoke('set_retry_count', { retryCount: count })
+      await adminInvoke('set_retry_count', { retryCount: count })
       retryCount.value = count
       return true
     } catch (err) {
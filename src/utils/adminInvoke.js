@@ -0,0 +1,24 @@
+import { invoke } from '@tauri-apps/api/core'
+import { useAuthStore } from '../stores/auth'
+import router from '../router'
+
+// 调用管理命令，自动附带当前会话令牌；会话失效时退出登录，仍在使用默认密码时跳转到设置页修改密码
+export async function adminInvoke(command, args = {}) {
+  const authStore = useAuthStore()
+
+  try {
+    return await invoke(command, { ...args, sessionToken: authStore.sessionToken })
+  } catch (error) {
+    const message = typeof error === 'string' ? error : error?.message || ''
+
+    if (message.startsWith('UNAUTHENTICATED')) {
+      authStore.clearSession()
+      await router.push('/login')
+    } else if (message.startsWith('PASSWORD_CHANGE_REQUIRED')) {
+      authStore.isDefaultPassword = true
+      await router.push('/settings')
+    }
+
+    throw error
+  }
+}